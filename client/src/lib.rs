@@ -25,12 +25,12 @@ use cfg_if::cfg_if;
 use wasm_bindgen::prelude::*;
 
 use core::{
-    bitmaps_to_transfer, build_traits, next_generation, program_from_source,
-    program_from_source_and_genotype, run_program_with_preamble, simplified_unparse, unparse,
+    bitmaps_to_transfer, build_traits, next_generation, palettes_to_transfer, program_from_source,
+    run_program_with_compiled_preamble, simplified_unparse, unparse,
 };
 use core::{
-    BitmapInfo, Context, Genotype, Packable, Program, RenderPacketGeometry, RenderPacketImage,
-    RenderPacketMask, TraitList, Vm
+    BitmapInfo, CompileCache, Context, Genotype, Packable, Program, RenderPacketBlend, RenderPacketGeometry,
+    RenderPacketImage, RenderPacketLayer, RenderPacketMask, TraitList, Vm
 };
 
 use log::{error, info};
@@ -127,6 +127,76 @@ impl From<&RenderPacketMask> for RenderPacketMaskWasm {
     }
 }
 
+#[wasm_bindgen]
+pub struct RenderPacketBlendWasm {
+    mode: i32,
+}
+
+#[wasm_bindgen]
+impl RenderPacketBlendWasm {
+    pub fn get_mode(&self) -> i32 {
+        self.mode
+    }
+}
+
+impl From<&RenderPacketBlend> for RenderPacketBlendWasm {
+    fn from(rpb: &RenderPacketBlend) -> RenderPacketBlendWasm {
+        RenderPacketBlendWasm {
+            mode: rpb.mode as i32,
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct RenderPacketLayerWasm {
+    name: String,
+    opacity: f32,
+    blend_mode: i32,
+    blur: f32,
+    contrast: f32,
+    brightness: f32,
+    saturation: f32,
+}
+
+#[wasm_bindgen]
+impl RenderPacketLayerWasm {
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+    pub fn get_blend_mode(&self) -> i32 {
+        self.blend_mode
+    }
+    pub fn get_blur(&self) -> f32 {
+        self.blur
+    }
+    pub fn get_contrast(&self) -> f32 {
+        self.contrast
+    }
+    pub fn get_brightness(&self) -> f32 {
+        self.brightness
+    }
+    pub fn get_saturation(&self) -> f32 {
+        self.saturation
+    }
+}
+
+impl From<&RenderPacketLayer> for RenderPacketLayerWasm {
+    fn from(rpl: &RenderPacketLayer) -> RenderPacketLayerWasm {
+        RenderPacketLayerWasm {
+            name: rpl.name.clone(),
+            opacity: rpl.opacity,
+            blend_mode: rpl.blend as i32,
+            blur: rpl.blur,
+            contrast: rpl.contrast,
+            brightness: rpl.brightness,
+            saturation: rpl.saturation,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct RenderPacketImageWasm {
     linear_colour_space: bool,
@@ -171,6 +241,9 @@ pub struct Bridge {
     genotype_list: Vec<Genotype>,
     // only used during sequence of calls for rendering
     program: Option<Program>,
+
+    // compiled preamble and templates for scripts rendered with genotypes
+    compile_cache: CompileCache,
 }
 
 #[wasm_bindgen]
@@ -189,6 +262,8 @@ impl Bridge {
 
             genotype_list: vec![],
             program: None,
+
+            compile_cache: Default::default(),
         }
     }
 
@@ -212,8 +287,10 @@ impl Bridge {
         source: &str,
         packed_genotype: &str,
     ) -> bool {
-        if let Ok((mut genotype, _)) = Genotype::unpack(packed_genotype) {
-            let res = program_from_source_and_genotype(source, &mut genotype);
+        if let Ok((genotype, _)) = Genotype::unpack(packed_genotype) {
+            let res = self
+                .compile_cache
+                .program_from_source_and_genotype(source, &genotype);
             match res {
                 Ok(program) => {
                     self.program = Some(program);
@@ -243,13 +320,37 @@ impl Bridge {
         }
     }
 
+    // return the list of required palette files to the host system
+    // it will send their contents back here via the add_palette function
+    pub fn get_palette_transfers_as_json(&self) -> String {
+        if let Some(program) = &self.program {
+            let palettes_to_transfer = palettes_to_transfer(program, &self.context);
+            vec_strings_as_json(&palettes_to_transfer)
+        } else {
+            "[]".to_string()
+        }
+    }
+
     // the program has been compiled,
-    // all required bitmaps have been loaded
+    // all required bitmaps and palettes have been loaded
     pub fn run_program(&mut self) -> usize {
         // todo: check that we're in the RENDER state
 
         if let Some(program) = &self.program {
-            match run_program_with_preamble(&mut self.vm, &mut self.context, &program) {
+            let preamble = match self.compile_cache.preamble() {
+                Ok(preamble) => preamble,
+                Err(e) => {
+                    error!("{}", e);
+                    return 0;
+                }
+            };
+
+            match run_program_with_compiled_preamble(
+                &mut self.vm,
+                &mut self.context,
+                preamble,
+                &program,
+            ) {
                 Ok(_) => {
                     self.vm.probe_clear();
                     self.context.render_list.remove_useless_render_packets();
@@ -285,6 +386,18 @@ impl Bridge {
         RenderPacketImageWasm::from(rpi)
     }
 
+    pub fn rp_blend(&self, packet_number: usize) -> RenderPacketBlendWasm {
+        let rpb = self.context.get_rp_blend(packet_number).unwrap();
+
+        RenderPacketBlendWasm::from(rpb)
+    }
+
+    pub fn rp_layer(&self, packet_number: usize) -> RenderPacketLayerWasm {
+        let rpl = self.context.get_rp_layer(packet_number).unwrap();
+
+        RenderPacketLayerWasm::from(rpl)
+    }
+
     pub fn rp_geometry(&self, packet_number: usize) -> RenderPacketGeometryWasm {
         let geometry = self.context.get_rp_geometry(packet_number).unwrap();
 
@@ -297,6 +410,17 @@ impl Bridge {
         self.context.bitmap_cache.insert(name, bitmap_info).unwrap();
     }
 
+    // source is either a GIMP palette (.gpl) or a list of hex colours
+    pub fn add_palette(&mut self, name: &str, source: &str) -> bool {
+        match self.context.palette_cache.insert_from_source(name, source) {
+            Ok(_) => true,
+            Err(e) => {
+                error!("{}", e);
+                false
+            }
+        }
+    }
+
     // todo: is bool the best return type?
     pub fn build_traits(&mut self, source: &str) -> String {
         info!("build_traits");
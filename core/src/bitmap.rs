@@ -15,6 +15,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::bitmap_cache::BitmapInfo;
 use crate::colour::{Colour, ColourFormat};
 use crate::context::Context;
 use crate::error::{Error, Result};
@@ -22,9 +23,22 @@ use crate::iname::Iname;
 use crate::keywords::Keyword;
 use crate::prng::PrngStateStruct;
 use crate::program::Program;
-use crate::vm::Vm;
+use crate::vm::{Var, Vm};
 
 use log::error;
+use std::cmp::{Ordering, Reverse};
+
+// larger bitmaps are sampled evenly down to this many pixels before clustering
+const PALETTE_MAX_SAMPLES: usize = 16_384;
+const PALETTE_MAX_ITERATIONS: usize = 32;
+// the cost of clustering grows with the number of colours
+const PALETTE_MAX_COLOURS: usize = 256;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaletteOrder {
+    Frequency, // most common first
+    Lightness, // darkest first
+}
 
 // invoke a function with args: x, y, r, g, b, a
 // colour values are normalized to 0..1
@@ -39,8 +53,6 @@ fn invoke_function(
     from_string: &str,
 ) -> Result<()> {
     let bitmap_info = context.bitmap_cache.get(from_string)?;
-    let ip = vm.ip;
-    let fn_info = &program.fn_info[fun];
     let colour = Colour::new(
         ColourFormat::Rgb,
         bitmap_info.data[index],
@@ -49,12 +61,11 @@ fn invoke_function(
         bitmap_info.data[index + 3],
     );
 
-    vm.function_call_default_arguments(context, program, fn_info)?;
-    vm.function_set_argument_to_col(fn_info, Iname::from(Keyword::Colour), &colour);
-    vm.function_set_argument_to_2d(fn_info, Iname::from(Keyword::Position), x as f32, y as f32);
-    vm.function_call_body(context, program, fn_info)?;
-
-    vm.ip = ip;
+    let args = [
+        (Keyword::Colour, Var::Colour(colour)),
+        (Keyword::Position, Var::V2D(x as f32, y as f32)),
+    ];
+    vm.invoke_function(context, program, fun, &args)?;
 
     Ok(())
 }
@@ -225,3 +236,243 @@ pub fn height(context: &mut Context, program: &Program, from: Iname) -> Result<f
 
     Ok(bitmap_info.height as f32)
 }
+
+// the num most representative colours of a bitmap, found with k-means
+// clustering in OKLab space. There may be fewer than num colours if the bitmap
+// doesn't have enough distinct ones and never more than PALETTE_MAX_COLOURS.
+pub fn palette(
+    context: &Context,
+    program: &Program,
+    from: Iname,
+    num: usize,
+    seed: i32,
+    order: PaletteOrder,
+) -> Result<Vec<Colour>> {
+    let from_string = string_from_iname(program, from)?;
+    let bitmap_info = context.bitmap_cache.get(from_string)?;
+
+    palette_from_bitmap_info(bitmap_info, num, seed, order)
+}
+
+fn palette_from_bitmap_info(
+    bitmap_info: &BitmapInfo,
+    num: usize,
+    seed: i32,
+    order: PaletteOrder,
+) -> Result<Vec<Colour>> {
+    let num_pixels = bitmap_info.width * bitmap_info.height;
+    let stride = num_pixels.div_ceil(PALETTE_MAX_SAMPLES).max(1);
+
+    let mut samples: Vec<[f32; 3]> = Vec::with_capacity(num_pixels / stride + 1);
+    for pixel in bitmap_info.data.chunks_exact(4).step_by(stride) {
+        // fully transparent pixels don't contribute any colour
+        if pixel[3] == 0.0 {
+            continue;
+        }
+        let lab = Colour::new(ColourFormat::Rgb, pixel[0], pixel[1], pixel[2], 1.0)
+            .convert(ColourFormat::Oklab)?;
+        samples.push([lab.e0, lab.e1, lab.e2]);
+    }
+
+    let mut clusters = kmeans(&samples, num.min(PALETTE_MAX_COLOURS), seed);
+
+    match order {
+        PaletteOrder::Frequency => clusters.sort_by_key(|c| Reverse(c.1)),
+        PaletteOrder::Lightness => {
+            clusters.sort_by(|a, b| a.0[0].partial_cmp(&b.0[0]).unwrap_or(Ordering::Equal))
+        }
+    }
+
+    clusters
+        .iter()
+        .map(|(centre, _)| {
+            let rgb = Colour::new(ColourFormat::Oklab, centre[0], centre[1], centre[2], 1.0)
+                .convert(ColourFormat::Rgb)?;
+            // the average of colours that are in gamut can still be slightly out
+            Ok(Colour::new(
+                ColourFormat::Rgb,
+                rgb.e0.clamp(0.0, 1.0),
+                rgb.e1.clamp(0.0, 1.0),
+                rgb.e2.clamp(0.0, 1.0),
+                1.0,
+            ))
+        })
+        .collect()
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1]) + (a[2] - b[2]) * (a[2] - b[2])
+}
+
+fn nearest(point: &[f32; 3], centres: &[[f32; 3]]) -> usize {
+    let mut best = 0;
+    let mut best_distance = f32::MAX;
+    for (i, centre) in centres.iter().enumerate() {
+        let d = distance_squared(point, centre);
+        if d < best_distance {
+            best = i;
+            best_distance = d;
+        }
+    }
+    best
+}
+
+// returns the centre of each cluster along with the number of points in it
+fn kmeans(points: &[[f32; 3]], k: usize, seed: i32) -> Vec<([f32; 3], usize)> {
+    if points.is_empty() || k == 0 {
+        return vec![];
+    }
+
+    let mut prng = PrngStateStruct::new(seed, 0.0, 1.0);
+
+    // k-means++ initialisation: each centre is picked with a probability
+    // proportional to its squared distance from the nearest existing centre
+    let mut centres: Vec<[f32; 3]> = Vec::with_capacity(k);
+    centres.push(points[prng.next_usize_range(0, points.len()).min(points.len() - 1)]);
+    let mut distances: Vec<f32> = points
+        .iter()
+        .map(|p| distance_squared(p, &centres[0]))
+        .collect();
+
+    while centres.len() < k {
+        let total: f32 = distances.iter().sum();
+        if total <= 0.0 {
+            // every point is already on a centre
+            break;
+        }
+
+        let mut target = prng.next_f32() * total;
+        let mut chosen = points.len() - 1;
+        for (i, d) in distances.iter().enumerate() {
+            if target < *d {
+                chosen = i;
+                break;
+            }
+            target -= d;
+        }
+
+        let centre = points[chosen];
+        for (d, p) in distances.iter_mut().zip(points) {
+            *d = d.min(distance_squared(p, &centre));
+        }
+        centres.push(centre);
+    }
+
+    // Lloyd's algorithm
+    let mut assignments: Vec<usize> = points.iter().map(|p| nearest(p, &centres)).collect();
+    for _ in 0..PALETTE_MAX_ITERATIONS {
+        let mut sums = vec![[0.0f32; 3]; centres.len()];
+        let mut counts = vec![0usize; centres.len()];
+        for (p, &a) in points.iter().zip(&assignments) {
+            sums[a][0] += p[0];
+            sums[a][1] += p[1];
+            sums[a][2] += p[2];
+            counts[a] += 1;
+        }
+        for (i, centre) in centres.iter_mut().enumerate() {
+            // an empty cluster keeps its previous centre
+            if counts[i] > 0 {
+                let n = counts[i] as f32;
+                *centre = [sums[i][0] / n, sums[i][1] / n, sums[i][2] / n];
+            }
+        }
+
+        let next: Vec<usize> = points.iter().map(|p| nearest(p, &centres)).collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+    }
+
+    let mut counts = vec![0usize; centres.len()];
+    for &a in &assignments {
+        counts[a] += 1;
+    }
+
+    centres
+        .into_iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rgb(colour: &Colour, r: f32, g: f32, b: f32) {
+        assert_eq!(colour.format, ColourFormat::Rgb);
+        assert!((colour.e0 - r).abs() < 0.001, "{:?}", colour);
+        assert!((colour.e1 - g).abs() < 0.001, "{:?}", colour);
+        assert!((colour.e2 - b).abs() < 0.001, "{:?}", colour);
+    }
+
+    // 5 red pixels, 2 blue and a transparent one
+    fn red_and_blue() -> BitmapInfo {
+        let mut data: Vec<u8> = vec![];
+        for _ in 0..5 {
+            data.extend_from_slice(&[255, 0, 0, 255]);
+        }
+        for _ in 0..2 {
+            data.extend_from_slice(&[0, 0, 255, 255]);
+        }
+        data.extend_from_slice(&[0, 255, 0, 0]);
+
+        BitmapInfo::new(4, 2, data)
+    }
+
+    #[test]
+    fn test_palette() {
+        let bitmap_info = red_and_blue();
+
+        let palette =
+            palette_from_bitmap_info(&bitmap_info, 2, 3, PaletteOrder::Frequency).unwrap();
+        assert_eq!(palette.len(), 2);
+        assert_rgb(&palette[0], 1.0, 0.0, 0.0);
+        assert_rgb(&palette[1], 0.0, 0.0, 1.0);
+
+        let palette =
+            palette_from_bitmap_info(&bitmap_info, 2, 3, PaletteOrder::Lightness).unwrap();
+        assert_rgb(&palette[0], 0.0, 0.0, 1.0);
+        assert_rgb(&palette[1], 1.0, 0.0, 0.0);
+
+        // there are only two distinct colours
+        let palette =
+            palette_from_bitmap_info(&bitmap_info, 5, 3, PaletteOrder::Frequency).unwrap();
+        assert_eq!(palette.len(), 2);
+
+        let palette =
+            palette_from_bitmap_info(&bitmap_info, 1, 3, PaletteOrder::Frequency).unwrap();
+        assert_eq!(palette.len(), 1);
+    }
+
+    #[test]
+    fn test_palette_max_colours() {
+        let data: Vec<u8> = (0..300)
+            .flat_map(|i| vec![(i % 256) as u8, (i / 256 * 128) as u8, 0, 255])
+            .collect();
+        let bitmap_info = BitmapInfo::new(300, 1, data);
+
+        let palette =
+            palette_from_bitmap_info(&bitmap_info, 100_000, 3, PaletteOrder::Frequency).unwrap();
+        assert_eq!(palette.len(), PALETTE_MAX_COLOURS);
+    }
+
+    #[test]
+    fn test_kmeans() {
+        let points: Vec<[f32; 3]> = (0..100)
+            .map(|i| {
+                let offset = if i % 3 == 0 { 10.0 } else { 0.0 };
+                [offset + (i % 7) as f32 * 0.1, offset, 0.0]
+            })
+            .collect();
+
+        let clusters = kmeans(&points, 2, 5);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters.iter().map(|c| c.1).sum::<usize>(), 100);
+        assert!(clusters.iter().any(|c| c.1 == 34 && c.0[1] == 10.0));
+
+        // deterministic for a seed
+        assert_eq!(clusters, kmeans(&points, 2, 5));
+    }
+}
@@ -18,9 +18,13 @@
 use crate::error::{Error, Result};
 use log::error;
 use std::collections::HashMap;
+use std::sync::Arc;
 
+// the pixel data is shared between clones, so every worker thread that renders
+// a population can have its own cache without copying the bitmaps
+#[derive(Clone)]
 pub struct BitmapCache {
-    pub info: HashMap<String, BitmapInfo>,
+    pub info: HashMap<String, Arc<BitmapInfo>>,
 }
 
 impl Default for BitmapCache {
@@ -33,13 +37,13 @@ impl Default for BitmapCache {
 
 impl BitmapCache {
     pub fn insert(&mut self, name: &str, info: BitmapInfo) -> Result<()> {
-        self.info.insert(name.into(), info);
+        self.info.insert(name.into(), Arc::new(info));
         Ok(())
     }
 
     pub fn get(&self, name: &str) -> Result<&BitmapInfo> {
         match self.info.get(name) {
-            Some(bitmap_info) => Ok(bitmap_info),
+            Some(bitmap_info) => Ok(bitmap_info.as_ref()),
             None => {
                 error!("can't find bitmap: {}", name);
                 Err(Error::BitmapCache)
@@ -63,7 +67,7 @@ impl BitmapCache {
 
 // given pixel data as u8 in sRGB colour space
 // this converts it to the 0..1 range
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct BitmapInfo {
     pub width: usize,
     pub height: usize,
@@ -22,9 +22,15 @@
 // | HSL    | H 0..360  | S 0..1      | L 0..1      |
 // | HSLuv  | H 0..360  | S 0..100    | L 0..100    |
 // | LAB    | L 0..100  | A -128..128 | B -128..128 |
+// | LCH    | L 0..100  | C 0..~180   | H 0..360    |
+// | OKLab  | L 0..1    | A -0.4..0.4 | B -0.4..0.4 |
+// | OKLCh  | L 0..1    | C 0..0.4    | H 0..360    |
 // |--------+-----------+-------------+-------------|
+//
+// LCH is the cylindrical form of CIELUV (the space that HSLuv is built on)
 
 use crate::colour_palettes::COLOUR_PALETTES;
+use crate::ease::{self, Easing};
 use crate::error::{Error, Result};
 use crate::keywords::Keyword;
 use crate::mathutil;
@@ -38,6 +44,10 @@ const COLOUR_UNIT_ANGLE: f64 = 360.0 / 12.0;
 const COLOUR_COMPLIMENTARY_ANGLE: f64 = COLOUR_UNIT_ANGLE * 6.0;
 const COLOUR_TRIAD_ANGLE: f64 = COLOUR_UNIT_ANGLE * 4.0;
 
+// how far outside of 0..1 an sRGB element can be and still count as in gamut
+const SRGB_GAMUT_TOLERANCE: f32 = 0.0001;
+const SRGB_GAMUT_SEARCH_STEPS: usize = 16;
+
 const REF_U: f64 = 0.197_830_006_642_836_807_640;
 const REF_V: f64 = 0.468_319_994_938_791_003_700;
 
@@ -62,6 +72,9 @@ pub enum ColourFormat {
     Hsluv,
     Hsv,
     Lab,
+    Lch,
+    Oklab,
+    Oklch,
 }
 
 // Colour with ColourFormat::Rgb is in sRGB colour space
@@ -90,10 +103,22 @@ pub enum ColourPreset {
 pub enum ProcColourType {
     ProceduralColour,
     BezierColour,
+    GradientColour,
+}
+
+// a colour at position t along a gradient, easing is used on the segment
+// between this stop and the next one
+#[derive(Clone, Debug)]
+pub struct GradientStop {
+    pub t: f32,
+    pub colour: Colour,
+    pub easing: Easing,
 }
 
 // ProceduralColour: a = rgb + alpha[0], b = rgb + alpha[0], c = rgb + alpha[0], d = rgb + alpha[0]
 // BezierColour: a = rgb + alpha[0], b = rgb + alpha[1], c = rgb + alpha[2], d = rgb + alpha[3]
+// GradientColour: stops are sorted by t and all of their colours are in the
+// format that's used for the interpolation
 #[derive(Clone, Debug)]
 pub struct ProcColourStateStruct {
     pub proc_colour_type: ProcColourType,
@@ -102,6 +127,7 @@ pub struct ProcColourStateStruct {
     pub c: [f32; 3],
     pub d: [f32; 3],
     pub alpha: [f32; 4],
+    pub stops: Vec<GradientStop>,
 }
 
 // note: ConvertibleColour's RGB is in linear space, whilst Colour is in sRGB space
@@ -115,6 +141,8 @@ enum ConvertibleColour {
     XYZ(f64, f64, f64, f64),
     LUV(f64, f64, f64, f64),
     LCH(f64, f64, f64, f64),
+    OKLab(f64, f64, f64, f64),
+    OKLCh(f64, f64, f64, f64),
 }
 
 impl ColourFormat {
@@ -125,9 +153,44 @@ impl ColourFormat {
             Keyword::Hsluv => Some(ColourFormat::Hsluv),
             Keyword::Hsv => Some(ColourFormat::Hsv),
             Keyword::Lab => Some(ColourFormat::Lab),
+            Keyword::Lch => Some(ColourFormat::Lch),
+            Keyword::Oklab => Some(ColourFormat::Oklab),
+            Keyword::Oklch => Some(ColourFormat::Oklch),
+            _ => None,
+        }
+    }
+
+    // the index of the element that's an angle in degrees
+    pub fn hue_element(self) -> Option<usize> {
+        match self {
+            ColourFormat::Hsl | ColourFormat::Hsluv | ColourFormat::Hsv => Some(0),
+            ColourFormat::Lch | ColourFormat::Oklch => Some(2),
             _ => None,
         }
     }
+
+    // the range of each element, as given in the table at the top of this file
+    pub fn element_ranges(self) -> [(f32, f32); 3] {
+        match self {
+            ColourFormat::Rgb => [(0.0, 1.0), (0.0, 1.0), (0.0, 1.0)],
+            ColourFormat::Hsl | ColourFormat::Hsv => [(0.0, 360.0), (0.0, 1.0), (0.0, 1.0)],
+            ColourFormat::Hsluv => [(0.0, 360.0), (0.0, 100.0), (0.0, 100.0)],
+            ColourFormat::Lab => [(0.0, 100.0), (-128.0, 128.0), (-128.0, 128.0)],
+            ColourFormat::Lch => [(0.0, 100.0), (0.0, 180.0), (0.0, 360.0)],
+            ColourFormat::Oklab => [(0.0, 1.0), (-0.4, 0.4), (-0.4, 0.4)],
+            ColourFormat::Oklch => [(0.0, 1.0), (0.0, 0.4), (0.0, 360.0)],
+        }
+    }
+
+    // the elements that move the colour away from grey without changing its
+    // lightness or hue
+    fn chroma_elements(self) -> &'static [usize] {
+        match self {
+            ColourFormat::Lab | ColourFormat::Oklab => &[1, 2],
+            ColourFormat::Lch | ColourFormat::Oklch => &[1],
+            _ => &[],
+        }
+    }
 }
 
 impl fmt::Display for ColourFormat {
@@ -138,6 +201,9 @@ impl fmt::Display for ColourFormat {
             ColourFormat::Hsluv => write!(f, "hsluv"),
             ColourFormat::Hsv => write!(f, "hsv"),
             ColourFormat::Lab => write!(f, "lab"),
+            ColourFormat::Lch => write!(f, "lch"),
+            ColourFormat::Oklab => write!(f, "oklab"),
+            ColourFormat::Oklch => write!(f, "oklch"),
         }
     }
 }
@@ -150,6 +216,9 @@ impl Packable for Colour {
             ColourFormat::Hsluv => Mule::pack_label_sp(cursor, "HSLUV"),
             ColourFormat::Hsv => Mule::pack_label_sp(cursor, "HSV"),
             ColourFormat::Lab => Mule::pack_label_sp(cursor, "LAB"),
+            ColourFormat::Lch => Mule::pack_label_sp(cursor, "LCH"),
+            ColourFormat::Oklab => Mule::pack_label_sp(cursor, "OKLAB"),
+            ColourFormat::Oklch => Mule::pack_label_sp(cursor, "OKLCH"),
         };
 
         Mule::pack_f32_sp(cursor, self.e0);
@@ -178,6 +247,15 @@ impl Packable for Colour {
         } else if rem.starts_with("LAB ") {
             rem = Mule::skip_forward(rem, "LAB ".len());
             ColourFormat::Lab
+        } else if rem.starts_with("LCH ") {
+            rem = Mule::skip_forward(rem, "LCH ".len());
+            ColourFormat::Lch
+        } else if rem.starts_with("OKLAB ") {
+            rem = Mule::skip_forward(rem, "OKLAB ".len());
+            ColourFormat::Oklab
+        } else if rem.starts_with("OKLCH ") {
+            rem = Mule::skip_forward(rem, "OKLCH ".len());
+            ColourFormat::Oklch
         } else {
             error!("Colour::unpack invalid format");
             return Err(Error::Packable);
@@ -200,6 +278,9 @@ impl fmt::Display for Colour {
             ColourFormat::Hsluv => write!(f, "hsluv"),
             ColourFormat::Hsv => write!(f, "hsv"),
             ColourFormat::Lab => write!(f, "lab"),
+            ColourFormat::Lch => write!(f, "lch"),
+            ColourFormat::Oklab => write!(f, "oklab"),
+            ColourFormat::Oklch => write!(f, "oklch"),
         }
     }
 }
@@ -237,7 +318,8 @@ impl Colour {
 
     // hex in the form: "ff00ff"
     pub fn from_rgb_hex(hex: &str) -> Result<Self> {
-        if hex.len() != 6 {
+        // checking for ascii means that slicing by bytes can't split a character
+        if hex.len() != 6 || !hex.is_ascii() {
             error!(
                 "Colour::from_rgb_hex expects input as 6 hex digits, actual: {}",
                 hex
@@ -279,6 +361,36 @@ impl Colour {
         colour_pair(ConvertibleColour::from(self).triad())
     }
 
+    // interpolates element-wise in this colour's format, hues go the short way
+    // round. other should have the same format.
+    pub fn lerp(&self, other: &Colour, t: f32) -> Colour {
+        let mut e = [
+            mathutil::lerp(t, self.e0, other.e0),
+            mathutil::lerp(t, self.e1, other.e1),
+            mathutil::lerp(t, self.e2, other.e2),
+        ];
+
+        if let Some(h) = self.format.hue_element() {
+            let from = [self.e0, self.e1, self.e2][h];
+            let to = [other.e0, other.e1, other.e2][h];
+            let mut delta = (to - from) % 360.0;
+            if delta > 180.0 {
+                delta -= 360.0;
+            } else if delta < -180.0 {
+                delta += 360.0;
+            }
+            e[h] = (from + delta * t).rem_euclid(360.0);
+        }
+
+        Colour::new(
+            self.format,
+            e[0],
+            e[1],
+            e[2],
+            mathutil::lerp(t, self.e3, other.e3),
+        )
+    }
+
     pub fn darken(&self, value: f32) -> Result<Colour> {
         let mut c = colour(ConvertibleColour::from(self).clone_as(ColourFormat::Lab))?;
 
@@ -292,6 +404,43 @@ impl Colour {
         c.e0 = mathutil::clamp(c.e0 + value, 0.0, 100.0);
         Ok(c)
     }
+
+    pub fn is_in_srgb_gamut(&self) -> Result<bool> {
+        let rgb = self.convert(ColourFormat::Rgb)?;
+        let within = |e: f32| (-SRGB_GAMUT_TOLERANCE..=1.0 + SRGB_GAMUT_TOLERANCE).contains(&e);
+
+        Ok(within(rgb.e0) && within(rgb.e1) && within(rgb.e2))
+    }
+
+    // reduce the chroma of a colour that's outside of sRGB until it fits, the
+    // lightness and hue stay the same
+    pub fn map_into_srgb_gamut(&self) -> Result<Colour> {
+        if self.is_in_srgb_gamut()? {
+            return Ok(*self);
+        }
+
+        let with_chroma_scaled = |scale: f32| {
+            let mut es = [self.e0, self.e1, self.e2];
+            for i in self.format.chroma_elements() {
+                es[*i] *= scale;
+            }
+            Colour::new(self.format, es[0], es[1], es[2], self.e3)
+        };
+
+        // binary search for the largest scale that's in gamut
+        let mut lo = 0.0;
+        let mut hi = 1.0;
+        for _ in 0..SRGB_GAMUT_SEARCH_STEPS {
+            let mid = (lo + hi) / 2.0;
+            if with_chroma_scaled(mid).is_in_srgb_gamut()? {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        Ok(with_chroma_scaled(lo))
+    }
 }
 
 fn colour(colour: Result<ConvertibleColour>) -> Result<Colour> {
@@ -386,6 +535,7 @@ impl Default for ProcColourStateStruct {
             c: [0.0, 0.0, 0.0],
             d: [0.0, 0.0, 0.0],
             alpha: [1.0, 0.0, 0.0, 0.0],
+            stops: vec![],
         }
     }
 }
@@ -395,9 +545,9 @@ impl ProcColourStateStruct {
         match self.proc_colour_type {
             ProcColourType::ProceduralColour => Colour::new(
                 ColourFormat::Rgb,
-                self.a[0] + self.b[0] * (mathutil::TAU * (self.c[0] * t + self.d[0])).cos(),
-                self.a[1] + self.b[1] * (mathutil::TAU * (self.c[1] * t + self.d[1])).cos(),
-                self.a[2] + self.b[2] * (mathutil::TAU * (self.c[2] * t + self.d[2])).cos(),
+                self.a[0] + self.b[0] * mathutil::cos(mathutil::TAU * (self.c[0] * t + self.d[0])),
+                self.a[1] + self.b[1] * mathutil::cos(mathutil::TAU * (self.c[1] * t + self.d[1])),
+                self.a[2] + self.b[2] * mathutil::cos(mathutil::TAU * (self.c[2] * t + self.d[2])),
                 self.alpha[0],
             ),
             ProcColourType::BezierColour => {
@@ -413,7 +563,41 @@ impl ProcColourStateStruct {
                 );
                 Colour::new(ColourFormat::Rgb, r, g, b, alpha)
             }
+            ProcColourType::GradientColour => self.gradient_colour(t),
+        }
+    }
+
+    // the stops must be in the same format, which is checked when building
+    pub fn gradient(stops: Vec<GradientStop>) -> Self {
+        ProcColourStateStruct {
+            proc_colour_type: ProcColourType::GradientColour,
+            stops,
+            ..Default::default()
+        }
+    }
+
+    fn gradient_colour(&self, t: f32) -> Colour {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Colour::default(),
+        };
+
+        if t <= first.t {
+            return first.colour;
+        }
+        if t >= last.t {
+            return last.colour;
         }
+
+        // t is strictly within the stops so there's always a segment containing it
+        let i = self.stops.iter().rposition(|stop| stop.t <= t).unwrap_or(0);
+        let from = &self.stops[i];
+        let to = &self.stops[(i + 1).min(self.stops.len() - 1)];
+
+        let span = to.t - from.t;
+        let u = if span > 0.0 { (t - from.t) / span } else { 1.0 };
+
+        from.colour.lerp(&to.colour, ease::easing(u, from.easing))
     }
 }
 
@@ -453,6 +637,24 @@ impl From<&Colour> for ConvertibleColour {
                 f64::from(colour.e2),
                 f64::from(colour.e3),
             ),
+            ColourFormat::Lch => ConvertibleColour::LCH(
+                f64::from(colour.e0),
+                f64::from(colour.e1),
+                f64::from(colour.e2),
+                f64::from(colour.e3),
+            ),
+            ColourFormat::Oklab => ConvertibleColour::OKLab(
+                f64::from(colour.e0),
+                f64::from(colour.e1),
+                f64::from(colour.e2),
+                f64::from(colour.e3),
+            ),
+            ColourFormat::Oklch => ConvertibleColour::OKLCh(
+                f64::from(colour.e0),
+                f64::from(colour.e1),
+                f64::from(colour.e2),
+                f64::from(colour.e3),
+            ),
         }
     }
 }
@@ -499,6 +701,27 @@ impl TryFrom<ConvertibleColour> for Colour {
                 b as f32,
                 al as f32,
             )),
+            ConvertibleColour::LCH(l, c, h, a) => Ok(Colour::new(
+                ColourFormat::Lch,
+                l as f32,
+                c as f32,
+                h as f32,
+                a as f32,
+            )),
+            ConvertibleColour::OKLab(l, a, b, al) => Ok(Colour::new(
+                ColourFormat::Oklab,
+                l as f32,
+                a as f32,
+                b as f32,
+                al as f32,
+            )),
+            ConvertibleColour::OKLCh(l, c, h, a) => Ok(Colour::new(
+                ColourFormat::Oklch,
+                l as f32,
+                c as f32,
+                h as f32,
+                a as f32,
+            )),
             _ => {
                 error!("try_from ConvertibleColour to Colour");
                 Err(Error::Colour)
@@ -566,6 +789,9 @@ impl ConvertibleColour {
                 ColourFormat::Hsv => hsv_from_rgb(rgb_from_hsl(*self)?),
                 ColourFormat::Lab => lab_from_xyz(xyz_from_rgb(rgb_from_hsl(*self)?)?),
                 ColourFormat::Rgb => rgb_from_hsl(*self),
+                ColourFormat::Lch => lch_from_xyz(xyz_from_rgb(rgb_from_hsl(*self)?)?),
+                ColourFormat::Oklab => oklab_from_rgb(rgb_from_hsl(*self)?),
+                ColourFormat::Oklch => oklch_from_oklab(oklab_from_rgb(rgb_from_hsl(*self)?)?),
             },
             ConvertibleColour::HSLuv(h, s, l, alpha) => match format {
                 ColourFormat::Hsl => hsl_from_rgb(rgb_from_xyz(xyz_from_hsluv(*self)?)?),
@@ -573,6 +799,11 @@ impl ConvertibleColour {
                 ColourFormat::Hsv => hsv_from_rgb(rgb_from_xyz(xyz_from_hsluv(*self)?)?),
                 ColourFormat::Lab => lab_from_xyz(xyz_from_hsluv(*self)?),
                 ColourFormat::Rgb => rgb_from_xyz(xyz_from_hsluv(*self)?),
+                ColourFormat::Lch => lch_from_hsluv(*self),
+                ColourFormat::Oklab => oklab_from_rgb(rgb_from_xyz(xyz_from_hsluv(*self)?)?),
+                ColourFormat::Oklch => {
+                    oklch_from_oklab(oklab_from_rgb(rgb_from_xyz(xyz_from_hsluv(*self)?)?)?)
+                }
             },
             ConvertibleColour::HSV(h, s, v, alpha) => match format {
                 ColourFormat::Hsl => hsl_from_rgb(rgb_from_hsv(*self)?),
@@ -580,6 +811,9 @@ impl ConvertibleColour {
                 ColourFormat::Hsv => Ok(ConvertibleColour::HSV(h, s, v, alpha)),
                 ColourFormat::Lab => lab_from_xyz(xyz_from_rgb(rgb_from_hsv(*self)?)?),
                 ColourFormat::Rgb => rgb_from_hsv(*self),
+                ColourFormat::Lch => lch_from_xyz(xyz_from_rgb(rgb_from_hsv(*self)?)?),
+                ColourFormat::Oklab => oklab_from_rgb(rgb_from_hsv(*self)?),
+                ColourFormat::Oklch => oklch_from_oklab(oklab_from_rgb(rgb_from_hsv(*self)?)?),
             },
             ConvertibleColour::LAB(l, a, b, alpha) => match format {
                 ColourFormat::Hsl => hsl_from_rgb(rgb_from_xyz(xyz_from_lab(*self)?)?),
@@ -587,6 +821,11 @@ impl ConvertibleColour {
                 ColourFormat::Hsv => hsv_from_rgb(rgb_from_xyz(xyz_from_lab(*self)?)?),
                 ColourFormat::Lab => Ok(ConvertibleColour::LAB(l, a, b, alpha)),
                 ColourFormat::Rgb => rgb_from_xyz(xyz_from_lab(*self)?),
+                ColourFormat::Lch => lch_from_xyz(xyz_from_lab(*self)?),
+                ColourFormat::Oklab => oklab_from_rgb(rgb_from_xyz(xyz_from_lab(*self)?)?),
+                ColourFormat::Oklch => {
+                    oklch_from_oklab(oklab_from_rgb(rgb_from_xyz(xyz_from_lab(*self)?)?)?)
+                }
             },
             ConvertibleColour::LinearRGB(r, g, b, alpha) => match format {
                 ColourFormat::Hsl => hsl_from_rgb(*self),
@@ -594,6 +833,25 @@ impl ConvertibleColour {
                 ColourFormat::Hsv => hsv_from_rgb(*self),
                 ColourFormat::Lab => lab_from_xyz(xyz_from_rgb(*self)?),
                 ColourFormat::Rgb => Ok(ConvertibleColour::LinearRGB(r, g, b, alpha)),
+                ColourFormat::Lch => lch_from_xyz(xyz_from_rgb(*self)?),
+                ColourFormat::Oklab => oklab_from_rgb(*self),
+                ColourFormat::Oklch => oklch_from_oklab(oklab_from_rgb(*self)?),
+            },
+            // the remaining formats go through linear RGB
+            ConvertibleColour::LCH(l, c, h, alpha) => match format {
+                ColourFormat::Lch => Ok(ConvertibleColour::LCH(l, c, h, alpha)),
+                ColourFormat::Hsluv => hsluv_from_lch(*self),
+                _ => rgb_from_xyz(xyz_from_lch(*self)?)?.clone_as(format),
+            },
+            ConvertibleColour::OKLab(l, a, b, alpha) => match format {
+                ColourFormat::Oklab => Ok(ConvertibleColour::OKLab(l, a, b, alpha)),
+                ColourFormat::Oklch => oklch_from_oklab(*self),
+                _ => rgb_from_oklab(*self)?.clone_as(format),
+            },
+            ConvertibleColour::OKLCh(l, c, h, alpha) => match format {
+                ColourFormat::Oklch => Ok(ConvertibleColour::OKLCh(l, c, h, alpha)),
+                ColourFormat::Oklab => oklab_from_oklch(*self),
+                _ => rgb_from_oklab(oklab_from_oklch(*self)?)?.clone_as(format),
             },
             _ => Err(Error::Colour),
         }
@@ -602,7 +860,7 @@ impl ConvertibleColour {
 
 fn gamma_expansion(component: f64) -> f64 {
     if component > 0.04045 {
-        ((component + 0.055) / 1.055).powf(2.4)
+        mathutil::powf_f64((component + 0.055) / 1.055, 2.4)
     } else {
         component / 12.92
     }
@@ -610,7 +868,7 @@ fn gamma_expansion(component: f64) -> f64 {
 
 fn gamma_correction(a: f64) -> f64 {
     if a > 0.003_130_8 {
-        (1.055 * a.powf(1.0 / 2.4)) - 0.055
+        (1.055 * mathutil::powf_f64(a, 1.0 / 2.4)) - 0.055
     } else {
         a * 12.92
     }
@@ -644,7 +902,7 @@ fn rgb_from_xyz(xyz: ConvertibleColour) -> Result<ConvertibleColour> {
 
 fn axis_to_lab_component(a: f64) -> f64 {
     if a > CIE_EPSILON {
-        a.cbrt()
+        mathutil::cbrt_f64(a)
     } else {
         ((CIE_KAPPA * a) + 16.0) / 116.0
     }
@@ -824,8 +1082,8 @@ fn rgb_from_hsl(hsl: ConvertibleColour) -> Result<ConvertibleColour> {
 }
 
 fn lab_component_to_axis(l: f64) -> f64 {
-    if l.powf(3.0) > CIE_EPSILON {
-        l.powf(3.0)
+    if mathutil::powf_f64(l, 3.0) > CIE_EPSILON {
+        mathutil::powf_f64(l, 3.0)
     } else {
         ((116.0 * l) - 16.0) / CIE_KAPPA
     }
@@ -943,7 +1201,7 @@ fn get_bounds(l: f64, bounds: &mut [Bounds]) {
 }
 
 fn ray_length_until_intersect(theta: f64, line: &Bounds) -> f64 {
-    line.b / (theta.sin() - line.a * theta.cos())
+    line.b / (mathutil::sin_f64(theta) - line.a * mathutil::cos_f64(theta))
 }
 
 fn max_chroma_for_lh(l: f64, h: f64) -> f64 {
@@ -973,7 +1231,7 @@ fn y2l(y: f64) -> f64 {
     if y <= CIE_EPSILON {
         y * CIE_KAPPA
     } else {
-        116.0 * y.cbrt() - 16.0
+        116.0 * mathutil::cbrt_f64(y) - 16.0
     }
 }
 
@@ -1033,7 +1291,7 @@ fn lch_from_luv(luv: ConvertibleColour) -> Result<ConvertibleColour> {
             if c < 0.000_000_01 {
                 h = 0.0;
             } else {
-                h = v.atan2(u) * 57.295_779_513_082_320_876_80; /* (180 / pi) */
+                h = mathutil::atan2_f64(v, u) * 57.295_779_513_082_320_876_80; /* (180 / pi) */
                 if h < 0.0 {
                     h += 360.0;
                 }
@@ -1049,8 +1307,8 @@ fn luv_from_lch(lch: ConvertibleColour) -> Result<ConvertibleColour> {
     match lch {
         ConvertibleColour::LCH(l, c, h, alpha) => {
             let hrad = h * 0.017_453_292_519_943_295_77; /* (pi / 180.0) */
-            let u = hrad.cos() * c;
-            let v = hrad.sin() * c;
+            let u = mathutil::cos_f64(hrad) * c;
+            let v = mathutil::sin_f64(hrad) * c;
 
             Ok(ConvertibleColour::LUV(l, u, v, alpha))
         }
@@ -1104,6 +1362,95 @@ fn hsluv_from_xyz(xyz: ConvertibleColour) -> Result<ConvertibleColour> {
     hsluv_from_lch(lch_from_luv(luv_from_xyz(xyz)?)?)
 }
 
+fn xyz_from_lch(lch: ConvertibleColour) -> Result<ConvertibleColour> {
+    xyz_from_luv(luv_from_lch(lch)?)
+}
+
+fn lch_from_xyz(xyz: ConvertibleColour) -> Result<ConvertibleColour> {
+    lch_from_luv(luv_from_xyz(xyz)?)
+}
+
+// https://bottosson.github.io/posts/oklab/
+fn oklab_from_rgb(rgb: ConvertibleColour) -> Result<ConvertibleColour> {
+    match rgb {
+        ConvertibleColour::LinearRGB(r, g, b, alpha) => {
+            let l = 0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b;
+            let m = 0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b;
+            let s = 0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b;
+
+            let l = mathutil::cbrt_f64(l);
+            let m = mathutil::cbrt_f64(m);
+            let s = mathutil::cbrt_f64(s);
+
+            Ok(ConvertibleColour::OKLab(
+                0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+                1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+                0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+                alpha,
+            ))
+        }
+        _ => Err(Error::Colour),
+    }
+}
+
+fn rgb_from_oklab(oklab: ConvertibleColour) -> Result<ConvertibleColour> {
+    match oklab {
+        ConvertibleColour::OKLab(l, a, b, alpha) => {
+            let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
+            let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
+            let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
+
+            let l = l_ * l_ * l_;
+            let m = m_ * m_ * m_;
+            let s = s_ * s_ * s_;
+
+            Ok(ConvertibleColour::LinearRGB(
+                4.076_741_662_1 * l - 3.307_711_591_3 * m + 0.230_969_929_2 * s,
+                -1.268_438_004_6 * l + 2.609_757_401_1 * m - 0.341_319_396_5 * s,
+                -0.004_196_086_3 * l - 0.703_418_614_7 * m + 1.707_614_701_0 * s,
+                alpha,
+            ))
+        }
+        _ => Err(Error::Colour),
+    }
+}
+
+fn oklch_from_oklab(oklab: ConvertibleColour) -> Result<ConvertibleColour> {
+    match oklab {
+        ConvertibleColour::OKLab(l, a, b, alpha) => {
+            let c = (a * a + b * b).sqrt();
+
+            // greys aren't quite at zero chroma due to the precision of the matrices
+            let h = if c < 0.000_001 {
+                0.0
+            } else {
+                let h = mathutil::atan2_f64(b, a).to_degrees();
+                if h < 0.0 {
+                    h + 360.0
+                } else {
+                    h
+                }
+            };
+
+            Ok(ConvertibleColour::OKLCh(l, c, h, alpha))
+        }
+        _ => Err(Error::Colour),
+    }
+}
+
+fn oklab_from_oklch(oklch: ConvertibleColour) -> Result<ConvertibleColour> {
+    match oklch {
+        ConvertibleColour::OKLCh(l, c, h, alpha) => {
+            let hrad = h.to_radians();
+            let a = mathutil::cos_f64(hrad) * c;
+            let b = mathutil::sin_f64(hrad) * c;
+
+            Ok(ConvertibleColour::OKLab(l, a, b, alpha))
+        }
+        _ => Err(Error::Colour),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -1185,6 +1532,27 @@ mod tests {
                 f64_within(TOLERANCE, b, c2, "RGB_B");
                 f64_within(TOLERANCE, alpha, c3, "RGB_alpha");
             }
+            ConvertibleColour::LCH(l, c, h, alpha) => {
+                is_format(format, ColourFormat::Lch);
+                f64_within(TOLERANCE, l, c0, "LCH_L");
+                f64_within(TOLERANCE, c, c1, "LCH_C");
+                f64_within(TOLERANCE, h, c2, "LCH_H");
+                f64_within(TOLERANCE, alpha, c3, "LCH_alpha");
+            }
+            ConvertibleColour::OKLab(l, a, b, alpha) => {
+                is_format(format, ColourFormat::Oklab);
+                f64_within(TOLERANCE, l, c0, "OKLAB_L");
+                f64_within(TOLERANCE, a, c1, "OKLAB_A");
+                f64_within(TOLERANCE, b, c2, "OKLAB_B");
+                f64_within(TOLERANCE, alpha, c3, "OKLAB_alpha");
+            }
+            ConvertibleColour::OKLCh(l, c, h, alpha) => {
+                is_format(format, ColourFormat::Oklch);
+                f64_within(TOLERANCE, l, c0, "OKLCH_L");
+                f64_within(TOLERANCE, c, c1, "OKLCH_C");
+                f64_within(TOLERANCE, h, c2, "OKLCH_H");
+                f64_within(TOLERANCE, alpha, c3, "OKLCH_alpha");
+            }
             _ => assert_eq!(true, false),
         }
     }
@@ -1206,6 +1574,15 @@ mod tests {
             ConvertibleColour::LinearRGB(r, g, b, alpha) => {
                 assert_col(col, ColourFormat::Rgb, r, g, b, alpha)
             }
+            ConvertibleColour::LCH(l, c, h, alpha) => {
+                assert_col(col, ColourFormat::Lch, l, c, h, alpha)
+            }
+            ConvertibleColour::OKLab(l, a, b, alpha) => {
+                assert_col(col, ColourFormat::Oklab, l, a, b, alpha)
+            }
+            ConvertibleColour::OKLCh(l, c, h, alpha) => {
+                assert_col(col, ColourFormat::Oklch, l, c, h, alpha)
+            }
             _ => assert_eq!(true, false),
         }
     }
@@ -1268,6 +1645,73 @@ mod tests {
         assert_colour_rgb_hsl_match(0.0, 0.0, 0.5, 240.0, 1.0, 0.25);
     }
 
+    #[test]
+    fn test_colour_ok() {
+        let rgb = ConvertibleColour::LinearRGB(1.0, 0.0, 0.0, 1.0);
+        let lch = ConvertibleColour::LCH(53.24, 179.086, 12.17, 1.0);
+        let oklab = ConvertibleColour::OKLab(0.627_955, 0.224_863, 0.125_846, 1.0);
+        let oklch = ConvertibleColour::OKLCh(0.627_955, 0.257_683, 29.233_885, 1.0);
+
+        assert_colour_match(lch, rgb.clone_as(ColourFormat::Lch).unwrap());
+        assert_colour_match(oklab, rgb.clone_as(ColourFormat::Oklab).unwrap());
+        assert_colour_match(oklch, rgb.clone_as(ColourFormat::Oklch).unwrap());
+        assert_colour_match(oklch, oklab.clone_as(ColourFormat::Oklch).unwrap());
+
+        assert_colour_match(rgb, lch.clone_as(ColourFormat::Rgb).unwrap());
+        assert_colour_match(rgb, oklab.clone_as(ColourFormat::Rgb).unwrap());
+        assert_colour_match(rgb, oklch.clone_as(ColourFormat::Rgb).unwrap());
+
+        // via the other formats
+        let hsl = ConvertibleColour::HSL(0.0, 1.0, 0.5, 1.0);
+        assert_colour_match(oklch, hsl.clone_as(ColourFormat::Oklch).unwrap());
+        assert_colour_match(hsl, oklch.clone_as(ColourFormat::Hsl).unwrap());
+        let hsluv = lch.clone_as(ColourFormat::Hsluv).unwrap();
+        assert_colour_match(lch, hsluv.clone_as(ColourFormat::Lch).unwrap());
+
+        // white has no chroma
+        let white = ConvertibleColour::LinearRGB(1.0, 1.0, 1.0, 1.0);
+        let oklch = ConvertibleColour::OKLCh(1.0, 0.0, 0.0, 1.0);
+        assert_colour_match(oklch, white.clone_as(ColourFormat::Oklch).unwrap());
+    }
+
+    #[test]
+    fn test_colour_lerp() {
+        let a = Colour::new(ColourFormat::Rgb, 0.0, 0.5, 1.0, 1.0);
+        let b = Colour::new(ColourFormat::Rgb, 1.0, 0.5, 0.0, 0.0);
+        assert_colour(a.lerp(&b, 0.25), ColourFormat::Rgb, 0.25, 0.5, 0.75, 0.75);
+
+        // hues take the shortest way round
+        let a = Colour::new(ColourFormat::Hsl, 350.0, 1.0, 0.5, 1.0);
+        let b = Colour::new(ColourFormat::Hsl, 30.0, 1.0, 0.5, 1.0);
+        assert_colour(a.lerp(&b, 0.25), ColourFormat::Hsl, 0.0, 1.0, 0.5, 1.0);
+        assert_colour(b.lerp(&a, 0.5), ColourFormat::Hsl, 10.0, 1.0, 0.5, 1.0);
+
+        let a = Colour::new(ColourFormat::Oklch, 0.5, 0.1, 20.0, 1.0);
+        let b = Colour::new(ColourFormat::Oklch, 0.5, 0.1, 300.0, 1.0);
+        assert_colour(a.lerp(&b, 0.5), ColourFormat::Oklch, 0.5, 0.1, 340.0, 1.0);
+    }
+
+    #[test]
+    fn test_colour_map_into_srgb_gamut() {
+        let inside = Colour::new(ColourFormat::Oklch, 0.6, 0.1, 30.0, 1.0);
+        assert!(inside.is_in_srgb_gamut().unwrap());
+        let mapped = inside.map_into_srgb_gamut().unwrap();
+        assert_colour(mapped, ColourFormat::Oklch, 0.6, 0.1, 30.0, 1.0);
+
+        // the chroma is reduced but the lightness and hue stay the same
+        let outside = Colour::new(ColourFormat::Oklch, 0.6, 0.4, 150.0, 1.0);
+        assert!(!outside.is_in_srgb_gamut().unwrap());
+        let mapped = outside.map_into_srgb_gamut().unwrap();
+        assert!(mapped.is_in_srgb_gamut().unwrap());
+        assert!(mapped.e1 > 0.05 && mapped.e1 < 0.4);
+        assert_eq!((mapped.e0, mapped.e2), (0.6, 150.0));
+
+        let outside = Colour::new(ColourFormat::Lab, 50.0, 120.0, -120.0, 1.0);
+        let mapped = outside.map_into_srgb_gamut().unwrap();
+        assert!(mapped.is_in_srgb_gamut().unwrap());
+        assert!(mapped.e1 < 120.0 && (mapped.e1 + mapped.e2).abs() < 0.001);
+    }
+
     #[test]
     fn test_colour_pack() {
         let mut res: String = "".into();
@@ -1280,11 +1724,22 @@ mod tests {
     fn test_colour_unpack() {
         let (res, _rem) = Colour::unpack("RGB 1.1 2.2 3.3 4.4").unwrap();
         assert_colour(res, ColourFormat::Rgb, 1.1, 2.2, 3.3, 4.4);
+
+        let mut packed: String = "".into();
+        let col = Colour::new(ColourFormat::Oklch, 0.5, 0.1, 200.0, 1.0);
+        col.pack(&mut packed).unwrap();
+        assert_eq!("OKLCH 0.5 0.1 200 1", packed);
+        let (res, _rem) = Colour::unpack(&packed).unwrap();
+        assert_colour(res, ColourFormat::Oklch, 0.5, 0.1, 200.0, 1.0);
     }
 
     #[test]
     fn test_hex_colour_parsing() {
         assert_hex_colour("ff00ff", ColourFormat::Rgb, 1.0, 0.0, 1.0, 1.0);
+
+        // 6 bytes that aren't 6 characters
+        assert!(Colour::from_rgb_hex("aé€").is_err());
+        assert!(Colour::from_rgb_hex("ff00f").is_err());
     }
 
     fn assert_equal_f64(a: f64, b: f64) {
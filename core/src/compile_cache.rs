@@ -0,0 +1,236 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::compiler::{
+    compile_preamble, compile_program_with_gene_slots, compile_program_with_genotype, GeneSlot,
+};
+use crate::error::{Error, Result};
+use crate::gene::{Gene, Genotype};
+use crate::node::Node;
+use crate::parser::{parse, WordLut};
+use crate::program::{BytecodeArg, Program};
+
+use log::error;
+
+/// A script that has been compiled once, new variants are created by
+/// patching gene values into the constant slots of the compiled bytecode.
+pub struct ProgramTemplate {
+    ast: Vec<Node>,
+    word_lut: WordLut,
+
+    program: Program,
+    genotype: Genotype,
+    gene_slots: Vec<GeneSlot>,
+
+    // genes that have at least one slot in the bytecode. The others (e.g. a
+    // name that selects which function is called) alter the structure of the
+    // program so changing them requires a full compile
+    patchable: Vec<bool>,
+}
+
+impl ProgramTemplate {
+    pub fn new(s: &str, genotype: &Genotype) -> Result<Self> {
+        let (mut ast, word_lut) = parse(s)?;
+
+        let mut genotype = genotype.clone();
+        let (program, gene_slots) =
+            compile_program_with_gene_slots(&mut ast, &word_lut, &mut genotype)?;
+
+        let mut patchable = vec![false; genotype.num_genes()];
+        for slot in &gene_slots {
+            patchable[slot.gene_index] = true;
+        }
+
+        Ok(ProgramTemplate {
+            ast,
+            word_lut,
+            program,
+            genotype,
+            gene_slots,
+            patchable,
+        })
+    }
+
+    pub fn program_for_genotype(&self, genotype: &Genotype) -> Result<Program> {
+        if genotype.num_genes() != self.genotype.num_genes() {
+            error!(
+                "program_for_genotype: expected {} genes, given {}",
+                self.genotype.num_genes(),
+                genotype.num_genes()
+            );
+            return Err(Error::Gene);
+        }
+
+        let requires_compile = self
+            .patchable
+            .iter()
+            .zip(genotype.genes.iter().zip(self.genotype.genes.iter()))
+            .any(|(patchable, (gene, template_gene))| !patchable && gene != template_gene);
+
+        if requires_compile {
+            let mut ast = self.ast.clone();
+            let mut genotype = genotype.clone();
+            return compile_program_with_genotype(&mut ast, &self.word_lut, &mut genotype);
+        }
+
+        let mut program = self.program.clone();
+        for slot in &self.gene_slots {
+            patch_gene_slot(&mut program, slot, &genotype.genes[slot.gene_index])?;
+        }
+
+        Ok(program)
+    }
+}
+
+fn patch_gene_slot(program: &mut Program, slot: &GeneSlot, gene: &Gene) -> Result<()> {
+    let i = slot.code_index;
+    match gene {
+        Gene::Float(f) => program.code[i].arg1 = BytecodeArg::Float(*f),
+        Gene::Colour(col) => program.code[i].arg1 = BytecodeArg::Colour(*col),
+        Gene::V2D(x, y) => {
+            program.code[i].arg1 = BytecodeArg::Float(*x);
+            program.code[i + 1].arg1 = BytecodeArg::Float(*y);
+        }
+        _ => {
+            error!(
+                "patch_gene_slot: gene can't be patched into bytecode {:?}",
+                gene
+            );
+            return Err(Error::Gene);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps the compiled preamble and a template for the most recent script that
+/// has been compiled with a genotype. Scripts are edited live so only the
+/// latest source is worth keeping, an older template is replaced rather than
+/// being kept around.
+#[derive(Default)]
+pub struct CompileCache {
+    preamble: Option<Program>,
+    template: Option<(String, ProgramTemplate)>,
+}
+
+impl CompileCache {
+    pub fn preamble(&mut self) -> Result<&Program> {
+        if self.preamble.is_none() {
+            self.preamble = Some(compile_preamble()?);
+        }
+        self.preamble.as_ref().ok_or(Error::Compiler)
+    }
+
+    pub fn program_from_source_and_genotype(
+        &mut self,
+        s: &str,
+        genotype: &Genotype,
+    ) -> Result<Program> {
+        let is_cached = match &self.template {
+            Some((source, _)) => source == s,
+            None => false,
+        };
+        if !is_cached {
+            let template = ProgramTemplate::new(s, genotype)?;
+            self.template = Some((s.to_string(), template));
+        }
+
+        match &self.template {
+            Some((_, template)) => template.program_for_genotype(genotype),
+            None => Err(Error::Compiler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_traits, program_from_source_and_genotype};
+
+    // returns the number of genes that can be patched
+    fn assert_same_as_full_compile(s: &str, seeds: &[i32]) -> usize {
+        let trait_list = build_traits(s).unwrap();
+        let mut cache: CompileCache = Default::default();
+
+        for seed in seeds {
+            let mut genotype = Genotype::build_from_seed(&trait_list, *seed).unwrap();
+            let patched = cache
+                .program_from_source_and_genotype(s, &genotype)
+                .unwrap();
+            let compiled = program_from_source_and_genotype(s, &mut genotype).unwrap();
+
+            assert_eq!(patched.code, compiled.code);
+        }
+
+        let (_, template) = cache.template.as_ref().unwrap();
+        template.patchable.iter().filter(|p| **p).count()
+    }
+
+    #[test]
+    fn test_compile_cache_patches_constants() {
+        let num_patchable = assert_same_as_full_compile(
+            "(rect position: [500 500]
+                   width: 100 ~ (gen/scalar min: 10 max: 200)
+                   height: 200 ~ (gen/int min: 10 max: 300)
+                   colour: (col/rgb r: 0 g: 0 b: 1 alpha: 1) ~ (gen/col))
+             (define v [1 2] ~ (gen/stray-2d from: [1 2] by: [1 1]))",
+            &[1, 2, 3, 42, 977],
+        );
+        assert_eq!(num_patchable, 5);
+    }
+
+    #[test]
+    fn test_compile_cache_falls_back_to_compile() {
+        // the selected name changes the structure of the program
+        let num_patchable = assert_same_as_full_compile(
+            "(define a 2.3 b 3.4 c 4.5 d 5.6)
+             (fn (f x: 1) (+ x 3 ~ (gen/scalar min: 1 max: 10)))
+             (f x: a ~ (gen/select from: '(a b c d)))",
+            &[1, 2, 3, 4, 5, 6, 7],
+        );
+        assert_eq!(num_patchable, 1);
+    }
+
+    #[test]
+    fn test_compile_cache_only_keeps_latest_source() {
+        let mut cache: CompileCache = Default::default();
+        let sources = [
+            "(rect width: 100 ~ (gen/scalar min: 10 max: 200))",
+            "(rect width: 150 ~ (gen/scalar min: 10 max: 200))",
+        ];
+
+        for s in sources.iter() {
+            let trait_list = build_traits(s).unwrap();
+            let mut genotype = Genotype::build_from_seed(&trait_list, 3).unwrap();
+            let patched = cache
+                .program_from_source_and_genotype(s, &genotype)
+                .unwrap();
+            let compiled = program_from_source_and_genotype(s, &mut genotype).unwrap();
+            assert_eq!(patched.code, compiled.code);
+
+            let (source, _) = cache.template.as_ref().unwrap();
+            assert_eq!(source, s);
+        }
+    }
+
+    #[test]
+    fn test_compile_cache_preamble() {
+        let mut cache: CompileCache = Default::default();
+        let len = cache.preamble().unwrap().code.len();
+        assert_eq!(len, compile_preamble().unwrap().code.len());
+    }
+}
@@ -44,6 +44,7 @@ pub fn compile_preamble() -> Result<Program> {
     Ok(Program {
         code: c.code,
         fn_info: c.fn_info,
+        code_lines: c.code_lines,
         ..Default::default()
     })
 }
@@ -60,6 +61,7 @@ pub fn compile_program(ast: &[Node], word_lut: &WordLut) -> Result<Program> {
     Ok(Program {
         code: c.code,
         fn_info: c.fn_info,
+        code_lines: c.code_lines,
         data,
     })
 }
@@ -76,6 +78,7 @@ pub fn compile_program_1(ast_node: &Node, word_lut: &WordLut) -> Result<Program>
     Ok(Program {
         code: c.code,
         fn_info: c.fn_info,
+        code_lines: c.code_lines,
         data,
     })
 }
@@ -106,6 +109,7 @@ pub fn compile_program_for_trait(
     Ok(Program {
         code: c.code,
         fn_info: c.fn_info,
+        code_lines: c.code_lines,
         data,
     })
 }
@@ -115,6 +119,17 @@ pub fn compile_program_with_genotype(
     word_lut: &WordLut,
     genotype: &mut Genotype,
 ) -> Result<Program> {
+    let (program, _) = compile_program_with_gene_slots(ast, word_lut, genotype)?;
+
+    Ok(program)
+}
+
+// also returns the locations in the bytecode where each gene's value was loaded
+pub(crate) fn compile_program_with_gene_slots(
+    ast: &mut [Node],
+    word_lut: &WordLut,
+    genotype: &mut Genotype,
+) -> Result<(Program, Vec<GeneSlot>)> {
     let mut c: Compilation = Default::default();
     let mut compiler: Compiler = Default::default();
 
@@ -125,11 +140,14 @@ pub fn compile_program_with_genotype(
     let mut data: Data = Default::default();
     data.strings = word_lut.get_script_inames();
 
-    Ok(Program {
+    let program = Program {
         code: c.code,
         fn_info: c.fn_info,
+        code_lines: c.code_lines,
         data,
-    })
+    };
+
+    Ok((program, c.gene_slots))
 }
 
 fn assign_genotype_to_ast(ast: &mut [Node], genotype: &mut Genotype) -> Result<()> {
@@ -143,13 +161,14 @@ fn assign_genotype_to_ast(ast: &mut [Node], genotype: &mut Genotype) -> Result<(
 }
 
 fn assign_gene(n: &Node, genotype: &mut Genotype) -> Result<Node> {
+    let gene_index = genotype.current_gene_index;
     match n {
         Node::Vector(meta, ns) => Ok(Node::Vector(
-            meta.new_with_gene(genotype.clone_next_gene()?),
+            meta.new_with_gene(genotype.clone_next_gene()?, gene_index),
             ns.clone(),
         )),
         Node::Float(meta, f, s) => Ok(Node::Float(
-            meta.new_with_gene(genotype.clone_next_gene()?),
+            meta.new_with_gene(genotype.clone_next_gene()?, gene_index),
             *f,
             s.to_string(),
         )),
@@ -175,6 +194,7 @@ fn assign_genes_to_nodes(node: &mut Node, genotype: &mut Genotype) -> Result<()>
     match node {
         Node::List(meta, ref mut ns) => {
             if let Some(ref mut gene_info) = meta.gene_info {
+                gene_info.gene_index = Some(genotype.current_gene_index);
                 gene_info.gene = Some(genotype.clone_next_gene()?);
             }
             for n in ns {
@@ -204,6 +224,7 @@ fn assign_genes_to_nodes(node: &mut Node, genotype: &mut Genotype) -> Result<()>
         | Node::Whitespace(meta, _)
         | Node::Comment(meta, _) => {
             if let Some(ref mut gene_info) = meta.gene_info {
+                gene_info.gene_index = Some(genotype.current_gene_index);
                 gene_info.gene = Some(genotype.clone_next_gene()?);
             }
         }
@@ -224,10 +245,24 @@ fn is_node_colour_constructor(children: &[&Node]) -> bool {
     false
 }
 
+// a bytecode whose arg1 is a constant loaded from the gene at gene_index
+// (Gene::V2D values occupy the two bytecodes starting at code_index)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GeneSlot {
+    pub gene_index: usize,
+    pub code_index: usize,
+}
+
 #[derive(Debug)]
 pub struct Compilation {
     code: Vec<Bytecode>,
 
+    // the source line of each bytecode in code
+    code_lines: Vec<usize>,
+    current_line: usize,
+
+    gene_slots: Vec<GeneSlot>,
+
     fn_info: Vec<FnInfo>,
     current_fn_info_index: Option<usize>,
     opcode_offset: i32,
@@ -257,6 +292,11 @@ impl Default for Compilation {
         Compilation {
             code: Vec::new(),
 
+            code_lines: Vec::new(),
+            current_line: 0,
+
+            gene_slots: Vec::new(),
+
             fn_info: Vec::new(),
             current_fn_info_index: None,
             opcode_offset: 0,
@@ -354,9 +394,20 @@ impl Compilation {
 
     fn add_bytecode(&mut self, bc: Bytecode) -> Result<()> {
         self.code.push(bc);
+        self.code_lines.push(self.current_line);
         Ok(())
     }
 
+    // call before emitting the bytecode that loads the node's gene value
+    fn add_gene_slot(&mut self, node: &Node) {
+        if let Some(gene_index) = node.get_gene_index() {
+            self.gene_slots.push(GeneSlot {
+                gene_index,
+                code_index: self.code.len(),
+            });
+        }
+    }
+
     fn get_fn_info_index(&self, node: &Node) -> Option<usize> {
         if let Node::Name(_, text, _) = node {
             for (i, fi) in self.fn_info.iter().enumerate() {
@@ -1121,6 +1172,16 @@ impl Compiler {
     }
 
     fn compile(&self, c: &mut Compilation, ast: &Node) -> Result<()> {
+        // record the source line of every bytecode emitted for this node
+        let outer_line = c.current_line;
+        c.current_line = ast.get_location().line;
+        let res = self.compile_node(c, ast);
+        c.current_line = outer_line;
+
+        res
+    }
+
+    fn compile_node(&self, c: &mut Compilation, ast: &Node) -> Result<()> {
         // todo: move this out of compile and into the c struct
         match ast {
             Node::List(meta, children) => {
@@ -1133,6 +1194,7 @@ impl Compiler {
                     // we have an alterable colour constructor so just load in the colour value stored in the gene
                     //
                     let col = self.get_colour(ast)?;
+                    c.add_gene_slot(ast);
                     c.emit(Opcode::LOAD, Mem::Constant, col)?;
                 } else {
                     if self.use_genes && meta.gene_info.is_some() {
@@ -1146,6 +1208,9 @@ impl Compiler {
             }
             Node::Float(_, _, _) => {
                 let f = self.get_float(ast)?;
+                if self.use_genes {
+                    c.add_gene_slot(ast);
+                }
                 return c.emit(Opcode::LOAD, Mem::Constant, f);
             }
             Node::Vector(_, children) => {
@@ -1446,6 +1511,10 @@ impl Compiler {
                 c.opcode_offset -= (num_args + 1) - 1;
             }
             Var::Keyword(kw) => c.emit(Opcode::LOAD, Mem::Constant, *kw)?,
+            Var::Bool(b) => {
+                let kw = if *b { Keyword::True } else { Keyword::False };
+                c.emit(Opcode::LOAD, Mem::Constant, kw)?
+            }
             // Var::Vector(vs) => {
             //     // pushing from the VOID means creating a new, empty vector
             //     c.emit(Opcode::LOAD, Mem::Void, 0)?;
@@ -2377,6 +2446,7 @@ impl Compiler {
         match node {
             Node::Float(_, _, _) => {
                 let f = self.get_float(node)?;
+                c.add_gene_slot(node);
                 c.emit(Opcode::LOAD, Mem::Constant, f)?;
             }
             Node::Vector(_, _elements) => {
@@ -2399,6 +2469,7 @@ impl Compiler {
 
         if node.has_gene() && use_gene {
             let (a, b) = self.get_2d(node)?;
+            c.add_gene_slot(node);
             c.emit(Opcode::LOAD, Mem::Constant, a)?;
             c.emit(Opcode::LOAD, Mem::Constant, b)?;
         } else {
@@ -2715,6 +2786,14 @@ mod tests {
         }
     }
 
+    fn load_const_bool(val: bool) -> Bytecode {
+        Bytecode {
+            op: Opcode::LOAD,
+            arg0: BytecodeArg::Mem(Mem::Constant),
+            arg1: BytecodeArg::Keyword(if val { Keyword::True } else { Keyword::False }),
+        }
+    }
+
     fn load_const_i32(val: i32) -> Bytecode {
         Bytecode {
             op: Opcode::LOAD,
@@ -3220,6 +3299,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_native_bool_default() {
+        // omitted arguments with a bool default are loaded as keywords
+        assert_eq!(
+            compile("(col/define-palette)"),
+            vec![
+                jump(1),
+                load_const_i32(3),
+                load_const_bool(false),
+                load_const_bool(false),
+                native(Native::ColDefinePalette, 2),
+                stop(),
+            ]
+        );
+
+        assert_eq!(
+            compile("(col/define-palette name: 1 colours: 2)"),
+            vec![
+                jump(1),
+                load_const_i32(0),
+                load_const_f32(2.0),
+                load_const_f32(1.0),
+                native(Native::ColDefinePalette, 2),
+                stop(),
+            ]
+        );
+    }
+
     #[test]
     fn test_fn_invocation_2() {
         let expected_bytecode = vec![
@@ -19,10 +19,15 @@ use crate::bitmap_cache::BitmapCache;
 use crate::colour::Colour;
 use crate::ease::Easing;
 use crate::error::{Error, Result};
+use crate::flow::SeparationGrid;
 use crate::geometry;
+use crate::geometry::polyline::{CapStyle, JoinStyle};
 use crate::matrix::MatrixStack;
+use crate::palette_cache::PaletteCache;
 use crate::render_list::{RPCommand, RenderList};
-use crate::render_packet::{RenderPacketGeometry, RenderPacketImage, RenderPacketMask};
+use crate::render_packet::{
+    RenderPacketBlend, RenderPacketGeometry, RenderPacketImage, RenderPacketLayer, RenderPacketMask,
+};
 use crate::rgb::Rgb;
 use crate::uvmapper::{BrushType, Mappings};
 use crate::vm::Var;
@@ -33,6 +38,8 @@ pub struct Context {
     pub mappings: Mappings,
     pub render_list: RenderList,
     pub bitmap_cache: BitmapCache,
+    pub palette_cache: PaletteCache,
+    pub separation_grid: SeparationGrid,
 }
 
 impl Default for Context {
@@ -42,6 +49,8 @@ impl Default for Context {
             mappings: Default::default(),
             render_list: Default::default(),
             bitmap_cache: Default::default(),
+            palette_cache: Default::default(),
+            separation_grid: Default::default(),
         }
     }
 }
@@ -54,6 +63,8 @@ impl Context {
     pub fn reset_for_piece(&mut self) {
         self.matrix_stack.reset();
         self.render_list.reset();
+        self.separation_grid.reset();
+        self.palette_cache.reset();
     }
 
     pub fn push_rp_mask(&mut self, render_packet_mask: RenderPacketMask) -> Result<()> {
@@ -64,6 +75,18 @@ impl Context {
         self.render_list.push_rp_image(render_packet_image)
     }
 
+    pub fn push_rp_blend(&mut self, render_packet_blend: RenderPacketBlend) -> Result<()> {
+        self.render_list.push_rp_blend(render_packet_blend)
+    }
+
+    pub fn push_rp_layer_begin(&mut self, render_packet_layer: RenderPacketLayer) -> Result<()> {
+        self.render_list.push_rp_layer_begin(render_packet_layer)
+    }
+
+    pub fn push_rp_layer_end(&mut self) -> Result<()> {
+        self.render_list.push_rp_layer_end()
+    }
+
     pub fn get_rp_command(&self, packet_number: usize) -> Result<RPCommand> {
         self.render_list.get_rp_command(packet_number)
     }
@@ -76,6 +99,14 @@ impl Context {
         self.render_list.get_rp_image(packet_number)
     }
 
+    pub fn get_rp_blend(&self, packet_number: usize) -> Result<&RenderPacketBlend> {
+        self.render_list.get_rp_blend(packet_number)
+    }
+
+    pub fn get_rp_layer(&self, packet_number: usize) -> Result<&RenderPacketLayer> {
+        self.render_list.get_rp_layer(packet_number)
+    }
+
     pub fn get_rp_geometry(&self, packet_number: usize) -> Result<&RenderPacketGeometry> {
         self.render_list.get_rp_geometry(packet_number)
     }
@@ -231,22 +262,181 @@ impl Context {
         }
     }
 
-    pub fn render_poly(&mut self, coords: &[Var], colours: &[Var]) -> Result<()> {
+    pub fn render_ellipse(
+        &mut self,
+        position: (f32, f32),
+        width: f32,
+        height: f32,
+        angle: f32,
+        colour: &Colour,
+        tessellation: usize,
+    ) -> Result<()> {
+        if let Some(matrix) = self.matrix_stack.peek() {
+            let uvm = self.mappings.get_uv_mapping(BrushType::Flat, 0);
+            let colour = Rgb::from_colour(colour)?;
+
+            geometry::ellipse::render(
+                &mut self.render_list,
+                matrix,
+                position,
+                width,
+                height,
+                angle,
+                &colour,
+                tessellation,
+                uvm,
+            )
+        } else {
+            error!("no matrix for render_ellipse");
+            Err(Error::Context)
+        }
+    }
+
+    pub fn render_arc(
+        &mut self,
+        position: (f32, f32),
+        radius: f32,
+        angle_start: f32,
+        angle_end: f32,
+        width: f32,
+        colour: &Colour,
+        tessellation: usize,
+        brush_type: BrushType,
+        brush_subtype: usize,
+    ) -> Result<()> {
+        if let Some(matrix) = self.matrix_stack.peek() {
+            let uvm = self.mappings.get_uv_mapping(brush_type, brush_subtype);
+            let colour = Rgb::from_colour(colour)?;
+
+            geometry::arc::render(
+                &mut self.render_list,
+                matrix,
+                position,
+                radius,
+                angle_start,
+                angle_end,
+                width,
+                &colour,
+                tessellation,
+                uvm,
+            )
+        } else {
+            error!("no matrix for render_arc");
+            Err(Error::Context)
+        }
+    }
+
+    pub fn render_rounded_rect(
+        &mut self,
+        position: (f32, f32),
+        width: f32,
+        height: f32,
+        corner_radii: &[f32; 4],
+        colour: &Colour,
+        tessellation: usize,
+    ) -> Result<()> {
+        if let Some(matrix) = self.matrix_stack.peek() {
+            let uvm = self.mappings.get_uv_mapping(BrushType::Flat, 0);
+            let colour = Rgb::from_colour(colour)?;
+
+            geometry::rounded_rect::render(
+                &mut self.render_list,
+                matrix,
+                position,
+                width,
+                height,
+                corner_radii,
+                &colour,
+                tessellation,
+                uvm,
+            )
+        } else {
+            error!("no matrix for render_rounded_rect");
+            Err(Error::Context)
+        }
+    }
+
+    pub fn render_poly(
+        &mut self,
+        coords: &[Var],
+        colours: Option<&Vec<Var>>,
+        colour: &Colour,
+        triangulate: bool,
+    ) -> Result<()> {
         let coords: Result<Vec<(f32, f32)>> = coords.iter().map(|c| var_to_f32_pair(c)).collect();
         let coords = coords?;
 
-        let colours: Result<Vec<Rgb>> = colours.iter().map(|c| var_to_rgb(c)).collect();
-        let colours = colours?;
+        let colours: Vec<Rgb> = match colours {
+            Some(cs) => cs.iter().map(var_to_rgb).collect::<Result<_>>()?,
+            None => vec![Rgb::from_colour(colour)?; coords.len()],
+        };
 
         if let Some(matrix) = self.matrix_stack.peek() {
             let uvm = self.mappings.get_uv_mapping(BrushType::Flat, 0);
-            geometry::poly::render(&mut self.render_list, matrix, &coords, &colours, uvm)
+            if triangulate {
+                geometry::poly::render_outline(
+                    &mut self.render_list,
+                    matrix,
+                    &coords,
+                    &colours,
+                    uvm,
+                )
+            } else {
+                geometry::poly::render(&mut self.render_list, matrix, &coords, &colours, uvm)
+            }
         } else {
             error!("no matrix for render_poly");
             Err(Error::Context)
         }
     }
 
+    pub fn render_polyline(
+        &mut self,
+        coords: &[Var],
+        width: f32,
+        widths: Option<&Vec<Var>>,
+        colour: &Colour,
+        colours: Option<&Vec<Var>>,
+        join: JoinStyle,
+        cap: CapStyle,
+        miter_limit: f32,
+        tessellation: usize,
+        brush_type: BrushType,
+        brush_subtype: usize,
+    ) -> Result<()> {
+        let coords: Result<Vec<(f32, f32)>> = coords.iter().map(var_to_f32_pair).collect();
+        let coords = coords?;
+
+        let widths: Vec<f32> = match widths {
+            Some(ws) => ws.iter().map(var_to_f32).collect::<Result<_>>()?,
+            None => vec![width; coords.len()],
+        };
+
+        let colours: Vec<Rgb> = match colours {
+            Some(cs) => cs.iter().map(var_to_rgb).collect::<Result<_>>()?,
+            None => vec![Rgb::from_colour(colour)?; coords.len()],
+        };
+
+        if let Some(matrix) = self.matrix_stack.peek() {
+            let uvm = self.mappings.get_uv_mapping(brush_type, brush_subtype);
+            geometry::polyline::render(
+                &mut self.render_list,
+                matrix,
+                &coords,
+                &widths,
+                &colours,
+                join,
+                cap,
+                miter_limit,
+                tessellation,
+                uvm,
+            )
+        } else {
+            error!("no matrix for render_polyline");
+            Err(Error::Context)
+        }
+    }
+
     pub fn render_quadratic(
         &mut self,
         coords: &[f32; 6],
@@ -401,6 +591,15 @@ fn var_to_f32_pair(v: &Var) -> Result<(f32, f32)> {
     }
 }
 
+fn var_to_f32(v: &Var) -> Result<f32> {
+    if let Var::Float(f) = v {
+        Ok(*f)
+    } else {
+        error!("var_to_f32");
+        Err(Error::Context)
+    }
+}
+
 fn var_to_rgb(v: &Var) -> Result<Rgb> {
     if let Var::Colour(col) = v {
         let rgb = Rgb::from_colour(&col)?;
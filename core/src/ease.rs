@@ -233,17 +233,17 @@ fn quintic_ease_in_out(p: f32) -> f32 {
 
 // Modeled after quarter-cycle of sine wave
 fn sin_ease_in(p: f32) -> f32 {
-    ((p - 1.0) * mathutil::PI_BY_2).sin() + 1.0
+    mathutil::sin((p - 1.0) * mathutil::PI_BY_2) + 1.0
 }
 
 // Modeled after quarter-cycle of sine wave (different phase)
 fn sin_ease_out(p: f32) -> f32 {
-    (p * mathutil::PI_BY_2).sin()
+    mathutil::sin(p * mathutil::PI_BY_2)
 }
 
 // Modeled after half sine wave
 fn sin_ease_in_out(p: f32) -> f32 {
-    0.5 * (1.0 - (p * mathutil::PI).cos())
+    0.5 * (1.0 - mathutil::cos(p * mathutil::PI))
 }
 
 // Modeled after shifted quadrant IV of unit circle
@@ -272,7 +272,7 @@ fn exponential_ease_in(p: f32) -> f32 {
     if p == 0.0 {
         p
     } else {
-        2.0_f32.powf(10.0 * (p - 1.0))
+        mathutil::powf(2.0_f32, 10.0 * (p - 1.0))
     }
 }
 
@@ -281,7 +281,7 @@ fn exponential_ease_out(p: f32) -> f32 {
     if (p - 1.0).abs() < std::f32::EPSILON {
         p
     } else {
-        1.0 - 2.0_f32.powf(-10.0 * p)
+        1.0 - mathutil::powf(2.0_f32, -10.0 * p)
     }
 }
 
@@ -294,20 +294,20 @@ fn exponential_ease_in_out(p: f32) -> f32 {
     }
 
     if p < 0.5 {
-        0.5 * 2.0_f32.powf((20.0 * p) - 10.0)
+        0.5 * mathutil::powf(2.0_f32, (20.0 * p) - 10.0)
     } else {
-        -0.5 * 2.0_f32.powf((-20.0 * p) + 10.0) + 1.0
+        -0.5 * mathutil::powf(2.0_f32, (-20.0 * p) + 10.0) + 1.0
     }
 }
 
 // damped sine wave y = sinf(13pi/2*x)*pow(2, 10 * (x - 1))
 fn elastic_ease_in(p: f32) -> f32 {
-    (13.0 * mathutil::PI_BY_2 * p).sin() * 2.0_f32.powf(10.0 * (p - 1.0))
+    mathutil::sin(13.0 * mathutil::PI_BY_2 * p) * mathutil::powf(2.0_f32, 10.0 * (p - 1.0))
 }
 
 // damped sine wave y = sinf(-13pi/2*(x + 1))*pow(2, -10x) + 1
 fn elastic_ease_out(p: f32) -> f32 {
-    (-13.0 * mathutil::PI_BY_2 * (p + 1.0)).sin() * 2.0_f32.powf(-10.0 * p) + 1.0
+    mathutil::sin(-13.0 * mathutil::PI_BY_2 * (p + 1.0)) * mathutil::powf(2.0_f32, -10.0 * p) + 1.0
 }
 
 // piecewise exponentially-damped sine wave:
@@ -315,23 +315,24 @@ fn elastic_ease_out(p: f32) -> f32 {
 // y = (1/2)*(sinf(-13pi/2*((2x-1)+1))*pow(2,-10(2*x-1)) + 2) ; [0.5, 1]
 fn elastic_ease_in_out(p: f32) -> f32 {
     if p < 0.5 {
-        0.5 * (13.0 * mathutil::PI_BY_2 * (2.0 * p)).sin() * 2.0_f32.powf(10.0 * ((2.0 * p) - 1.0))
+        0.5 * mathutil::sin(13.0 * mathutil::PI_BY_2 * (2.0 * p))
+            * mathutil::powf(2.0_f32, 10.0 * ((2.0 * p) - 1.0))
     } else {
-        0.5 * ((-13.0 * mathutil::PI_BY_2 * ((2.0 * p - 1.0) + 1.0)).sin()
-            * 2.0_f32.powf(-10.0 * (2.0 * p - 1.0))
+        0.5 * (mathutil::sin(-13.0 * mathutil::PI_BY_2 * ((2.0 * p - 1.0) + 1.0))
+            * mathutil::powf(2.0_f32, -10.0 * (2.0 * p - 1.0))
             + 2.0)
     }
 }
 
 // overshooting cubic y = x^3-x*sinf(x*pi)
 fn back_ease_in(p: f32) -> f32 {
-    p * p * p - p * (p * mathutil::PI).sin()
+    p * p * p - p * mathutil::sin(p * mathutil::PI)
 }
 
 // Modeled after overshooting cubic y = 1-((1-x)^3-(1-x)*sinf((1-x)*pi))
 fn back_ease_out(p: f32) -> f32 {
     let f = 1.0 - p;
-    1.0 - (f * f * f - f * (f * mathutil::PI).sin())
+    1.0 - (f * f * f - f * mathutil::sin(f * mathutil::PI))
 }
 
 // piecewise overshooting cubic function:
@@ -340,10 +341,10 @@ fn back_ease_out(p: f32) -> f32 {
 fn back_ease_in_out(p: f32) -> f32 {
     if p < 0.5 {
         let f = 2.0 * p;
-        0.5 * (f * f * f - f * (f * mathutil::PI).sin())
+        0.5 * (f * f * f - f * mathutil::sin(f * mathutil::PI))
     } else {
         let f = 1.0 - (2.0 * p - 1.0);
-        0.5 * (1.0 - (f * f * f - f * (f * mathutil::PI).sin())) + 0.5
+        0.5 * (1.0 - (f * f * f - f * mathutil::sin(f * mathutil::PI))) + 0.5
     }
 }
 
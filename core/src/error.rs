@@ -30,10 +30,12 @@ pub enum Error {
     Ease,
     Gene,
     Geometry,
+    Golden,
     Lexer,
     Native,
     Node,
     Packable,
+    PaletteCache,
     Parser,
     Program,
     RenderList,
@@ -77,10 +79,12 @@ impl fmt::Display for Error {
             Error::Ease => write!(f, "seni core: Ease"),
             Error::Gene => write!(f, "seni core: Gene"),
             Error::Geometry => write!(f, "seni core: Geometry"),
+            Error::Golden => write!(f, "seni core: Golden"),
             Error::Lexer => write!(f, "seni core: Lexer"),
             Error::Native => write!(f, "seni core: Native"),
             Error::Node => write!(f, "seni core: Node"),
             Error::Packable => write!(f, "seni core: Packable"),
+            Error::PaletteCache => write!(f, "seni core: PaletteCache"),
             Error::Parser => write!(f, "seni core: Parser"),
             Error::Program => write!(f, "seni core: Program"),
             Error::RenderList => write!(f, "seni core: RenderList"),
@@ -0,0 +1,334 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Filling a shape with strokes: hatching with parallel lines and stippling
+// with short dashes scattered across the shape.
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::keywords::Keyword;
+use crate::mathutil;
+use crate::prng::PrngStateStruct;
+use crate::program::Program;
+use crate::sample::{self, Region};
+use crate::vm::{Var, Vm};
+
+use log::error;
+
+// stops a tiny spacing from generating an enormous number of lines
+const HATCH_MAX_LINES: usize = 100_000;
+
+// the start and end points of a stroke
+pub type Line = ((f32, f32), (f32, f32));
+
+pub enum Shape {
+    Region(Region),
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Shape {
+    // min x, min y, max x, max y
+    fn bounds(&self) -> (f32, f32, f32, f32) {
+        match self {
+            Shape::Region(region) => region.bounds(),
+            Shape::Polygon(points) => points.iter().fold(
+                (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                |(min_x, min_y, max_x, max_y), (x, y)| {
+                    (min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y))
+                },
+            ),
+        }
+    }
+
+    // even-odd rule for polygons, so holes that have been bridged to their
+    // outline are left empty
+    fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Shape::Region(region) => region.contains(x, y),
+            Shape::Polygon(points) => {
+                let mut inside = false;
+                let mut j = points.len() - 1;
+                for i in 0..points.len() {
+                    let (xi, yi) = points[i];
+                    let (xj, yj) = points[j];
+                    if (yi > y) != (yj > y) && x < xi + (y - yi) * (xj - xi) / (yj - yi) {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    // the parts of the line origin + t * direction that are inside the shape,
+    // as pairs of t values. direction is a unit vector
+    fn spans(&self, origin: (f32, f32), direction: (f32, f32)) -> Vec<(f32, f32)> {
+        match self {
+            Shape::Region(Region::Rect { .. }) => {
+                let (min_x, min_y, max_x, max_y) = self.bounds();
+                let corners = [
+                    (min_x, min_y),
+                    (max_x, min_y),
+                    (max_x, max_y),
+                    (min_x, max_y),
+                ];
+                polygon_spans(&corners, origin, direction)
+            }
+            Shape::Region(Region::Circle { position, radius }) => {
+                let (px, py) = (origin.0 - position.0, origin.1 - position.1);
+                let b = px * direction.0 + py * direction.1;
+                let c = px * px + py * py - radius * radius;
+                let discriminant = b * b - c;
+                if discriminant <= 0.0 {
+                    vec![]
+                } else {
+                    let d = discriminant.sqrt();
+                    vec![(-b - d, -b + d)]
+                }
+            }
+            Shape::Polygon(points) => polygon_spans(points, origin, direction),
+        }
+    }
+}
+
+fn polygon_spans(
+    points: &[(f32, f32)],
+    origin: (f32, f32),
+    direction: (f32, f32),
+) -> Vec<(f32, f32)> {
+    let normal = (-direction.1, direction.0);
+    let side = |(x, y): (f32, f32)| (x - origin.0) * normal.0 + (y - origin.1) * normal.1;
+    let along = |(x, y): (f32, f32)| (x - origin.0) * direction.0 + (y - origin.1) * direction.1;
+
+    // treating points exactly on the line as being above it means that a
+    // vertex on the line is only counted once
+    let mut ts: Vec<f32> = vec![];
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let (a, b) = (points[j], points[i]);
+        let (side_a, side_b) = (side(a), side(b));
+        if (side_a >= 0.0) != (side_b >= 0.0) {
+            let s = side_a / (side_a - side_b);
+            let crossing = (a.0 + s * (b.0 - a.0), a.1 + s * (b.1 - a.1));
+            ts.push(along(crossing));
+        }
+        j = i;
+    }
+    ts.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    ts.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+// parallel lines at angle (in radians) that are spacing apart, clipped to the
+// shape. The lines are placed relative to the centre of the shape's bounds so
+// the pattern doesn't shift around as the shape grows
+pub fn hatch(shape: &Shape, angle: f32, spacing: f32) -> Result<Vec<Line>> {
+    let mut res = vec![];
+    if spacing <= 0.0 {
+        return Ok(res);
+    }
+    if let Shape::Polygon(points) = shape {
+        if points.len() < 3 {
+            return Ok(res);
+        }
+    }
+
+    let (min_x, min_y, max_x, max_y) = shape.bounds();
+    let centre = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    let reach = mathutil::length_v2(max_x - min_x, max_y - min_y) / 2.0;
+
+    // compare before casting, a tiny spacing or an enormous shape would
+    // saturate the count (and NaN bounds would become 0)
+    let half_lines = reach / spacing;
+    if half_lines.is_nan() || half_lines > ((HATCH_MAX_LINES - 1) / 2) as f32 {
+        error!(
+            "fill/hatch: spacing of {} would need more than {} lines",
+            spacing, HATCH_MAX_LINES
+        );
+        return Err(Error::Native);
+    }
+
+    let count = half_lines.ceil() as i32;
+
+    let direction = (mathutil::cos(angle), mathutil::sin(angle));
+    let normal = (-direction.1, direction.0);
+
+    for k in -count..=count {
+        let o = k as f32 * spacing;
+        let origin = (centre.0 + o * normal.0, centre.1 + o * normal.1);
+        for (t0, t1) in shape.spans(origin, direction) {
+            res.push((
+                (origin.0 + t0 * direction.0, origin.1 + t0 * direction.1),
+                (origin.0 + t1 * direction.0, origin.1 + t1 * direction.1),
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
+// invoke a function with the arg: position, returns the density at that point
+fn invoke_function(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    fun: usize,
+    x: f32,
+    y: f32,
+) -> Result<f32> {
+    let args = [(Keyword::Position, Var::V2D(x, y))];
+
+    match vm.invoke_function(context, program, fun, &args)? {
+        Var::Float(density) => Ok(density),
+        _ => {
+            error!("fill/stipple: fn should return a density between 0 and 1");
+            Err(Error::Native)
+        }
+    }
+}
+
+// poisson disk points across the shape that are no closer than distance. If a
+// density function is given then each point is only kept with the probability
+// that it returns for that point
+pub fn stipple(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    shape: &Shape,
+    distance: f32,
+    fun: Option<usize>,
+    seed: i32,
+) -> Result<Vec<(f32, f32)>> {
+    let region = match shape {
+        Shape::Region(region) => *region,
+        Shape::Polygon(points) => {
+            if points.len() < 3 {
+                return Ok(vec![]);
+            }
+            let (min_x, min_y, max_x, max_y) = shape.bounds();
+            Region::Rect {
+                position: ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0),
+                width: max_x - min_x,
+                height: max_y - min_y,
+            }
+        }
+    };
+
+    let candidates = sample::poisson_disk(&region, distance, seed)?;
+    let mut prng = PrngStateStruct::new(seed, 0.0, 1.0);
+
+    let mut res = vec![];
+    for (x, y) in candidates {
+        if !shape.contains(x, y) {
+            continue;
+        }
+        if let Some(fun) = fun {
+            let density = invoke_function(vm, context, program, fun, x, y)?;
+            if prng.next_f32() >= density {
+                continue;
+            }
+        }
+        res.push((x, y));
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_length(lines: &[Line]) -> f32 {
+        lines
+            .iter()
+            .map(|(a, b)| mathutil::length_v2(b.0 - a.0, b.1 - a.1))
+            .sum()
+    }
+
+    #[test]
+    fn test_hatch_rect() {
+        let shape = Shape::Region(Region::Rect {
+            position: (50.0, 50.0),
+            width: 100.0,
+            height: 95.0,
+        });
+        let lines = hatch(&shape, 0.0, 10.0).unwrap();
+
+        // horizontal lines at y = 10, 20, .. 90 each spanning the width
+        assert_eq!(lines.len(), 9);
+        for (a, b) in &lines {
+            assert!((a.1 - b.1).abs() < 0.001);
+            assert!((b.0 - a.0 - 100.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_hatch_circle_and_polygon() {
+        let circle = Shape::Region(Region::Circle {
+            position: (0.0, 0.0),
+            radius: 100.0,
+        });
+        // the covered area is roughly the total length times the spacing
+        let area = total_length(&hatch(&circle, 0.7, 1.0).unwrap());
+        assert!((area - std::f32::consts::PI * 10_000.0).abs() < 100.0);
+
+        // a square with a square hole bridged to the outline
+        let square_with_hole = Shape::Polygon(vec![
+            (0.0, 0.0),
+            (100.0, 0.0),
+            (100.0, 100.0),
+            (0.0, 100.0),
+            (0.0, 0.0),
+            (25.0, 25.0),
+            (25.0, 75.0),
+            (75.0, 75.0),
+            (75.0, 25.0),
+            (25.0, 25.0),
+        ]);
+        let area = total_length(&hatch(&square_with_hole, 0.3, 0.5).unwrap()) * 0.5;
+        assert!((area - 7500.0).abs() < 75.0);
+    }
+
+    #[test]
+    fn test_hatch_degenerate() {
+        let shape = Shape::Region(Region::Circle {
+            position: (0.0, 0.0),
+            radius: 100.0,
+        });
+        assert!(hatch(&shape, 0.0, 0.0).unwrap().is_empty());
+        let line = Shape::Polygon(vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert!(hatch(&line, 0.0, 1.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hatch_too_many_lines() {
+        let shape = Shape::Region(Region::Circle {
+            position: (0.0, 0.0),
+            radius: 100.0,
+        });
+        assert!(hatch(&shape, 0.0, 0.001).is_err());
+        assert!(hatch(&shape, 0.0, 1e-42).is_err());
+
+        let huge = Shape::Region(Region::Rect {
+            position: (0.0, 0.0),
+            width: 1e38,
+            height: 1e38,
+        });
+        assert!(hatch(&huge, 0.0, 10.0).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Tracing lines through a vector field, one step at a time.
+//
+// The field is either a user defined function which is given a position and
+// returns a direction (as a vector or an angle in radians), or fractal noise
+// whose value is used as an angle.
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::keywords::Keyword;
+use crate::mathutil::{self, length_v2, TAU};
+use crate::noise::{self, NoiseBasis};
+use crate::program::Program;
+use crate::vm::{Var, Vm};
+
+use std::collections::HashMap;
+
+use log::error;
+
+// stops a script from asking for an enormous trace
+const TRACE_MAX_STEPS: usize = 100_000;
+
+pub enum Field {
+    Function(usize),
+    Noise {
+        basis: NoiseBasis,
+        seed: i32,
+        frequency: f32,
+        octaves: usize,
+    },
+}
+
+// The points of every trace that was given a min-separation, lines that come
+// too close to them will stop. This is reset for every piece.
+#[derive(Default)]
+pub struct SeparationGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<(f32, f32)>>,
+}
+
+impl SeparationGrid {
+    pub fn reset(&mut self) {
+        self.cells.clear();
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn add(&mut self, points: &[(f32, f32)], min_separation: f32) {
+        if self.cells.is_empty() {
+            self.cell_size = min_separation;
+        }
+        for (x, y) in points {
+            let cell = self.cell(*x, *y);
+            self.cells.entry(cell).or_default().push((*x, *y));
+        }
+    }
+
+    fn is_clear(&self, x: f32, y: f32, min_separation: f32) -> bool {
+        if self.cells.is_empty() {
+            return true;
+        }
+
+        let reach = (min_separation / self.cell_size).ceil() as i32;
+        let (cx, cy) = self.cell(x, y);
+        for j in (cy - reach)..=(cy + reach) {
+            for i in (cx - reach)..=(cx + reach) {
+                if let Some(points) = self.cells.get(&(i, j)) {
+                    if points
+                        .iter()
+                        .any(|(px, py)| length_v2(px - x, py - y) < min_separation)
+                    {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+// invoke a function with 2 args: step and position, returns the unit direction
+fn invoke_function(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    fun: usize,
+    step: f32,
+    x: f32,
+    y: f32,
+) -> Result<(f32, f32)> {
+    let args = [
+        (Keyword::N, Var::Float(step)),
+        (Keyword::Position, Var::V2D(x, y)),
+    ];
+
+    match vm.invoke_function(context, program, fun, &args)? {
+        Var::Float(angle) => Ok((mathutil::cos(angle), mathutil::sin(angle))),
+        Var::V2D(dx, dy) => {
+            let len = length_v2(dx, dy);
+            if len > 0.0 {
+                Ok((dx / len, dy / len))
+            } else {
+                Ok((0.0, 0.0))
+            }
+        }
+        _ => {
+            error!("flow/trace: fn should return either an angle or a direction vector");
+            Err(Error::Native)
+        }
+    }
+}
+
+fn direction(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    field: &Field,
+    step: usize,
+    x: f32,
+    y: f32,
+) -> Result<(f32, f32)> {
+    match field {
+        Field::Function(fun) => invoke_function(vm, context, program, *fun, step as f32, x, y),
+        Field::Noise {
+            basis,
+            seed,
+            frequency,
+            octaves,
+        } => Ok(noise_direction(*basis, *seed, *frequency, *octaves, x, y)),
+    }
+}
+
+// the value of fractal noise at a point used as an angle
+pub fn noise_direction(
+    basis: NoiseBasis,
+    seed: i32,
+    frequency: f32,
+    octaves: usize,
+    x: f32,
+    y: f32,
+) -> (f32, f32) {
+    let value = noise::fbm(seed, octaves, 2.0, 0.5, |s, f| {
+        basis.sample2(s, x * frequency * f, y * frequency * f)
+    });
+    let angle = value * TAU;
+    (mathutil::cos(angle), mathutil::sin(angle))
+}
+
+// Returns the points along the traced line, including the starting point. A
+// trace stops early if the field has no direction or, when min_separation is
+// positive, if it comes within min_separation of an earlier trace.
+pub fn trace(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    field: &Field,
+    from: (f32, f32),
+    step_size: f32,
+    steps: usize,
+    min_separation: f32,
+) -> Result<Vec<(f32, f32)>> {
+    if steps > TRACE_MAX_STEPS {
+        error!(
+            "flow/trace: {} steps is more than the limit of {}",
+            steps, TRACE_MAX_STEPS
+        );
+        return Err(Error::Native);
+    }
+
+    let avoid_others = min_separation > 0.0;
+
+    let mut points = vec![];
+    if avoid_others
+        && !context
+            .separation_grid
+            .is_clear(from.0, from.1, min_separation)
+    {
+        return Ok(points);
+    }
+
+    let (mut x, mut y) = from;
+    points.push((x, y));
+
+    for step in 0..steps {
+        let (dx, dy) = direction(vm, context, program, field, step, x, y)?;
+        if dx == 0.0 && dy == 0.0 {
+            break;
+        }
+
+        x += dx * step_size;
+        y += dy * step_size;
+
+        if avoid_others && !context.separation_grid.is_clear(x, y, min_separation) {
+            break;
+        }
+        points.push((x, y));
+    }
+
+    if avoid_others {
+        context.separation_grid.add(&points, min_separation);
+    }
+
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separation_grid() {
+        let mut grid: SeparationGrid = Default::default();
+        assert!(grid.is_clear(0.0, 0.0, 5.0));
+
+        grid.add(&[(0.0, 0.0), (10.0, 0.0)], 5.0);
+        assert!(!grid.is_clear(3.0, 3.0, 5.0));
+        assert!(!grid.is_clear(14.0, 0.0, 5.0));
+        assert!(grid.is_clear(5.0, 4.0, 5.0));
+
+        // a larger separation than the grid was built with
+        assert!(!grid.is_clear(5.0, 18.0, 20.0));
+
+        grid.reset();
+        assert!(grid.is_clear(0.0, 0.0, 5.0));
+    }
+}
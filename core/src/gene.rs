@@ -23,6 +23,7 @@ use crate::iname::Iname;
 use crate::keywords::Keyword;
 use crate::packable::{Mule, Packable};
 use crate::prng::PrngStateStruct;
+use crate::program::Program;
 use crate::trait_list::{Trait, TraitList};
 use crate::vm::{VMProfiling, Var, Vm};
 use log::error;
@@ -65,15 +66,19 @@ impl Gene {
         }
     }
 
-    pub fn build_from_trait(vm: &mut Vm, context: &mut Context, t: &Trait) -> Result<Self> {
+    pub fn build_from_trait(
+        vm: &mut Vm,
+        context: &mut Context,
+        preamble: &Program,
+        t: &Trait,
+    ) -> Result<Self> {
         context.reset_for_piece();
         vm.reset();
         vm.building_with_trait_within_vector = t.within_vector;
         vm.trait_within_vector_index = t.index;
 
         // setup the env with the global variables in preamble
-        let preamble = compile_preamble()?;
-        vm.interpret(context, &preamble)?;
+        vm.interpret(context, preamble)?;
 
         // reset the ip and setup any profiling of the main program
         vm.init_for_main_program(&t.program, VMProfiling::Off)?;
@@ -188,6 +193,7 @@ impl Genotype {
         let mut vm: Vm = Default::default();
         let mut context: Context = Default::default();
         let mut genotype: Genotype = Default::default();
+        let preamble = compile_preamble()?;
 
         // the seed is set once per genotype (should it be once per-gene?)
         //
@@ -196,7 +202,7 @@ impl Genotype {
         for t in &trait_list.traits {
             genotype
                 .genes
-                .push(Gene::build_from_trait(&mut vm, &mut context, t)?);
+                .push(Gene::build_from_trait(&mut vm, &mut context, &preamble, t)?);
         }
 
         Ok(genotype)
@@ -275,9 +281,10 @@ impl Genotype {
         let mut vm: Vm = Default::default();
         let mut context: Context = Default::default();
         let t = trait_list.get_trait(idx);
+        let preamble = compile_preamble()?;
 
         vm.set_prng_state(prng.clone());
-        self.genes[idx] = Gene::build_from_trait(&mut vm, &mut context, t)?;
+        self.genes[idx] = Gene::build_from_trait(&mut vm, &mut context, &preamble, t)?;
         prng.clone_rng(vm.prng_state);
 
         Ok(())
@@ -705,6 +712,34 @@ mod tests {
         assert_eq!(genotype.genes.len(), 1);
     }
 
+    #[test]
+    fn genotype_col_format() {
+        let s = "(col/rgb r: 0.1) ~ (gen/col alpha: 0.3 format: OKLAB)";
+
+        let (res, _genotype) = run_with_seeded_genotype(s, 432).unwrap();
+        if let Var::Colour(col) = res {
+            assert_eq!(col.format, ColourFormat::Oklab);
+            assert!(col.e0 >= 0.0 && col.e0 <= 1.0);
+            assert!(col.e1 >= -0.4 && col.e1 <= 0.4);
+            assert!(col.e2 >= -0.4 && col.e2 <= 0.4);
+        } else {
+            panic!("expected a colour");
+        }
+
+        // generated colours can always be shown without clipping
+        for format in &["LAB", "LCH", "OKLAB", "OKLCH"] {
+            let s = format!("(col/rgb r: 0.1) ~ (gen/col alpha: 1 format: {})", format);
+            for seed in 0..20 {
+                let (res, _genotype) = run_with_seeded_genotype(&s, seed * 97).unwrap();
+                if let Var::Colour(col) = res {
+                    assert!(col.is_in_srgb_gamut().unwrap(), "{} {}", format, seed);
+                } else {
+                    panic!("expected a colour");
+                }
+            }
+        }
+    }
+
     #[test]
     fn genotype_compile_xxx() {
         geno_test(
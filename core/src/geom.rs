@@ -0,0 +1,605 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Computational geometry on sets of points.
+//
+// The calculations are done with f64 to avoid problems with nearly
+// co-circular points, the results are converted back to f32 at the end.
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangulation {
+    // indices into the points, in counter-clockwise order
+    pub triangles: Vec<[usize; 3]>,
+    // for each triangle the indices of the triangles that share an edge with it
+    pub neighbours: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Voronoi {
+    // one cell for every point, a cell is empty if its point is outside the
+    // bounds or is a duplicate of an earlier point
+    pub cells: Vec<Vec<(f32, f32)>>,
+    // for each cell the indices of the cells that share an edge with it
+    pub neighbours: Vec<Vec<usize>>,
+}
+
+#[derive(Copy, Clone)]
+struct Circumcircle {
+    x: f64,
+    y: f64,
+    radius_squared: f64,
+}
+
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> Circumcircle {
+    let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+    if d == 0.0 {
+        // degenerate, collinear points
+        return Circumcircle {
+            x: 0.0,
+            y: 0.0,
+            radius_squared: f64::MAX,
+        };
+    }
+
+    let a2 = a.0 * a.0 + a.1 * a.1;
+    let b2 = b.0 * b.0 + b.1 * b.1;
+    let c2 = c.0 * c.0 + c.1 * c.1;
+    let x = (a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d;
+    let y = (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d;
+
+    Circumcircle {
+        x,
+        y,
+        radius_squared: (a.0 - x) * (a.0 - x) + (a.1 - y) * (a.1 - y),
+    }
+}
+
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn to_f64(p: &(f32, f32)) -> (f64, f64) {
+    (f64::from(p.0), f64::from(p.1))
+}
+
+// the first index of each distinct point
+fn distinct_points(points: &[(f32, f32)]) -> Vec<usize> {
+    let mut seen: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut res = vec![];
+    for (i, p) in points.iter().enumerate() {
+        if seen.insert((p.0.to_bits(), p.1.to_bits()), i).is_none() {
+            res.push(i);
+        }
+    }
+    res
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+// Bowyer-Watson: points are added one at a time and the triangles whose
+// circumcircles contain the new point are replaced
+pub fn delaunay(points: &[(f32, f32)]) -> Triangulation {
+    let indices = distinct_points(points);
+    if indices.len() < 3 {
+        return Triangulation {
+            triangles: vec![],
+            neighbours: vec![],
+        };
+    }
+
+    let mut verts: Vec<(f64, f64)> = points.iter().map(to_f64).collect();
+
+    // a triangle that's large enough to contain every point
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for i in &indices {
+        let (x, y) = verts[*i];
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let size = (max_x - min_x).max(max_y - min_y).max(1.0) * 20.0;
+    let (mid_x, mid_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    let first_super = verts.len();
+    verts.push((mid_x - size, mid_y - size));
+    verts.push((mid_x + size, mid_y - size));
+    verts.push((mid_x, mid_y + size));
+
+    let circle_of =
+        |t: &[usize; 3], verts: &[(f64, f64)]| circumcircle(verts[t[0]], verts[t[1]], verts[t[2]]);
+
+    let super_triangle = [first_super, first_super + 1, first_super + 2];
+    let mut triangles: Vec<([usize; 3], Circumcircle)> =
+        vec![(super_triangle, circle_of(&super_triangle, &verts))];
+
+    for i in indices {
+        let p = verts[i];
+
+        let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edges: Vec<(usize, usize)> = vec![];
+        triangles.retain(|(t, c)| {
+            let dx = p.0 - c.x;
+            let dy = p.1 - c.y;
+            if dx * dx + dy * dy < c.radius_squared {
+                for e in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                    *edge_count.entry(edge_key(e.0, e.1)).or_insert(0) += 1;
+                    edges.push(*e);
+                }
+                false
+            } else {
+                true
+            }
+        });
+
+        // the edges that aren't shared by two removed triangles form the
+        // boundary of the hole, connect each of them to the new point
+        for (a, b) in edges {
+            if edge_count[&edge_key(a, b)] == 1 {
+                let mut t = [a, b, i];
+                if cross(verts[a], verts[b], p) < 0.0 {
+                    t.swap(0, 1);
+                }
+                triangles.push((t, circle_of(&t, &verts)));
+            }
+        }
+    }
+
+    let mut triangles: Vec<[usize; 3]> = triangles
+        .into_iter()
+        .map(|(t, _)| t)
+        .filter(|t| t.iter().all(|v| *v < first_super))
+        .collect();
+
+    if fill_hull(&mut triangles, &verts) {
+        legalize(&mut triangles, &verts);
+    }
+
+    let mut edge_triangles: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (ti, t) in triangles.iter().enumerate() {
+        for e in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+            edge_triangles
+                .entry(edge_key(e.0, e.1))
+                .or_default()
+                .push(ti);
+        }
+    }
+
+    let neighbours = triangles
+        .iter()
+        .enumerate()
+        .map(|(ti, t)| {
+            let mut ns = vec![];
+            for e in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                for n in &edge_triangles[&edge_key(e.0, e.1)] {
+                    if *n != ti {
+                        ns.push(*n);
+                    }
+                }
+            }
+            ns.sort_unstable();
+            ns
+        })
+        .collect();
+
+    Triangulation {
+        triangles,
+        neighbours,
+    }
+}
+
+// the vertices around the edge of a triangulation in counter-clockwise order,
+// None if the edge isn't a single simple loop
+fn boundary_loop(triangles: &[[usize; 3]]) -> Option<Vec<usize>> {
+    let mut directed: HashMap<(usize, usize), usize> = HashMap::new();
+    for t in triangles {
+        for k in 0..3 {
+            *directed.entry((t[k], t[(k + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+
+    let mut next: HashMap<usize, usize> = HashMap::new();
+    for (a, b) in directed.keys() {
+        if !directed.contains_key(&(*b, *a)) && next.insert(*a, *b).is_some() {
+            return None;
+        }
+    }
+
+    let start = *next.keys().min()?;
+    let mut res = vec![start];
+    let mut v = next[&start];
+    while v != start {
+        if res.len() > next.len() {
+            return None;
+        }
+        res.push(v);
+        v = *next.get(&v)?;
+    }
+
+    if res.len() == next.len() {
+        Some(res)
+    } else {
+        None
+    }
+}
+
+// The super triangle can't be made large enough to guarantee that every
+// triangle along the convex hull is created, the ones whose circumcircles
+// reach the super triangle's vertices are removed along with it. Adds
+// triangles to the concave parts of the boundary until it's convex again,
+// returns true if any were added
+fn fill_hull(triangles: &mut Vec<[usize; 3]>, verts: &[(f64, f64)]) -> bool {
+    let mut filled = false;
+
+    while let Some(boundary) = boundary_loop(triangles) {
+        let n = boundary.len();
+        let ear = (0..n).find_map(|k| {
+            let (a, b, c) = (
+                boundary[(k + n - 1) % n],
+                boundary[k],
+                boundary[(k + 1) % n],
+            );
+            if cross(verts[a], verts[b], verts[c]) >= 0.0 {
+                return None;
+            }
+            // the new triangle can't overlap any other part of the boundary
+            let overlaps = boundary.iter().any(|v| {
+                let p = verts[*v];
+                *v != a
+                    && *v != b
+                    && *v != c
+                    && cross(verts[a], verts[c], p) > 0.0
+                    && cross(verts[c], verts[b], p) > 0.0
+                    && cross(verts[b], verts[a], p) > 0.0
+            });
+            if overlaps {
+                None
+            } else {
+                Some([a, c, b])
+            }
+        });
+
+        match ear {
+            Some(t) => {
+                triangles.push(t);
+                filled = true;
+            }
+            None => break,
+        }
+    }
+
+    filled
+}
+
+// flips edges until the vertex opposite each edge is outside the circumcircle
+// of the triangle on the other side
+fn legalize(triangles: &mut [[usize; 3]], verts: &[(f64, f64)]) {
+    // a limit in case nearly co-circular points keep flipping back and forth
+    for _ in 0..(triangles.len() * triangles.len()).max(1) {
+        // directed edge -> (triangle, opposite vertex)
+        let mut edges: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        for (ti, t) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                edges.insert((t[k], t[(k + 1) % 3]), (ti, t[(k + 2) % 3]));
+            }
+        }
+
+        // edges are checked in triangle order so that the result is deterministic
+        let flip = triangles.iter().enumerate().find_map(|(ti, t)| {
+            (0..3).find_map(|k| {
+                let (a, b, c) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+                let (tj, d) = *edges.get(&(b, a))?;
+                let circle = circumcircle(verts[a], verts[b], verts[c]);
+                let (dx, dy) = (verts[d].0 - circle.x, verts[d].1 - circle.y);
+                let inside = dx * dx + dy * dy < circle.radius_squared * (1.0 - 1e-9);
+                let convex = cross(verts[a], verts[d], verts[c]) > 0.0
+                    && cross(verts[d], verts[b], verts[c]) > 0.0;
+                if inside && convex {
+                    Some((ti, [a, d, c], tj, [d, b, c]))
+                } else {
+                    None
+                }
+            })
+        });
+
+        match flip {
+            Some((ti, t, tj, u)) => {
+                triangles[ti] = t;
+                triangles[tj] = u;
+            }
+            None => return,
+        }
+    }
+}
+
+// clips a convex polygon so that only the part where (p - m).n <= 0 remains
+fn clip_half_plane(polygon: &[(f64, f64)], m: (f64, f64), n: (f64, f64)) -> Vec<(f64, f64)> {
+    let side = |p: (f64, f64)| (p.0 - m.0) * n.0 + (p.1 - m.1) * n.1;
+
+    let mut res = vec![];
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (sa, sb) = (side(*a), side(b));
+        if sa <= 0.0 {
+            res.push(*a);
+        }
+        if (sa < 0.0 && sb > 0.0) || (sa > 0.0 && sb < 0.0) {
+            let t = sa / (sa - sb);
+            res.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+        }
+    }
+    res
+}
+
+// the voronoi cell of each point, clipped to the bounding rect given by its
+// min and max corners
+pub fn voronoi(points: &[(f32, f32)], min: (f32, f32), max: (f32, f32)) -> Voronoi {
+    let triangulation = delaunay(points);
+
+    // the neighbours of each site in the delaunay triangulation are the only
+    // sites that can share an edge with its cell
+    let mut sites: Vec<Vec<usize>> = vec![vec![]; points.len()];
+    for t in &triangulation.triangles {
+        for (a, b) in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+            sites[*a].push(*b);
+            sites[*b].push(*a);
+        }
+    }
+
+    let (min, max) = (to_f64(&min), to_f64(&max));
+    let bounds = vec![min, (max.0, min.1), max, (min.0, max.1)];
+    let distinct = distinct_points(points);
+    let is_distinct = {
+        let mut d = vec![false; points.len()];
+        for i in &distinct {
+            d[*i] = true;
+        }
+        d
+    };
+
+    let mut cells = vec![];
+    let mut neighbours = vec![];
+
+    for (i, p) in points.iter().enumerate() {
+        let p = to_f64(p);
+        let inside = p.0 >= min.0 && p.0 <= max.0 && p.1 >= min.1 && p.1 <= max.1;
+        if !inside || !is_distinct[i] {
+            cells.push(vec![]);
+            neighbours.push(vec![]);
+            continue;
+        }
+
+        let mut ns = sites[i].clone();
+        if distinct.len() < 3 || triangulation.triangles.is_empty() {
+            // too few points (or all collinear) for a triangulation
+            ns = distinct.iter().cloned().filter(|j| *j != i).collect();
+        }
+        ns.sort_unstable();
+        ns.dedup();
+
+        let mut cell = bounds.clone();
+        for j in &ns {
+            let q = to_f64(&points[*j]);
+            let m = ((p.0 + q.0) / 2.0, (p.1 + q.1) / 2.0);
+            cell = clip_half_plane(&cell, m, (q.0 - p.0, q.1 - p.1));
+        }
+
+        // a neighbour only counts if the bisector contributes an edge to the cell
+        let tolerance = 1e-6 * (max.0 - min.0).abs().max((max.1 - min.1).abs()).max(1.0);
+        let shares_edge = |j: &usize| {
+            let q = to_f64(&points[*j]);
+            let m = ((p.0 + q.0) / 2.0, (p.1 + q.1) / 2.0);
+            let n = (q.0 - p.0, q.1 - p.1);
+            let len = (n.0 * n.0 + n.1 * n.1).sqrt();
+            let on_bisector =
+                |v: &(f64, f64)| ((v.0 - m.0) * n.0 + (v.1 - m.1) * n.1).abs() / len < tolerance;
+            (0..cell.len())
+                .any(|k| on_bisector(&cell[k]) && on_bisector(&cell[(k + 1) % cell.len()]))
+        };
+        let ns: Vec<usize> = ns.into_iter().filter(|j| shares_edge(j)).collect();
+
+        cells.push(cell.iter().map(|(x, y)| (*x as f32, *y as f32)).collect());
+        neighbours.push(ns);
+    }
+
+    Voronoi { cells, neighbours }
+}
+
+fn polygon_area(polygon: &[(f64, f64)]) -> f64 {
+    let mut res = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        res += a.0 * b.1 - b.0 * a.1;
+    }
+    res / 2.0
+}
+
+fn in_triangle(p: (f64, f64), a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+// Ear clipping triangulation of a simple polygon (concave is fine, self
+// intersecting isn't). Returns indices into the polygon.
+pub fn triangulate_polygon(polygon: &[(f32, f32)]) -> Vec<[usize; 3]> {
+    let verts: Vec<(f64, f64)> = polygon.iter().map(to_f64).collect();
+    let mut res = vec![];
+    if verts.len() < 3 {
+        return res;
+    }
+
+    // work with a counter-clockwise winding
+    let mut remaining: Vec<usize> = (0..verts.len()).collect();
+    if polygon_area(&verts) < 0.0 {
+        remaining.reverse();
+    }
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let mut ear = None;
+        for i in 0..n {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            if cross(verts[a], verts[b], verts[c]) <= 0.0 {
+                // reflex or degenerate
+                continue;
+            }
+            let contains_other = remaining.iter().any(|v| {
+                *v != a
+                    && *v != b
+                    && *v != c
+                    && verts[*v] != verts[a]
+                    && verts[*v] != verts[b]
+                    && verts[*v] != verts[c]
+                    && in_triangle(verts[*v], verts[a], verts[b], verts[c])
+            });
+            if !contains_other {
+                ear = Some(i);
+                break;
+            }
+        }
+
+        match ear {
+            Some(i) => {
+                res.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+            // only happens with degenerate or self intersecting polygons,
+            // remove a vertex so that we always terminate
+            None => {
+                remaining.remove(0);
+            }
+        }
+    }
+    res.push([remaining[0], remaining[1], remaining[2]]);
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(polygon: &[(f32, f32)]) -> f32 {
+        let mut res = 0.0;
+        for (i, a) in polygon.iter().enumerate() {
+            let b = polygon[(i + 1) % polygon.len()];
+            res += a.0 * b.1 - b.0 * a.1;
+        }
+        res / 2.0
+    }
+
+    #[test]
+    fn test_delaunay() {
+        // a square with a point in the middle
+        let points = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (5.0, 5.0),
+        ];
+        let t = delaunay(&points);
+        assert_eq!(t.triangles.len(), 4);
+        for (i, tri) in t.triangles.iter().enumerate() {
+            assert!(tri.contains(&4));
+            assert_eq!(t.neighbours[i].len(), 2);
+            let corners: Vec<(f32, f32)> = tri.iter().map(|v| points[*v]).collect();
+            assert_eq!(area(&corners), 25.0);
+        }
+
+        // a thin triangle along the hull has a circumcircle that reaches
+        // further than the starting super triangle
+        let points = [(0.0, 0.0), (500.0, 1.0), (1000.0, 0.0), (500.0, 500.0)];
+        let t = delaunay(&points);
+        assert_eq!(t.triangles.len(), 3);
+        let total: f32 = t
+            .triangles
+            .iter()
+            .map(|tri| area(&[points[tri[0]], points[tri[1]], points[tri[2]]]))
+            .sum();
+        assert_eq!(total, 250_000.0);
+        assert!(t
+            .triangles
+            .iter()
+            .any(|tri| [0, 1, 2].iter().all(|v| tri.contains(v))));
+
+        assert!(delaunay(&[(0.0, 0.0), (1.0, 1.0)]).triangles.is_empty());
+        // duplicates are ignored
+        assert_eq!(
+            delaunay(&[(0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (0.0, 1.0)])
+                .triangles
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_triangulate_polygon() {
+        // an L shape, clockwise
+        let l = [
+            (0.0, 0.0),
+            (0.0, 20.0),
+            (10.0, 20.0),
+            (10.0, 10.0),
+            (20.0, 10.0),
+            (20.0, 0.0),
+        ];
+        let triangles = triangulate_polygon(&l);
+        assert_eq!(triangles.len(), 4);
+        let total: f32 = triangles
+            .iter()
+            .map(|t| area(&[l[t[0]], l[t[1]], l[t[2]]]))
+            .sum();
+        assert_eq!(total, 300.0);
+    }
+
+    #[test]
+    fn test_voronoi() {
+        let points = [(25.0, 50.0), (75.0, 50.0), (50.0, 90.0), (500.0, 500.0)];
+        let v = voronoi(&points, (0.0, 0.0), (100.0, 100.0));
+
+        assert_eq!(v.cells.len(), 4);
+        // the point outside of the bounds has no cell
+        assert!(v.cells[3].is_empty());
+
+        // the cells cover the bounds
+        let total: f32 = v.cells.iter().map(|c| area(c)).sum();
+        assert!((total - 10_000.0).abs() < 0.1);
+
+        assert_eq!(v.neighbours[0], vec![1, 2]);
+        assert_eq!(v.neighbours[2], vec![0, 1]);
+
+        // two points split the bounds in half
+        let v = voronoi(&[(25.0, 50.0), (75.0, 50.0)], (0.0, 0.0), (100.0, 100.0));
+        assert_eq!(area(&v.cells[0]), 5000.0);
+        assert_eq!(v.neighbours[0], vec![1]);
+    }
+}
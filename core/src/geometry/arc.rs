@@ -0,0 +1,99 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{Error, Result};
+use crate::mathutil::*;
+use crate::matrix::Matrix;
+use crate::render_list::RenderList;
+use crate::rgb::Rgb;
+use crate::uvmapper::UvMapping;
+
+// a stroke that follows part of a circle's circumference. The brush texture is
+// stretched along the length of the arc in the same way as quadratic and bezier
+pub fn render(
+    render_list: &mut RenderList,
+    matrix: &Matrix,
+    position: (f32, f32),
+    radius: f32,
+    angle_start: f32,
+    angle_end: f32,
+    width: f32,
+    colour: &Rgb,
+    tessellation: usize,
+    uvm: &UvMapping,
+) -> Result<()> {
+    let tessellation = tessellation.max(1);
+
+    let r_start = deg_to_rad(angle_start);
+    let r_end = deg_to_rad(angle_end);
+    let unit_angle = (r_end - r_start) / tessellation as f32;
+
+    // modify the width so that the brush textures provide good coverage
+    //
+    let half_width = (width * uvm.width_scale) / 2.0;
+    let outer = radius + half_width;
+    let inner = radius - half_width;
+
+    render_list.prepare_to_add_triangle_strip(
+        matrix,
+        (tessellation * 2) + 2,
+        (sin(r_start) * outer) + position.0,
+        (cos(r_start) * outer) + position.1,
+    )?;
+
+    let rp = render_list
+        .render_packets
+        .last_mut()
+        .ok_or(Error::Geometry)?;
+    let rpg = rp.get_mut_render_packet_geometry()?;
+
+    for i in 0..=tessellation {
+        let angle = if i == tessellation {
+            r_end
+        } else {
+            r_start + (unit_angle * i as f32)
+        };
+        let s = sin(angle);
+        let c = cos(angle);
+
+        let uv_t = i as f32 / tessellation as f32;
+
+        let u = lerp(uv_t, uvm.map[2], uvm.map[6]);
+        let v = lerp(uv_t, uvm.map[3], uvm.map[7]);
+        rpg.add_vertex(
+            matrix,
+            (s * outer) + position.0,
+            (c * outer) + position.1,
+            colour,
+            u,
+            v,
+        );
+
+        let u = lerp(uv_t, uvm.map[0], uvm.map[4]);
+        let v = lerp(uv_t, uvm.map[1], uvm.map[5]);
+        rpg.add_vertex(
+            matrix,
+            (s * inner) + position.0,
+            (c * inner) + position.1,
+            colour,
+            u,
+            v,
+        );
+    }
+
+    Ok(())
+}
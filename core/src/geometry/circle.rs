@@ -49,8 +49,8 @@ pub fn render(
 
     for i in 0..tessellation {
         let angle = unit_angle * i as f32;
-        let vx = (angle.sin() * width) + position.0;
-        let vy = (angle.cos() * height) + position.1;
+        let vx = (sin(angle) * width) + position.0;
+        let vy = (cos(angle) * height) + position.1;
 
         rpg.add_vertex(
             matrix, position.0, position.1, colour, uvm.map[4], uvm.map[5],
@@ -59,8 +59,8 @@ pub fn render(
     }
 
     let angle: f32 = 0.0;
-    let vx = (angle.sin() * width) + position.0;
-    let vy = (angle.cos() * height) + position.1;
+    let vx = (sin(angle) * width) + position.0;
+    let vy = (cos(angle) * height) + position.1;
 
     rpg.add_vertex(
         matrix, position.0, position.1, colour, uvm.map[4], uvm.map[5],
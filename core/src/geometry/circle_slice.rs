@@ -40,8 +40,8 @@ pub fn render(
     let r_end = deg_to_rad(angle_end);
     let unit_angle = (r_end - r_start) / tessellation as f32;
 
-    let mut innervx = (r_start.sin() * inner_width) + position.0;
-    let mut innervy = (r_start.cos() * inner_height) + position.1;
+    let mut innervx = (sin(r_start) * inner_width) + position.0;
+    let mut innervy = (cos(r_start) * inner_height) + position.1;
 
     render_list.prepare_to_add_triangle_strip(matrix, (tessellation * 2) + 2, innervx, innervy)?;
 
@@ -54,22 +54,22 @@ pub fn render(
     for i in 0..tessellation {
         let angle = r_start + (unit_angle * i as f32);
 
-        innervx = (angle.sin() * inner_width) + position.0;
-        innervy = (angle.cos() * inner_height) + position.1;
+        innervx = (sin(angle) * inner_width) + position.0;
+        innervy = (cos(angle) * inner_height) + position.1;
 
-        let vx = (angle.sin() * width) + position.0;
-        let vy = (angle.cos() * height) + position.1;
+        let vx = (sin(angle) * width) + position.0;
+        let vy = (cos(angle) * height) + position.1;
 
         rpg.add_vertex(matrix, innervx, innervy, colour, uvm.map[4], uvm.map[5]);
         rpg.add_vertex(matrix, vx, vy, colour, uvm.map[4], uvm.map[5]);
     }
 
     let angle: f32 = r_end;
-    innervx = (angle.sin() * inner_width) + position.0;
-    innervy = (angle.cos() * inner_height) + position.1;
+    innervx = (sin(angle) * inner_width) + position.0;
+    innervy = (cos(angle) * inner_height) + position.1;
 
-    let vx = (angle.sin() * width) + position.0;
-    let vy = (angle.cos() * height) + position.1;
+    let vx = (sin(angle) * width) + position.0;
+    let vy = (cos(angle) * height) + position.1;
 
     rpg.add_vertex(matrix, innervx, innervy, colour, uvm.map[4], uvm.map[5]);
     rpg.add_vertex(matrix, vx, vy, colour, uvm.map[4], uvm.map[5]);
@@ -15,13 +15,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod arc;
 pub mod bezier;
 pub mod bezier_bulging;
 pub mod circle;
 pub mod circle_slice;
+pub mod ellipse;
 pub mod line;
 pub mod poly;
+pub mod polyline;
 pub mod quadratic;
 pub mod rect;
 pub mod ring;
+pub mod rounded_rect;
 pub mod stroked_bezier;
@@ -16,6 +16,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::error::{Error, Result};
+use crate::geom::triangulate_polygon;
 use crate::matrix::Matrix;
 use crate::render_list::RenderList;
 use crate::rgb::Rgb;
@@ -53,3 +54,35 @@ pub fn render(
 
     Ok(())
 }
+
+// coords are the outline of a polygon rather than a triangle strip
+pub fn render_outline(
+    render_list: &mut RenderList,
+    matrix: &Matrix,
+    coords: &[(f32, f32)],
+    colours: &[Rgb],
+    uvm: &UvMapping,
+) -> Result<()> {
+    if colours.len() != coords.len() {
+        error!("render_poly: coords and colours length mismatch");
+        return Err(Error::Geometry);
+    }
+
+    for t in triangulate_polygon(coords) {
+        let (x, y) = coords[t[0]];
+        render_list.prepare_to_add_triangle_strip(matrix, 3, x, y)?;
+
+        let rp = render_list
+            .render_packets
+            .last_mut()
+            .ok_or(Error::Geometry)?;
+        let rpg = rp.get_mut_render_packet_geometry()?;
+
+        for i in &t {
+            let (x, y) = coords[*i];
+            rpg.add_vertex(matrix, x, y, &colours[*i], uvm.map[4], uvm.map[5])
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,334 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{Error, Result};
+use crate::mathutil::*;
+use crate::matrix::Matrix;
+use crate::render_list::RenderList;
+use crate::rgb::Rgb;
+use crate::uvmapper::UvMapping;
+use log::error;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JoinStyle {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CapStyle {
+    Butt,
+    Round,
+    Square,
+}
+
+// a vertex of the triangle strip that makes up the stroke. t is the distance
+// along the polyline in the range 0..1 and is used to map the brush texture
+struct StripVertex {
+    x: f32,
+    y: f32,
+    t: f32,
+    col: Rgb,
+}
+
+struct Strip {
+    // pairs of vertices, the first of each pair is on the left side of the stroke
+    vertices: Vec<StripVertex>,
+}
+
+impl Strip {
+    fn add_pair(&mut self, left: (f32, f32), right: (f32, f32), t: f32, col: &Rgb) {
+        self.vertices.push(StripVertex {
+            x: left.0,
+            y: left.1,
+            t,
+            col: *col,
+        });
+        self.vertices.push(StripVertex {
+            x: right.0,
+            y: right.1,
+            t,
+            col: *col,
+        });
+    }
+}
+
+// a stroke along the points, widths and colours have an entry per point
+pub fn render(
+    render_list: &mut RenderList,
+    matrix: &Matrix,
+    points: &[(f32, f32)],
+    widths: &[f32],
+    colours: &[Rgb],
+    join: JoinStyle,
+    cap: CapStyle,
+    miter_limit: f32,
+    tessellation: usize,
+    uvm: &UvMapping,
+) -> Result<()> {
+    if widths.len() != points.len() || colours.len() != points.len() {
+        error!("polyline requires a width and colour for every point");
+        return Err(Error::Geometry);
+    }
+
+    // remove any zero length segments as they don't have a direction
+    let mut indices: Vec<usize> = Vec::with_capacity(points.len());
+    for (i, p) in points.iter().enumerate() {
+        match indices.last() {
+            Some(&prev) if points[prev] == *p => {}
+            _ => indices.push(i),
+        }
+    }
+    if indices.len() < 2 {
+        return Ok(());
+    }
+
+    let pts: Vec<(f32, f32)> = indices.iter().map(|i| points[*i]).collect();
+    let half_widths: Vec<f32> = indices
+        .iter()
+        .map(|i| (widths[*i] * uvm.width_scale) / 2.0)
+        .collect();
+    let cols: Vec<&Rgb> = indices.iter().map(|i| &colours[*i]).collect();
+
+    // unit direction of each segment and the distance along the polyline
+    let mut dirs: Vec<(f32, f32)> = Vec::with_capacity(pts.len() - 1);
+    let mut ts: Vec<f32> = vec![0.0];
+    let mut total = 0.0;
+    for w in pts.windows(2) {
+        dirs.push(normalize(w[1].0 - w[0].0, w[1].1 - w[0].1));
+        total += distance_v2(w[0].0, w[0].1, w[1].0, w[1].1);
+        ts.push(total);
+    }
+    for t in ts.iter_mut() {
+        *t /= total;
+    }
+
+    let tessellation = tessellation.max(1);
+    let last = pts.len() - 1;
+    let mut strip = Strip { vertices: vec![] };
+
+    // start cap
+    let (p, hw, dir) = (pts[0], half_widths[0], dirs[0]);
+    match cap {
+        CapStyle::Butt => add_straight(&mut strip, p, dir, hw, 0.0, cols[0]),
+        CapStyle::Square => {
+            let p = (p.0 - dir.0 * hw, p.1 - dir.1 * hw);
+            add_straight(&mut strip, p, dir, hw, 0.0, cols[0]);
+        }
+        CapStyle::Round => add_round_cap(
+            &mut strip,
+            p,
+            (-dir.0, -dir.1),
+            hw,
+            true,
+            tessellation,
+            0.0,
+            cols[0],
+        ),
+    }
+
+    // joins between segments
+    for i in 1..last {
+        add_join(
+            &mut strip,
+            pts[i],
+            dirs[i - 1],
+            dirs[i],
+            half_widths[i],
+            join,
+            miter_limit,
+            tessellation,
+            ts[i],
+            cols[i],
+        );
+    }
+
+    // end cap
+    let (p, hw, dir) = (pts[last], half_widths[last], dirs[last - 1]);
+    match cap {
+        CapStyle::Butt => add_straight(&mut strip, p, dir, hw, 1.0, cols[last]),
+        CapStyle::Square => {
+            let p = (p.0 + dir.0 * hw, p.1 + dir.1 * hw);
+            add_straight(&mut strip, p, dir, hw, 1.0, cols[last]);
+        }
+        CapStyle::Round => {
+            add_round_cap(&mut strip, p, dir, hw, false, tessellation, 1.0, cols[last])
+        }
+    }
+
+    render_list.prepare_to_add_triangle_strip(
+        matrix,
+        strip.vertices.len(),
+        strip.vertices[0].x,
+        strip.vertices[0].y,
+    )?;
+
+    let rp = render_list
+        .render_packets
+        .last_mut()
+        .ok_or(Error::Geometry)?;
+    let rpg = rp.get_mut_render_packet_geometry()?;
+
+    for (i, v) in strip.vertices.iter().enumerate() {
+        let (u, vv) = if i % 2 == 0 {
+            (
+                lerp(v.t, uvm.map[2], uvm.map[6]),
+                lerp(v.t, uvm.map[3], uvm.map[7]),
+            )
+        } else {
+            (
+                lerp(v.t, uvm.map[0], uvm.map[4]),
+                lerp(v.t, uvm.map[1], uvm.map[5]),
+            )
+        };
+        rpg.add_vertex(matrix, v.x, v.y, &v.col, u, vv);
+    }
+
+    Ok(())
+}
+
+// the left hand normal of a direction
+fn left_of(dir: (f32, f32)) -> (f32, f32) {
+    (-dir.1, dir.0)
+}
+
+fn offset(p: (f32, f32), n: (f32, f32), len: f32) -> (f32, f32) {
+    (p.0 + n.0 * len, p.1 + n.1 * len)
+}
+
+fn add_straight(strip: &mut Strip, p: (f32, f32), dir: (f32, f32), hw: f32, t: f32, col: &Rgb) {
+    let n = left_of(dir);
+    strip.add_pair(offset(p, n, hw), offset(p, n, -hw), t, col);
+}
+
+// a semi-circle around p, facing in the direction of outwards
+fn add_round_cap(
+    strip: &mut Strip,
+    p: (f32, f32),
+    outwards: (f32, f32),
+    hw: f32,
+    is_start: bool,
+    tessellation: usize,
+    t: f32,
+    col: &Rgb,
+) {
+    // the strip always has the stroke's left side first so the normal is
+    // taken relative to the direction of travel rather than outwards
+    let n = if is_start {
+        left_of((-outwards.0, -outwards.1))
+    } else {
+        left_of(outwards)
+    };
+
+    for k in 0..=tessellation {
+        // start caps sweep from the tip of the cap to the sides of the
+        // stroke, end caps sweep back from the sides to the tip
+        let step = if is_start { k } else { tessellation - k };
+        let angle = PI_BY_2 * step as f32 / tessellation as f32;
+        let (s, c) = (sin(angle), cos(angle));
+
+        let left = (
+            p.0 + (outwards.0 * c + n.0 * s) * hw,
+            p.1 + (outwards.1 * c + n.1 * s) * hw,
+        );
+        let right = (
+            p.0 + (outwards.0 * c - n.0 * s) * hw,
+            p.1 + (outwards.1 * c - n.1 * s) * hw,
+        );
+        strip.add_pair(left, right, t, col);
+    }
+}
+
+fn add_join(
+    strip: &mut Strip,
+    p: (f32, f32),
+    dir_in: (f32, f32),
+    dir_out: (f32, f32),
+    hw: f32,
+    join: JoinStyle,
+    miter_limit: f32,
+    tessellation: usize,
+    t: f32,
+    col: &Rgb,
+) {
+    let n_in = left_of(dir_in);
+    let n_out = left_of(dir_out);
+
+    // the miter direction bisects the two normals
+    let (mx, my) = (n_in.0 + n_out.0, n_in.1 + n_out.1);
+    let m_len = length_v2(mx, my);
+    if m_len < 0.000_01 {
+        // the stroke doubles back on itself
+        add_straight(strip, p, dir_in, hw, t, col);
+        add_straight(strip, p, dir_out, hw, t, col);
+        return;
+    }
+    let miter = (mx / m_len, my / m_len);
+    let cos_half = miter.0 * n_in.0 + miter.1 * n_in.1;
+    let miter_len = hw / cos_half;
+
+    if join == JoinStyle::Miter && miter_len <= miter_limit * hw {
+        strip.add_pair(
+            offset(p, miter, miter_len),
+            offset(p, miter, -miter_len),
+            t,
+            col,
+        );
+        return;
+    }
+
+    // the inner side of the join always meets at the (clamped) miter point
+    let inner_len = miter_len.min(miter_limit * hw);
+
+    // turning left means the outer side of the join is on the right
+    let cross = dir_in.0 * dir_out.1 - dir_in.1 * dir_out.0;
+    let outer_is_left = cross < 0.0;
+    let outer_sign = if outer_is_left { 1.0 } else { -1.0 };
+    let inner = offset(p, miter, -outer_sign * inner_len);
+
+    let mut add_outer = |outer: (f32, f32)| {
+        if outer_is_left {
+            strip.add_pair(outer, inner, t, col);
+        } else {
+            strip.add_pair(inner, outer, t, col);
+        }
+    };
+
+    let outer_in = offset(p, n_in, outer_sign * hw);
+    let outer_out = offset(p, n_out, outer_sign * hw);
+
+    if join == JoinStyle::Round {
+        let from = (outer_sign * n_in.0, outer_sign * n_in.1);
+        let to = (outer_sign * n_out.0, outer_sign * n_out.1);
+        let a_from = atan2(from.1, from.0);
+        let mut sweep = atan2(to.1, to.0) - a_from;
+        if sweep > PI {
+            sweep -= TAU;
+        } else if sweep < -PI {
+            sweep += TAU;
+        }
+        for k in 0..=tessellation {
+            let angle = a_from + sweep * k as f32 / tessellation as f32;
+            add_outer(offset(p, (cos(angle), sin(angle)), hw));
+        }
+    } else {
+        // bevel, or a miter that's exceeded the limit
+        add_outer(outer_in);
+        add_outer(outer_out);
+    }
+}
@@ -75,10 +75,10 @@ fn ring_segment(
     for i in 0..tessellation {
         let angle = unit_angle * i as f32;
 
-        let innervx = (angle.sin() * inner_radius) + position.0;
-        let innervy = (angle.cos() * inner_radius) + position.1;
-        let outervx = (angle.sin() * outer_radius) + position.0;
-        let outervy = (angle.cos() * outer_radius) + position.1;
+        let innervx = (sin(angle) * inner_radius) + position.0;
+        let innervy = (cos(angle) * inner_radius) + position.1;
+        let outervx = (sin(angle) * outer_radius) + position.0;
+        let outervy = (cos(angle) * outer_radius) + position.1;
 
         rpg.add_vertex(
             matrix,
@@ -100,10 +100,10 @@ fn ring_segment(
 
     let angle: f32 = TAU;
 
-    let innervx = (angle.sin() * inner_radius) + position.0;
-    let innervy = (angle.cos() * inner_radius) + position.1;
-    let outervx = (angle.sin() * outer_radius) + position.0;
-    let outervy = (angle.cos() * outer_radius) + position.1;
+    let innervx = (sin(angle) * inner_radius) + position.0;
+    let innervy = (cos(angle) * inner_radius) + position.1;
+    let outervx = (sin(angle) * outer_radius) + position.0;
+    let outervy = (cos(angle) * outer_radius) + position.1;
 
     rpg.add_vertex(
         matrix,
@@ -0,0 +1,82 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{Error, Result};
+use crate::mathutil::*;
+use crate::matrix::Matrix;
+use crate::render_list::RenderList;
+use crate::rgb::Rgb;
+use crate::uvmapper::UvMapping;
+
+// a rect centered at position. The corner radii are given in the order:
+// [-x -y] [+x -y] [+x +y] [-x +y] and each corner is tessellated separately
+pub fn render(
+    render_list: &mut RenderList,
+    matrix: &Matrix,
+    position: (f32, f32),
+    width: f32,
+    height: f32,
+    corner_radii: &[f32; 4],
+    colour: &Rgb,
+    tessellation: usize,
+    uvm: &UvMapping,
+) -> Result<()> {
+    // a corner needs at least one segment, zero would divide by zero below
+    let tessellation = tessellation.max(1);
+
+    let half_width = width / 2.0;
+    let half_height = height / 2.0;
+    let max_radius = half_width.min(half_height);
+
+    // the centre of each corner's arc and the angle that it starts from
+    let corners = [
+        (-1.0, -1.0, PI),
+        (1.0, -1.0, PI + PI_BY_2),
+        (1.0, 1.0, 0.0),
+        (-1.0, 1.0, PI_BY_2),
+    ];
+
+    let mut outline: Vec<(f32, f32)> = Vec::with_capacity((corners.len() * (tessellation + 1)) + 1);
+    for (i, (sx, sy, angle_start)) in corners.iter().enumerate() {
+        let radius = clamp(corner_radii[i], 0.0, max_radius);
+        let cx = position.0 + sx * (half_width - radius);
+        let cy = position.1 + sy * (half_height - radius);
+
+        for j in 0..=tessellation {
+            let angle = angle_start + (PI_BY_2 * j as f32 / tessellation as f32);
+            outline.push((cx + cos(angle) * radius, cy + sin(angle) * radius));
+        }
+    }
+    outline.push(outline[0]);
+
+    render_list.prepare_to_add_triangle_strip(matrix, outline.len() * 2, position.0, position.1)?;
+
+    let rp = render_list
+        .render_packets
+        .last_mut()
+        .ok_or(Error::Geometry)?;
+    let rpg = rp.get_mut_render_packet_geometry()?;
+
+    for (vx, vy) in outline {
+        rpg.add_vertex(
+            matrix, position.0, position.1, colour, uvm.map[4], uvm.map[5],
+        );
+        rpg.add_vertex(matrix, vx, vy, colour, uvm.map[4], uvm.map[5]);
+    }
+
+    Ok(())
+}
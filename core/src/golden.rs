@@ -0,0 +1,339 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Regression testing of the gallery scripts against checked-in goldens.
+//
+// Every script is rendered with a fixed set of seeds and a summary of the
+// resulting render list is compared against the golden. An identical geometry
+// hash is a pass, otherwise the vertex count, bounds and float sum have to be
+// within a tolerance.
+//
+// To regenerate the goldens after an intentional change:
+//   make bless-goldens
+
+use crate::bitmap_cache::BitmapCache;
+use crate::build_traits;
+use crate::error::{Error, Result};
+use crate::gene::Genotype;
+use crate::population::render_population;
+use crate::render_list::RenderList;
+use crate::render_packet::{RenderPacket, RENDER_PACKET_FLOAT_PER_VERTEX};
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use log::error;
+
+pub const GOLDEN_SEEDS: [i32; 2] = [1, 977];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Golden {
+    pub script: String,
+    pub seed: i32,
+    pub hash: u64,
+    pub num_vertices: usize,
+    // sum of every float in the render list, a cheap check that's tolerant of
+    // small precision differences
+    pub sum: f64,
+    // min x, min y, max x, max y of the vertex positions
+    pub bounds: [f32; 4],
+}
+
+impl Golden {
+    pub fn from_render_list(script: &str, seed: i32, render_list: &RenderList) -> Self {
+        let mut sum = 0.0;
+        let mut bounds = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+
+        for rp in &render_list.render_packets {
+            if let RenderPacket::Geometry(rpg) = rp {
+                for f in &rpg.geo {
+                    sum += f64::from(*f);
+                }
+                for vert in rpg.geo.chunks(RENDER_PACKET_FLOAT_PER_VERTEX) {
+                    bounds[0] = bounds[0].min(vert[0]);
+                    bounds[1] = bounds[1].min(vert[1]);
+                    bounds[2] = bounds[2].max(vert[0]);
+                    bounds[3] = bounds[3].max(vert[1]);
+                }
+            }
+        }
+
+        let num_vertices = render_list.get_num_vertices();
+        if num_vertices == 0 {
+            bounds = [0.0; 4];
+        }
+
+        Golden {
+            script: script.to_string(),
+            seed,
+            hash: render_list.geometry_hash(),
+            num_vertices,
+            sum,
+            bounds,
+        }
+    }
+
+    /// format used in the goldens file, one line per script and seed
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {} {:016x} {} {:.3} {:.3} {:.3} {:.3} {:.3}",
+            self.script,
+            self.seed,
+            self.hash,
+            self.num_vertices,
+            self.sum,
+            self.bounds[0],
+            self.bounds[1],
+            self.bounds[2],
+            self.bounds[3]
+        )
+    }
+
+    pub fn from_line(line: &str) -> Result<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 9 {
+            error!("Golden::from_line: malformed line: {}", line);
+            return Err(Error::Golden);
+        }
+
+        Ok(Golden {
+            script: parts[0].to_string(),
+            seed: parts[1].parse::<i32>()?,
+            hash: u64::from_str_radix(parts[2], 16)?,
+            num_vertices: parts[3].parse::<usize>()?,
+            sum: parts[4].parse::<f64>()?,
+            bounds: [
+                parts[5].parse::<f32>()?,
+                parts[6].parse::<f32>()?,
+                parts[7].parse::<f32>()?,
+                parts[8].parse::<f32>()?,
+            ],
+        })
+    }
+
+    /// None if actual is a match for self, otherwise a description of the differences
+    pub fn diff(&self, actual: &Golden, tolerance: f64) -> Option<String> {
+        if self.hash == actual.hash {
+            return None;
+        }
+
+        let close = |a: f64, b: f64| (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0);
+
+        let mut res = String::new();
+        if self.num_vertices != actual.num_vertices {
+            let _ = writeln!(
+                res,
+                "  vertices: expected {} actual {}",
+                self.num_vertices, actual.num_vertices
+            );
+        }
+        if !close(self.sum, actual.sum) {
+            let _ = writeln!(
+                res,
+                "  sum: expected {:.3} actual {:.3}",
+                self.sum, actual.sum
+            );
+        }
+        let names = ["min x", "min y", "max x", "max y"];
+        for (i, name) in names.iter().enumerate() {
+            let (e, a) = (self.bounds[i], actual.bounds[i]);
+            if !close(f64::from(e), f64::from(a)) {
+                let _ = writeln!(res, "  {}: expected {:.3} actual {:.3}", name, e, a);
+            }
+        }
+
+        if res.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "{} seed {} (hash {:016x} -> {:016x})\n{}",
+                self.script, self.seed, self.hash, actual.hash, res
+            ))
+        }
+    }
+}
+
+/// the name and source of every script in the given directory, sorted by name
+pub fn gallery_scripts(dir: &Path) -> Result<Vec<(String, String)>> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        error!("gallery_scripts: can't read {:?}: {}", dir, e);
+        Error::Golden
+    })?;
+
+    let mut scripts = vec![];
+    for entry in entries {
+        let path = entry.map_err(|_| Error::Golden)?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("seni") {
+            continue;
+        }
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let source = fs::read_to_string(&path).map_err(|_| Error::Golden)?;
+        scripts.push((name, source));
+    }
+    scripts.sort();
+
+    Ok(scripts)
+}
+
+/// Renders the script with each of the seeds. Scripts which can't be rendered
+/// outside of the client (e.g. they require bitmaps) return an error.
+pub fn render_goldens(
+    script: &str,
+    source: &str,
+    seeds: &[i32],
+    bitmap_cache: &BitmapCache,
+    num_threads: usize,
+) -> Result<Vec<Golden>> {
+    let trait_list = build_traits(source)?;
+    let genotypes = seeds
+        .iter()
+        .map(|seed| Genotype::build_from_seed(&trait_list, *seed))
+        .collect::<Result<Vec<Genotype>>>()?;
+
+    let render_lists = render_population(
+        source,
+        &genotypes,
+        bitmap_cache,
+        &Default::default(),
+        num_threads,
+    )?;
+
+    Ok(seeds
+        .iter()
+        .zip(render_lists.iter())
+        .map(|(seed, render_list)| Golden::from_render_list(script, *seed, render_list))
+        .collect())
+}
+
+pub fn read_goldens(path: &Path) -> Result<Vec<Golden>> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        error!("read_goldens: can't read {:?}: {}", path, e);
+        Error::Golden
+    })?;
+
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Golden::from_line)
+        .collect()
+}
+
+pub fn write_goldens(path: &Path, goldens: &[Golden]) -> Result<()> {
+    let mut contents = String::new();
+    contents.push_str("# script seed hash vertices sum min-x min-y max-x max-y\n");
+    for golden in goldens {
+        contents.push_str(&golden.to_line());
+        contents.push('\n');
+    }
+
+    fs::write(path, contents).map_err(|e| {
+        error!("write_goldens: can't write {:?}: {}", path, e);
+        Error::Golden
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-4;
+
+    fn manifest_path(p: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(p)
+    }
+
+    #[test]
+    fn test_golden_line_roundtrip() {
+        let golden = Golden {
+            script: "a.seni".to_string(),
+            seed: 42,
+            hash: 0x0123_4567_89ab_cdef,
+            num_vertices: 1000,
+            sum: 1234.5,
+            bounds: [-1.5, 0.0, 999.25, 1000.0],
+        };
+        let parsed = Golden::from_line(&golden.to_line()).unwrap();
+        assert_eq!(parsed, golden);
+        assert_eq!(golden.diff(&parsed, TOLERANCE), None);
+
+        let mut moved = golden.clone();
+        moved.hash = 1;
+        assert_eq!(golden.diff(&moved, TOLERANCE), None);
+        moved.bounds[2] = 1010.0;
+        assert!(golden.diff(&moved, TOLERANCE).unwrap().contains("max x"));
+    }
+
+    // set SENI_BLESS=1 to overwrite the goldens with the current output
+    #[test]
+    fn test_gallery_goldens() {
+        let goldens_path = manifest_path("golden/gallery.txt");
+        let diff_path = manifest_path("target/golden-diff.txt");
+        let bless = std::env::var("SENI_BLESS").is_ok();
+
+        let bitmap_cache: BitmapCache = Default::default();
+        let mut actual: Vec<Golden> = vec![];
+        for (name, source) in gallery_scripts(&manifest_path("../server/db/seni")).unwrap() {
+            if let Ok(goldens) = render_goldens(&name, &source, &GOLDEN_SEEDS, &bitmap_cache, 4) {
+                actual.extend(goldens);
+            }
+        }
+
+        if bless {
+            write_goldens(&goldens_path, &actual).unwrap();
+            return;
+        }
+
+        let expected = read_goldens(&goldens_path).unwrap();
+        let mut report = String::new();
+
+        for e in &expected {
+            match actual
+                .iter()
+                .find(|a| a.script == e.script && a.seed == e.seed)
+            {
+                Some(a) => {
+                    if let Some(diff) = e.diff(a, TOLERANCE) {
+                        report.push_str(&diff);
+                    }
+                }
+                None => {
+                    let _ = writeln!(report, "{} seed {} no longer renders", e.script, e.seed);
+                }
+            }
+        }
+        for a in &actual {
+            if !expected
+                .iter()
+                .any(|e| e.script == a.script && e.seed == a.seed)
+            {
+                let _ = writeln!(report, "{} seed {} has no golden", a.script, a.seed);
+            }
+        }
+
+        if !report.is_empty() {
+            let _ = fs::write(&diff_path, &report);
+            panic!(
+                "gallery doesn't match the goldens (written to {:?}):\n{}",
+                diff_path, report
+            );
+        }
+    }
+}
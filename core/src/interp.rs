@@ -91,11 +91,11 @@ pub fn scalar(a: f32, b: f32, mapping: ease::Easing, clamping: bool, t: f32) ->
 }
 
 pub fn cos(amplitude: f32, frequency: f32, t: f32) -> f32 {
-    amplitude * (t * frequency).cos()
+    amplitude * mathutil::cos(t * frequency)
 }
 
 pub fn sin(amplitude: f32, frequency: f32, t: f32) -> f32 {
-    amplitude * (t * frequency).sin()
+    amplitude * mathutil::sin(t * frequency)
 }
 
 pub fn bezier(coords: &[f32; 8], t: f32) -> (f32, f32) {
@@ -116,8 +116,8 @@ pub fn circle(position: (f32, f32), radius: f32, t: f32) -> (f32, f32) {
     let angle = t * mathutil::TAU;
 
     (
-        (angle.sin() * radius) + position.0,
-        (angle.cos() * radius) + position.1,
+        (mathutil::sin(angle) * radius) + position.0,
+        (mathutil::cos(angle) * radius) + position.1,
     )
 }
 
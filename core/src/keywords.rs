@@ -114,6 +114,12 @@ pub enum Keyword {
     Lab,
     #[strum(serialize = "HSV")]
     Hsv,
+    #[strum(serialize = "LCH")]
+    Lch,
+    #[strum(serialize = "OKLAB")]
+    Oklab,
+    #[strum(serialize = "OKLCH")]
+    Oklch,
 
     // pre-defined colours
     //
@@ -174,6 +180,90 @@ pub enum Keyword {
     #[strum(serialize = "brush/*")] // globally mapped in preamble
     BrushAll,
 
+    // stroke joins and caps
+    //
+    #[strum(serialize = "join/miter")]
+    JoinMiter,
+    #[strum(serialize = "join/round")]
+    JoinRound,
+    #[strum(serialize = "join/bevel")]
+    JoinBevel,
+    #[strum(serialize = "cap/butt")]
+    CapButt,
+    #[strum(serialize = "cap/round")]
+    CapRound,
+    #[strum(serialize = "cap/square")]
+    CapSquare,
+
+    // blend modes
+    //
+    #[strum(serialize = "blend/normal")]
+    BlendNormal,
+    #[strum(serialize = "blend/multiply")]
+    BlendMultiply,
+    #[strum(serialize = "blend/screen")]
+    BlendScreen,
+    #[strum(serialize = "blend/overlay")]
+    BlendOverlay,
+    #[strum(serialize = "blend/additive")]
+    BlendAdditive,
+    #[strum(serialize = "blend/darken")]
+    BlendDarken,
+    #[strum(serialize = "blend/lighten")]
+    BlendLighten,
+
+    // noise
+    //
+    #[strum(serialize = "basis/perlin")]
+    BasisPerlin,
+    #[strum(serialize = "basis/simplex")]
+    BasisSimplex,
+    #[strum(serialize = "basis/worley")]
+    BasisWorley,
+    #[strum(serialize = "worley/f1")]
+    WorleyF1,
+    #[strum(serialize = "worley/f2")]
+    WorleyF2,
+    #[strum(serialize = "worley/f2-f1")]
+    WorleyF2MinusF1,
+
+    // wallpaper groups
+    //
+    #[strum(serialize = "wallpaper/p1")]
+    WallpaperP1,
+    #[strum(serialize = "wallpaper/p2")]
+    WallpaperP2,
+    #[strum(serialize = "wallpaper/pm")]
+    WallpaperPm,
+    #[strum(serialize = "wallpaper/pg")]
+    WallpaperPg,
+    #[strum(serialize = "wallpaper/cm")]
+    WallpaperCm,
+    #[strum(serialize = "wallpaper/pmm")]
+    WallpaperPmm,
+    #[strum(serialize = "wallpaper/pmg")]
+    WallpaperPmg,
+    #[strum(serialize = "wallpaper/pgg")]
+    WallpaperPgg,
+    #[strum(serialize = "wallpaper/cmm")]
+    WallpaperCmm,
+    #[strum(serialize = "wallpaper/p4")]
+    WallpaperP4,
+    #[strum(serialize = "wallpaper/p4m")]
+    WallpaperP4m,
+    #[strum(serialize = "wallpaper/p4g")]
+    WallpaperP4g,
+    #[strum(serialize = "wallpaper/p3")]
+    WallpaperP3,
+    #[strum(serialize = "wallpaper/p3m1")]
+    WallpaperP3m1,
+    #[strum(serialize = "wallpaper/p31m")]
+    WallpaperP31m,
+    #[strum(serialize = "wallpaper/p6")]
+    WallpaperP6,
+    #[strum(serialize = "wallpaper/p6m")]
+    WallpaperP6m,
+
     // interpolation
     //
     #[strum(serialize = "linear")]
@@ -276,6 +366,8 @@ pub enum Keyword {
     U,
     #[strum(serialize = "v")]
     V,
+    #[strum(serialize = "w")]
+    W,
     #[strum(serialize = "x")]
     X,
     #[strum(serialize = "y")]
@@ -292,6 +384,18 @@ pub enum Keyword {
     AngleEnd,
     #[strum(serialize = "angle-start")]
     AngleStart,
+    #[strum(serialize = "attractors")]
+    Attractors,
+    #[strum(serialize = "attraction")]
+    Attraction,
+    #[strum(serialize = "axiom")]
+    Axiom,
+    #[strum(serialize = "basis")]
+    Basis,
+    #[strum(serialize = "blend")]
+    Blend,
+    #[strum(serialize = "blur")]
+    Blur,
     #[strum(serialize = "brightness")]
     Brightness,
     #[strum(serialize = "brush")]
@@ -300,12 +404,18 @@ pub enum Keyword {
     BrushSubtype,
     #[strum(serialize = "by")]
     By,
+    #[strum(serialize = "cap")]
+    Cap,
     #[strum(serialize = "clamping")]
     Clamping,
+    #[strum(serialize = "clip")]
+    Clip,
     #[strum(serialize = "colour")]
     Colour,
     #[strum(serialize = "colour-volatility")]
     ColourVolatility,
+    #[strum(serialize = "columns")]
+    Columns,
     #[strum(serialize = "colours")]
     Colours,
     #[strum(serialize = "contrast")]
@@ -316,12 +426,24 @@ pub enum Keyword {
     Copies,
     #[strum(serialize = "copy")]
     Copy,
+    #[strum(serialize = "corner-radii")]
+    CornerRadii,
+    #[strum(serialize = "cross")]
+    Cross,
+    #[strum(serialize = "damping")]
+    Damping,
     #[strum(serialize = "default-colour")]
     DefaultColour,
+    #[strum(serialize = "depth")]
+    Depth,
     #[strum(serialize = "direction")]
     Direction,
     #[strum(serialize = "distance")]
     Distance,
+    #[strum(serialize = "exponent")]
+    Exponent,
+    #[strum(serialize = "feature")]
+    Feature,
     #[strum(serialize = "format")]
     Format,
     #[strum(serialize = "frequency")]
@@ -330,6 +452,10 @@ pub enum Keyword {
     From,
     #[strum(serialize = "from-colour")]
     FromColour,
+    #[strum(serialize = "gain")]
+    Gain,
+    #[strum(serialize = "group")]
+    Group,
     #[strum(serialize = "height")]
     Height,
     #[strum(serialize = "inc")]
@@ -348,6 +474,16 @@ pub enum Keyword {
     Invert,
     #[strum(serialize = "iterations")]
     Iterations,
+    #[strum(serialize = "jitter")]
+    Jitter,
+    #[strum(serialize = "join")]
+    Join,
+    #[strum(serialize = "lacunarity")]
+    Lacunarity,
+    #[strum(serialize = "length")]
+    Length,
+    #[strum(serialize = "lightness")]
+    Lightness,
     #[strum(serialize = "linear-colour-space")]
     LinearColourSpace,
     #[strum(serialize = "line-width")]
@@ -364,8 +500,22 @@ pub enum Keyword {
     Max,
     #[strum(serialize = "min")]
     Min,
+    #[strum(serialize = "min-separation")]
+    MinSeparation,
+    #[strum(serialize = "miter-limit")]
+    MiterLimit,
+    #[strum(serialize = "mode")]
+    Mode,
+    #[strum(serialize = "name")]
+    Name,
+    #[strum(serialize = "neighbours")]
+    Neighbours,
     #[strum(serialize = "num")]
     Num,
+    #[strum(serialize = "octaves")]
+    Octaves,
+    #[strum(serialize = "opacity")]
+    Opacity,
     #[strum(serialize = "outer-colour")]
     OuterColour,
     #[strum(serialize = "outer-radius")]
@@ -374,12 +524,22 @@ pub enum Keyword {
     Overlap,
     #[strum(serialize = "point")]
     Point,
+    #[strum(serialize = "points")]
+    Points,
     #[strum(serialize = "position")]
     Position,
     #[strum(serialize = "preset")]
     Preset,
     #[strum(serialize = "radius")]
     Radius,
+    #[strum(serialize = "render")]
+    Render,
+    #[strum(serialize = "rng")]
+    Rng,
+    #[strum(serialize = "rows")]
+    Rows,
+    #[strum(serialize = "rules")]
+    Rules,
     #[strum(serialize = "saturation")]
     Saturation,
     #[strum(serialize = "scalar")]
@@ -390,8 +550,18 @@ pub enum Keyword {
     Seed,
     #[strum(serialize = "shuffle-seed")]
     ShuffleSeed,
+    #[strum(serialize = "sort")]
+    Sort,
+    #[strum(serialize = "spacing")]
+    Spacing,
+    #[strum(serialize = "step-size")]
+    StepSize,
     #[strum(serialize = "steps")]
     Steps,
+    #[strum(serialize = "stops")]
+    Stops,
+    #[strum(serialize = "strength")]
+    Strength,
     #[strum(serialize = "stroke-line-width-end")]
     StrokeLineWidthEnd,
     #[strum(serialize = "stroke-line-width-start")]
@@ -412,6 +582,8 @@ pub enum Keyword {
     To,
     #[strum(serialize = "to-colour")]
     ToColour,
+    #[strum(serialize = "triangulate")]
+    Triangulate,
     #[strum(serialize = "upto")]
     Upto,
     #[strum(serialize = "value")]
@@ -422,10 +594,14 @@ pub enum Keyword {
     Vec2,
     #[strum(serialize = "vector")]
     Vector,
+    #[strum(serialize = "velocity")]
+    Velocity,
     #[strum(serialize = "volatility")]
     Volatility,
     #[strum(serialize = "width")]
     Width,
+    #[strum(serialize = "widths")]
+    Widths,
     #[strum(serialize = "worldspace")]
     WorldSpace,
 
@@ -36,48 +36,71 @@ mod bitmap;
 mod bitmap_cache;
 pub mod colour;
 mod colour_palettes;
+mod compile_cache;
 mod compiler;
 pub mod constants;
 mod context;
 mod ease;
 pub mod error;
+mod fill;
+mod flow;
 mod focal;
 mod gene;
+mod geom;
 mod geometry;
+mod golden;
 mod iname;
 mod interp;
 mod keywords;
 mod lexer;
+mod lsystem;
 mod mathutil;
 mod matrix;
 mod native;
 mod node;
+mod noise;
 mod opcodes;
 mod packable;
+mod palette_cache;
 mod parser;
+mod particle;
 mod path;
+mod polygon;
+mod population;
 mod prng;
+mod profiler;
 mod program;
 mod render_list;
 mod render_packet;
 mod repeat;
 mod rgb;
+mod sample;
 mod trait_list;
 mod unparser;
 mod uvmapper;
 mod vm;
 
 pub use crate::bitmap_cache::{BitmapCache, BitmapInfo};
+pub use crate::compile_cache::{CompileCache, ProgramTemplate};
 pub use crate::compiler::{compile_preamble, compile_program, compile_program_with_genotype};
 pub use crate::context::Context;
 pub use crate::error::{Error, Result};
 pub use crate::gene::{next_generation, Genotype};
+pub use crate::golden::{
+    gallery_scripts, read_goldens, render_goldens, write_goldens, Golden, GOLDEN_SEEDS,
+};
 pub use crate::packable::Packable;
+pub use crate::palette_cache::PaletteCache;
 pub use crate::parser::{parse, WordLut};
+pub use crate::population::render_population;
+pub use crate::profiler::{
+    format_profile_table, ProfileMetric, ProfileRow, ProfileStats, Profiler,
+};
 pub use crate::program::Program;
 pub use crate::render_list::{RPCommand, RenderList};
 pub use crate::render_packet::{
-    RenderPacket, RenderPacketGeometry, RenderPacketImage, RenderPacketMask,
+    BlendMode, RenderPacket, RenderPacketBlend, RenderPacketGeometry, RenderPacketImage,
+    RenderPacketLayer, RenderPacketMask,
 };
 pub use crate::trait_list::TraitList;
 pub use crate::unparser::{simplified_unparse, unparse};
@@ -87,13 +110,22 @@ pub fn run_program_with_preamble(
     vm: &mut Vm,
     context: &mut Context,
     program: &Program,
+) -> Result<Var> {
+    let preamble = compile_preamble()?;
+    run_program_with_compiled_preamble(vm, context, &preamble, program)
+}
+
+pub fn run_program_with_compiled_preamble(
+    vm: &mut Vm,
+    context: &mut Context,
+    preamble: &Program,
+    program: &Program,
 ) -> Result<Var> {
     context.reset_for_piece();
     vm.reset();
 
     // setup the env with the global variables in preamble
-    let preamble = compile_preamble()?;
-    vm.interpret(context, &preamble)?;
+    vm.interpret(context, preamble)?;
 
     // reset the ip and setup any profiling of the main program
     vm.init_for_main_program(&program, VMProfiling::Off)?;
@@ -124,6 +156,14 @@ pub fn bitmaps_to_transfer(program: &Program, context: &Context) -> Vec<String>
     context.bitmap_cache.uncached(bitmap_strings)
 }
 
+pub fn palettes_to_transfer(program: &Program, context: &Context) -> Vec<String> {
+    // the palette files used by the current program
+    let palette_strings = program.data.palette_strings();
+
+    // keep the names that haven't already been imported
+    context.palette_cache.uncached(palette_strings)
+}
+
 pub fn build_traits(s: &str) -> Result<TraitList> {
     let (ast, word_lut) = parse(s)?;
     let trait_list = TraitList::compile(&ast, &word_lut)?;
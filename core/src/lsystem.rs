@@ -0,0 +1,246 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Lindenmayer systems: a string of symbols is rewritten a number of times by
+// replacing each symbol with the successor of its rule, the result is then
+// interpreted as instructions for a turtle.
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::keywords::Keyword;
+use crate::mathutil::PI;
+use crate::prng::PrngStateStruct;
+use crate::program::Program;
+use crate::vm::{Var, Vm};
+
+use std::collections::HashMap;
+
+use log::error;
+
+// a few iterations of a branching rule grow exponentially
+const MAX_SYMBOLS: usize = 1_000_000;
+
+// rules that don't grow the symbols would otherwise be rewritten forever
+const MAX_ITERATIONS: usize = 100;
+
+pub struct Rule {
+    pub predecessor: char,
+    pub successor: Vec<char>,
+    pub weight: f32,
+}
+
+// When a symbol has several rules one of them is picked at random, in
+// proportion to their weights, using the prng. Without a prng the first
+// rule for the symbol is always used.
+pub fn expand(
+    axiom: &[char],
+    rules: &[Rule],
+    iterations: usize,
+    mut prng: Option<&mut PrngStateStruct>,
+) -> Result<Vec<char>> {
+    if iterations > MAX_ITERATIONS {
+        error!(
+            "lsystem/expand: {} iterations is more than the limit of {}",
+            iterations, MAX_ITERATIONS
+        );
+        return Err(Error::Native);
+    }
+
+    let mut lookup: HashMap<char, Vec<&Rule>> = HashMap::new();
+    for rule in rules {
+        lookup.entry(rule.predecessor).or_default().push(rule);
+    }
+
+    let mut symbols = axiom.to_vec();
+    for _ in 0..iterations {
+        let mut next = Vec::with_capacity(symbols.len());
+        for c in &symbols {
+            match lookup.get(c) {
+                Some(candidates) => {
+                    let rule = match prng.as_mut() {
+                        Some(prng) if candidates.len() > 1 => choose(candidates, prng),
+                        _ => candidates[0],
+                    };
+                    next.extend_from_slice(&rule.successor);
+                }
+                None => next.push(*c),
+            }
+
+            if next.len() > MAX_SYMBOLS {
+                error!("lsystem/expand: more than {} symbols", MAX_SYMBOLS);
+                return Err(Error::Native);
+            }
+        }
+        symbols = next;
+    }
+
+    Ok(symbols)
+}
+
+fn choose<'a>(candidates: &[&'a Rule], prng: &mut PrngStateStruct) -> &'a Rule {
+    let total: f32 = candidates.iter().map(|rule| rule.weight.max(0.0)).sum();
+    let mut r = prng.next_f32() * total;
+    for rule in candidates {
+        r -= rule.weight.max(0.0);
+        if r < 0.0 {
+            return rule;
+        }
+    }
+    candidates[candidates.len() - 1]
+}
+
+// invoke a function with 3 args: n, depth and length
+fn invoke_function(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    fun: usize,
+    n: usize,
+    depth: usize,
+    length: f32,
+) -> Result<()> {
+    let args = [
+        (Keyword::N, Var::Float(n as f32)),
+        (Keyword::Depth, Var::Float(depth as f32)),
+        (Keyword::Length, Var::Float(length)),
+    ];
+
+    context.matrix_stack.push();
+    let res = vm.invoke_function(context, program, fun, &args);
+    context.matrix_stack.pop();
+
+    res.map(|_| ())
+}
+
+// The turtle starts at the origin facing up the y axis:
+//
+// F, G : invoke fun for a segment from [0 0] to [0 length] then move to its end
+// f    : move forward by length without invoking fun
+// +, - : turn anticlockwise or clockwise by angle (in radians)
+// |    : turn around
+// [, ] : push and pop the turtle's state
+//
+// all other symbols are ignored
+pub fn turtle(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    symbols: &[char],
+    fun: usize,
+    length: f32,
+    angle: f32,
+) -> Result<()> {
+    let mut n = 0;
+    let mut depth = 0;
+
+    context.matrix_stack.push();
+    for c in symbols {
+        match c {
+            'F' | 'G' => {
+                invoke_function(vm, context, program, fun, n, depth, length)?;
+                context.matrix_stack.translate(0.0, length);
+                n += 1;
+            }
+            'f' => context.matrix_stack.translate(0.0, length),
+            '+' => context.matrix_stack.rotate(angle),
+            '-' => context.matrix_stack.rotate(-angle),
+            '|' => context.matrix_stack.rotate(PI),
+            '[' => {
+                context.matrix_stack.push();
+                depth += 1;
+            }
+            // unbalanced brackets shouldn't pop matrices that were pushed
+            // before the turtle started
+            ']' if depth > 0 => {
+                context.matrix_stack.pop();
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    for _ in 0..=depth {
+        context.matrix_stack.pop();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(predecessor: char, successor: &str, weight: f32) -> Rule {
+        Rule {
+            predecessor,
+            successor: successor.chars().collect(),
+            weight,
+        }
+    }
+
+    fn expand_str(axiom: &str, rules: &[Rule], iterations: usize) -> String {
+        let axiom: Vec<char> = axiom.chars().collect();
+        expand(&axiom, rules, iterations, None)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_expand() {
+        // Lindenmayer's algae
+        let algae = [rule('A', "AB", 1.0), rule('B', "A", 1.0)];
+        assert_eq!(expand_str("A", &algae, 0), "A");
+        assert_eq!(expand_str("A", &algae, 1), "AB");
+        assert_eq!(expand_str("A", &algae, 4), "ABAABABA");
+
+        // symbols without rules are kept
+        let koch = [rule('F', "F+F-F-F+F", 1.0)];
+        assert_eq!(expand_str("F", &koch, 1), "F+F-F-F+F");
+        assert_eq!(expand_str("F", &koch, 3).len(), 1 + 8 * (1 + 5 + 25));
+    }
+
+    #[test]
+    fn test_expand_stochastic() {
+        let rules = [rule('F', "a", 1.0), rule('F', "b", 3.0)];
+        let axiom: Vec<char> = "F".repeat(1000).chars().collect();
+
+        let mut prng = PrngStateStruct::new(42, 0.0, 1.0);
+        let res = expand(&axiom, &rules, 1, Some(&mut prng)).unwrap();
+        let num_a = res.iter().filter(|c| **c == 'a').count();
+        assert!(num_a > 200 && num_a < 300);
+
+        // without a prng the first rule is used
+        let res = expand(&axiom, &rules, 1, None).unwrap();
+        assert!(res.iter().all(|c| *c == 'a'));
+
+        let mut prng = PrngStateStruct::new(42, 0.0, 1.0);
+        let again = expand(&axiom, &rules, 1, Some(&mut prng)).unwrap();
+        let mut prng = PrngStateStruct::new(42, 0.0, 1.0);
+        assert_eq!(again, expand(&axiom, &rules, 1, Some(&mut prng)).unwrap());
+    }
+
+    #[test]
+    fn test_expand_limit() {
+        let rules = [rule('F', "FF", 1.0)];
+        assert!(expand(&['F'], &rules, 30, None).is_err());
+
+        // an identity rule never grows but there's still a limit
+        let identity = [rule('F', "F", 1.0)];
+        assert_eq!(expand_str("F", &identity, 100), "F");
+        assert!(expand(&['F'], &identity, 10_000_000_000, None).is_err());
+    }
+}
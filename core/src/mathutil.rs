@@ -19,6 +19,59 @@ pub const PI: f32 = std::f32::consts::PI;
 pub const PI_BY_2: f32 = std::f32::consts::FRAC_PI_2;
 pub const TAU: f32 = std::f32::consts::PI * 2.0;
 
+// The transcendental functions in std call into the platform's maths library
+// and so can give different results on native builds and on wasm. Any float
+// that ends up in the render list should use these instead, they're
+// implemented in Rust by libm and are bit-identical everywhere.
+
+pub fn sin(x: f32) -> f32 {
+    libm::sinf(x)
+}
+
+pub fn cos(x: f32) -> f32 {
+    libm::cosf(x)
+}
+
+pub fn powf(x: f32, y: f32) -> f32 {
+    libm::powf(x, y)
+}
+
+pub fn tan(x: f32) -> f32 {
+    libm::tanf(x)
+}
+
+pub fn atan2(y: f32, x: f32) -> f32 {
+    libm::atan2f(y, x)
+}
+
+pub fn exp(x: f32) -> f32 {
+    libm::expf(x)
+}
+
+pub fn ln(x: f32) -> f32 {
+    libm::logf(x)
+}
+
+pub fn sin_f64(x: f64) -> f64 {
+    libm::sin(x)
+}
+
+pub fn cos_f64(x: f64) -> f64 {
+    libm::cos(x)
+}
+
+pub fn powf_f64(x: f64, y: f64) -> f64 {
+    libm::pow(x, y)
+}
+
+pub fn cbrt_f64(x: f64) -> f64 {
+    libm::cbrt(x)
+}
+
+pub fn atan2_f64(y: f64, x: f64) -> f64 {
+    libm::atan2(y, x)
+}
+
 pub fn deg_to_rad(a: f32) -> f32 {
     a * (PI / 180.0)
 }
@@ -57,12 +110,12 @@ pub fn map_quick_ease(x: f32) -> f32 {
 }
 
 pub fn map_slow_ease_in(x: f32) -> f32 {
-    let s = (x * PI_BY_2).sin();
+    let s = sin(x * PI_BY_2);
     s * s * s * s
 }
 
 pub fn map_slow_ease_in_ease_out(x: f32) -> f32 {
-    x - ((x * TAU).sin() / TAU)
+    x - (sin(x * TAU) / TAU)
 }
 
 pub fn length_v2(x: f32, y: f32) -> f32 {
@@ -107,3 +160,18 @@ pub fn bezier_point(a: f32, b: f32, c: f32, d: f32, t: f32) -> f32 {
 pub fn bezier_tangent(a: f32, b: f32, c: f32, d: f32, t: f32) -> f32 {
     3.0 * t * t * (-a + 3.0 * b - 3.0 * c + d) + 6.0 * t * (a - 2.0 * b + c) + 3.0 * (-a + b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcendentals_are_bit_exact() {
+        // these values must be the same on every platform
+        assert_eq!(sin(1.0).to_bits(), 0x3f57_6aa4);
+        assert_eq!(cos(1.0).to_bits(), 0x3f0a_5140);
+        assert_eq!(powf(2.0, 0.5).to_bits(), 0x3fb5_04f3);
+        assert_eq!(cbrt_f64(2.0).to_bits(), 0x3ff4_28a2_f98d_728b);
+        assert_eq!(atan2_f64(1.0, 2.0).to_bits(), 0x3fdd_ac67_0561_bb4f);
+    }
+}
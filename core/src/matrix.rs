@@ -15,6 +15,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::mathutil;
+
 pub struct Matrix {
     m: [f32; 16],
 }
@@ -28,6 +30,15 @@ impl Matrix {
         }
     }
 
+    // the 2D affine transform: x' = a*x + c*y + e, y' = b*x + d*y + f
+    pub fn affine(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Matrix {
+            m: [
+                a, b, 0.0, 0.0, c, d, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, e, f, 0.0, 1.0,
+            ],
+        }
+    }
+
     pub fn copy_matrix(a: &Matrix) -> Self {
         Matrix { m: a.m }
     }
@@ -124,8 +135,8 @@ impl Matrix {
     }
 
     pub fn rotate_z(&mut self, rad: f32) {
-        let s = rad.sin();
-        let c = rad.cos();
+        let s = mathutil::sin(rad);
+        let c = mathutil::cos(rad);
         let a00 = self.m[0];
         let a01 = self.m[1];
         let a02 = self.m[2];
@@ -230,6 +241,36 @@ impl MatrixStack {
         self.stack[len - 1].multiply(&m);
     }
 
+    pub fn skew_x(&mut self, a: f32) {
+        let m = Matrix::affine(1.0, 0.0, mathutil::tan(a), 1.0, 0.0, 0.0);
+
+        let len = self.stack.len();
+        self.stack[len - 1].multiply(&m);
+    }
+
+    pub fn skew_y(&mut self, a: f32) {
+        let m = Matrix::affine(1.0, mathutil::tan(a), 0.0, 1.0, 0.0, 0.0);
+
+        let len = self.stack.len();
+        self.stack[len - 1].multiply(&m);
+    }
+
+    // reflect about the line that passes through (x, y) at angle a
+    pub fn reflect(&mut self, x: f32, y: f32, a: f32) {
+        let c = mathutil::cos(2.0 * a);
+        let s = mathutil::sin(2.0 * a);
+        let m = Matrix::affine(c, s, s, -c, x - (c * x + s * y), y - (s * x - c * y));
+
+        let len = self.stack.len();
+        self.stack[len - 1].multiply(&m);
+    }
+
+    // replaces the current transform rather than composing with it
+    pub fn set(&mut self, m: Matrix) {
+        let len = self.stack.len();
+        self.stack[len - 1] = m;
+    }
+
     // todo: should this return a Result? (and not the dodgy else clause)
     // is that too much of a performance hit?
     pub fn transform_vec2(&self, x: f32, y: f32) -> (f32, f32) {
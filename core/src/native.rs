@@ -15,30 +15,44 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::bitmap;
-use crate::colour::{Colour, ColourFormat, ColourPreset, ProcColourStateStruct, ProcColourType};
+use crate::bitmap::{self, PaletteOrder};
+use crate::colour::{
+    Colour, ColourFormat, ColourPreset, GradientStop, ProcColourStateStruct, ProcColourType,
+};
 use crate::constants;
 use crate::context::Context;
 use crate::ease::Easing;
 use crate::error::{Error, Result};
+use crate::fill::{self, Shape};
+use crate::flow::{self, Field};
 use crate::focal;
+use crate::geom;
+use crate::geometry::polyline::{CapStyle, JoinStyle};
 use crate::iname::Iname;
 use crate::interp;
 use crate::keywords::Keyword;
+use crate::lsystem::{self, Rule};
 use crate::mathutil;
+use crate::matrix::Matrix;
+use crate::noise::{self, NoiseBasis, WorleyFeature};
 use crate::packable::{Mule, Packable};
+use crate::particle::{self, Forces, NoiseForce, Particle};
 use crate::path;
+use crate::polygon::{self, BooleanOp};
 use crate::prng;
 use crate::program::Program;
-use crate::render_packet::{RenderPacketImage, RenderPacketMask};
-use crate::repeat;
+use crate::render_packet::{
+    BlendMode, RenderPacketBlend, RenderPacketImage, RenderPacketLayer, RenderPacketMask,
+};
+use crate::repeat::{self, Tiling, WallpaperGroup};
+use crate::sample::{self, Region};
 use crate::uvmapper::BrushType;
 use crate::vm::{StackPeek, Var, Vm};
 use log::error;
-use std::cell::{RefCell, RefMut};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::rc::Rc;
 use std::convert::TryFrom;
+use std::sync::{Arc, Mutex, MutexGuard};
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString};
 
@@ -74,10 +88,18 @@ pub enum Native {
     Circle,
     #[strum(serialize = "circle-slice")]
     CircleSlice,
+    #[strum(serialize = "ellipse")]
+    Ellipse,
+    #[strum(serialize = "arc")]
+    Arc,
     #[strum(serialize = "ring")]
     Ring,
+    #[strum(serialize = "rounded-rect")]
+    RoundedRect,
     #[strum(serialize = "poly")]
     Poly,
+    #[strum(serialize = "polyline")]
+    Polyline,
     #[strum(serialize = "quadratic")]
     Quadratic,
     #[strum(serialize = "bezier")]
@@ -99,6 +121,16 @@ pub enum Native {
     Rotate,
     #[strum(serialize = "scale")]
     Scale,
+    #[strum(serialize = "skew-x")]
+    SkewX,
+    #[strum(serialize = "skew-y")]
+    SkewY,
+    #[strum(serialize = "reflect")]
+    Reflect,
+    #[strum(serialize = "transform/set")]
+    TransformSet,
+    #[strum(serialize = "transform/point")]
+    TransformPoint,
 
     // colour
     //
@@ -116,6 +148,12 @@ pub enum Native {
     ColHSV,
     #[strum(serialize = "col/lab")]
     ColLAB,
+    #[strum(serialize = "col/lch")]
+    ColLCH,
+    #[strum(serialize = "col/oklab")]
+    ColOKLab,
+    #[strum(serialize = "col/oklch")]
+    ColOKLCH,
     #[strum(serialize = "__colour_constructor_end")]
     ColConstructorEnd_, // Special Enums required by the compiler to recognise colour constructors
     #[strum(serialize = "col/complementary")]
@@ -158,10 +196,16 @@ pub enum Native {
     ColBuildProcedural,
     #[strum(serialize = "col/build-bezier")]
     ColBuildBezier,
+    #[strum(serialize = "col/build-gradient")]
+    ColBuildGradient,
     #[strum(serialize = "col/value")]
     ColValue,
     #[strum(serialize = "col/palette")]
     ColPalette,
+    #[strum(serialize = "col/palette-from-bitmap")]
+    ColPaletteFromBitmap,
+    #[strum(serialize = "col/define-palette")]
+    ColDefinePalette,
 
     // math
     //
@@ -177,6 +221,53 @@ pub enum Native {
     MathCos,
     #[strum(serialize = "math/sin")]
     MathSin,
+    #[strum(serialize = "math/degrees->radians")]
+    MathDegreesRadians,
+    #[strum(serialize = "math/atan2")]
+    MathAtan2,
+    #[strum(serialize = "math/pow")]
+    MathPow,
+    #[strum(serialize = "math/floor")]
+    MathFloor,
+    #[strum(serialize = "math/ceil")]
+    MathCeil,
+    #[strum(serialize = "math/round")]
+    MathRound,
+    #[strum(serialize = "math/min")]
+    MathMin,
+    #[strum(serialize = "math/max")]
+    MathMax,
+    #[strum(serialize = "math/abs")]
+    MathAbs,
+    #[strum(serialize = "math/exp")]
+    MathExp,
+    #[strum(serialize = "math/log")]
+    MathLog,
+    #[strum(serialize = "math/map-range")]
+    MathMapRange,
+
+    // v2
+    //
+    #[strum(serialize = "v2/add")]
+    V2Add,
+    #[strum(serialize = "v2/sub")]
+    V2Sub,
+    #[strum(serialize = "v2/scale")]
+    V2Scale,
+    #[strum(serialize = "v2/dot")]
+    V2Dot,
+    #[strum(serialize = "v2/cross")]
+    V2Cross,
+    #[strum(serialize = "v2/rotate")]
+    V2Rotate,
+    #[strum(serialize = "v2/lerp")]
+    V2Lerp,
+    #[strum(serialize = "v2/angle")]
+    V2Angle,
+    #[strum(serialize = "v2/length")]
+    V2Length,
+    #[strum(serialize = "v2/normalize")]
+    V2Normalize,
 
     // prng
     //
@@ -189,6 +280,15 @@ pub enum Native {
     #[strum(serialize = "prng/perlin")]
     PrngPerlin,
 
+    // noise
+    //
+    #[strum(serialize = "noise/simplex")]
+    NoiseSimplex,
+    #[strum(serialize = "noise/worley")]
+    NoiseWorley,
+    #[strum(serialize = "noise/fbm")]
+    NoiseFbm,
+
     // interp
     //
     #[strum(serialize = "interp/build")]
@@ -221,6 +321,54 @@ pub enum Native {
     #[strum(serialize = "path/bezier")]
     PathBezier,
 
+    // flow
+    //
+    #[strum(serialize = "flow/trace")]
+    FlowTrace,
+
+    // sample
+    //
+    #[strum(serialize = "sample/poisson-disk")]
+    SamplePoissonDisk,
+    #[strum(serialize = "sample/jittered-grid")]
+    SampleJitteredGrid,
+    #[strum(serialize = "sample/halton")]
+    SampleHalton,
+
+    // geom
+    //
+    #[strum(serialize = "geom/delaunay")]
+    GeomDelaunay,
+    #[strum(serialize = "geom/voronoi")]
+    GeomVoronoi,
+    #[strum(serialize = "geom/union")]
+    GeomUnion,
+    #[strum(serialize = "geom/intersection")]
+    GeomIntersection,
+    #[strum(serialize = "geom/difference")]
+    GeomDifference,
+    #[strum(serialize = "geom/offset")]
+    GeomOffset,
+
+    // fill
+    //
+    #[strum(serialize = "fill/hatch")]
+    FillHatch,
+    #[strum(serialize = "fill/stipple")]
+    FillStipple,
+
+    // lsystem
+    //
+    #[strum(serialize = "lsystem/expand")]
+    LsystemExpand,
+    #[strum(serialize = "lsystem/turtle")]
+    LsystemTurtle,
+
+    // particle
+    //
+    #[strum(serialize = "particle/simulate")]
+    ParticleSimulate,
+
     // repeat
     //
     #[strum(serialize = "repeat/symmetry-vertical")]
@@ -235,6 +383,12 @@ pub enum Native {
     RepeatRotate,
     #[strum(serialize = "repeat/rotate-mirrored")]
     RepeatRotateMirrored,
+    #[strum(serialize = "repeat/wallpaper")]
+    RepeatWallpaper,
+    #[strum(serialize = "repeat/grid")]
+    RepeatGrid,
+    #[strum(serialize = "repeat/hex-grid")]
+    RepeatHexGrid,
 
     // focal
     //
@@ -263,6 +417,18 @@ pub enum Native {
     #[strum(serialize = "mask/set")]
     MaskSet,
 
+    // blending
+    //
+    #[strum(serialize = "blend/set")]
+    BlendSet,
+
+    // layers
+    //
+    #[strum(serialize = "layer/begin")]
+    LayerBegin,
+    #[strum(serialize = "layer/end")]
+    LayerEnd,
+
     // gen
     //
     #[strum(serialize = "gen/stray-int")]
@@ -323,8 +489,12 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::Rect => rect_parameter_info(),
         Native::Circle => circle_parameter_info(),
         Native::CircleSlice => circle_slice_parameter_info(),
+        Native::Ellipse => ellipse_parameter_info(),
+        Native::Arc => arc_parameter_info(),
         Native::Ring => ring_parameter_info(),
+        Native::RoundedRect => rounded_rect_parameter_info(),
         Native::Poly => poly_parameter_info(),
+        Native::Polyline => polyline_parameter_info(),
         Native::Quadratic => quadratic_parameter_info(),
         Native::Bezier => bezier_parameter_info(),
         Native::BezierBulging => bezier_bulging_parameter_info(),
@@ -335,6 +505,11 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::Translate => translate_parameter_info(),
         Native::Rotate => rotate_parameter_info(),
         Native::Scale => scale_parameter_info(),
+        Native::SkewX => skew_x_parameter_info(),
+        Native::SkewY => skew_y_parameter_info(),
+        Native::Reflect => reflect_parameter_info(),
+        Native::TransformSet => transform_set_parameter_info(),
+        Native::TransformPoint => transform_point_parameter_info(),
         // colour
         Native::ColConvert => col_convert_parameter_info(),
         Native::ColRGB => col_rgb_parameter_info(),
@@ -342,6 +517,9 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::ColHSLuv => col_hsluv_parameter_info(),
         Native::ColHSV => col_hsv_parameter_info(),
         Native::ColLAB => col_lab_parameter_info(),
+        Native::ColLCH => col_lch_parameter_info(),
+        Native::ColOKLab => col_oklab_parameter_info(),
+        Native::ColOKLCH => col_oklch_parameter_info(),
         Native::ColComplementary => col_complementary_parameter_info(),
         Native::ColSplitComplementary => col_split_complementary_parameter_info(),
         Native::ColAnalagous => col_analagous_parameter_info(),
@@ -362,8 +540,11 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::ColAddAlpha => col_add_parameter_info(),
         Native::ColBuildProcedural => col_build_procedural_parameter_info(),
         Native::ColBuildBezier => col_build_bezier_parameter_info(),
+        Native::ColBuildGradient => col_build_gradient_parameter_info(),
         Native::ColValue => col_value_parameter_info(),
         Native::ColPalette => col_palette_parameter_info(),
+        Native::ColPaletteFromBitmap => col_palette_from_bitmap_parameter_info(),
+        Native::ColDefinePalette => col_define_palette_parameter_info(),
         // math
         Native::MathDistance => math_distance_parameter_info(),
         Native::MathNormal => math_normal_parameter_info(),
@@ -371,11 +552,37 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::MathRadiansDegrees => math_radians_degrees_parameter_info(),
         Native::MathCos => math_cos_parameter_info(),
         Native::MathSin => math_sin_parameter_info(),
+        Native::MathDegreesRadians => math_unary_parameter_info(),
+        Native::MathAtan2 => math_atan2_parameter_info(),
+        Native::MathPow => math_pow_parameter_info(),
+        Native::MathFloor => math_unary_parameter_info(),
+        Native::MathCeil => math_unary_parameter_info(),
+        Native::MathRound => math_unary_parameter_info(),
+        Native::MathMin => math_binary_parameter_info(),
+        Native::MathMax => math_binary_parameter_info(),
+        Native::MathAbs => math_unary_parameter_info(),
+        Native::MathExp => math_unary_parameter_info(),
+        Native::MathLog => math_unary_parameter_info(),
+        Native::MathMapRange => math_map_range_parameter_info(),
+        // v2
+        Native::V2Add => v2_pair_parameter_info(),
+        Native::V2Sub => v2_pair_parameter_info(),
+        Native::V2Scale => v2_scale_parameter_info(),
+        Native::V2Dot => v2_pair_parameter_info(),
+        Native::V2Cross => v2_pair_parameter_info(),
+        Native::V2Rotate => v2_rotate_parameter_info(),
+        Native::V2Lerp => v2_lerp_parameter_info(),
+        Native::V2Angle => v2_single_parameter_info(),
+        Native::V2Length => v2_single_parameter_info(),
+        Native::V2Normalize => v2_single_parameter_info(),
         // prng
         Native::PrngBuild => prng_build_parameter_info(),
         Native::PrngValues => prng_values_parameter_info(),
         Native::PrngValue => prng_value_parameter_info(),
         Native::PrngPerlin => prng_perlin_parameter_info(),
+        Native::NoiseSimplex => noise_simplex_parameter_info(),
+        Native::NoiseWorley => noise_worley_parameter_info(),
+        Native::NoiseFbm => noise_fbm_parameter_info(),
         // interp
         Native::InterpBuild => interp_build_parameter_info(),
         Native::InterpValue => interp_value_parameter_info(),
@@ -391,6 +598,24 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::PathCircle => path_circle_parameter_info(),
         Native::PathSpline => path_spline_parameter_info(),
         Native::PathBezier => path_bezier_parameter_info(),
+        // flow
+        Native::FlowTrace => flow_trace_parameter_info(),
+        // sample
+        Native::SamplePoissonDisk => sample_poisson_disk_parameter_info(),
+        Native::SampleJitteredGrid => sample_jittered_grid_parameter_info(),
+        Native::SampleHalton => sample_halton_parameter_info(),
+        // geom
+        Native::GeomDelaunay => geom_delaunay_parameter_info(),
+        Native::GeomVoronoi => geom_voronoi_parameter_info(),
+        Native::GeomUnion => geom_boolean_parameter_info(),
+        Native::GeomIntersection => geom_boolean_parameter_info(),
+        Native::GeomDifference => geom_boolean_parameter_info(),
+        Native::GeomOffset => geom_offset_parameter_info(),
+        Native::FillHatch => fill_hatch_parameter_info(),
+        Native::FillStipple => fill_stipple_parameter_info(),
+        Native::LsystemExpand => lsystem_expand_parameter_info(),
+        Native::LsystemTurtle => lsystem_turtle_parameter_info(),
+        Native::ParticleSimulate => particle_simulate_parameter_info(),
         // repeat
         Native::RepeatSymmetryVertical => repeat_symmetry_vertical_parameter_info(),
         Native::RepeatSymmetryHorizontal => repeat_symmetry_horizontal_parameter_info(),
@@ -398,6 +623,9 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::RepeatSymmetry8 => repeat_symmetry_8_parameter_info(),
         Native::RepeatRotate => repeat_rotate_parameter_info(),
         Native::RepeatRotateMirrored => repeat_rotate_mirrored_parameter_info(),
+        Native::RepeatWallpaper => repeat_wallpaper_parameter_info(),
+        Native::RepeatGrid => repeat_grid_parameter_info(),
+        Native::RepeatHexGrid => repeat_hex_grid_parameter_info(),
         // focal
         Native::FocalBuildPoint => focal_build_generic_parameter_info(),
         Native::FocalBuildVLine => focal_build_generic_parameter_info(),
@@ -410,6 +638,11 @@ pub fn parameter_info(native: Native) -> Result<(Vec<(Keyword, Var)>, i32)> {
         Native::BitmapHeight => bitmap_height_parameter_info(),
         // masking
         Native::MaskSet => mask_set_parameter_info(),
+        // blending
+        Native::BlendSet => blend_set_parameter_info(),
+        // layers
+        Native::LayerBegin => layer_begin_parameter_info(),
+        Native::LayerEnd => layer_end_parameter_info(),
         // gen
         Native::GenStrayInt => gen_stray_int_parameter_info(),
         Native::GenStray => gen_stray_parameter_info(),
@@ -447,8 +680,12 @@ pub fn execute_native(
         Native::Rect => rect_execute(vm, context),
         Native::Circle => circle_execute(vm, context),
         Native::CircleSlice => circle_slice_execute(vm, context),
+        Native::Ellipse => ellipse_execute(vm, context),
+        Native::Arc => arc_execute(vm, context),
         Native::Ring => ring_execute(vm, context),
+        Native::RoundedRect => rounded_rect_execute(vm, context),
         Native::Poly => poly_execute(vm, context),
+        Native::Polyline => polyline_execute(vm, context),
         Native::Quadratic => quadratic_execute(vm, context),
         Native::Bezier => bezier_execute(vm, context),
         Native::BezierBulging => bezier_bulging_execute(vm, context),
@@ -459,6 +696,11 @@ pub fn execute_native(
         Native::Translate => translate_execute(vm, context),
         Native::Rotate => rotate_execute(vm, context),
         Native::Scale => scale_execute(vm, context),
+        Native::SkewX => skew_x_execute(vm, context),
+        Native::SkewY => skew_y_execute(vm, context),
+        Native::Reflect => reflect_execute(vm, context),
+        Native::TransformSet => transform_set_execute(vm, context),
+        Native::TransformPoint => transform_point_execute(vm, context),
         // colours
         Native::ColConvert => col_convert_execute(vm),
         Native::ColRGB => col_rgb_execute(vm),
@@ -466,6 +708,9 @@ pub fn execute_native(
         Native::ColHSLuv => col_hsluv_execute(vm),
         Native::ColHSV => col_hsv_execute(vm),
         Native::ColLAB => col_lab_execute(vm),
+        Native::ColLCH => col_lch_execute(vm),
+        Native::ColOKLab => col_oklab_execute(vm),
+        Native::ColOKLCH => col_oklch_execute(vm),
         Native::ColComplementary => col_complementary_execute(vm),
         Native::ColSplitComplementary => col_split_complementary_execute(vm),
         Native::ColAnalagous => col_analagous_execute(vm),
@@ -486,8 +731,11 @@ pub fn execute_native(
         Native::ColAddAlpha => col_add_execute(vm, 3),
         Native::ColBuildProcedural => col_build_procedural_execute(vm),
         Native::ColBuildBezier => col_build_bezier_execute(vm),
+        Native::ColBuildGradient => col_build_gradient_execute(vm),
         Native::ColValue => col_value_execute(vm),
-        Native::ColPalette => col_palette_execute(vm),
+        Native::ColPalette => col_palette_execute(vm, context, program),
+        Native::ColPaletteFromBitmap => col_palette_from_bitmap_execute(vm, context, program),
+        Native::ColDefinePalette => col_define_palette_execute(vm, context, program),
         // math
         Native::MathDistance => math_distance_execute(vm),
         Native::MathNormal => math_normal_execute(vm),
@@ -495,11 +743,37 @@ pub fn execute_native(
         Native::MathRadiansDegrees => math_radians_degrees_execute(vm),
         Native::MathCos => math_cos_execute(vm),
         Native::MathSin => math_sin_execute(vm),
+        Native::MathDegreesRadians => math_unary_execute(vm, mathutil::deg_to_rad),
+        Native::MathAtan2 => math_atan2_execute(vm),
+        Native::MathPow => math_pow_execute(vm),
+        Native::MathFloor => math_unary_execute(vm, f32::floor),
+        Native::MathCeil => math_unary_execute(vm, f32::ceil),
+        Native::MathRound => math_unary_execute(vm, f32::round),
+        Native::MathMin => math_binary_execute(vm, f32::min),
+        Native::MathMax => math_binary_execute(vm, f32::max),
+        Native::MathAbs => math_unary_execute(vm, f32::abs),
+        Native::MathExp => math_unary_execute(vm, mathutil::exp),
+        Native::MathLog => math_log_execute(vm),
+        Native::MathMapRange => math_map_range_execute(vm),
+        // v2
+        Native::V2Add => v2_add_execute(vm),
+        Native::V2Sub => v2_sub_execute(vm),
+        Native::V2Scale => v2_scale_execute(vm),
+        Native::V2Dot => v2_dot_execute(vm),
+        Native::V2Cross => v2_cross_execute(vm),
+        Native::V2Rotate => v2_rotate_execute(vm),
+        Native::V2Lerp => v2_lerp_execute(vm),
+        Native::V2Angle => v2_angle_execute(vm),
+        Native::V2Length => v2_length_execute(vm),
+        Native::V2Normalize => v2_normalize_execute(vm),
         // prng
         Native::PrngBuild => prng_build_execute(vm),
         Native::PrngValues => prng_values_execute(vm),
         Native::PrngValue => prng_value_execute(vm),
         Native::PrngPerlin => prng_perlin_execute(vm),
+        Native::NoiseSimplex => noise_simplex_execute(vm),
+        Native::NoiseWorley => noise_worley_execute(vm),
+        Native::NoiseFbm => noise_fbm_execute(vm),
         // interp
         Native::InterpBuild => interp_build_execute(vm),
         Native::InterpValue => interp_value_execute(vm),
@@ -515,6 +789,24 @@ pub fn execute_native(
         Native::PathCircle => path_circle_execute(vm, context, program),
         Native::PathSpline => path_spline_execute(vm, context, program),
         Native::PathBezier => path_bezier_execute(vm, context, program),
+        // flow
+        Native::FlowTrace => flow_trace_execute(vm, context, program),
+        // sample
+        Native::SamplePoissonDisk => sample_poisson_disk_execute(vm),
+        Native::SampleJitteredGrid => sample_jittered_grid_execute(vm),
+        Native::SampleHalton => sample_halton_execute(vm),
+        // geom
+        Native::GeomDelaunay => geom_delaunay_execute(vm),
+        Native::GeomVoronoi => geom_voronoi_execute(vm),
+        Native::GeomUnion => geom_boolean_execute(vm, BooleanOp::Union),
+        Native::GeomIntersection => geom_boolean_execute(vm, BooleanOp::Intersection),
+        Native::GeomDifference => geom_boolean_execute(vm, BooleanOp::Difference),
+        Native::GeomOffset => geom_offset_execute(vm),
+        Native::FillHatch => fill_hatch_execute(vm, context),
+        Native::FillStipple => fill_stipple_execute(vm, context, program),
+        Native::LsystemExpand => lsystem_expand_execute(vm, program),
+        Native::LsystemTurtle => lsystem_turtle_execute(vm, context, program),
+        Native::ParticleSimulate => particle_simulate_execute(vm, context, program),
         // repeat
         Native::RepeatSymmetryVertical => repeat_symmetry_vertical_execute(vm, context, program),
         Native::RepeatSymmetryHorizontal => {
@@ -524,6 +816,9 @@ pub fn execute_native(
         Native::RepeatSymmetry8 => repeat_symmetry_8_execute(vm, context, program),
         Native::RepeatRotate => repeat_rotate_execute(vm, context, program),
         Native::RepeatRotateMirrored => repeat_rotate_mirrored_execute(vm, context, program),
+        Native::RepeatWallpaper => repeat_wallpaper_execute(vm, context, program),
+        Native::RepeatGrid => repeat_grid_execute(vm, context, program),
+        Native::RepeatHexGrid => repeat_hex_grid_execute(vm, context, program),
         // focal
         Native::FocalBuildPoint => focal_build_point_execute(vm),
         Native::FocalBuildVLine => focal_build_vline_execute(vm),
@@ -536,6 +831,11 @@ pub fn execute_native(
         Native::BitmapHeight => bitmap_height_execute(vm, context, program),
         // masking
         Native::MaskSet => mask_set_execute(vm, context, program),
+        // blending
+        Native::BlendSet => blend_set_execute(vm, context),
+        // layers
+        Native::LayerBegin => layer_begin_execute(vm, context, program),
+        Native::LayerEnd => layer_end_execute(context),
         // gen
         Native::GenStrayInt => gen_stray_int_execute(vm),
         Native::GenStray => gen_stray_execute(vm),
@@ -640,9 +940,12 @@ fn ref_mut_prng_state_struct(
     stack: &[Var],
     sp: usize,
     offset: usize,
-) -> Result<RefMut<prng::PrngStateStruct>> {
+) -> Result<MutexGuard<'_, prng::PrngStateStruct>> {
     if let Var::PrngState(prng_state_struct) = &stack[sp - offset] {
-        Ok(prng_state_struct.borrow_mut())
+        prng_state_struct.lock().map_err(|_| {
+            error!("Var::PrngState is poisoned");
+            Error::Native
+        })
     } else {
         error!("expected Var::PrngState");
         Err(Error::Native)
@@ -662,6 +965,28 @@ fn to_f32_3(vecs: &[Var]) -> Result<[f32; 3]> {
     Err(Error::Native)
 }
 
+fn to_f32_4(vecs: &[Var]) -> Result<[f32; 4]> {
+    if let [Var::Float(a), Var::Float(b), Var::Float(c), Var::Float(d)] = vecs {
+        return Ok([*a, *b, *c, *d]);
+    }
+
+    error!("to_f32_4");
+    Err(Error::Native)
+}
+
+fn to_points(vecs: &[Var]) -> Result<Vec<(f32, f32)>> {
+    vecs.iter()
+        .map(|v| {
+            if let Var::V2D(x, y) = v {
+                Ok((*x, *y))
+            } else {
+                error!("to_points: expected a vector of 2D points");
+                Err(Error::Native)
+            }
+        })
+        .collect()
+}
+
 fn nth_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -1054,6 +1379,92 @@ fn circle_slice_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var
     Ok(None)
 }
 
+fn ellipse_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Width, Var::Float(4.0)),
+            (Keyword::Height, Var::Float(10.0)),
+            (Keyword::Position, Var::V2D(10.0, 10.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Tessellation, Var::Float(10.0)),
+            (Keyword::Radius, Var::Float(10.0)),
+            (Keyword::Angle, Var::Float(0.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn ellipse_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let mut width: f32 = vm.stack_peek(1)?;
+    let mut height: f32 = vm.stack_peek(2)?;
+    let position: (f32, f32) = vm.stack_peek(3)?;
+    let col: Colour = vm.stack_peek(4)?;
+    let tessellation: usize = vm.stack_peek(5)?;
+    let radius: f32 = vm.stack_peek(6)?;
+    let angle: f32 = vm.stack_peek(7)?;
+
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    if is_arg_given(default_mask, 6) {
+        // given a radius value
+        width = radius;
+        height = radius;
+    }
+
+    context.render_ellipse(position, width, height, angle, &col, tessellation)?;
+
+    Ok(None)
+}
+
+fn arc_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(10.0, 10.0)),
+            (Keyword::Radius, Var::Float(10.0)),
+            (Keyword::AngleStart, Var::Float(0.0)),
+            (Keyword::AngleEnd, Var::Float(90.0)),
+            (Keyword::Width, Var::Float(4.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Tessellation, Var::Float(10.0)),
+            (Keyword::Brush, Var::Keyword(Keyword::BrushFlat)),
+            (Keyword::BrushSubtype, Var::Float(1.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn arc_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let position: (f32, f32) = vm.stack_peek(1)?;
+    let radius: f32 = vm.stack_peek(2)?;
+    let angle_start: f32 = vm.stack_peek(3)?;
+    let angle_end: f32 = vm.stack_peek(4)?;
+    let width: f32 = vm.stack_peek(5)?;
+    let col: Colour = vm.stack_peek(6)?;
+    let tessellation: usize = vm.stack_peek(7)?;
+    let brush: Keyword = vm.stack_peek(8)?;
+    let brush_subtype: usize = vm.stack_peek(9)?;
+
+    let brush_type = read_brush(brush);
+
+    context.render_arc(
+        position,
+        radius,
+        angle_start,
+        angle_end,
+        width,
+        &col,
+        tessellation,
+        brush_type,
+        brush_subtype,
+    )?;
+
+    Ok(None)
+}
+
 fn ring_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -1090,12 +1501,53 @@ fn ring_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
     Ok(None)
 }
 
+fn rounded_rect_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Width, Var::Float(4.0)),
+            (Keyword::Height, Var::Float(10.0)),
+            (Keyword::Position, Var::V2D(10.0, 10.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Radius, Var::Float(1.0)),
+            (Keyword::CornerRadii, Var::Float(0.0)),
+            (Keyword::Tessellation, Var::Float(4.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn rounded_rect_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let width: f32 = vm.stack_peek(1)?;
+    let height: f32 = vm.stack_peek(2)?;
+    let position: (f32, f32) = vm.stack_peek(3)?;
+    let col: Colour = vm.stack_peek(4)?;
+    let radius: f32 = vm.stack_peek(5)?;
+    let tessellation: usize = vm.stack_peek(7)?;
+
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    let corner_radii = if is_arg_given(default_mask, 6) {
+        // a separate radius for each corner
+        to_f32_4(stack_peek_vars(&vm.stack, vm.sp, 6)?)?
+    } else {
+        [radius; 4]
+    };
+
+    context.render_rounded_rect(position, width, height, &corner_radii, &col, tessellation)?;
+
+    Ok(None)
+}
+
 fn poly_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Coords, Var::Bool(false)),
-            (Keyword::Colours, Var::Bool(false)),
+            (Keyword::Coords, Var::Float(0.0)),
+            (Keyword::Colours, Var::Float(0.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Triangulate, Var::Keyword(Keyword::False)),
         ],
         // stack offset
         0,
@@ -1103,18 +1555,104 @@ fn poly_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
 }
 
 fn poly_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(3)?;
+    let col: Colour = vm.stack_peek(3)?;
+    let triangulate: Keyword = vm.stack_peek(4)?;
 
-    if !is_arg_given(default_mask, 1) || !is_arg_given(default_mask, 2) {
-        error!("poly requires both coords and colours");
+    let default_mask: i32 = vm.stack_peek(5)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("poly requires coords");
         return Err(Error::Native);
     }
 
     // code looks like this thanks to the borrow checker being anal
     let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
-    let colours = stack_peek_vars(&vm.stack, vm.sp, 2)?;
+    let colours = if is_arg_given(default_mask, 2) {
+        Some(stack_peek_vars(&vm.stack, vm.sp, 2)?)
+    } else {
+        None
+    };
+
+    context.render_poly(coords, colours, &col, triangulate == Keyword::True)?;
+
+    Ok(None)
+}
+
+fn polyline_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Coords, Var::Float(0.0)),
+            (Keyword::Width, Var::Float(4.0)),
+            (Keyword::Widths, Var::Float(0.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Colours, Var::Float(0.0)),
+            (Keyword::Join, Var::Keyword(Keyword::JoinMiter)),
+            (Keyword::Cap, Var::Keyword(Keyword::CapButt)),
+            (Keyword::MiterLimit, Var::Float(4.0)),
+            (Keyword::Tessellation, Var::Float(6.0)),
+            (Keyword::Brush, Var::Keyword(Keyword::BrushFlat)),
+            (Keyword::BrushSubtype, Var::Float(1.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn polyline_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let width: f32 = vm.stack_peek(2)?;
+    let col: Colour = vm.stack_peek(4)?;
+    let join: Keyword = vm.stack_peek(6)?;
+    let cap: Keyword = vm.stack_peek(7)?;
+    let miter_limit: f32 = vm.stack_peek(8)?;
+    let tessellation: usize = vm.stack_peek(9)?;
+    let brush: Keyword = vm.stack_peek(10)?;
+    let brush_subtype: usize = vm.stack_peek(11)?;
+
+    let default_mask: i32 = vm.stack_peek(12)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("polyline requires coords");
+        return Err(Error::Native);
+    }
+
+    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
+    let widths = if is_arg_given(default_mask, 3) {
+        Some(stack_peek_vars(&vm.stack, vm.sp, 3)?)
+    } else {
+        None
+    };
+    let colours = if is_arg_given(default_mask, 5) {
+        Some(stack_peek_vars(&vm.stack, vm.sp, 5)?)
+    } else {
+        None
+    };
+
+    let join = match join {
+        Keyword::JoinRound => JoinStyle::Round,
+        Keyword::JoinBevel => JoinStyle::Bevel,
+        _ => JoinStyle::Miter,
+    };
+    let cap = match cap {
+        Keyword::CapRound => CapStyle::Round,
+        Keyword::CapSquare => CapStyle::Square,
+        _ => CapStyle::Butt,
+    };
+    let brush_type = read_brush(brush);
 
-    context.render_poly(coords, colours)?;
+    context.render_polyline(
+        coords,
+        width,
+        widths,
+        &col,
+        colours,
+        join,
+        cap,
+        miter_limit,
+        tessellation,
+        brush_type,
+        brush_subtype,
+    )?;
 
     Ok(None)
 }
@@ -1552,6 +2090,104 @@ fn scale_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
     Ok(None)
 }
 
+fn skew_x_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::Angle, Var::Float(0.0))],
+        // stack offset
+        0,
+    ))
+}
+
+fn skew_x_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let angle: f32 = vm.stack_peek(1)?;
+
+    context.matrix_stack.skew_x(mathutil::deg_to_rad(angle));
+
+    Ok(None)
+}
+
+fn skew_y_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::Angle, Var::Float(0.0))],
+        // stack offset
+        0,
+    ))
+}
+
+fn skew_y_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let angle: f32 = vm.stack_peek(1)?;
+
+    context.matrix_stack.skew_y(mathutil::deg_to_rad(angle));
+
+    Ok(None)
+}
+
+fn reflect_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Angle, Var::Float(0.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn reflect_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
+    let angle: f32 = vm.stack_peek(2)?;
+
+    context
+        .matrix_stack
+        .reflect(x, y, mathutil::deg_to_rad(angle));
+
+    Ok(None)
+}
+
+fn transform_set_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::X, Var::V2D(1.0, 0.0)),
+            (Keyword::Y, Var::V2D(0.0, 1.0)),
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn transform_set_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    // x and y are where the unit axes end up, position is where the origin does
+    let (a, b): (f32, f32) = vm.stack_peek(1)?;
+    let (c, d): (f32, f32) = vm.stack_peek(2)?;
+    let (e, f): (f32, f32) = vm.stack_peek(3)?;
+
+    context.matrix_stack.set(Matrix::affine(a, b, c, d, e, f));
+
+    Ok(None)
+}
+
+fn transform_point_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::Point, Var::V2D(0.0, 0.0))],
+        // stack offset
+        1,
+    ))
+}
+
+fn transform_point_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
+
+    let (nx, ny) = context.matrix_stack.transform_vec2(x, y);
+
+    Ok(Some(Var::V2D(nx, ny)))
+}
+
 fn col_convert_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -1723,13 +2359,100 @@ fn col_lab_execute(vm: &mut Vm) -> Result<Option<Var>> {
     ))))
 }
 
-fn col_complementary_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn col_lch_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![(Keyword::From, Var::Colour(Default::default()))],
-        // stack offset
-        1,
-    ))
+        vec![
+            (Keyword::L, Var::Float(0.0)),
+            (Keyword::C, Var::Float(0.0)),
+            (Keyword::H, Var::Float(0.0)),
+            (Keyword::Alpha, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn col_lch_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let l: f32 = vm.stack_peek(1)?;
+    let c: f32 = vm.stack_peek(2)?;
+    let h: f32 = vm.stack_peek(3)?;
+    let alpha: f32 = vm.stack_peek(4)?;
+
+    Ok(Some(Var::Colour(Colour::new(
+        ColourFormat::Lch,
+        l,
+        c,
+        h,
+        alpha,
+    ))))
+}
+
+fn col_oklab_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::L, Var::Float(0.0)),
+            (Keyword::A, Var::Float(0.0)),
+            (Keyword::B, Var::Float(0.0)),
+            (Keyword::Alpha, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn col_oklab_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let l: f32 = vm.stack_peek(1)?;
+    let a: f32 = vm.stack_peek(2)?;
+    let b: f32 = vm.stack_peek(3)?;
+    let alpha: f32 = vm.stack_peek(4)?;
+
+    Ok(Some(Var::Colour(Colour::new(
+        ColourFormat::Oklab,
+        l,
+        a,
+        b,
+        alpha,
+    ))))
+}
+
+fn col_oklch_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::L, Var::Float(0.0)),
+            (Keyword::C, Var::Float(0.0)),
+            (Keyword::H, Var::Float(0.0)),
+            (Keyword::Alpha, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn col_oklch_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let l: f32 = vm.stack_peek(1)?;
+    let c: f32 = vm.stack_peek(2)?;
+    let h: f32 = vm.stack_peek(3)?;
+    let alpha: f32 = vm.stack_peek(4)?;
+
+    Ok(Some(Var::Colour(Colour::new(
+        ColourFormat::Oklch,
+        l,
+        c,
+        h,
+        alpha,
+    ))))
+}
+
+fn col_complementary_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::From, Var::Colour(Default::default()))],
+        // stack offset
+        1,
+    ))
 }
 
 fn col_complementary_execute(vm: &mut Vm) -> Result<Option<Var>> {
@@ -1960,6 +2683,7 @@ fn col_build_procedural_execute(vm: &mut Vm) -> Result<Option<Var>> {
         c,
         d,
         alpha: [alpha, 0.0, 0.0, 0.0],
+        stops: vec![],
     })))
 }
 
@@ -1990,9 +2714,81 @@ fn col_build_bezier_execute(vm: &mut Vm) -> Result<Option<Var>> {
         c: [col_c.e0, col_c.e1, col_c.e2],
         d: [col_d.e0, col_d.e1, col_d.e2],
         alpha: [col_a.e3, col_b.e3, col_c.e3, col_d.e3],
+        stops: vec![],
     })))
 }
 
+fn col_build_gradient_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Stops, Var::Bool(false)),
+            (Keyword::Format, Var::Keyword(Keyword::Oklab)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+// a stop is a vector of a position, a colour and optionally the mapping used
+// until the next stop
+fn read_gradient_stop(var: &Var, format: ColourFormat, mapping: Easing) -> Result<GradientStop> {
+    if let Var::Vector(vs) = var {
+        if let (Some(Var::Float(t)), Some(Var::Colour(colour))) = (vs.first(), vs.get(1)) {
+            let easing = match vs.get(2) {
+                Some(Var::Keyword(kw)) => Easing::try_from(*kw)?,
+                _ => mapping,
+            };
+            return Ok(GradientStop {
+                t: *t,
+                colour: colour.convert(format)?,
+                easing,
+            });
+        }
+    }
+
+    error!("col/build-gradient stops should be vectors of a position and a colour");
+    Err(Error::Native)
+}
+
+fn col_build_gradient_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(4)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("col/build-gradient requires stops parameter");
+        return Err(Error::Native);
+    }
+
+    let format: Keyword = vm.stack_peek(2)?;
+    let mapping: Keyword = vm.stack_peek(3)?;
+
+    let format = if let Some(format) = ColourFormat::from_keyword(format) {
+        format
+    } else {
+        error!("col/build-gradient format should be a colour format");
+        return Err(Error::Native);
+    };
+    let mapping = Easing::try_from(mapping)?;
+
+    let mut stops = stack_peek_vars(&vm.stack, vm.sp, 1)?
+        .iter()
+        .map(|var| read_gradient_stop(var, format, mapping))
+        .collect::<Result<Vec<GradientStop>>>()?;
+
+    if stops.is_empty() {
+        error!("col/build-gradient requires at least one stop");
+        return Err(Error::Native);
+    }
+
+    // stops can be given in any order
+    stops.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
+
+    Ok(Some(Var::ProcColourState(ProcColourStateStruct::gradient(
+        stops,
+    ))))
+}
+
 fn col_value_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -2024,22 +2820,34 @@ fn col_value_execute(vm: &mut Vm) -> Result<Option<Var>> {
 fn col_palette_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![(Keyword::Index, Var::Float(0.0))],
+        vec![
+            (Keyword::Index, Var::Float(0.0)),
+            (Keyword::From, Var::Bool(false)),
+        ],
         // stack offset
         1,
     ))
 }
 
-fn col_palette_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(2)?;
+fn col_palette_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
 
-    if !is_arg_given(default_mask, 1) {
-        error!("col_palette_execute requires index parameter");
+    let palette = if is_arg_given(default_mask, 2) {
+        // a named palette that's either been imported or defined by the script
+        let from: Iname = vm.stack_peek(2)?;
+        let name = program.data.string_from_iname(from)?;
+        context.palette_cache.get(&name)?.clone()
+    } else if is_arg_given(default_mask, 1) {
+        let index: f32 = vm.stack_peek(1)?;
+        Colour::palette(index as usize)?
+    } else {
+        error!("col_palette_execute requires either index or from parameter");
         return Err(Error::Native);
-    }
-
-    let index: f32 = vm.stack_peek(1)?;
-    let palette = Colour::palette(index as usize)?;
+    };
 
     let mut vs: Vec<Var> = Vec::new();
     for colour in palette {
@@ -2049,6 +2857,108 @@ fn col_palette_execute(vm: &mut Vm) -> Result<Option<Var>> {
     Ok(Some(Var::Vector(vs)))
 }
 
+fn col_define_palette_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Name, Var::Bool(false)),
+            (Keyword::Colours, Var::Bool(false)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn col_define_palette_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("col/define-palette requires a name parameter");
+        return Err(Error::Native);
+    }
+    if !is_arg_given(default_mask, 2) {
+        error!("col/define-palette requires a colours parameter");
+        return Err(Error::Native);
+    }
+
+    let name: Iname = vm.stack_peek(1)?;
+    let name = program.data.string_from_iname(name)?;
+
+    let mut colours: Vec<Colour> = Vec::new();
+    for var in stack_peek_vars(&vm.stack, vm.sp, 2)? {
+        if let Var::Colour(colour) = var {
+            colours.push(*colour);
+        } else {
+            error!("col/define-palette colours should only contain colours");
+            return Err(Error::Native);
+        }
+    }
+
+    context.palette_cache.define(&name, colours.clone())?;
+
+    let vs = colours.into_iter().map(Var::Colour).collect();
+
+    Ok(Some(Var::Vector(vs)))
+}
+
+fn col_palette_from_bitmap_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::Bool(false)),
+            (Keyword::Num, Var::Float(5.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Sort, Var::Keyword(Keyword::Frequency)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn col_palette_from_bitmap_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(5)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("col/palette-from-bitmap requires a from parameter");
+        return Err(Error::Native);
+    }
+
+    let from: Iname = vm.stack_peek(1)?;
+    let num: f32 = vm.stack_peek(2)?;
+    let seed: f32 = vm.stack_peek(3)?;
+    let sort: Keyword = vm.stack_peek(4)?;
+
+    let order = match sort {
+        Keyword::Frequency => PaletteOrder::Frequency,
+        Keyword::Lightness => PaletteOrder::Lightness,
+        _ => {
+            error!("col/palette-from-bitmap sort should be either frequency or lightness");
+            return Err(Error::Native);
+        }
+    };
+
+    let palette = bitmap::palette(
+        context,
+        program,
+        from,
+        num.max(0.0) as usize,
+        seed as i32,
+        order,
+    )?;
+
+    let vs = palette.into_iter().map(Var::Colour).collect();
+
+    Ok(Some(Var::Vector(vs)))
+}
+
 fn math_distance_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -2152,7 +3062,7 @@ fn math_cos_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
 fn math_cos_execute(vm: &mut Vm) -> Result<Option<Var>> {
     let angle: f32 = vm.stack_peek(1)?;
 
-    let c = angle.cos();
+    let c = mathutil::cos(angle);
 
     Ok(Some(Var::Float(c)))
 }
@@ -2169,119 +3079,93 @@ fn math_sin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
 fn math_sin_execute(vm: &mut Vm) -> Result<Option<Var>> {
     let angle: f32 = vm.stack_peek(1)?;
 
-    let s = angle.sin();
+    let s = mathutil::sin(angle);
 
     Ok(Some(Var::Float(s)))
 }
 
-fn prng_build_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn math_unary_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![
-            (Keyword::Seed, Var::Float(1.0)),
-            (Keyword::Min, Var::Float(0.0)),
-            (Keyword::Max, Var::Float(1.0)),
-        ],
+        vec![(Keyword::From, Var::Float(0.0))],
         // stack offset
         1,
     ))
 }
 
-fn prng_build_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let seed: f32 = vm.stack_peek(1)?;
-    let min: f32 = vm.stack_peek(2)?;
-    let max: f32 = vm.stack_peek(3)?;
-
-    let prng_state_struct = prng::PrngStateStruct::new(seed as i32, min, max);
+fn math_unary_execute(vm: &mut Vm, f: fn(f32) -> f32) -> Result<Option<Var>> {
+    let from: f32 = vm.stack_peek(1)?;
 
-    Ok(Some(Var::PrngState(Rc::new(RefCell::new(
-        prng_state_struct,
-    )))))
+    Ok(Some(Var::Float(f(from))))
 }
 
-fn prng_values_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn math_binary_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![
-            (Keyword::From, Var::Bool(false)),
-            (Keyword::Num, Var::Float(1.0)),
-        ],
+        vec![(Keyword::A, Var::Float(0.0)), (Keyword::B, Var::Float(0.0))],
         // stack offset
         1,
     ))
 }
 
-fn prng_values_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(3)?;
-
-    if !is_arg_given(default_mask, 1) {
-        error!("prng/values requires a from parameter");
-        return Err(Error::Native);
-    }
-
-    let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 1)?;
-    let num: f32 = vm.stack_peek(2)?;
-    let num = num as i32;
+fn math_binary_execute(vm: &mut Vm, f: fn(f32, f32) -> f32) -> Result<Option<Var>> {
+    let a: f32 = vm.stack_peek(1)?;
+    let b: f32 = vm.stack_peek(2)?;
 
-    let mut vs: Vec<Var> = Vec::new();
-    for _ in 0..num {
-        let f = ref_mut_prng_state.next_f32_defined_range();
-        vs.push(Var::Float(f))
-    }
-
-    Ok(Some(Var::Vector(vs)))
+    Ok(Some(Var::Float(f(a, b))))
 }
 
-fn prng_value_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn math_atan2_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![(Keyword::From, Var::Bool(false))],
+        vec![(Keyword::Y, Var::Float(0.0)), (Keyword::X, Var::Float(1.0))],
         // stack offset
         1,
     ))
 }
 
-fn prng_value_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(2)?;
-
-    if !is_arg_given(default_mask, 1) {
-        error!("prng/value requires a from parameter");
-        return Err(Error::Native);
-    }
-
-    let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 1)?;
-    let res = ref_mut_prng_state.next_f32_defined_range();
+fn math_atan2_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let y: f32 = vm.stack_peek(1)?;
+    let x: f32 = vm.stack_peek(2)?;
 
-    Ok(Some(Var::Float(res)))
+    Ok(Some(Var::Float(mathutil::atan2(y, x))))
 }
 
-fn prng_perlin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn math_pow_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::X, Var::Float(1.0)),
-            (Keyword::Y, Var::Float(1.0)),
-            (Keyword::Z, Var::Float(1.0)),
+            (Keyword::From, Var::Float(0.0)),
+            (Keyword::Exponent, Var::Float(1.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn prng_perlin_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let x: f32 = vm.stack_peek(1)?;
-    let y: f32 = vm.stack_peek(2)?;
-    let z: f32 = vm.stack_peek(3)?;
+fn math_pow_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let from: f32 = vm.stack_peek(1)?;
+    let exponent: f32 = vm.stack_peek(2)?;
 
-    let res = prng::perlin(x, y, z);
+    Ok(Some(Var::Float(mathutil::powf(from, exponent))))
+}
 
-    Ok(Some(Var::Float(res)))
+fn math_log_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let from: f32 = vm.stack_peek(1)?;
+
+    if from <= 0.0 {
+        error!("math/log requires a positive number");
+        return Err(Error::Native);
+    }
+
+    Ok(Some(Var::Float(mathutil::ln(from))))
 }
 
-fn interp_build_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn math_map_range_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
+            (Keyword::Value, Var::Float(0.0)),
             (Keyword::From, Var::V2D(0.0, 1.0)),
             (Keyword::To, Var::V2D(0.0, 100.0)),
             (Keyword::Clamping, Var::Keyword(Keyword::False)),
@@ -2292,549 +3176,1738 @@ fn interp_build_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     ))
 }
 
-fn interp_build_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let from: (f32, f32) = vm.stack_peek(1)?;
-    let to: (f32, f32) = vm.stack_peek(2)?;
-    let clamping: Keyword = vm.stack_peek(3)?;
-    let mapping: Keyword = vm.stack_peek(4)?;
+fn math_map_range_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let value: f32 = vm.stack_peek(1)?;
+    let from: (f32, f32) = vm.stack_peek(2)?;
+    let to: (f32, f32) = vm.stack_peek(3)?;
+    let clamping: Keyword = vm.stack_peek(4)?;
+    let mapping: Keyword = vm.stack_peek(5)?;
 
-    let clamping = clamping == Keyword::True;
     let mapping = Easing::try_from(mapping)?;
 
-    Ok(Some(Var::InterpState(interp::InterpStateStruct {
-        from_a: from.0,
-        from_b: from.1,
-        to_a: to.0,
-        to_b: to.1,
-        clamping,
-        mapping,
-    })))
+    // an empty from range maps everything onto the start of the to range
+    let t = if from.0 == from.1 {
+        0.0
+    } else {
+        mathutil::unlerp(value, from.0, from.1)
+    };
+
+    let res = interp::scalar(to.0, to.1, mapping, clamping == Keyword::True, t);
+
+    Ok(Some(Var::Float(res)))
 }
 
-fn interp_value_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn v2_pair_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::From, Var::Bool(false)),
-            (Keyword::T, Var::Float(0.0)),
+            (Keyword::Vec1, Var::V2D(0.0, 0.0)),
+            (Keyword::Vec2, Var::V2D(0.0, 0.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_value_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(3)?;
-
-    if !is_arg_given(default_mask, 1) {
-        error!("interp/value requires a from parameter");
-        return Err(Error::Native);
-    }
+fn v2_single_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::From, Var::V2D(0.0, 0.0))],
+        // stack offset
+        1,
+    ))
+}
 
-    let interp_state = stack_peek_interp_state_struct(&vm.stack, vm.sp, 1)?;
-    let t: f32 = vm.stack_peek(2)?;
+fn v2_add_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x1, y1): (f32, f32) = vm.stack_peek(1)?;
+    let (x2, y2): (f32, f32) = vm.stack_peek(2)?;
 
-    let res = interp_state.value(t);
+    Ok(Some(Var::V2D(x1 + x2, y1 + y2)))
+}
 
-    // error!("interp/value = {:?} t: {} interp_state: {:?}", res, t, &interp_state);
+fn v2_sub_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x1, y1): (f32, f32) = vm.stack_peek(1)?;
+    let (x2, y2): (f32, f32) = vm.stack_peek(2)?;
 
-    Ok(Some(Var::Float(res)))
+    Ok(Some(Var::V2D(x1 - x2, y1 - y2)))
 }
 
-fn interp_cos_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn v2_scale_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Amplitude, Var::Float(1.0)),
-            (Keyword::Frequency, Var::Float(1.0)),
-            (Keyword::T, Var::Float(1.0)),
+            (Keyword::From, Var::V2D(0.0, 0.0)),
+            (Keyword::Scalar, Var::Float(1.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_cos_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let amplitude: f32 = vm.stack_peek(1)?;
-    let frequency: f32 = vm.stack_peek(2)?;
-    let t: f32 = vm.stack_peek(3)?;
+fn v2_scale_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
+    let scalar: f32 = vm.stack_peek(2)?;
 
-    let res = interp::cos(amplitude, frequency, t);
+    Ok(Some(Var::V2D(x * scalar, y * scalar)))
+}
 
-    Ok(Some(Var::Float(res)))
+fn v2_dot_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x1, y1): (f32, f32) = vm.stack_peek(1)?;
+    let (x2, y2): (f32, f32) = vm.stack_peek(2)?;
+
+    Ok(Some(Var::Float(x1 * x2 + y1 * y2)))
 }
 
-fn interp_sin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+// the z component of the 3d cross product
+fn v2_cross_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x1, y1): (f32, f32) = vm.stack_peek(1)?;
+    let (x2, y2): (f32, f32) = vm.stack_peek(2)?;
+
+    Ok(Some(Var::Float(x1 * y2 - y1 * x2)))
+}
+
+fn v2_rotate_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Amplitude, Var::Float(1.0)),
-            (Keyword::Frequency, Var::Float(1.0)),
-            (Keyword::T, Var::Float(1.0)),
+            (Keyword::From, Var::V2D(0.0, 0.0)),
+            (Keyword::Angle, Var::Float(0.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_sin_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let amplitude: f32 = vm.stack_peek(1)?;
-    let frequency: f32 = vm.stack_peek(2)?;
-    let t: f32 = vm.stack_peek(3)?;
+fn v2_rotate_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
+    let angle: f32 = vm.stack_peek(2)?;
 
-    let res = interp::sin(amplitude, frequency, t);
+    let angle = mathutil::deg_to_rad(angle);
+    let (s, c) = (mathutil::sin(angle), mathutil::cos(angle));
 
-    Ok(Some(Var::Float(res)))
+    Ok(Some(Var::V2D(x * c - y * s, x * s + y * c)))
 }
 
-fn interp_bezier_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn v2_lerp_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Coords, Var::Bool(false)),
-            (Keyword::T, Var::Float(1.0)),
+            (Keyword::Vec1, Var::V2D(0.0, 0.0)),
+            (Keyword::Vec2, Var::V2D(0.0, 0.0)),
+            (Keyword::T, Var::Float(0.5)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_bezier_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(3)?;
+fn v2_lerp_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x1, y1): (f32, f32) = vm.stack_peek(1)?;
+    let (x2, y2): (f32, f32) = vm.stack_peek(2)?;
+    let t: f32 = vm.stack_peek(3)?;
 
-    if !is_arg_given(default_mask, 1) {
-        error!("interp/bezier requires coords parameter");
-        return Err(Error::Native);
-    }
+    Ok(Some(Var::V2D(
+        mathutil::lerp(t, x1, x2),
+        mathutil::lerp(t, y1, y2),
+    )))
+}
 
-    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
-    let t: f32 = vm.stack_peek(2)?;
+fn v2_angle_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
 
-    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
-        (x, y)
-    } else {
-        error!("coords 0 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
-        (x, y)
-    } else {
-        error!("coords 1 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
-        (x, y)
-    } else {
-        error!("coords 2 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
-        (x, y)
-    } else {
-        error!("coords 3 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
+    // degrees, to match v2/rotate
+    Ok(Some(Var::Float(mathutil::rad_to_deg(mathutil::atan2(
+        y, x,
+    )))))
+}
 
-    let (x, y) = interp::bezier(&[x0, y0, x1, y1, x2, y2, x3, y3], t);
+fn v2_length_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
 
-    Ok(Some(Var::V2D(x, y)))
+    Ok(Some(Var::Float(mathutil::length_v2(x, y))))
 }
 
-fn interp_bezier_tangent_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn v2_normalize_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let (x, y): (f32, f32) = vm.stack_peek(1)?;
+
+    // a zero length vector stays as it is
+    if x == 0.0 && y == 0.0 {
+        return Ok(Some(Var::V2D(0.0, 0.0)));
+    }
+    let (nx, ny) = mathutil::normalize(x, y);
+
+    Ok(Some(Var::V2D(nx, ny)))
+}
+
+fn prng_build_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Coords, Var::Bool(false)),
-            (Keyword::T, Var::Float(1.0)),
+            (Keyword::Seed, Var::Float(1.0)),
+            (Keyword::Min, Var::Float(0.0)),
+            (Keyword::Max, Var::Float(1.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_bezier_tangent_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(3)?;
-
-    if !is_arg_given(default_mask, 1) {
-        error!("interp/bezier requires coords parameter");
-        return Err(Error::Native);
-    }
-
-    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
-    let t: f32 = vm.stack_peek(2)?;
-
-    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
-        (x, y)
-    } else {
-        error!("coords 0 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
-        (x, y)
-    } else {
-        error!("coords 1 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
-        (x, y)
-    } else {
-        error!("coords 2 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
-        (x, y)
-    } else {
-        error!("coords 3 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
+fn prng_build_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let seed: f32 = vm.stack_peek(1)?;
+    let min: f32 = vm.stack_peek(2)?;
+    let max: f32 = vm.stack_peek(3)?;
 
-    let (x, y) = interp::bezier_tangent(&[x0, y0, x1, y1, x2, y2, x3, y3], t);
+    let prng_state_struct = prng::PrngStateStruct::new(seed as i32, min, max);
 
-    Ok(Some(Var::V2D(x, y)))
+    Ok(Some(Var::PrngState(Arc::new(Mutex::new(
+        prng_state_struct,
+    )))))
 }
 
-fn interp_ray_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn prng_values_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Point, Var::V2D(0.0, 0.0)),
-            (Keyword::Direction, Var::V2D(1000.0, 1000.0)),
-            (Keyword::T, Var::Float(1.0)),
+            (Keyword::From, Var::Bool(false)),
+            (Keyword::Num, Var::Float(1.0)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_ray_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let point: (f32, f32) = vm.stack_peek(1)?;
-    let direction: (f32, f32) = vm.stack_peek(2)?;
-    let t: f32 = vm.stack_peek(3)?;
+fn prng_values_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
 
-    let (x, y) = interp::ray(point, direction, t);
+    if !is_arg_given(default_mask, 1) {
+        error!("prng/values requires a from parameter");
+        return Err(Error::Native);
+    }
 
-    Ok(Some(Var::V2D(x, y)))
+    let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 1)?;
+    let num: f32 = vm.stack_peek(2)?;
+    let num = num as i32;
+
+    let mut vs: Vec<Var> = Vec::new();
+    for _ in 0..num {
+        let f = ref_mut_prng_state.next_f32_defined_range();
+        vs.push(Var::Float(f))
+    }
+
+    Ok(Some(Var::Vector(vs)))
 }
 
-fn interp_line_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn prng_value_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![
-            (Keyword::From, Var::V2D(0.0, 0.0)),
-            (Keyword::To, Var::V2D(0.0, 0.0)),
-            (Keyword::Clamping, Var::Keyword(Keyword::False)),
-            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
-            (Keyword::T, Var::Float(1.0)),
-        ],
+        vec![(Keyword::From, Var::Bool(false))],
         // stack offset
         1,
     ))
 }
 
-fn interp_line_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let from: (f32, f32) = vm.stack_peek(1)?;
-    let to: (f32, f32) = vm.stack_peek(2)?;
-    let clamping: Keyword = vm.stack_peek(3)?;
-    let mapping: Keyword = vm.stack_peek(4)?;
-    let t: f32 = vm.stack_peek(5)?;
+fn prng_value_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(2)?;
 
-    let clamping = clamping == Keyword::True;
-    let mapping = Easing::try_from(mapping)?;
-    let x = interp::scalar(from.0, to.0, mapping, clamping, t);
-    let y = interp::scalar(from.1, to.1, mapping, clamping, t);
+    if !is_arg_given(default_mask, 1) {
+        error!("prng/value requires a from parameter");
+        return Err(Error::Native);
+    }
+
+    let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 1)?;
+    let res = ref_mut_prng_state.next_f32_defined_range();
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn prng_perlin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::X, Var::Float(1.0)),
+            (Keyword::Y, Var::Float(1.0)),
+            (Keyword::Z, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn prng_perlin_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let x: f32 = vm.stack_peek(1)?;
+    let y: f32 = vm.stack_peek(2)?;
+    let z: f32 = vm.stack_peek(3)?;
+
+    let res = prng::perlin(x, y, z);
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn read_worley_feature(feature: Keyword) -> WorleyFeature {
+    match feature {
+        Keyword::WorleyF2 => WorleyFeature::F2,
+        Keyword::WorleyF2MinusF1 => WorleyFeature::F2MinusF1,
+        _ => WorleyFeature::F1,
+    }
+}
+
+// the noise basis of a field that's used as a direction
+fn read_noise_basis(basis: Keyword) -> Result<NoiseBasis> {
+    match basis {
+        Keyword::BasisPerlin => Ok(NoiseBasis::Perlin),
+        Keyword::BasisSimplex => Ok(NoiseBasis::Simplex),
+        Keyword::BasisWorley => Ok(NoiseBasis::Worley(WorleyFeature::F1)),
+        _ => {
+            error!("basis should be one of basis/simplex, basis/worley or basis/perlin");
+            Err(Error::Native)
+        }
+    }
+}
+
+fn noise_simplex_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::X, Var::Float(0.0)),
+            (Keyword::Y, Var::Float(0.0)),
+            (Keyword::Z, Var::Float(0.0)),
+            (Keyword::W, Var::Float(0.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn noise_simplex_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let x: f32 = vm.stack_peek(1)?;
+    let y: f32 = vm.stack_peek(2)?;
+    let z: f32 = vm.stack_peek(3)?;
+    let w: f32 = vm.stack_peek(4)?;
+    let seed: f32 = vm.stack_peek(5)?;
+
+    let default_mask: i32 = vm.stack_peek(6)?;
+
+    // the dimensionality of the noise depends on which arguments were given
+    let seed = seed as i32;
+    let res = if is_arg_given(default_mask, 4) {
+        noise::simplex4(seed, x, y, z, w)
+    } else if is_arg_given(default_mask, 3) {
+        noise::simplex3(seed, x, y, z)
+    } else {
+        noise::simplex2(seed, x, y)
+    };
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn noise_worley_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::X, Var::Float(0.0)),
+            (Keyword::Y, Var::Float(0.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Feature, Var::Keyword(Keyword::WorleyF1)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn noise_worley_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let x: f32 = vm.stack_peek(1)?;
+    let y: f32 = vm.stack_peek(2)?;
+    let seed: f32 = vm.stack_peek(3)?;
+    let feature: Keyword = vm.stack_peek(4)?;
+
+    let res = noise::worley(seed as i32, x, y, read_worley_feature(feature));
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn noise_fbm_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::X, Var::Float(0.0)),
+            (Keyword::Y, Var::Float(0.0)),
+            (Keyword::Z, Var::Float(0.0)),
+            (Keyword::W, Var::Float(0.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Basis, Var::Keyword(Keyword::BasisSimplex)),
+            (Keyword::Feature, Var::Keyword(Keyword::WorleyF1)),
+            (Keyword::Octaves, Var::Float(4.0)),
+            (Keyword::Lacunarity, Var::Float(2.0)),
+            (Keyword::Gain, Var::Float(0.5)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn noise_fbm_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let x: f32 = vm.stack_peek(1)?;
+    let y: f32 = vm.stack_peek(2)?;
+    let z: f32 = vm.stack_peek(3)?;
+    let w: f32 = vm.stack_peek(4)?;
+    let seed: f32 = vm.stack_peek(5)?;
+    let basis: Keyword = vm.stack_peek(6)?;
+    let feature: Keyword = vm.stack_peek(7)?;
+    let octaves: usize = vm.stack_peek(8)?;
+    let lacunarity: f32 = vm.stack_peek(9)?;
+    let gain: f32 = vm.stack_peek(10)?;
+
+    let default_mask: i32 = vm.stack_peek(11)?;
+
+    let seed = seed as i32;
+    let res = match basis {
+        Keyword::BasisPerlin => noise::fbm(seed, octaves, lacunarity, gain, |s, f| {
+            noise::perlin(s, x * f, y * f, z * f)
+        }),
+        Keyword::BasisWorley => {
+            let feature = read_worley_feature(feature);
+            noise::fbm(seed, octaves, lacunarity, gain, |s, f| {
+                noise::worley(s, x * f, y * f, feature)
+            })
+        }
+        Keyword::BasisSimplex => {
+            if is_arg_given(default_mask, 4) {
+                noise::fbm(seed, octaves, lacunarity, gain, |s, f| {
+                    noise::simplex4(s, x * f, y * f, z * f, w * f)
+                })
+            } else if is_arg_given(default_mask, 3) {
+                noise::fbm(seed, octaves, lacunarity, gain, |s, f| {
+                    noise::simplex3(s, x * f, y * f, z * f)
+                })
+            } else {
+                noise::fbm(seed, octaves, lacunarity, gain, |s, f| {
+                    noise::simplex2(s, x * f, y * f)
+                })
+            }
+        }
+        _ => {
+            error!("noise/fbm: basis should be one of basis/simplex, basis/worley or basis/perlin");
+            return Err(Error::Native);
+        }
+    };
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn interp_build_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::V2D(0.0, 1.0)),
+            (Keyword::To, Var::V2D(0.0, 100.0)),
+            (Keyword::Clamping, Var::Keyword(Keyword::False)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_build_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let from: (f32, f32) = vm.stack_peek(1)?;
+    let to: (f32, f32) = vm.stack_peek(2)?;
+    let clamping: Keyword = vm.stack_peek(3)?;
+    let mapping: Keyword = vm.stack_peek(4)?;
+
+    let clamping = clamping == Keyword::True;
+    let mapping = Easing::try_from(mapping)?;
+
+    Ok(Some(Var::InterpState(interp::InterpStateStruct {
+        from_a: from.0,
+        from_b: from.1,
+        to_a: to.0,
+        to_b: to.1,
+        clamping,
+        mapping,
+    })))
+}
+
+fn interp_value_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::Bool(false)),
+            (Keyword::T, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_value_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("interp/value requires a from parameter");
+        return Err(Error::Native);
+    }
+
+    let interp_state = stack_peek_interp_state_struct(&vm.stack, vm.sp, 1)?;
+    let t: f32 = vm.stack_peek(2)?;
+
+    let res = interp_state.value(t);
+
+    // error!("interp/value = {:?} t: {} interp_state: {:?}", res, t, &interp_state);
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn interp_cos_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Amplitude, Var::Float(1.0)),
+            (Keyword::Frequency, Var::Float(1.0)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_cos_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let amplitude: f32 = vm.stack_peek(1)?;
+    let frequency: f32 = vm.stack_peek(2)?;
+    let t: f32 = vm.stack_peek(3)?;
+
+    let res = interp::cos(amplitude, frequency, t);
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn interp_sin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Amplitude, Var::Float(1.0)),
+            (Keyword::Frequency, Var::Float(1.0)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_sin_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let amplitude: f32 = vm.stack_peek(1)?;
+    let frequency: f32 = vm.stack_peek(2)?;
+    let t: f32 = vm.stack_peek(3)?;
+
+    let res = interp::sin(amplitude, frequency, t);
+
+    Ok(Some(Var::Float(res)))
+}
+
+fn interp_bezier_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Coords, Var::Bool(false)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_bezier_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("interp/bezier requires coords parameter");
+        return Err(Error::Native);
+    }
+
+    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
+    let t: f32 = vm.stack_peek(2)?;
+
+    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
+        (x, y)
+    } else {
+        error!("coords 0 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
+        (x, y)
+    } else {
+        error!("coords 1 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
+        (x, y)
+    } else {
+        error!("coords 2 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
+        (x, y)
+    } else {
+        error!("coords 3 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+
+    let (x, y) = interp::bezier(&[x0, y0, x1, y1, x2, y2, x3, y3], t);
+
+    Ok(Some(Var::V2D(x, y)))
+}
+
+fn interp_bezier_tangent_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Coords, Var::Bool(false)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_bezier_tangent_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("interp/bezier requires coords parameter");
+        return Err(Error::Native);
+    }
+
+    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
+    let t: f32 = vm.stack_peek(2)?;
+
+    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
+        (x, y)
+    } else {
+        error!("coords 0 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
+        (x, y)
+    } else {
+        error!("coords 1 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
+        (x, y)
+    } else {
+        error!("coords 2 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
+        (x, y)
+    } else {
+        error!("coords 3 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+
+    let (x, y) = interp::bezier_tangent(&[x0, y0, x1, y1, x2, y2, x3, y3], t);
+
+    Ok(Some(Var::V2D(x, y)))
+}
+
+fn interp_ray_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Point, Var::V2D(0.0, 0.0)),
+            (Keyword::Direction, Var::V2D(1000.0, 1000.0)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_ray_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let point: (f32, f32) = vm.stack_peek(1)?;
+    let direction: (f32, f32) = vm.stack_peek(2)?;
+    let t: f32 = vm.stack_peek(3)?;
+
+    let (x, y) = interp::ray(point, direction, t);
+
+    Ok(Some(Var::V2D(x, y)))
+}
+
+fn interp_line_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::V2D(0.0, 0.0)),
+            (Keyword::To, Var::V2D(0.0, 0.0)),
+            (Keyword::Clamping, Var::Keyword(Keyword::False)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+            (Keyword::T, Var::Float(1.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_line_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let from: (f32, f32) = vm.stack_peek(1)?;
+    let to: (f32, f32) = vm.stack_peek(2)?;
+    let clamping: Keyword = vm.stack_peek(3)?;
+    let mapping: Keyword = vm.stack_peek(4)?;
+    let t: f32 = vm.stack_peek(5)?;
+
+    let clamping = clamping == Keyword::True;
+    let mapping = Easing::try_from(mapping)?;
+    let x = interp::scalar(from.0, to.0, mapping, clamping, t);
+    let y = interp::scalar(from.1, to.1, mapping, clamping, t);
+
+    Ok(Some(Var::V2D(x, y)))
+}
+
+fn interp_circle_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Radius, Var::Float(1.0)),
+            (Keyword::T, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn interp_circle_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let position: (f32, f32) = vm.stack_peek(1)?;
+    let radius: f32 = vm.stack_peek(2)?;
+    let t: f32 = vm.stack_peek(3)?;
+
+    let (x, y) = interp::circle(position, radius, t);
+
+    Ok(Some(Var::V2D(x, y)))
+}
+
+fn path_linear_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::V2D(0.0, 0.0)),
+            (Keyword::To, Var::V2D(10.0, 10.0)),
+            (Keyword::Steps, Var::Float(10.0)),
+            (Keyword::TStart, Var::Float(0.0)),
+            (Keyword::TEnd, Var::Float(1.0)),
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn path_linear_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    if !is_arg_given(default_mask, 6) {
+        error!("path_linear_execute requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let from: (f32, f32) = vm.stack_peek(1)?;
+    let to: (f32, f32) = vm.stack_peek(2)?;
+    let steps: f32 = vm.stack_peek(3)?;
+    let t_start: f32 = vm.stack_peek(4)?;
+    let t_end: f32 = vm.stack_peek(5)?;
+    let fun: i32 = vm.stack_peek(6)?;
+    let mapping_kw: Keyword = vm.stack_peek(7)?;
+    let mapping = Easing::try_from(mapping_kw)?;
+
+    path::linear(
+        vm,
+        context,
+        program,
+        fun as usize,
+        steps as i32,
+        t_start,
+        t_end,
+        from.0,
+        from.1,
+        to.0,
+        to.1,
+        mapping,
+    )?;
+
+
+    Ok(None)
+}
+
+fn path_circle_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Radius, Var::Float(100.0)),
+            (Keyword::Steps, Var::Float(10.0)),
+            (Keyword::TStart, Var::Float(0.0)),
+            (Keyword::TEnd, Var::Float(1.0)),
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn path_circle_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    if !is_arg_given(default_mask, 6) {
+        error!("path_circle_execute requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let position: (f32, f32) = vm.stack_peek(1)?;
+    let radius: f32 = vm.stack_peek(2)?;
+    let steps: f32 = vm.stack_peek(3)?;
+    let t_start: f32 = vm.stack_peek(4)?;
+    let t_end: f32 = vm.stack_peek(5)?;
+    let fun: i32 = vm.stack_peek(6)?;
+    let mapping_kw: Keyword = vm.stack_peek(7)?;
+    let mapping = Easing::try_from(mapping_kw)?;
+
+    path::circular(
+        vm,
+        context,
+        program,
+        fun as usize,
+        steps as i32,
+        t_start,
+        t_end,
+        position.0,
+        position.1,
+        radius,
+        mapping,
+    )?;
+
+
+    Ok(None)
+}
+
+fn path_spline_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Coords, Var::Bool(false)),
+            (Keyword::Steps, Var::Float(10.0)),
+            (Keyword::TStart, Var::Float(0.0)),
+            (Keyword::TEnd, Var::Float(1.0)),
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn path_spline_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(7)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("path_spline_execute requires coords argument");
+        return Err(Error::Native);
+    }
+    if !is_arg_given(default_mask, 5) {
+        error!("path_spline_execute requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
+    let steps: f32 = vm.stack_peek(2)?;
+    let t_start: f32 = vm.stack_peek(3)?;
+    let t_end: f32 = vm.stack_peek(4)?;
+    let fun: i32 = vm.stack_peek(5)?;
+    let mapping_kw: Keyword = vm.stack_peek(6)?;
+    let mapping = Easing::try_from(mapping_kw)?;
+
+    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
+        (x, y)
+    } else {
+        error!("coords 0 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
+        (x, y)
+    } else {
+        error!("coords 1 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
+        (x, y)
+    } else {
+        error!("coords 2 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+
+    path::spline(
+        vm,
+        context,
+        program,
+        fun as usize,
+        steps as i32,
+        t_start,
+        t_end,
+        [x0, y0, x1, y1, x2, y2],
+        mapping,
+    )?;
+
+    Ok(None)
+}
+
+fn path_bezier_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Coords, Var::Bool(false)),
+            (Keyword::Steps, Var::Float(10.0)),
+            (Keyword::TStart, Var::Float(0.0)),
+            (Keyword::TEnd, Var::Float(1.0)),
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn path_bezier_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(7)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("path_bezier_execute requires coords argument");
+        return Err(Error::Native);
+    }
+    if !is_arg_given(default_mask, 5) {
+        error!("path_bezier_execute requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
+    let steps: f32 = vm.stack_peek(2)?;
+    let t_start: f32 = vm.stack_peek(3)?;
+    let t_end: f32 = vm.stack_peek(4)?;
+    let fun: i32 = vm.stack_peek(5)?;
+    let mapping_kw: Keyword = vm.stack_peek(6)?;
+    let mapping = Easing::try_from(mapping_kw)?;
+
+
+    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
+        (x, y)
+    } else {
+        error!("coords 0 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
+        (x, y)
+    } else {
+        error!("coords 1 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
+        (x, y)
+    } else {
+        error!("coords 2 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
+        (x, y)
+    } else {
+        error!("coords 3 should be a Vec::V2D");
+        return Err(Error::Native);
+    };
+
+    path::bezier(
+        vm,
+        context,
+        program,
+        fun as usize,
+        steps as i32,
+        t_start,
+        t_end,
+        [x0, y0, x1, y1, x2, y2, x3, y3],
+        mapping,
+    )?;
+
+    Ok(None)
+}
+
+fn flow_trace_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::V2D(0.0, 0.0)),
+            (Keyword::Fn, Var::Float(0.0)),
+            (Keyword::Basis, Var::Keyword(Keyword::BasisSimplex)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Frequency, Var::Float(0.005)),
+            (Keyword::Octaves, Var::Float(1.0)),
+            (Keyword::StepSize, Var::Float(2.0)),
+            (Keyword::Steps, Var::Float(100.0)),
+            (Keyword::MinSeparation, Var::Float(0.0)),
+            (Keyword::Render, Var::Keyword(Keyword::False)),
+            (Keyword::Width, Var::Float(4.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Brush, Var::Keyword(Keyword::BrushFlat)),
+            (Keyword::BrushSubtype, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn flow_trace_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let from: (f32, f32) = vm.stack_peek(1)?;
+    let basis: Keyword = vm.stack_peek(3)?;
+    let seed: f32 = vm.stack_peek(4)?;
+    let frequency: f32 = vm.stack_peek(5)?;
+    let octaves: usize = vm.stack_peek(6)?;
+    let step_size: f32 = vm.stack_peek(7)?;
+    let steps: usize = vm.stack_peek(8)?;
+    let min_separation: f32 = vm.stack_peek(9)?;
+    let render: Keyword = vm.stack_peek(10)?;
+    let width: f32 = vm.stack_peek(11)?;
+    let col: Colour = vm.stack_peek(12)?;
+    let brush: Keyword = vm.stack_peek(13)?;
+    let brush_subtype: usize = vm.stack_peek(14)?;
+
+    let default_mask: i32 = vm.stack_peek(15)?;
+
+    // a user defined fn takes precedence over the noise field
+    let field = if is_arg_given(default_mask, 2) {
+        let fun: i32 = vm.stack_peek(2)?;
+        Field::Function(fun as usize)
+    } else {
+        let basis = read_noise_basis(basis)?;
+        Field::Noise {
+            basis,
+            seed: seed as i32,
+            frequency,
+            octaves,
+        }
+    };
+
+    let points = flow::trace(
+        vm,
+        context,
+        program,
+        &field,
+        from,
+        step_size,
+        steps,
+        min_separation,
+    )?;
+
+    let coords: Vec<Var> = points.iter().map(|(x, y)| Var::V2D(*x, *y)).collect();
+
+    if render == Keyword::True {
+        context.render_polyline(
+            &coords,
+            width,
+            None,
+            &col,
+            None,
+            JoinStyle::Miter,
+            CapStyle::Butt,
+            4.0,
+            6,
+            read_brush(brush),
+            brush_subtype,
+        )?;
+    }
+
+    Ok(Some(Var::Vector(coords)))
+}
+
+// the region arguments are position, width, height and radius. If a radius is
+// given then the region is a circle rather than a rect
+fn read_region(vm: &Vm, default_mask: i32) -> Result<Region> {
+    let position: (f32, f32) = vm.stack_peek(1)?;
+    let width: f32 = vm.stack_peek(2)?;
+    let height: f32 = vm.stack_peek(3)?;
+    let radius: f32 = vm.stack_peek(4)?;
+
+    if is_arg_given(default_mask, 4) {
+        Ok(Region::Circle { position, radius })
+    } else {
+        Ok(Region::Rect {
+            position,
+            width,
+            height,
+        })
+    }
+}
+
+fn points_to_var(points: Vec<(f32, f32)>) -> Var {
+    Var::Vector(points.into_iter().map(|(x, y)| Var::V2D(x, y)).collect())
+}
+
+fn sample_poisson_disk_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Radius, Var::Float(500.0)),
+            (Keyword::Distance, Var::Float(50.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn sample_poisson_disk_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let distance: f32 = vm.stack_peek(5)?;
+    let seed: f32 = vm.stack_peek(6)?;
+
+    let default_mask: i32 = vm.stack_peek(7)?;
+    let region = read_region(vm, default_mask)?;
+
+    if distance <= 0.0 {
+        error!("sample/poisson-disk requires a positive distance");
+        return Err(Error::Native);
+    }
+
+    let points = sample::poisson_disk(&region, distance, seed as i32)?;
+
+    Ok(Some(points_to_var(points)))
+}
+
+fn sample_jittered_grid_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Radius, Var::Float(500.0)),
+            (Keyword::Columns, Var::Float(10.0)),
+            (Keyword::Rows, Var::Float(10.0)),
+            (Keyword::Jitter, Var::Float(1.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn sample_jittered_grid_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let columns: usize = vm.stack_peek(5)?;
+    let rows: usize = vm.stack_peek(6)?;
+    let jitter: f32 = vm.stack_peek(7)?;
+    let seed: f32 = vm.stack_peek(8)?;
+
+    let default_mask: i32 = vm.stack_peek(9)?;
+    let region = read_region(vm, default_mask)?;
+
+    let points = sample::jittered_grid(&region, columns, rows, jitter, seed as i32)?;
+
+    Ok(Some(points_to_var(points)))
+}
 
-    Ok(Some(Var::V2D(x, y)))
+fn sample_halton_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Radius, Var::Float(500.0)),
+            (Keyword::Num, Var::Float(100.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
 }
 
-fn interp_circle_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn sample_halton_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let num: usize = vm.stack_peek(5)?;
+    let seed: f32 = vm.stack_peek(6)?;
+
+    let default_mask: i32 = vm.stack_peek(7)?;
+    let region = read_region(vm, default_mask)?;
+
+    let points = sample::halton(&region, num, seed as i32)?;
+
+    Ok(Some(points_to_var(points)))
+}
+
+fn indices_to_var(indices: &[Vec<usize>]) -> Var {
+    Var::Vector(
+        indices
+            .iter()
+            .map(|is| Var::Vector(is.iter().map(|i| Var::Float(*i as f32)).collect()))
+            .collect(),
+    )
+}
+
+fn geom_delaunay_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Position, Var::V2D(0.0, 0.0)),
-            (Keyword::Radius, Var::Float(1.0)),
-            (Keyword::T, Var::Float(0.0)),
+            (Keyword::Points, Var::Float(0.0)),
+            (Keyword::Neighbours, Var::Keyword(Keyword::False)),
         ],
         // stack offset
         1,
     ))
 }
 
-fn interp_circle_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let position: (f32, f32) = vm.stack_peek(1)?;
-    let radius: f32 = vm.stack_peek(2)?;
-    let t: f32 = vm.stack_peek(3)?;
+fn geom_delaunay_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let neighbours: Keyword = vm.stack_peek(2)?;
 
-    let (x, y) = interp::circle(position, radius, t);
+    let default_mask: i32 = vm.stack_peek(3)?;
+    if !is_arg_given(default_mask, 1) {
+        error!("geom/delaunay requires points");
+        return Err(Error::Native);
+    }
 
-    Ok(Some(Var::V2D(x, y)))
+    let points = to_points(stack_peek_vars(&vm.stack, vm.sp, 1)?)?;
+    let triangulation = geom::delaunay(&points);
+
+    if neighbours == Keyword::True {
+        return Ok(Some(indices_to_var(&triangulation.neighbours)));
+    }
+
+    let triangles = triangulation
+        .triangles
+        .iter()
+        .map(|t| {
+            Var::Vector(
+                t.iter()
+                    .map(|i| Var::V2D(points[*i].0, points[*i].1))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    Ok(Some(Var::Vector(triangles)))
 }
 
-fn path_linear_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn geom_voronoi_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::From, Var::V2D(0.0, 0.0)),
-            (Keyword::To, Var::V2D(10.0, 10.0)),
-            (Keyword::Steps, Var::Float(10.0)),
-            (Keyword::TStart, Var::Float(0.0)),
-            (Keyword::TEnd, Var::Float(1.0)),
-            (Keyword::Fn, Var::Bool(false)),
-            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+            (Keyword::Points, Var::Float(0.0)),
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Neighbours, Var::Keyword(Keyword::False)),
         ],
         // stack offset
-        0,
+        1,
     ))
 }
 
-fn path_linear_execute(
-    vm: &mut Vm,
-    context: &mut Context,
-    program: &Program,
-) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(8)?;
+fn geom_voronoi_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let position: (f32, f32) = vm.stack_peek(2)?;
+    let width: f32 = vm.stack_peek(3)?;
+    let height: f32 = vm.stack_peek(4)?;
+    let neighbours: Keyword = vm.stack_peek(5)?;
 
-    if !is_arg_given(default_mask, 6) {
-        error!("path_linear_execute requires fn argument");
+    let default_mask: i32 = vm.stack_peek(6)?;
+    if !is_arg_given(default_mask, 1) {
+        error!("geom/voronoi requires points");
         return Err(Error::Native);
     }
 
-    let from: (f32, f32) = vm.stack_peek(1)?;
-    let to: (f32, f32) = vm.stack_peek(2)?;
-    let steps: f32 = vm.stack_peek(3)?;
-    let t_start: f32 = vm.stack_peek(4)?;
-    let t_end: f32 = vm.stack_peek(5)?;
-    let fun: i32 = vm.stack_peek(6)?;
-    let mapping_kw: Keyword = vm.stack_peek(7)?;
-    let mapping = Easing::try_from(mapping_kw)?;
+    let points = to_points(stack_peek_vars(&vm.stack, vm.sp, 1)?)?;
+    let min = (position.0 - width / 2.0, position.1 - height / 2.0);
+    let max = (position.0 + width / 2.0, position.1 + height / 2.0);
+    let voronoi = geom::voronoi(&points, min, max);
 
-    path::linear(
-        vm,
-        context,
-        program,
-        fun as usize,
-        steps as i32,
-        t_start,
-        t_end,
-        from.0,
-        from.1,
-        to.0,
-        to.1,
-        mapping,
-    )?;
+    if neighbours == Keyword::True {
+        return Ok(Some(indices_to_var(&voronoi.neighbours)));
+    }
+
+    let cells = voronoi.cells.into_iter().map(points_to_var).collect();
+
+    Ok(Some(Var::Vector(cells)))
+}
+
+fn geom_boolean_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::Float(0.0)),
+            (Keyword::Clip, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn geom_boolean_execute(vm: &mut Vm, op: BooleanOp) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(3)?;
+    if !is_arg_given(default_mask, 1) || !is_arg_given(default_mask, 2) {
+        error!("geom/union, geom/intersection and geom/difference require from and clip polygons");
+        return Err(Error::Native);
+    }
+
+    let from = to_points(stack_peek_vars(&vm.stack, vm.sp, 1)?)?;
+    let clip = to_points(stack_peek_vars(&vm.stack, vm.sp, 2)?)?;
+
+    let polygons = polygon::boolean(&from, &clip, op);
+
+    Ok(Some(Var::Vector(
+        polygons.into_iter().map(points_to_var).collect(),
+    )))
+}
+
+fn geom_offset_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::Float(0.0)),
+            (Keyword::Distance, Var::Float(10.0)),
+            (Keyword::MiterLimit, Var::Float(4.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn geom_offset_execute(vm: &mut Vm) -> Result<Option<Var>> {
+    let distance: f32 = vm.stack_peek(2)?;
+    let miter_limit: f32 = vm.stack_peek(3)?;
+
+    let default_mask: i32 = vm.stack_peek(4)?;
+    if !is_arg_given(default_mask, 1) {
+        error!("geom/offset requires a from polygon");
+        return Err(Error::Native);
+    }
+
+    let from = to_points(stack_peek_vars(&vm.stack, vm.sp, 1)?)?;
+
+    Ok(Some(points_to_var(polygon::offset(
+        &from,
+        distance,
+        miter_limit,
+    ))))
+}
+
+// the shape arguments are the region arguments followed by coords. A polygon
+// given as coords takes precedence over the region
+fn read_shape(vm: &Vm, default_mask: i32) -> Result<Shape> {
+    if is_arg_given(default_mask, 5) {
+        let points = to_points(stack_peek_vars(&vm.stack, vm.sp, 5)?)?;
+        Ok(Shape::Polygon(points))
+    } else {
+        Ok(Shape::Region(read_region(vm, default_mask)?))
+    }
+}
 
+fn fill_hatch_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Radius, Var::Float(500.0)),
+            (Keyword::Coords, Var::Float(0.0)),
+            (Keyword::Angle, Var::Float(45.0)),
+            (Keyword::Spacing, Var::Float(10.0)),
+            (Keyword::Cross, Var::Keyword(Keyword::False)),
+            (Keyword::LineWidth, Var::Float(1.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Brush, Var::Keyword(Keyword::BrushFlat)),
+            (Keyword::BrushSubtype, Var::Float(0.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn fill_hatch_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let angle: f32 = vm.stack_peek(6)?;
+    let spacing: f32 = vm.stack_peek(7)?;
+    let cross: Keyword = vm.stack_peek(8)?;
+    let line_width: f32 = vm.stack_peek(9)?;
+    let col: Colour = vm.stack_peek(10)?;
+    let brush: Keyword = vm.stack_peek(11)?;
+    let brush_subtype: usize = vm.stack_peek(12)?;
+
+    let default_mask: i32 = vm.stack_peek(13)?;
+    let shape = read_shape(vm, default_mask)?;
+
+    if spacing <= 0.0 {
+        error!("fill/hatch requires a positive spacing");
+        return Err(Error::Native);
+    }
+
+    let mut lines = fill::hatch(&shape, mathutil::deg_to_rad(angle), spacing)?;
+    if cross == Keyword::True {
+        lines.append(&mut fill::hatch(
+            &shape,
+            mathutil::deg_to_rad(angle + 90.0),
+            spacing,
+        )?);
+    }
+
+    let brush_type = read_brush(brush);
+    for (from, to) in lines {
+        context.render_line(from, to, line_width, &col, &col, brush_type, brush_subtype)?;
+    }
 
     Ok(None)
 }
 
-fn path_circle_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn fill_stipple_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Position, Var::V2D(0.0, 0.0)),
-            (Keyword::Radius, Var::Float(100.0)),
-            (Keyword::Steps, Var::Float(10.0)),
-            (Keyword::TStart, Var::Float(0.0)),
-            (Keyword::TEnd, Var::Float(1.0)),
-            (Keyword::Fn, Var::Bool(false)),
-            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+            (Keyword::Position, Var::V2D(500.0, 500.0)),
+            (Keyword::Width, Var::Float(1000.0)),
+            (Keyword::Height, Var::Float(1000.0)),
+            (Keyword::Radius, Var::Float(500.0)),
+            (Keyword::Coords, Var::Float(0.0)),
+            (Keyword::Fn, Var::Float(0.0)),
+            (Keyword::Distance, Var::Float(5.0)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Length, Var::Float(2.0)),
+            (Keyword::Angle, Var::Float(0.0)),
+            (Keyword::LineWidth, Var::Float(2.0)),
+            (Keyword::Colour, Var::Colour(Default::default())),
+            (Keyword::Brush, Var::Keyword(Keyword::BrushFlat)),
+            (Keyword::BrushSubtype, Var::Float(0.0)),
         ],
         // stack offset
         0,
     ))
 }
 
-fn path_circle_execute(
+fn fill_stipple_execute(
     vm: &mut Vm,
     context: &mut Context,
     program: &Program,
 ) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(8)?;
+    let distance: f32 = vm.stack_peek(7)?;
+    let seed: f32 = vm.stack_peek(8)?;
+    let length: f32 = vm.stack_peek(9)?;
+    let angle: f32 = vm.stack_peek(10)?;
+    let line_width: f32 = vm.stack_peek(11)?;
+    let col: Colour = vm.stack_peek(12)?;
+    let brush: Keyword = vm.stack_peek(13)?;
+    let brush_subtype: usize = vm.stack_peek(14)?;
+
+    let default_mask: i32 = vm.stack_peek(15)?;
+    let shape = read_shape(vm, default_mask)?;
+
+    let fun = if is_arg_given(default_mask, 6) {
+        let fun: i32 = vm.stack_peek(6)?;
+        Some(fun as usize)
+    } else {
+        None
+    };
 
-    if !is_arg_given(default_mask, 6) {
-        error!("path_circle_execute requires fn argument");
+    if distance <= 0.0 || length <= 0.0 {
+        error!("fill/stipple requires a positive distance and length");
         return Err(Error::Native);
     }
 
-    let position: (f32, f32) = vm.stack_peek(1)?;
-    let radius: f32 = vm.stack_peek(2)?;
-    let steps: f32 = vm.stack_peek(3)?;
-    let t_start: f32 = vm.stack_peek(4)?;
-    let t_end: f32 = vm.stack_peek(5)?;
-    let fun: i32 = vm.stack_peek(6)?;
-    let mapping_kw: Keyword = vm.stack_peek(7)?;
-    let mapping = Easing::try_from(mapping_kw)?;
-
-    path::circular(
-        vm,
-        context,
-        program,
-        fun as usize,
-        steps as i32,
-        t_start,
-        t_end,
-        position.0,
-        position.1,
-        radius,
-        mapping,
-    )?;
+    let points = fill::stipple(vm, context, program, &shape, distance, fun, seed as i32)?;
 
+    // each stipple is a short dash centred on its point
+    let a = mathutil::deg_to_rad(angle);
+    let (dx, dy) = (
+        mathutil::cos(a) * length / 2.0,
+        mathutil::sin(a) * length / 2.0,
+    );
+    let brush_type = read_brush(brush);
+    for (x, y) in points {
+        context.render_line(
+            (x - dx, y - dy),
+            (x + dx, y + dy),
+            line_width,
+            &col,
+            &col,
+            brush_type,
+            brush_subtype,
+        )?;
+    }
 
     Ok(None)
 }
 
-fn path_spline_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+// symbols are either given as a string or as the vector of character codes
+// that's returned by lsystem/expand
+fn read_symbols(var: &Var, program: &Program) -> Result<Vec<char>> {
+    match var {
+        Var::String(iname) => Ok(program.data.string_from_iname(*iname)?.chars().collect()),
+        Var::Vector(vs) => vs
+            .iter()
+            .map(|v| match v {
+                Var::Float(f) => std::char::from_u32(*f as u32).ok_or_else(|| {
+                    error!("read_symbols: invalid character code {}", f);
+                    Error::Native
+                }),
+                _ => {
+                    error!("read_symbols: expected a vector of character codes");
+                    Err(Error::Native)
+                }
+            })
+            .collect(),
+        _ => {
+            error!("read_symbols: expected a string or a vector of character codes");
+            Err(Error::Native)
+        }
+    }
+}
+
+fn stack_peek_symbols(
+    stack: &[Var],
+    sp: usize,
+    offset: usize,
+    program: &Program,
+) -> Result<Vec<char>> {
+    read_symbols(&stack[sp - offset], program)
+}
+
+// each rule is a vector of the predecessor, successor and an optional weight
+fn read_rule(var: &Var, program: &Program) -> Result<Rule> {
+    if let Var::Vector(vs) = var {
+        if vs.len() == 2 || vs.len() == 3 {
+            let predecessor = read_symbols(&vs[0], program)?;
+            let successor = read_symbols(&vs[1], program)?;
+            let weight = match vs.get(2) {
+                Some(Var::Float(f)) => *f,
+                Some(_) => {
+                    error!("lsystem/expand: the weight of a rule should be a number");
+                    return Err(Error::Native);
+                }
+                None => 1.0,
+            };
+            if predecessor.len() == 1 {
+                return Ok(Rule {
+                    predecessor: predecessor[0],
+                    successor,
+                    weight,
+                });
+            }
+        }
+    }
+
+    error!("lsystem/expand: each rule should be [\"X\" \"successor\"] with an optional weight");
+    Err(Error::Native)
+}
+
+fn lsystem_expand_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Coords, Var::Bool(false)),
-            (Keyword::Steps, Var::Float(10.0)),
-            (Keyword::TStart, Var::Float(0.0)),
-            (Keyword::TEnd, Var::Float(1.0)),
-            (Keyword::Fn, Var::Bool(false)),
-            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+            (Keyword::Axiom, Var::Float(0.0)),
+            (Keyword::Rules, Var::Float(0.0)),
+            (Keyword::Iterations, Var::Float(3.0)),
+            (Keyword::Rng, Var::Float(0.0)),
+        ],
+        // stack offset
+        1,
+    ))
+}
+
+fn lsystem_expand_execute(vm: &mut Vm, program: &Program) -> Result<Option<Var>> {
+    let iterations: usize = vm.stack_peek(3)?;
+
+    let default_mask: i32 = vm.stack_peek(5)?;
+    if !is_arg_given(default_mask, 1) || !is_arg_given(default_mask, 2) {
+        error!("lsystem/expand requires an axiom and rules");
+        return Err(Error::Native);
+    }
+
+    let axiom = stack_peek_symbols(&vm.stack, vm.sp, 1, program)?;
+    let rules = stack_peek_vars(&vm.stack, vm.sp, 2)?
+        .iter()
+        .map(|rule| read_rule(rule, program))
+        .collect::<Result<Vec<Rule>>>()?;
+
+    let symbols = if is_arg_given(default_mask, 4) {
+        let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 4)?;
+        lsystem::expand(&axiom, &rules, iterations, Some(&mut ref_mut_prng_state))?
+    } else {
+        lsystem::expand(&axiom, &rules, iterations, None)?
+    };
+
+    Ok(Some(Var::Vector(
+        symbols
+            .into_iter()
+            .map(|c| Var::Float(c as u32 as f32))
+            .collect(),
+    )))
+}
+
+fn lsystem_turtle_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::From, Var::Float(0.0)),
+            (Keyword::Fn, Var::Float(0.0)),
+            (Keyword::Length, Var::Float(10.0)),
+            (Keyword::Angle, Var::Float(25.0)),
         ],
         // stack offset
         0,
     ))
 }
 
-fn path_spline_execute(
+fn lsystem_turtle_execute(
     vm: &mut Vm,
     context: &mut Context,
     program: &Program,
 ) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(7)?;
+    let length: f32 = vm.stack_peek(3)?;
+    let angle: f32 = vm.stack_peek(4)?;
 
-    if !is_arg_given(default_mask, 1) {
-        error!("path_spline_execute requires coords argument");
-        return Err(Error::Native);
-    }
-    if !is_arg_given(default_mask, 5) {
-        error!("path_spline_execute requires fn argument");
+    let default_mask: i32 = vm.stack_peek(5)?;
+    if !is_arg_given(default_mask, 1) || !is_arg_given(default_mask, 2) {
+        error!("lsystem/turtle requires from and fn arguments");
         return Err(Error::Native);
     }
 
-    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
-    let steps: f32 = vm.stack_peek(2)?;
-    let t_start: f32 = vm.stack_peek(3)?;
-    let t_end: f32 = vm.stack_peek(4)?;
-    let fun: i32 = vm.stack_peek(5)?;
-    let mapping_kw: Keyword = vm.stack_peek(6)?;
-    let mapping = Easing::try_from(mapping_kw)?;
-
-    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
-        (x, y)
-    } else {
-        error!("coords 0 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
-        (x, y)
-    } else {
-        error!("coords 1 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
-    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
-        (x, y)
-    } else {
-        error!("coords 2 should be a Vec::V2D");
-        return Err(Error::Native);
-    };
+    let symbols = stack_peek_symbols(&vm.stack, vm.sp, 1, program)?;
+    let fun: i32 = vm.stack_peek(2)?;
 
-    path::spline(
+    lsystem::turtle(
         vm,
         context,
         program,
+        &symbols,
         fun as usize,
-        steps as i32,
-        t_start,
-        t_end,
-        [x0, y0, x1, y1, x2, y2],
-        mapping,
+        length,
+        mathutil::deg_to_rad(angle),
     )?;
 
     Ok(None)
 }
 
-fn path_bezier_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+fn particle_simulate_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
         vec![
-            (Keyword::Coords, Var::Bool(false)),
-            (Keyword::Steps, Var::Float(10.0)),
-            (Keyword::TStart, Var::Float(0.0)),
-            (Keyword::TEnd, Var::Float(1.0)),
-            (Keyword::Fn, Var::Bool(false)),
-            (Keyword::Mapping, Var::Keyword(Keyword::Linear)),
+            (Keyword::From, Var::Float(0.0)),
+            (Keyword::Velocity, Var::V2D(0.0, 0.0)),
+            (Keyword::Jitter, Var::Float(0.0)),
+            (Keyword::Rng, Var::Float(0.0)),
+            (Keyword::Fn, Var::Float(0.0)),
+            (Keyword::Basis, Var::Keyword(Keyword::BasisSimplex)),
+            (Keyword::Seed, Var::Float(0.0)),
+            (Keyword::Frequency, Var::Float(0.005)),
+            (Keyword::Octaves, Var::Float(1.0)),
+            (Keyword::Strength, Var::Float(0.0)),
+            (Keyword::Attractors, Var::Float(0.0)),
+            (Keyword::Attraction, Var::Float(0.1)),
+            (Keyword::Damping, Var::Float(0.02)),
+            (Keyword::Steps, Var::Float(100.0)),
         ],
         // stack offset
-        0,
+        1,
     ))
 }
 
-fn path_bezier_execute(
+fn particle_simulate_execute(
     vm: &mut Vm,
     context: &mut Context,
     program: &Program,
 ) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(7)?;
-
+    let velocity: (f32, f32) = vm.stack_peek(2)?;
+    let jitter: f32 = vm.stack_peek(3)?;
+    let basis: Keyword = vm.stack_peek(6)?;
+    let seed: f32 = vm.stack_peek(7)?;
+    let frequency: f32 = vm.stack_peek(8)?;
+    let octaves: usize = vm.stack_peek(9)?;
+    let strength: f32 = vm.stack_peek(10)?;
+    let attraction: f32 = vm.stack_peek(12)?;
+    let damping: f32 = vm.stack_peek(13)?;
+    let steps: usize = vm.stack_peek(14)?;
+
+    let default_mask: i32 = vm.stack_peek(15)?;
     if !is_arg_given(default_mask, 1) {
-        error!("path_bezier_execute requires coords argument");
-        return Err(Error::Native);
-    }
-    if !is_arg_given(default_mask, 5) {
-        error!("path_bezier_execute requires fn argument");
+        error!("particle/simulate requires a from vector of positions");
         return Err(Error::Native);
     }
 
-    let coords = stack_peek_vars(&vm.stack, vm.sp, 1)?;
-    let steps: f32 = vm.stack_peek(2)?;
-    let t_start: f32 = vm.stack_peek(3)?;
-    let t_end: f32 = vm.stack_peek(4)?;
-    let fun: i32 = vm.stack_peek(5)?;
-    let mapping_kw: Keyword = vm.stack_peek(6)?;
-    let mapping = Easing::try_from(mapping_kw)?;
+    let mut particles: Vec<Particle> = to_points(stack_peek_vars(&vm.stack, vm.sp, 1)?)?
+        .into_iter()
+        .map(|position| Particle { position, velocity })
+        .collect();
 
+    if jitter != 0.0 {
+        if !is_arg_given(default_mask, 4) {
+            error!("particle/simulate requires an rng to jitter the velocities");
+            return Err(Error::Native);
+        }
+        let mut ref_mut_prng_state = ref_mut_prng_state_struct(&vm.stack, vm.sp, 4)?;
+        particle::scatter_velocities(&mut particles, jitter, &mut ref_mut_prng_state);
+    }
 
-    let (x0, y0) = if let Var::V2D(x, y) = coords[0] {
-        (x, y)
+    let function = if is_arg_given(default_mask, 5) {
+        let fun: i32 = vm.stack_peek(5)?;
+        Some(fun as usize)
     } else {
-        error!("coords 0 should be a Vec::V2D");
-        return Err(Error::Native);
+        None
     };
-    let (x1, y1) = if let Var::V2D(x, y) = coords[1] {
-        (x, y)
+
+    let noise = if strength != 0.0 {
+        let basis = read_noise_basis(basis)?;
+        Some(NoiseForce {
+            basis,
+            seed: seed as i32,
+            frequency,
+            octaves,
+            strength,
+        })
     } else {
-        error!("coords 1 should be a Vec::V2D");
-        return Err(Error::Native);
+        None
     };
-    let (x2, y2) = if let Var::V2D(x, y) = coords[2] {
-        (x, y)
+
+    let attractors = if is_arg_given(default_mask, 11) {
+        to_points(stack_peek_vars(&vm.stack, vm.sp, 11)?)?
     } else {
-        error!("coords 2 should be a Vec::V2D");
-        return Err(Error::Native);
+        vec![]
     };
-    let (x3, y3) = if let Var::V2D(x, y) = coords[3] {
-        (x, y)
-    } else {
-        error!("coords 3 should be a Vec::V2D");
-        return Err(Error::Native);
+
+    let forces = Forces {
+        function,
+        noise,
+        attractors,
+        attraction,
+        damping,
     };
 
-    path::bezier(
-        vm,
-        context,
-        program,
-        fun as usize,
-        steps as i32,
-        t_start,
-        t_end,
-        [x0, y0, x1, y1, x2, y2, x3, y3],
-        mapping,
-    )?;
+    let trajectories = particle::simulate(vm, context, program, particles, &forces, steps)?;
 
-    Ok(None)
+    Ok(Some(Var::Vector(
+        trajectories.into_iter().map(points_to_var).collect(),
+    )))
 }
 
 fn repeat_symmetry_vertical_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
@@ -3013,6 +5086,178 @@ fn repeat_rotate_mirrored_execute(
     Ok(None)
 }
 
+fn read_wallpaper_group(group: Keyword) -> Result<WallpaperGroup> {
+    match group {
+        Keyword::WallpaperP1 => Ok(WallpaperGroup::P1),
+        Keyword::WallpaperP2 => Ok(WallpaperGroup::P2),
+        Keyword::WallpaperPm => Ok(WallpaperGroup::Pm),
+        Keyword::WallpaperPg => Ok(WallpaperGroup::Pg),
+        Keyword::WallpaperCm => Ok(WallpaperGroup::Cm),
+        Keyword::WallpaperPmm => Ok(WallpaperGroup::Pmm),
+        Keyword::WallpaperPmg => Ok(WallpaperGroup::Pmg),
+        Keyword::WallpaperPgg => Ok(WallpaperGroup::Pgg),
+        Keyword::WallpaperCmm => Ok(WallpaperGroup::Cmm),
+        Keyword::WallpaperP4 => Ok(WallpaperGroup::P4),
+        Keyword::WallpaperP4m => Ok(WallpaperGroup::P4m),
+        Keyword::WallpaperP4g => Ok(WallpaperGroup::P4g),
+        Keyword::WallpaperP3 => Ok(WallpaperGroup::P3),
+        Keyword::WallpaperP3m1 => Ok(WallpaperGroup::P3m1),
+        Keyword::WallpaperP31m => Ok(WallpaperGroup::P31m),
+        Keyword::WallpaperP6 => Ok(WallpaperGroup::P6),
+        Keyword::WallpaperP6m => Ok(WallpaperGroup::P6m),
+        _ => {
+            error!("group should be one of the wallpaper/ groups");
+            Err(Error::Native)
+        }
+    }
+}
+
+fn repeat_wallpaper_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Group, Var::Keyword(Keyword::WallpaperP1)),
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Width, Var::Float(100.0)),
+            (Keyword::Height, Var::Float(100.0)),
+            (Keyword::Columns, Var::Float(3.0)),
+            (Keyword::Rows, Var::Float(3.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn repeat_wallpaper_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("repeat_wallpaper requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let fun: i32 = vm.stack_peek(1)?;
+    let group: Keyword = vm.stack_peek(2)?;
+    let position: (f32, f32) = vm.stack_peek(3)?;
+    let width: f32 = vm.stack_peek(4)?;
+    let height: f32 = vm.stack_peek(5)?;
+    let columns: usize = vm.stack_peek(6)?;
+    let rows: usize = vm.stack_peek(7)?;
+
+    let group = read_wallpaper_group(group)?;
+    check_tiling_cells("repeat/wallpaper", columns, rows)?;
+
+    let tiling = Tiling::wallpaper(group, position, width, height, columns, rows);
+    repeat::tile(vm, context, program, fun as usize, &tiling)?;
+
+    Ok(None)
+}
+
+fn check_tiling_cells(name: &str, columns: usize, rows: usize) -> Result<()> {
+    match columns.checked_mul(rows) {
+        Some(cells) if cells <= repeat::TILING_MAX_CELLS => Ok(()),
+        _ => {
+            error!(
+                "{}: {} columns and {} rows is more than the limit of {} cells",
+                name,
+                columns,
+                rows,
+                repeat::TILING_MAX_CELLS
+            );
+            Err(Error::Native)
+        }
+    }
+}
+
+fn repeat_grid_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Width, Var::Float(100.0)),
+            (Keyword::Height, Var::Float(100.0)),
+            (Keyword::Columns, Var::Float(3.0)),
+            (Keyword::Rows, Var::Float(3.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn repeat_grid_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(7)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("repeat_grid requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let fun: i32 = vm.stack_peek(1)?;
+    let position: (f32, f32) = vm.stack_peek(2)?;
+    let width: f32 = vm.stack_peek(3)?;
+    let height: f32 = vm.stack_peek(4)?;
+    let columns: usize = vm.stack_peek(5)?;
+    let rows: usize = vm.stack_peek(6)?;
+
+    check_tiling_cells("repeat/grid", columns, rows)?;
+
+    let tiling = Tiling::grid(position, width, height, columns, rows);
+    repeat::tile(vm, context, program, fun as usize, &tiling)?;
+
+    Ok(None)
+}
+
+fn repeat_hex_grid_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Fn, Var::Bool(false)),
+            (Keyword::Position, Var::V2D(0.0, 0.0)),
+            (Keyword::Radius, Var::Float(50.0)),
+            (Keyword::Columns, Var::Float(3.0)),
+            (Keyword::Rows, Var::Float(3.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn repeat_hex_grid_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(6)?;
+
+    if !is_arg_given(default_mask, 1) {
+        error!("repeat_hex_grid requires fn argument");
+        return Err(Error::Native);
+    }
+
+    let fun: i32 = vm.stack_peek(1)?;
+    let position: (f32, f32) = vm.stack_peek(2)?;
+    let radius: f32 = vm.stack_peek(3)?;
+    let columns: usize = vm.stack_peek(4)?;
+    let rows: usize = vm.stack_peek(5)?;
+
+    check_tiling_cells("repeat/hex-grid", columns, rows)?;
+
+    let tiling = Tiling::hex_grid(position, radius, columns, rows);
+    repeat::tile(vm, context, program, fun as usize, &tiling)?;
+
+    Ok(None)
+}
+
 fn focal_build_generic_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
@@ -3268,7 +5513,113 @@ fn mask_set_execute(vm: &mut Vm, context: &mut Context, program: &Program) -> Re
         invert: invert_f32 > 0.0,
     };
 
-    context.push_rp_mask(render_packet_mask)?;
+    context.push_rp_mask(render_packet_mask)?;
+
+    Ok(None)
+}
+
+fn read_blend_mode(mode: Keyword) -> Result<BlendMode> {
+    match mode {
+        Keyword::BlendNormal => Ok(BlendMode::Normal),
+        Keyword::BlendMultiply => Ok(BlendMode::Multiply),
+        Keyword::BlendScreen => Ok(BlendMode::Screen),
+        Keyword::BlendOverlay => Ok(BlendMode::Overlay),
+        Keyword::BlendAdditive => Ok(BlendMode::Additive),
+        Keyword::BlendDarken => Ok(BlendMode::Darken),
+        Keyword::BlendLighten => Ok(BlendMode::Lighten),
+        _ => {
+            error!("mode should be one of the blend/ modes");
+            Err(Error::Native)
+        }
+    }
+}
+
+fn blend_set_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![(Keyword::Mode, Var::Keyword(Keyword::BlendNormal))],
+        // stack offset
+        0,
+    ))
+}
+
+fn blend_set_execute(vm: &mut Vm, context: &mut Context) -> Result<Option<Var>> {
+    let mode: Keyword = vm.stack_peek(1)?;
+
+    let render_packet_blend = RenderPacketBlend {
+        mode: read_blend_mode(mode)?,
+    };
+
+    context.push_rp_blend(render_packet_blend)?;
+
+    Ok(None)
+}
+
+fn layer_begin_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![
+            (Keyword::Name, Var::Bool(false)),
+            (Keyword::Opacity, Var::Float(1.0)),
+            (Keyword::Blend, Var::Keyword(Keyword::BlendNormal)),
+            (Keyword::Blur, Var::Float(0.0)),
+            (Keyword::Contrast, Var::Float(1.0)),
+            (Keyword::Brightness, Var::Float(0.0)),
+            (Keyword::Saturation, Var::Float(1.0)),
+        ],
+        // stack offset
+        0,
+    ))
+}
+
+fn layer_begin_execute(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+) -> Result<Option<Var>> {
+    let default_mask: i32 = vm.stack_peek(8)?;
+
+    // layers don't have to be named
+    let name = if is_arg_given(default_mask, 1) {
+        let name: Iname = vm.stack_peek(1)?;
+        program.data.string_from_iname(name)?
+    } else {
+        "".to_string()
+    };
+
+    let opacity: f32 = vm.stack_peek(2)?;
+    let blend: Keyword = vm.stack_peek(3)?;
+    let blur: f32 = vm.stack_peek(4)?;
+    let contrast: f32 = vm.stack_peek(5)?;
+    let brightness: f32 = vm.stack_peek(6)?;
+    let saturation: f32 = vm.stack_peek(7)?;
+
+    let render_packet_layer = RenderPacketLayer {
+        name,
+        opacity: opacity.clamp(0.0, 1.0),
+        blend: read_blend_mode(blend)?,
+        blur: blur.max(0.0),
+        contrast,
+        brightness,
+        saturation,
+    };
+
+    context.push_rp_layer_begin(render_packet_layer)?;
+
+    Ok(None)
+}
+
+fn layer_end_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
+    Ok((
+        // input arguments
+        vec![],
+        // stack offset
+        0,
+    ))
+}
+
+fn layer_end_execute(context: &mut Context) -> Result<Option<Var>> {
+    context.push_rp_layer_end()?;
 
     Ok(None)
 }
@@ -3561,14 +5912,25 @@ fn gen_select_execute(vm: &mut Vm) -> Result<Option<Var>> {
 fn gen_col_parameter_info() -> Result<(Vec<(Keyword, Var)>, i32)> {
     Ok((
         // input arguments
-        vec![(Keyword::Alpha, Var::Float(1.0))],
+        vec![
+            (Keyword::Alpha, Var::Float(1.0)),
+            (Keyword::Format, Var::Keyword(Keyword::Rgb)),
+        ],
         // stack offset
         1,
     ))
 }
 
 fn gen_col_execute(vm: &mut Vm) -> Result<Option<Var>> {
-    let default_mask: i32 = vm.stack_peek(2)?;
+    let format: Keyword = vm.stack_peek(2)?;
+    let default_mask: i32 = vm.stack_peek(3)?;
+
+    let format = if let Some(format) = ColourFormat::from_keyword(format) {
+        format
+    } else {
+        error!("gen/col format should be a colour format");
+        return Err(Error::Native);
+    };
 
     let alpha: f32 = if is_arg_given(default_mask, 1) {
         vm.stack_peek(1)?
@@ -3577,13 +5939,18 @@ fn gen_col_execute(vm: &mut Vm) -> Result<Option<Var>> {
         vm.prng_state.next_f32_range(0.0, 1.0)
     };
 
-    Ok(Some(Var::Colour(Colour::new(
-        ColourFormat::Rgb,
-        vm.prng_state.next_f32_range(0.0, 1.0),
-        vm.prng_state.next_f32_range(0.0, 1.0),
-        vm.prng_state.next_f32_range(0.0, 1.0),
+    let [r0, r1, r2] = format.element_ranges();
+
+    let colour = Colour::new(
+        format,
+        vm.prng_state.next_f32_range(r0.0, r0.1),
+        vm.prng_state.next_f32_range(r1.0, r1.1),
+        vm.prng_state.next_f32_range(r2.0, r2.1),
         alpha,
-    ))))
+    );
+
+    // most of the LAB, LCH, OKLAB and OKLCH ranges can't be shown on screen
+    Ok(Some(Var::Colour(colour.map_into_srgb_gamut()?)))
 }
 
 #[cfg(test)]
@@ -3591,6 +5958,8 @@ mod tests {
     use super::*;
 
     use crate::colour::ColourFormat;
+    use crate::render_list::RPCommand;
+    use crate::render_packet::RENDER_PACKET_FLOAT_PER_VERTEX;
     // use crate::geometry::RENDER_PACKET_FLOAT_PER_VERTEX;
     use crate::vm::tests::*;
     use crate::vm::*;
@@ -3604,6 +5973,8 @@ mod tests {
             assert_eq!(col.e1, g);
             assert_eq!(col.e2, b);
             assert_eq!(col.e3, alpha);
+        } else {
+            panic!("expected a colour");
         }
     }
     /*
@@ -3714,7 +6085,767 @@ mod tests {
 
         is_float("(math/cos from: 0.7)", 0.7648422);
         is_float("(math/sin from: 0.9)", 0.7833269);
+
+        is_float("(math/degrees->radians from: 180)", mathutil::PI);
+        is_float("(math/atan2 y: 1 x: 0)", mathutil::PI_BY_2);
+        is_float("(math/pow from: 2 exponent: 10)", 1024.0);
+        is_float("(math/floor from: -1.5)", -2.0);
+        is_float("(math/ceil from: 1.2)", 2.0);
+        is_float("(math/round from: 2.5)", 3.0);
+        is_float("(math/min a: 3 b: -4)", -4.0);
+        is_float("(math/max a: 3 b: -4)", 3.0);
+        is_float("(math/abs from: -7)", 7.0);
+        is_float("(math/exp from: 0)", 1.0);
+        is_float("(math/log from: 1)", 0.0);
+
+        is_float(
+            "(math/map-range value: 5 from: [0 10] to: [100 200])",
+            150.0,
+        );
+        is_float(
+            "(math/map-range value: 20 from: [0 10] to: [100 200])",
+            300.0,
+        );
+        is_float(
+            "(math/map-range value: 20 from: [0 10] to: [100 200] clamping: true)",
+            200.0,
+        );
+        is_float(
+            "(math/map-range value: 5 from: [0 10] to: [0 100] mapping: ease/quadratic-in)",
+            25.0,
+        );
+    }
+    // the vertex positions of the first render packet
+    fn rendered_positions(s: &str) -> Vec<(f32, f32)> {
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        vm_run(&mut vm, &mut context, s);
+
+        let geo = &context.get_rp_geometry(0).unwrap().geo;
+        geo.chunks(RENDER_PACKET_FLOAT_PER_VERTEX)
+            .map(|v| (v[0], v[1]))
+            .collect()
+    }
+
+    fn x_extent(positions: &[(f32, f32)]) -> f32 {
+        let min = positions.iter().map(|p| p.0).fold(f32::MAX, f32::min);
+        let max = positions.iter().map(|p| p.0).fold(f32::MIN, f32::max);
+        max - min
+    }
+
+    #[test]
+    fn test_ellipse() {
+        let positions =
+            rendered_positions("(ellipse position: [0 0] width: 100 height: 20 tessellation: 4)");
+        assert_eq!(positions.len(), 10);
+        assert!((x_extent(&positions) - 200.0).abs() < 0.01);
+
+        let positions = rendered_positions(
+            "(ellipse position: [0 0] width: 100 height: 20 tessellation: 4 angle: 90)",
+        );
+        assert!((x_extent(&positions) - 40.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_arc() {
+        let positions = rendered_positions(
+            "(arc position: [0 0] radius: 100 width: 10 angle-start: 0 angle-end: 180 tessellation: 8)",
+        );
+        assert_eq!(positions.len(), 18);
+        for (i, (x, y)) in positions.iter().enumerate() {
+            let dist = (x * x + y * y).sqrt();
+            let expected = if i % 2 == 0 { 105.0 } else { 95.0 };
+            assert!((dist - expected).abs() < 0.01);
+        }
+
+        // there's always at least one segment
+        let positions = rendered_positions("(arc position: [500 500] radius: 100 tessellation: 0)");
+        assert_eq!(positions.len(), 4);
+        assert!(positions
+            .iter()
+            .all(|(x, y)| x.is_finite() && y.is_finite()));
+    }
+
+    #[test]
+    fn test_rounded_rect() {
+        let positions =
+            rendered_positions("(rounded-rect position: [0 0] width: 100 height: 50 radius: 10)");
+        assert_eq!(positions.len(), 42);
+        assert!((x_extent(&positions) - 100.0).abs() < 0.01);
+
+        // radii are clamped to fit within the rect
+        let positions = rendered_positions(
+            "(rounded-rect position: [0 0] width: 100 height: 50 corner-radii: [0 5 10 1000])",
+        );
+        assert_eq!(positions.len(), 42);
+        assert!((x_extent(&positions) - 100.0).abs() < 0.01);
+        assert!(positions.contains(&(-50.0, -25.0)));
+
+        // each corner has at least one segment
+        let positions = rendered_positions(
+            "(rounded-rect position: [0 0] width: 100 height: 50 radius: 10 tessellation: 0)",
+        );
+        assert_eq!(positions.len(), 18);
+        assert!(positions
+            .iter()
+            .all(|(x, y)| x.is_finite() && y.is_finite()));
+    }
+
+    #[test]
+    fn test_v2() {
+        is_v2d("(v2/add vec1: [1 2] vec2: [3 4])", (4.0, 6.0));
+        is_v2d("(v2/sub vec1: [1 2] vec2: [3 5])", (-2.0, -3.0));
+        is_v2d("(v2/scale from: [1 2] scalar: 3)", (3.0, 6.0));
+        is_float("(v2/dot vec1: [1 2] vec2: [3 4])", 11.0);
+        is_float("(v2/cross vec1: [1 0] vec2: [0 1])", 1.0);
+        is_v2d("(v2/rotate from: [1 0] angle: 90)", (0.0, 1.0));
+        is_v2d("(v2/rotate from: [0 2] angle: -90)", (2.0, 0.0));
+        is_v2d("(v2/lerp vec1: [0 0] vec2: [10 20] t: 0.25)", (2.5, 5.0));
+        is_float("(v2/angle from: [0 2])", 90.0);
+        is_float("(v2/length from: [3 4])", 5.0);
+        is_v2d("(v2/normalize from: [3 4])", (0.6, 0.8));
+        is_v2d("(v2/normalize from: [0 0])", (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_col_palette_named() {
+        let palettes = "(col/define-palette name: \"warm\"
+                                            colours: [(col/rgb r: 1 g: 0 b: 0) (col/rgb r: 1 g: 1 b: 0)])
+                        (col/define-palette name: \"cool\"
+                                            colours: [(col/rgb r: 0 g: 0 b: 1)])";
+
+        is_col_rgb(
+            &format!("{} (nth from: (col/palette from: \"warm\") n: 1)", palettes),
+            1.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+
+        // indexed palettes don't need a name
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        if let Var::Vector(vs) = vm_exec(&mut vm, &mut context, "(col/palette index: 0)") {
+            assert!(!vs.is_empty());
+        } else {
+            panic!("expected a vector");
+        }
+
+        // imported palettes are available to every script
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        context
+            .palette_cache
+            .insert_from_source("sea.hex", "#00ff00\n#0000ff")
+            .unwrap();
+        if let Var::Vector(vs) = vm_exec(&mut vm, &mut context, "(col/palette from: \"sea.hex\")") {
+            assert_eq!(vs.len(), 2);
+        } else {
+            panic!("expected a vector");
+        }
+
+        // the palette name can be a gene
+        let s = format!(
+            "{} (nth from: (col/palette from: \"warm\" ~ (gen/select from: '(\"warm\" \"cool\"))) n: 0)",
+            palettes
+        );
+        for (seed, blue) in [(1, 0.0), (432, 1.0)].iter() {
+            if let Var::Colour(col) =
+                crate::compile_and_execute_with_seeded_genotype(&s, *seed).unwrap()
+            {
+                assert_eq!(col.e2, *blue);
+            } else {
+                panic!("expected a colour");
+            }
+        }
+    }
+
+    #[test]
+    fn test_native_bool_default() {
+        // omitting an argument whose default is a bool compiles and the native
+        // sees that it wasn't given
+        let res = crate::compile_and_execute("(col/define-palette colours: [(col/rgb)])");
+        assert!(matches!(res, Err(Error::Native)));
+
+        // the argument is used when it's given
+        is_col_rgb(
+            "(col/define-palette name: \"p\" colours: [(col/rgb r: 1)])
+             (nth from: (col/palette from: \"p\") n: 0)",
+            1.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+    }
+
+    #[test]
+    fn test_col_build_gradient() {
+        let gradient = "(define g (col/build-gradient format: RGB
+                                   stops: [[1 (col/rgb r: 1 g: 1 b: 1)]
+                                           [0 (col/rgb r: 0 g: 0 b: 0)]
+                                           [0.5 (col/rgb r: 1 g: 0 b: 0 alpha: 0.5) ease/quick]]))";
+
+        // stops are sorted by position and clamped at either end
+        is_col_rgb(
+            &format!("{} (col/value from: g t: -1)", gradient),
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+        is_col_rgb(
+            &format!("{} (col/value from: g t: 0)", gradient),
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+        is_col_rgb(
+            &format!("{} (col/value from: g t: 0.25)", gradient),
+            0.5,
+            0.0,
+            0.0,
+            0.75,
+        );
+        is_col_rgb(
+            &format!("{} (col/value from: g t: 0.5)", gradient),
+            1.0,
+            0.0,
+            0.0,
+            0.5,
+        );
+        is_col_rgb(
+            &format!("{} (col/value from: g t: 2)", gradient),
+            1.0,
+            1.0,
+            1.0,
+            1.0,
+        );
+
+        // the second segment uses its own mapping
+        let quick = mathutil::map_quick_ease(0.5);
+        is_col_rgb(
+            &format!("{} (col/value from: g t: 0.75)", gradient),
+            1.0,
+            quick,
+            quick,
+            0.5 + quick * 0.5,
+        );
+    }
+
+    #[test]
+    fn test_transforms() {
+        is_v2d("(transform/point point: [3 4])", (3.0, 4.0));
+        is_v2d(
+            "(translate vector: [10 20]) (transform/point point: [1 1])",
+            (11.0, 21.0),
+        );
+        is_v2d(
+            "(on-matrix-stack (translate vector: [10 20])) (transform/point point: [1 1])",
+            (1.0, 1.0),
+        );
+        is_v2d(
+            "(skew-x angle: 45) (transform/point point: [0 2])",
+            (2.0, 2.0),
+        );
+        is_v2d(
+            "(skew-y angle: 45) (transform/point point: [2 0])",
+            (2.0, 2.0),
+        );
+        is_v2d(
+            "(reflect position: [5 0] angle: 90) (transform/point point: [7 3])",
+            (3.0, 3.0),
+        );
+        is_v2d(
+            "(reflect angle: 45) (transform/point point: [1 0])",
+            (0.0, 1.0),
+        );
+        is_v2d(
+            "(translate vector: [100 100])
+             (transform/set x: [0 1] y: [-1 0] position: [10 0])
+             (transform/point point: [1 2])",
+            (8.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn test_blend_set() {
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        vm_run(
+            &mut vm,
+            &mut context,
+            "(rect) (blend/set mode: blend/multiply) (rect) (blend/set)",
+        );
+
+        let commands: Vec<RPCommand> = (0..context.render_list.get_num_render_packets())
+            .map(|i| context.get_rp_command(i).unwrap())
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                RPCommand::Geometry,
+                RPCommand::Blend,
+                RPCommand::Geometry,
+                RPCommand::Blend
+            ]
+        );
+        assert_eq!(context.get_rp_blend(1).unwrap().mode, BlendMode::Multiply);
+        assert_eq!(context.get_rp_blend(3).unwrap().mode, BlendMode::Normal);
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        vm_run(
+            &mut vm,
+            &mut context,
+            "(rect)
+             (layer/begin name: \"glaze\" opacity: 0.5 blend: blend/multiply blur: 3)
+             (rect)
+             (layer/end)
+             (layer/begin)
+             (rect)",
+        );
+        // the unended layer is ended when the render packets are cleaned up
+        context.render_list.remove_useless_render_packets();
+
+        let commands: Vec<RPCommand> = (0..context.render_list.get_num_render_packets())
+            .map(|i| context.get_rp_command(i).unwrap())
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                RPCommand::Geometry,
+                RPCommand::LayerBegin,
+                RPCommand::Geometry,
+                RPCommand::LayerEnd,
+                RPCommand::LayerBegin,
+                RPCommand::Geometry,
+                RPCommand::LayerEnd
+            ]
+        );
+
+        let glaze = context.get_rp_layer(1).unwrap();
+        assert_eq!(glaze.name, "glaze");
+        assert_eq!(glaze.opacity, 0.5);
+        assert_eq!(glaze.blend, BlendMode::Multiply);
+        assert_eq!(glaze.blur, 3.0);
+        assert_eq!(glaze.saturation, 1.0);
+        assert_eq!(context.get_rp_layer(4).unwrap().name, "");
+
+        assert!(crate::compile_and_execute("(layer/end)").is_err());
+    }
+
+    #[test]
+    fn test_noise() {
+        is_float(
+            "(noise/simplex x: 1.3 y: 2.7 seed: 5)",
+            noise::simplex2(5, 1.3, 2.7),
+        );
+        is_float(
+            "(noise/simplex x: 1.3 y: 2.7 z: 0.4 seed: 5)",
+            noise::simplex3(5, 1.3, 2.7, 0.4),
+        );
+        is_float(
+            "(noise/simplex x: 1.3 y: 2.7 z: 0.4 w: 9 seed: 5)",
+            noise::simplex4(5, 1.3, 2.7, 0.4, 9.0),
+        );
+        is_float(
+            "(noise/worley x: 1.3 y: 2.7 seed: 5 feature: worley/f2)",
+            noise::worley(5, 1.3, 2.7, WorleyFeature::F2),
+        );
+        is_float(
+            "(noise/fbm x: 1.3 y: 2.7 seed: 5 octaves: 1)",
+            noise::simplex2(5, 1.3, 2.7),
+        );
+        is_float(
+            "(noise/fbm basis: basis/worley x: 1.3 y: 2.7 seed: 5 octaves: 1)",
+            noise::worley(5, 1.3, 2.7, WorleyFeature::F1),
+        );
+
+        // coordinates beyond the range of an i32
+        assert!(crate::compile_and_execute("(noise/simplex x: 3000000000 y: 5)").is_ok());
+        assert!(crate::compile_and_execute("(noise/fbm x: 100000000 y: 2 octaves: 16)").is_ok());
+        assert!(crate::compile_and_execute("(noise/worley x: 3000000000 y: 5)").is_ok());
+
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        match vm_exec(
+            &mut vm,
+            &mut context,
+            "(noise/fbm x: 1 y: 2 gain: -1 octaves: 2)",
+        ) {
+            Var::Float(f) => assert!(f.is_finite()),
+            _ => panic!("noise/fbm should return a float"),
+        }
+    }
+
+    #[test]
+    fn test_flow_trace() {
+        let east = "(fn (east position: [0 0]) [3 0])";
+        let flat = "(fn (flat position: [0 0]) 0)";
+
+        is_int(
+            &format!(
+                "{} (vector/length from: (flow/trace from: [0 0] fn: (address-of east) step-size: 10 steps: 5))",
+                east
+            ),
+            6,
+        );
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        let last = vm_exec(
+            &mut vm,
+            &mut context,
+            &format!(
+                "{} (nth from: (flow/trace from: [0 0] fn: (address-of flat) step-size: 10 steps: 5) n: 5)",
+                flat
+            ),
+        );
+        match last {
+            Var::V2D(x, y) => assert_eq!((x, y), (50.0, 0.0)),
+            _ => panic!("flow/trace should return a vector of points"),
+        }
+
+        // the second trace starts too close to the first
+        is_int(
+            &format!(
+                "{} (flow/trace from: [0 0] fn: (address-of east) step-size: 10 steps: 5 min-separation: 5)
+                 (vector/length from: (flow/trace from: [20 3] fn: (address-of east) min-separation: 5))",
+                east
+            ),
+            0,
+        );
+
+        // a noise field
+        is_int(
+            "(vector/length from: (flow/trace from: [500 500] seed: 3 steps: 20))",
+            21,
+        );
+        assert!(crate::compile_and_execute(
+            "(flow/trace from: [500 500] seed: 3 steps: 100000000000000)"
+        )
+        .is_err());
+
+        let positions = rendered_positions(&format!(
+            "{} (flow/trace from: [0 0] fn: (address-of east) step-size: 10 steps: 5 render: true width: 2)",
+            east
+        ));
+        assert_eq!(positions.len(), 12);
+        assert!((x_extent(&positions) - 50.0).abs() < 0.01);
+
+        let positions = rendered_positions(&format!(
+            "{} (loop (y from: 0 upto: 2)
+                   (flow/trace from: [0 (* y 10)] fn: (address-of east) step-size: 10 steps: 5 render: true))",
+            east
+        ));
+        assert_eq!(positions.len(), 40);
+    }
+
+    #[test]
+    fn test_sample() {
+        is_int("(vector/length from: (sample/halton num: 37 seed: 2))", 37);
+        is_int(
+            "(vector/length from: (sample/jittered-grid columns: 3 rows: 4 seed: 2))",
+            12,
+        );
+
+        // the points can be used with each
+        let positions = rendered_positions(
+            "(each (p from: (sample/poisson-disk distance: 300 seed: 3))
+               (rect position: p width: 2 height: 2))",
+        );
+        assert!(positions.len() > 4 * 4);
+        is_int(
+            "(vector/length from: (sample/jittered-grid position: [0 0] radius: 100 columns: 20 rows: 20 jitter: 0))",
+            316,
+        );
+    }
+
+    #[test]
+    fn test_geom() {
+        let square = "(define pts [[0 0] [100 0] [100 100] [0 100] [50 50]])";
+
+        is_int(
+            &format!(
+                "{} (vector/length from: (geom/delaunay points: pts))",
+                square
+            ),
+            4,
+        );
+        is_int(
+            &format!(
+                "{} (vector/length from: (nth from: (geom/delaunay points: pts) n: 0))",
+                square
+            ),
+            3,
+        );
+        is_int(
+            &format!(
+                "{} (vector/length from: (nth from: (geom/delaunay points: pts neighbours: true) n: 0))",
+                square
+            ),
+            2,
+        );
+
+        is_int(
+            &format!(
+                "{} (vector/length from: (geom/voronoi points: pts position: [50 50] width: 100 height: 100))",
+                square
+            ),
+            5,
+        );
+        // the cell of the middle point is a diamond
+        is_int(
+            &format!(
+                "{} (vector/length from: (nth from: (geom/voronoi points: pts position: [50 50] width: 100 height: 100) n: 4))",
+                square
+            ),
+            4,
+        );
+
+        // cells can be drawn with poly
+        let positions = rendered_positions(&format!(
+            "{} (each (cell from: (geom/voronoi points: pts position: [50 50] width: 100 height: 100))
+                   (poly coords: cell triangulate: true))",
+            square
+        ));
+        // 2 triangles for the middle cell and 1 for each of the corners, with
+        // degenerate triangles joining them together
+        assert_eq!(positions.len(), 6 * 3 + 5 * 2);
+    }
+
+    #[test]
+    fn test_geom_boolean() {
+        let squares = "(define a [[0 0] [10 0] [10 10] [0 10]] b [[5 5] [15 5] [15 15] [5 15]])";
+        let num_polygons = |op: &str, expected: i32| {
+            is_int(
+                &format!("{} (vector/length from: ({} from: a clip: b))", squares, op),
+                expected,
+            )
+        };
+        num_polygons("geom/union", 1);
+        num_polygons("geom/intersection", 1);
+        num_polygons("geom/difference", 1);
+
+        // the overlap of the squares is a square
+        is_int(
+            &format!(
+                "{} (vector/length from: (nth from: (geom/intersection from: a clip: b) n: 0))",
+                squares
+            ),
+            4,
+        );
+
+        is_int(
+            &format!(
+                "{} (vector/length from: (geom/offset from: a distance: -2))",
+                squares
+            ),
+            4,
+        );
+        is_int(
+            &format!(
+                "{} (vector/length from: (geom/offset from: a distance: -8))",
+                squares
+            ),
+            0,
+        );
+    }
+
+    #[test]
+    fn test_fill_hatch() {
+        let rect = "(fill/hatch position: [50 50] width: 95 height: 95 angle: 0 spacing: 10";
+        let num_verts = |args: &str| rendered_positions(&format!("{} {})", rect, args)).len();
+
+        let single = num_verts("");
+        assert!(single > 0);
+        assert_eq!(num_verts("cross: true"), single * 2 + 2);
+
+        let positions = rendered_positions(&format!("{})", rect));
+        assert!((x_extent(&positions) - 95.0).abs() < 0.01);
+
+        let positions = rendered_positions(
+            "(fill/hatch coords: [[0 0] [40 0] [40 40]] spacing: 5 line-width: 1)",
+        );
+        assert!(positions
+            .iter()
+            .all(|(x, y)| *x >= -1.0 && *x <= 41.0 && *y >= -1.0 && *y <= 41.0));
+
+        assert!(crate::compile_and_execute("(fill/hatch spacing: 0.001)").is_err());
+        // the number of lines is too big to cast to an integer
+        let tiny = format!("(fill/hatch spacing: 0.{}1)", "0".repeat(41));
+        assert!(crate::compile_and_execute(&tiny).is_err());
+        let huge = format!("(fill/hatch width: 1{} spacing: 10)", "0".repeat(38));
+        assert!(crate::compile_and_execute(&huge).is_err());
+    }
+
+    #[test]
+    fn test_fill_stipple() {
+        let circle = "(fill/stipple position: [0 0] radius: 100 distance: 10 seed: 4";
+        let num_verts = |args: &str| rendered_positions(&format!("{} {})", circle, args)).len();
+
+        let all = num_verts("");
+        assert!(all > 0);
+
+        // only stipple the left half
+        let left = num_verts(
+            "fn: (address-of left)) (fn (left position: [0 0]) (if (< (nth from: position n: 0) 0) 1 0)",
+        );
+        assert!(left > all / 3 && left < all * 2 / 3);
+
+        let positions = rendered_positions(&format!("{} length: 4)", circle));
+        assert!(positions.iter().all(|(x, y)| x * x + y * y < 105.0 * 105.0));
+    }
+
+    #[test]
+    fn test_lsystem() {
+        let algae = "(lsystem/expand axiom: \"A\" rules: [[\"A\" \"AB\"] [\"B\" \"A\"]]";
+        is_int(
+            &format!("(vector/length from: {} iterations: 4))", algae),
+            8,
+        );
+        // B
+        is_float(&format!("(nth from: {} iterations: 1) n: 1)", algae), 66.0);
+
+        // an expansion can be expanded again
+        is_int(
+            &format!(
+                "(vector/length from: (lsystem/expand axiom: {} iterations: 2) rules: [[\"A\" \"AB\"] [\"B\" \"A\"]] iterations: 2))",
+                algae
+            ),
+            8,
+        );
+
+        is_int(
+            "(define rng (prng/build seed: 5))
+             (vector/length from: (lsystem/expand axiom: \"FF\" rules: [[\"F\" \"a\" 1] [\"F\" \"bb\" 2]] iterations: 1 rng: rng))",
+            3,
+        );
+
+        assert!(crate::compile_and_execute(
+            "(lsystem/expand axiom: \"F\" rules: [[\"F\" \"F\"]] iterations: 10000000000)"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_lsystem_turtle() {
+        let seg = "(fn (seg length: 1) (line from: [0 0] to: [0 length] width: 2))";
+        let turtle = |from: &str| {
+            rendered_positions(&format!(
+                "{} (lsystem/turtle from: {} fn: (address-of seg) length: 100 angle: 90)",
+                seg, from
+            ))
+        };
+        let close =
+            |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01;
+
+        // up then left
+        let positions = turtle("\"F+F\"");
+        assert!((x_extent(&positions) - 101.0).abs() < 0.01);
+        assert!(positions.iter().any(|p| close(*p, (-100.0, 99.0))));
+
+        // the branch returns to where it started
+        let positions = turtle("\"F[+F]F\"");
+        assert!(positions.iter().any(|p| close(*p, (1.0, 200.0))));
+
+        // f moves without drawing and an unbalanced ] is ignored
+        let positions = turtle("\"f]F\"");
+        assert_eq!(positions.len(), 4);
+        assert!(positions.iter().any(|p| close(*p, (1.0, 200.0))));
+    }
+
+    #[test]
+    fn test_particle_simulate() {
+        let last_position = |args: &str| {
+            let mut vm: Vm = Default::default();
+            let mut context: Context = Default::default();
+            let s = format!(
+                "(define rng (prng/build seed: 3) other-rng (prng/build seed: 432))
+                 (fn (wind position: [0 0] velocity: [0 0]) [0 1])
+                 (nth from: (nth from: (particle/simulate {} steps: 3) n: 0) n: 3)",
+                args
+            );
+            match vm_exec(&mut vm, &mut context, &s) {
+                Var::V2D(x, y) => (x, y),
+                _ => panic!("particle/simulate should return vectors of points"),
+            }
+        };
+
+        // each trajectory includes the starting position
+        is_int(
+            "(vector/length from: (particle/simulate from: [[0 0] [10 10]] steps: 5))",
+            2,
+        );
+        is_int(
+            "(vector/length from: (nth from: (particle/simulate from: [[0 0]] steps: 5) n: 0))",
+            6,
+        );
+
+        let (x, y) = last_position("from: [[0 0]] velocity: [2 0] damping: 0");
+        assert_eq!((x, y), (6.0, 0.0));
+
+        let (x, y) = last_position("from: [[0 0]] fn: (address-of wind) damping: 0");
+        assert_eq!((x, y), (0.0, 6.0));
+
+        // damping slows the particle down
+        let (x, _) = last_position("from: [[0 0]] velocity: [2 0] damping: 0.5");
+        assert!(x < 2.0);
+
+        // the particle is pulled towards the attractor
+        let (x, _) = last_position("from: [[0 0]] attractors: [[100 0]] attraction: 5");
+        assert!(x > 20.0);
+
+        // jitter uses the script's prng so it's reproducible
+        let a = last_position("from: [[0 0]] jitter: 5 rng: rng");
+        assert!((a.0 - 5.517223).abs() < 0.001);
+        assert!((a.1 - 6.409852).abs() < 0.001);
+        let b = last_position("from: [[0 0]] jitter: 5 rng: other-rng");
+        assert!(a != b);
+
+        assert!(crate::compile_and_execute(
+            "(particle/simulate from: [[0 0]] steps: 100000000000000000000)"
+        )
+        .is_err());
+        assert!(
+            crate::compile_and_execute("(particle/simulate from: [] steps: 100000000000000)")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_polyline() {
+        let path = "(polyline coords: [[0 0] [100 0] [100 100]] width: 10 tessellation: 4";
+        let num_verts = |args: &str| rendered_positions(&format!("{} {})", path, args)).len();
+
+        assert_eq!(num_verts(""), 6);
+        assert_eq!(num_verts("join: join/bevel"), 8);
+        assert_eq!(num_verts("join: join/round"), 14);
+        assert_eq!(num_verts("cap: cap/round"), 22);
+
+        let positions = rendered_positions(&format!("{})", path));
+        let close =
+            |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01;
+        assert!(close(positions[2], (95.0, 5.0)));
+        assert!(close(positions[3], (105.0, -5.0)));
+
+        // a sharp turn beyond the miter limit is bevelled
+        assert_eq!(
+            rendered_positions("(polyline coords: [[0 0] [100 0] [0 5]] width: 10)").len(),
+            8
+        );
+
+        let positions = rendered_positions(&format!("{} cap: cap/square)", path));
+        assert!(close(positions[0], (-5.0, 5.0)));
+
+        let positions = rendered_positions(&format!("{} widths: [2 10 20])", path));
+        assert!(close(positions[0], (0.0, 1.0)));
+        assert!(close(positions[4], (90.0, 100.0)));
     }
+
     #[test]
     fn dev_new_args() {
         is_float("(math/clamp from: 3 min: 2 max: 5)", 3.0);
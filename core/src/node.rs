@@ -31,6 +31,7 @@ pub struct NodeLocation {
 pub struct NodeGene {
     // todo: the whole idea of having a gene here seems wrong. maybe a unique id that can be a key into a HashMap?
     pub gene: Option<Gene>, // option because we don't know what gene it is at construction time?
+    pub gene_index: Option<usize>, // the gene's position in the genotype
     pub parameter_ast: Vec<Node>,
     pub parameter_prefix: Vec<Node>,
 }
@@ -56,11 +57,12 @@ pub enum Node {
 }
 
 impl NodeMeta {
-    pub fn new_with_gene(&self, gene: Gene) -> Self {
+    pub fn new_with_gene(&self, gene: Gene, gene_index: usize) -> Self {
         NodeMeta {
             loc: self.loc,
             gene_info: Some(NodeGene {
                 gene: Some(gene),
+                gene_index: Some(gene_index),
                 parameter_ast: Vec::new(),
                 parameter_prefix: Vec::new(),
             }),
@@ -233,6 +235,21 @@ impl Node {
             | Node::Comment(meta, _) => meta.loc,
         }
     }
+
+    pub fn get_gene_index(&self) -> Option<usize> {
+        match self {
+            Node::List(meta, _)
+            | Node::Vector(meta, _)
+            | Node::Float(meta, _, _)
+            | Node::FromName(meta, _, _)
+            | Node::Name(meta, _, _)
+            | Node::Label(meta, _, _)
+            | Node::String(meta, _, _)
+            | Node::Tilde(meta)
+            | Node::Whitespace(meta, _)
+            | Node::Comment(meta, _) => meta.gene_info.as_ref().and_then(|gi| gi.gene_index),
+        }
+    }
 }
 
 fn gene_name(gene: &Gene) -> Result<Iname> {
@@ -0,0 +1,433 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Gradient noise and cellular noise that take an explicit seed so that a
+// script always produces the same values for the same genotype.
+//
+// Rather than a shuffled permutation table the lattice points are hashed
+// together with the seed, this makes changing the seed free.
+
+use crate::mathutil::length_v2;
+use crate::prng;
+
+// skewing and unskewing factors for 2, 3 and 4 dimensions
+const F2: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+const F3: f32 = 1.0 / 3.0;
+const G3: f32 = 1.0 / 6.0;
+const F4: f32 = 0.309_017; // (sqrt(5) - 1) / 4
+const G4: f32 = 0.138_196_6; // (5 - sqrt(5)) / 20
+
+const GRAD2: [[f32; 2]; 8] = [
+    [1.0, 0.0],
+    [-1.0, 0.0],
+    [0.0, 1.0],
+    [0.0, -1.0],
+    [0.707_106_77, 0.707_106_77],
+    [-0.707_106_77, 0.707_106_77],
+    [0.707_106_77, -0.707_106_77],
+    [-0.707_106_77, -0.707_106_77],
+];
+
+const GRAD3: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WorleyFeature {
+    F1,
+    F2,
+    F2MinusF1,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoiseBasis {
+    Perlin,
+    Simplex,
+    Worley(WorleyFeature),
+}
+
+impl NoiseBasis {
+    pub fn sample2(self, seed: i32, x: f32, y: f32) -> f32 {
+        match self {
+            NoiseBasis::Perlin => perlin(seed, x, y, 0.0),
+            NoiseBasis::Simplex => simplex2(seed, x, y),
+            NoiseBasis::Worley(feature) => worley(seed, x, y, feature),
+        }
+    }
+}
+
+// a well mixed 32 bit hash of a lattice point
+fn hash(seed: i32, coords: &[i32]) -> u32 {
+    let mut h = (seed as u32).wrapping_mul(0x27d4_eb2d) ^ 0x1656_67b1;
+    for c in coords {
+        h ^= (*c as u32).wrapping_mul(0x9e37_79b9);
+        h = h.rotate_left(15).wrapping_mul(0x85eb_ca6b);
+    }
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+// 0..1
+fn hash_f32(seed: i32, coords: &[i32]) -> f32 {
+    (hash(seed, coords) >> 8) as f32 / (1 << 24) as f32
+}
+
+// Inputs beyond the range of an i32 saturate, so the lattice arithmetic below
+// wraps rather than overflowing
+fn fast_floor(x: f32) -> i32 {
+    x.floor() as i32
+}
+
+// returns a value in the range -1..1
+pub fn simplex2(seed: i32, x: f32, y: f32) -> f32 {
+    let s = (x + y) * F2;
+    let i = fast_floor(x + s);
+    let j = fast_floor(y + s);
+
+    let t = (i as f32 + j as f32) * G2;
+    let x0 = x - (i as f32 - t);
+    let y0 = y - (j as f32 - t);
+
+    // which of the two triangles of the skewed cell the point is in
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+    let corners = [
+        (0, 0, x0, y0),
+        (i1, j1, x0 - i1 as f32 + G2, y0 - j1 as f32 + G2),
+        (1, 1, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2),
+    ];
+
+    let mut n = 0.0;
+    for (ci, cj, cx, cy) in corners.iter() {
+        let t = 0.5 - cx * cx - cy * cy;
+        if t > 0.0 {
+            let corner = [i.wrapping_add(*ci), j.wrapping_add(*cj)];
+            let g = GRAD2[(hash(seed, &corner) & 7) as usize];
+            let t2 = t * t;
+            n += t2 * t2 * (g[0] * cx + g[1] * cy);
+        }
+    }
+
+    70.0 * n
+}
+
+// returns a value in the range -1..1
+pub fn simplex3(seed: i32, x: f32, y: f32, z: f32) -> f32 {
+    let s = (x + y + z) * F3;
+    let i = fast_floor(x + s);
+    let j = fast_floor(y + s);
+    let k = fast_floor(z + s);
+
+    let t = (i as f32 + j as f32 + k as f32) * G3;
+    let x0 = x - (i as f32 - t);
+    let y0 = y - (j as f32 - t);
+    let z0 = z - (k as f32 - t);
+
+    // the offsets of the second and third corners of the simplex
+    let (o1, o2) = if x0 >= y0 {
+        if y0 >= z0 {
+            ([1, 0, 0], [1, 1, 0])
+        } else if x0 >= z0 {
+            ([1, 0, 0], [1, 0, 1])
+        } else {
+            ([0, 0, 1], [1, 0, 1])
+        }
+    } else if y0 < z0 {
+        ([0, 0, 1], [0, 1, 1])
+    } else if x0 < z0 {
+        ([0, 1, 0], [0, 1, 1])
+    } else {
+        ([0, 1, 0], [1, 1, 0])
+    };
+
+    let offsets = [[0, 0, 0], o1, o2, [1, 1, 1]];
+
+    let mut n = 0.0;
+    for (c, o) in offsets.iter().enumerate() {
+        let g_off = c as f32 * G3;
+        let cx = x0 - o[0] as f32 + g_off;
+        let cy = y0 - o[1] as f32 + g_off;
+        let cz = z0 - o[2] as f32 + g_off;
+
+        let t = 0.6 - cx * cx - cy * cy - cz * cz;
+        if t > 0.0 {
+            let corner = [
+                i.wrapping_add(o[0]),
+                j.wrapping_add(o[1]),
+                k.wrapping_add(o[2]),
+            ];
+            let g = GRAD3[(hash(seed, &corner) % 12) as usize];
+            let t2 = t * t;
+            n += t2 * t2 * (g[0] * cx + g[1] * cy + g[2] * cz);
+        }
+    }
+
+    32.0 * n
+}
+
+// returns a value in the range -1..1
+pub fn simplex4(seed: i32, x: f32, y: f32, z: f32, w: f32) -> f32 {
+    let s = (x + y + z + w) * F4;
+    let i = fast_floor(x + s);
+    let j = fast_floor(y + s);
+    let k = fast_floor(z + s);
+    let l = fast_floor(w + s);
+
+    let t = (i as f32 + j as f32 + k as f32 + l as f32) * G4;
+    let p0 = [
+        x - (i as f32 - t),
+        y - (j as f32 - t),
+        z - (k as f32 - t),
+        w - (l as f32 - t),
+    ];
+
+    // rank each axis by the magnitude of its coordinate, the simplex is
+    // traversed by stepping along the axes from the largest to the smallest
+    let mut rank = [0; 4];
+    for a in 0..4 {
+        for b in (a + 1)..4 {
+            if p0[a] > p0[b] {
+                rank[a] += 1;
+            } else {
+                rank[b] += 1;
+            }
+        }
+    }
+
+    let mut n = 0.0;
+    for c in 0..5 {
+        // corner c has a 1 for every axis whose rank is at least 4 - c
+        let mut o = [0; 4];
+        for a in 0..4 {
+            if c > 0 && rank[a] >= 4 - c {
+                o[a] = 1;
+            }
+        }
+
+        let g_off = c as f32 * G4;
+        let d = [
+            p0[0] - o[0] as f32 + g_off,
+            p0[1] - o[1] as f32 + g_off,
+            p0[2] - o[2] as f32 + g_off,
+            p0[3] - o[3] as f32 + g_off,
+        ];
+
+        let t = 0.6 - d[0] * d[0] - d[1] * d[1] - d[2] * d[2] - d[3] * d[3];
+        if t > 0.0 {
+            let corner = [
+                i.wrapping_add(o[0]),
+                j.wrapping_add(o[1]),
+                k.wrapping_add(o[2]),
+                l.wrapping_add(o[3]),
+            ];
+            let h = hash(seed, &corner);
+            let t2 = t * t;
+            n += t2 * t2 * grad4(h, &d);
+        }
+    }
+
+    27.0 * n
+}
+
+// one of the 32 gradients pointing to the midpoints of the edges of a tesseract
+fn grad4(h: u32, d: &[f32; 4]) -> f32 {
+    let h = h & 31;
+    let zero_axis = (h >> 3) as usize;
+    let mut res = 0.0;
+    let mut bit = 0;
+    for (a, v) in d.iter().enumerate() {
+        if a != zero_axis {
+            res += if (h >> bit) & 1 == 0 { *v } else { -*v };
+            bit += 1;
+        }
+    }
+    res
+}
+
+// prng/perlin isn't seeded, so instead the seed picks a far away region of it
+pub fn perlin(seed: i32, x: f32, y: f32, z: f32) -> f32 {
+    let ox = hash_f32(seed, &[0]) * 256.0;
+    let oy = hash_f32(seed, &[1]) * 256.0;
+    let oz = hash_f32(seed, &[2]) * 256.0;
+
+    prng::perlin(x + ox, y + oy, z + oz)
+}
+
+// Cellular noise with one feature point per cell. Returns a distance in the
+// range 0..~1.5 (in cell units)
+pub fn worley(seed: i32, x: f32, y: f32, feature: WorleyFeature) -> f32 {
+    let xi = fast_floor(x);
+    let yi = fast_floor(y);
+
+    let mut f1 = f32::MAX;
+    let mut f2 = f32::MAX;
+
+    for dj in -1..=1 {
+        for di in -1..=1 {
+            let i = xi.wrapping_add(di);
+            let j = yi.wrapping_add(dj);
+            let px = xi as f32 + di as f32 + hash_f32(seed, &[i, j, 0]);
+            let py = yi as f32 + dj as f32 + hash_f32(seed, &[i, j, 1]);
+            let d = length_v2(px - x, py - y);
+            if d < f1 {
+                f2 = f1;
+                f1 = d;
+            } else if d < f2 {
+                f2 = d;
+            }
+        }
+    }
+
+    match feature {
+        WorleyFeature::F1 => f1,
+        WorleyFeature::F2 => f2,
+        WorleyFeature::F2MinusF1 => f2 - f1,
+    }
+}
+
+// beyond this the octaves are too small to make a visible difference
+pub const FBM_MAX_OCTAVES: usize = 16;
+
+// Fractal Brownian motion: the sum of octaves of noise, each one at a higher
+// frequency and lower amplitude than the last. The result is normalised by the
+// magnitudes of the amplitudes so that it has the same range as the basis
+// noise, even when a negative gain makes the amplitudes cancel out
+pub fn fbm<F>(seed: i32, octaves: usize, lacunarity: f32, gain: f32, noise: F) -> f32
+where
+    F: Fn(i32, f32) -> f32,
+{
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude: f32 = 1.0;
+    let mut total_amplitude = 0.0;
+
+    for octave in 0..octaves.clamp(1, FBM_MAX_OCTAVES) {
+        // each octave uses a different seed so that they're uncorrelated
+        sum += amplitude * noise(seed.wrapping_add(octave as i32), frequency);
+        total_amplitude += amplitude.abs();
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+
+    sum / total_amplitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples<F: Fn(f32, f32) -> f32>(f: F) -> Vec<f32> {
+        let mut res = vec![];
+        for j in 0..40 {
+            for i in 0..40 {
+                res.push(f(i as f32 * 0.173 - 3.1, j as f32 * 0.219 - 4.3));
+            }
+        }
+        res
+    }
+
+    fn assert_range(vs: &[f32], min: f32, max: f32) {
+        for v in vs {
+            assert!(*v >= min && *v <= max, "{} not in {}..{}", v, min, max);
+        }
+        // it's noise, so the values shouldn't all be the same
+        let lowest = vs.iter().cloned().fold(f32::MAX, f32::min);
+        let highest = vs.iter().cloned().fold(f32::MIN, f32::max);
+        assert!(highest - lowest > (max - min) * 0.25);
+    }
+
+    #[test]
+    fn test_simplex() {
+        assert_range(&samples(|x, y| simplex2(7, x, y)), -1.0, 1.0);
+        assert_range(&samples(|x, y| simplex3(7, x, y, 0.4)), -1.0, 1.0);
+        assert_range(&samples(|x, y| simplex4(7, x, y, 0.4, 1.3)), -1.0, 1.0);
+
+        // the same seed gives the same noise, different seeds don't
+        assert_eq!(
+            samples(|x, y| simplex3(7, x, y, 2.0)),
+            samples(|x, y| simplex3(7, x, y, 2.0))
+        );
+        assert_ne!(
+            samples(|x, y| simplex3(7, x, y, 2.0)),
+            samples(|x, y| simplex3(8, x, y, 2.0))
+        );
+
+        // noise is zero at the lattice points
+        assert_eq!(simplex2(3, 0.0, 0.0), 0.0);
+
+        // coordinates beyond the range of an i32 don't overflow the lattice
+        for x in &[3.0e9, -3.0e9, f32::MAX] {
+            assert!(simplex2(7, *x, 5.0).abs() <= 1.0);
+            assert!(simplex3(7, *x, 5.0, *x).abs() <= 1.0);
+            assert!(simplex4(7, *x, 5.0, *x, *x).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_worley() {
+        let f1 = samples(|x, y| worley(5, x, y, WorleyFeature::F1));
+        let f2 = samples(|x, y| worley(5, x, y, WorleyFeature::F2));
+        assert_range(&f1, 0.0, 1.5);
+        for (a, b) in f1.iter().zip(f2.iter()) {
+            assert!(a <= b);
+        }
+        assert_ne!(f1, samples(|x, y| worley(6, x, y, WorleyFeature::F1)));
+
+        assert!(worley(5, 3.0e9, 5.0, WorleyFeature::F1).is_finite());
+        assert!(worley(5, -3.0e9, 5.0, WorleyFeature::F1).is_finite());
+    }
+
+    #[test]
+    fn test_fbm() {
+        // a single octave is just the basis noise
+        let basis = simplex2(4, 1.3, 2.7);
+        assert_eq!(
+            fbm(4, 1, 2.0, 0.5, |s, f| simplex2(s, 1.3 * f, 2.7 * f)),
+            basis
+        );
+
+        let vs = samples(|x, y| fbm(4, 5, 2.0, 0.5, |s, f| simplex2(s, x * f, y * f)));
+        assert_range(&vs, -1.0, 1.0);
+
+        // the number of octaves is limited
+        let octaves = std::cell::Cell::new(0);
+        fbm(4, 1_000_000, 2.0, 0.5, |_, _| {
+            octaves.set(octaves.get() + 1);
+            0.0
+        });
+        assert_eq!(octaves.get(), FBM_MAX_OCTAVES);
+
+        // amplitudes of 1 and -1 would otherwise sum to zero
+        let vs = samples(|x, y| fbm(4, 2, 2.0, -1.0, |s, f| simplex2(s, x * f, y * f)));
+        assert_range(&vs, -1.0, 1.0);
+    }
+}
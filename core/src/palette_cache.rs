@@ -0,0 +1,221 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::colour::{Colour, ColourFormat};
+use crate::error::{Error, Result};
+use log::error;
+use std::collections::HashMap;
+
+#[derive(Clone, Default)]
+pub struct PaletteCache {
+    // palettes imported by the host, these remain valid for the whole session
+    pub imported: HashMap<String, Vec<Colour>>,
+    // palettes defined by the current script, these are reset for every piece
+    pub defined: HashMap<String, Vec<Colour>>,
+}
+
+impl PaletteCache {
+    pub fn reset(&mut self) {
+        self.defined.clear();
+    }
+
+    pub fn insert(&mut self, name: &str, colours: Vec<Colour>) -> Result<()> {
+        if colours.is_empty() {
+            error!("palette {} has no colours", name);
+            return Err(Error::PaletteCache);
+        }
+        self.imported.insert(name.into(), colours);
+        Ok(())
+    }
+
+    // source is the text of a GIMP palette (.gpl) or a list of hex colours
+    pub fn insert_from_source(&mut self, name: &str, source: &str) -> Result<()> {
+        let colours = parse_palette(source)?;
+        self.insert(name, colours)
+    }
+
+    pub fn define(&mut self, name: &str, colours: Vec<Colour>) -> Result<()> {
+        if colours.is_empty() {
+            error!("palette {} has no colours", name);
+            return Err(Error::PaletteCache);
+        }
+        self.defined.insert(name.into(), colours);
+        Ok(())
+    }
+
+    // palettes defined by the script take precedence over imported ones
+    pub fn get(&self, name: &str) -> Result<&Vec<Colour>> {
+        match self.defined.get(name).or_else(|| self.imported.get(name)) {
+            Some(colours) => Ok(colours),
+            None => {
+                error!("can't find palette: {}", name);
+                Err(Error::PaletteCache)
+            }
+        }
+    }
+
+    // returns the subset of palette_names which haven't been imported
+    pub fn uncached(&self, palette_names: Vec<String>) -> Vec<String> {
+        let mut res = vec![];
+
+        for palette_name in palette_names {
+            if !self.imported.contains_key(&palette_name) {
+                res.push(palette_name)
+            }
+        }
+
+        res
+    }
+}
+
+pub fn parse_palette(source: &str) -> Result<Vec<Colour>> {
+    let colours = if source.trim_start().starts_with("GIMP Palette") {
+        parse_gpl(source)?
+    } else {
+        parse_hex_list(source)?
+    };
+
+    if colours.is_empty() {
+        error!("parse_palette: no colours found");
+        return Err(Error::PaletteCache);
+    }
+
+    Ok(colours)
+}
+
+// GIMP palettes have a header followed by lines of "r g b optional-name"
+// with each element in the range 0..255
+//
+fn parse_gpl(source: &str) -> Result<Vec<Colour>> {
+    let mut colours = vec![];
+
+    for line in source.lines().skip(1) {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+
+        let elements = line
+            .split_whitespace()
+            .take(3)
+            .map(|e| e.parse::<u8>())
+            .collect::<std::result::Result<Vec<u8>, _>>();
+
+        match elements {
+            Ok(rgb) if rgb.len() == 3 => colours.push(Colour::new(
+                ColourFormat::Rgb,
+                f32::from(rgb[0]) / 255.0,
+                f32::from(rgb[1]) / 255.0,
+                f32::from(rgb[2]) / 255.0,
+                1.0,
+            )),
+            _ => {
+                error!("parse_gpl: invalid line: {}", line);
+                return Err(Error::PaletteCache);
+            }
+        }
+    }
+
+    Ok(colours)
+}
+
+// hex colours separated by whitespace or commas, each optionally prefixed with
+// a '#'. Lines beginning with ';' are comments
+//
+fn parse_hex_list(source: &str) -> Result<Vec<Colour>> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(';'))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|hex| !hex.is_empty())
+        .map(|hex| Colour::from_rgb_hex(hex.trim_start_matches('#')))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rgb(colour: &Colour, r: f32, g: f32, b: f32) {
+        assert_eq!(colour.format, ColourFormat::Rgb);
+        assert!((colour.e0 - r).abs() < 0.001, "{:?}", colour);
+        assert!((colour.e1 - g).abs() < 0.001, "{:?}", colour);
+        assert!((colour.e2 - b).abs() < 0.001, "{:?}", colour);
+    }
+
+    #[test]
+    fn test_parse_gpl() {
+        let source = "GIMP Palette
+Name: Test
+Columns: 2
+#
+255   0   0	Red
+  0 255   0
+  0   0 255	Blue
+";
+        let colours = parse_palette(source).unwrap();
+        assert_eq!(colours.len(), 3);
+        assert_rgb(&colours[0], 1.0, 0.0, 0.0);
+        assert_rgb(&colours[1], 0.0, 1.0, 0.0);
+        assert_rgb(&colours[2], 0.0, 0.0, 1.0);
+
+        assert!(parse_palette("GIMP Palette\n255 0\n").is_err());
+        assert!(parse_palette("GIMP Palette\nName: Empty\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_hex_list() {
+        let colours = parse_palette("; comment\nff0000\n#00ff00, 0000ff\n\n").unwrap();
+        assert_eq!(colours.len(), 3);
+        assert_rgb(&colours[0], 1.0, 0.0, 0.0);
+        assert_rgb(&colours[1], 0.0, 1.0, 0.0);
+        assert_rgb(&colours[2], 0.0, 0.0, 1.0);
+
+        assert!(parse_palette("ff00").is_err());
+        assert!(parse_palette("aé€").is_err());
+        assert!(parse_palette("").is_err());
+    }
+
+    #[test]
+    fn test_palette_cache() {
+        let mut cache: PaletteCache = Default::default();
+        cache
+            .insert_from_source("warm.hex", "ff0000 ffff00")
+            .unwrap();
+        cache
+            .define(
+                "warm.hex",
+                vec![Colour::new(ColourFormat::Rgb, 0.0, 0.0, 1.0, 1.0)],
+            )
+            .unwrap();
+
+        // defined palettes shadow imported ones until the next piece
+        assert_eq!(cache.get("warm.hex").unwrap().len(), 1);
+        cache.reset();
+        assert_eq!(cache.get("warm.hex").unwrap().len(), 2);
+
+        assert!(cache.get("cool.hex").is_err());
+        assert_eq!(
+            cache.uncached(vec!["warm.hex".into(), "cool.hex".into()]),
+            vec!["cool.hex".to_string()]
+        );
+    }
+}
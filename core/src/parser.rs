@@ -491,6 +491,7 @@ fn augment_node_with_alterable<'a>(
         loc: nar.node.get_location(),
         gene_info: Some(NodeGene {
             gene: None,
+            gene_index: None,
             parameter_ast,
             parameter_prefix,
         }),
@@ -731,6 +732,7 @@ mod tests {
                         },
                         gene_info: Some(NodeGene {
                             gene: None,
+                            gene_index: None,
                             parameter_ast: vec![Node::List(
                                 meta_loc(1, 11),
                                 vec![Node::Name(
@@ -771,6 +773,7 @@ mod tests {
                         },
                         gene_info: Some(NodeGene {
                             gene: None,
+                            gene_index: None,
                             parameter_ast: vec![Node::List(
                                 meta_loc(1, 15),
                                 vec![Node::Name(
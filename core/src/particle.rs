@@ -0,0 +1,271 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// A simple particle simulation. Every step each particle's velocity is changed
+// by the forces acting on it, damped and then added to its position. The
+// simulation is deterministic so the same genotype always gives the same
+// trajectories.
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::flow;
+use crate::keywords::Keyword;
+use crate::mathutil::{self, length_v2, TAU};
+use crate::noise::NoiseBasis;
+use crate::prng::PrngStateStruct;
+use crate::program::Program;
+use crate::vm::{Var, Vm};
+
+use log::error;
+
+// the maximum number of positions (particles multiplied by steps) that a
+// simulation can generate
+const MAX_POSITIONS: usize = 1_000_000;
+
+// the maximum number of steps, even a simulation without any particles would
+// otherwise loop over every one of them
+const MAX_STEPS: usize = 100_000;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+}
+
+pub struct NoiseForce {
+    pub basis: NoiseBasis,
+    pub seed: i32,
+    pub frequency: f32,
+    pub octaves: usize,
+    pub strength: f32,
+}
+
+pub struct Forces {
+    pub function: Option<usize>,
+    pub noise: Option<NoiseForce>,
+    pub attractors: Vec<(f32, f32)>,
+    pub attraction: f32,
+    pub damping: f32,
+}
+
+// adds a random velocity of up to amount in any direction to each particle
+pub fn scatter_velocities(particles: &mut [Particle], amount: f32, prng: &mut PrngStateStruct) {
+    for particle in particles {
+        let angle = prng.next_f32() * TAU;
+        let speed = prng.next_f32() * amount;
+        particle.velocity.0 += speed * mathutil::cos(angle);
+        particle.velocity.1 += speed * mathutil::sin(angle);
+    }
+}
+
+// invoke a function with 4 args: n, index, position and velocity. Returns the force
+fn invoke_function(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    fun: usize,
+    step: usize,
+    index: usize,
+    particle: &Particle,
+) -> Result<(f32, f32)> {
+    let args = [
+        (Keyword::N, Var::Float(step as f32)),
+        (Keyword::Index, Var::Float(index as f32)),
+        (
+            Keyword::Position,
+            Var::V2D(particle.position.0, particle.position.1),
+        ),
+        (
+            Keyword::Velocity,
+            Var::V2D(particle.velocity.0, particle.velocity.1),
+        ),
+    ];
+
+    if let Var::V2D(fx, fy) = vm.invoke_function(context, program, fun, &args)? {
+        Ok((fx, fy))
+    } else {
+        error!("particle/simulate: fn should return a force vector");
+        Err(Error::Native)
+    }
+}
+
+// the forces that don't need the vm
+fn field_force(particle: &Particle, forces: &Forces) -> (f32, f32) {
+    let (x, y) = particle.position;
+    let mut force = (0.0, 0.0);
+
+    if let Some(noise) = &forces.noise {
+        let (dx, dy) = flow::noise_direction(
+            noise.basis,
+            noise.seed,
+            noise.frequency,
+            noise.octaves,
+            x,
+            y,
+        );
+        force.0 += dx * noise.strength;
+        force.1 += dy * noise.strength;
+    }
+
+    // a constant pull towards each attractor, which stops once the particle
+    // is within a unit of it
+    for (ax, ay) in &forces.attractors {
+        let (dx, dy) = (ax - x, ay - y);
+        let distance = length_v2(dx, dy);
+        if distance > 1.0 {
+            force.0 += dx / distance * forces.attraction;
+            force.1 += dy / distance * forces.attraction;
+        }
+    }
+
+    force
+}
+
+fn step(particle: &mut Particle, force: (f32, f32), damping: f32) {
+    let keep = 1.0 - damping;
+    particle.velocity.0 = (particle.velocity.0 + force.0) * keep;
+    particle.velocity.1 = (particle.velocity.1 + force.1) * keep;
+    particle.position.0 += particle.velocity.0;
+    particle.position.1 += particle.velocity.1;
+}
+
+// Returns the trajectory of every particle, each one starts with the
+// particle's initial position and is steps + 1 long
+pub fn simulate(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    mut particles: Vec<Particle>,
+    forces: &Forces,
+    steps: usize,
+) -> Result<Vec<Vec<(f32, f32)>>> {
+    if steps > MAX_STEPS {
+        error!(
+            "particle/simulate: {} steps is more than the limit of {}",
+            steps, MAX_STEPS
+        );
+        return Err(Error::Native);
+    }
+
+    if particles.len().saturating_mul(steps + 1) > MAX_POSITIONS {
+        error!(
+            "particle/simulate: {} particles for {} steps is more than the limit of {} positions",
+            particles.len(),
+            steps,
+            MAX_POSITIONS
+        );
+        return Err(Error::Native);
+    }
+
+    let mut trajectories: Vec<Vec<(f32, f32)>> = particles
+        .iter()
+        .map(|particle| {
+            let mut trajectory = Vec::with_capacity(steps + 1);
+            trajectory.push(particle.position);
+            trajectory
+        })
+        .collect();
+
+    for n in 0..steps {
+        for (index, particle) in particles.iter_mut().enumerate() {
+            let mut force = field_force(particle, forces);
+            if let Some(fun) = forces.function {
+                let (fx, fy) = invoke_function(vm, context, program, fun, n, index, particle)?;
+                force.0 += fx;
+                force.1 += fy;
+            }
+
+            step(particle, force, forces.damping);
+            trajectories[index].push(particle.position);
+        }
+    }
+
+    Ok(trajectories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_forces() -> Forces {
+        Forces {
+            function: None,
+            noise: None,
+            attractors: vec![],
+            attraction: 0.0,
+            damping: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut particle = Particle {
+            position: (0.0, 0.0),
+            velocity: (1.0, 0.0),
+        };
+        step(&mut particle, (0.0, 1.0), 0.0);
+        assert_eq!(particle.position, (1.0, 1.0));
+
+        step(&mut particle, (0.0, 0.0), 0.5);
+        assert_eq!(particle.velocity, (0.5, 0.5));
+        assert_eq!(particle.position, (1.5, 1.5));
+    }
+
+    #[test]
+    fn test_field_force() {
+        let particle = Particle {
+            position: (0.0, 0.0),
+            velocity: (0.0, 0.0),
+        };
+        assert_eq!(field_force(&particle, &no_forces()), (0.0, 0.0));
+
+        let mut forces = no_forces();
+        forces.attractors = vec![(10.0, 0.0), (0.0, -20.0), (0.5, 0.5)];
+        forces.attraction = 2.0;
+        assert_eq!(field_force(&particle, &forces), (2.0, -2.0));
+
+        let mut forces = no_forces();
+        forces.noise = Some(NoiseForce {
+            basis: NoiseBasis::Simplex,
+            seed: 3,
+            frequency: 0.01,
+            octaves: 2,
+            strength: 0.5,
+        });
+        let (fx, fy) = field_force(&particle, &forces);
+        assert!((length_v2(fx, fy) - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_scatter_velocities() {
+        let mut particles = vec![
+            Particle {
+                position: (0.0, 0.0),
+                velocity: (1.0, 0.0),
+            };
+            20
+        ];
+        let mut prng = PrngStateStruct::new(7, 0.0, 1.0);
+        scatter_velocities(&mut particles, 2.0, &mut prng);
+
+        for particle in &particles {
+            let (vx, vy) = particle.velocity;
+            assert!(length_v2(vx - 1.0, vy) <= 2.0);
+        }
+        assert!(particles[0].velocity != particles[1].velocity);
+    }
+}
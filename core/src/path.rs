@@ -18,9 +18,8 @@
 use crate::context::Context;
 use crate::ease::{easing, Easing};
 use crate::error::Result;
-use crate::iname::Iname;
 use crate::keywords::Keyword;
-use crate::mathutil::{bezier_point, quadratic_point, TAU};
+use crate::mathutil::{self, bezier_point, quadratic_point, TAU};
 use crate::program::Program;
 use crate::vm::*;
 
@@ -35,17 +34,12 @@ fn invoke_function(
     x: f32,
     y: f32,
 ) -> Result<()> {
-    let ip = vm.ip;
-
-    let fn_info = &program.fn_info[fun];
-
-    vm.function_call_default_arguments(context, program, fn_info)?;
-    vm.function_set_argument_to_f32(fn_info, Iname::from(Keyword::N), step);
-    vm.function_set_argument_to_f32(fn_info, Iname::from(Keyword::T), t);
-    vm.function_set_argument_to_2d(fn_info, Iname::from(Keyword::Position), x, y);
-    vm.function_call_body(context, program, fn_info)?;
-
-    vm.ip = ip;
+    let args = [
+        (Keyword::N, Var::Float(step)),
+        (Keyword::T, Var::Float(t)),
+        (Keyword::Position, Var::V2D(x, y)),
+    ];
+    vm.invoke_function(context, program, fun, &args)?;
 
     Ok(())
 }
@@ -98,8 +92,8 @@ pub fn circular(
     for i in 0..steps {
         let step = i as f32;
         let angle = (unit_angle * step) + (t_start * TAU);
-        let vx = (angle.sin() * radius) + pos_x;
-        let vy = (angle.cos() * radius) + pos_y;
+        let vx = (mathutil::sin(angle) * radius) + pos_x;
+        let vy = (mathutil::cos(angle) * radius) + pos_y;
         let t = easing(t_start + (unit * step), mapping);
 
         invoke_function(vm, context, program, fun, step, t, vx, vy)?;
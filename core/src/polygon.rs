@@ -0,0 +1,724 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Boolean operations and offsetting of simple polygons.
+//
+// The booleans use the Greiner-Hormann algorithm: the intersections between
+// the two outlines are inserted into both of them and then the result is
+// traced by walking along one outline and switching to the other at each
+// intersection. Greiner-Hormann can't cope with a vertex lying exactly on the
+// other outline, so when that happens the clip polygon is nudged by a tiny
+// amount and the operation is tried again. Any slivers that the nudge leaves
+// between coincident edges are removed from the result.
+//
+// A polygon with a hole is returned as a single outline with a zero width
+// bridge cut between the hole and the outside, so that it can be filled with
+// poly.
+
+const NUDGE_ATTEMPTS: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+type Point = (f64, f64);
+
+struct Node {
+    p: Point,
+    next: usize,
+    prev: usize,
+    // the index of the same intersection in the other polygon's nodes
+    neighbour: usize,
+    intersection: bool,
+    entry: bool,
+    visited: bool,
+}
+
+fn signed_area(polygon: &[Point]) -> f64 {
+    let mut res = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        res += a.0 * b.1 - b.0 * a.1;
+    }
+    res / 2.0
+}
+
+// even-odd rule
+fn contains(polygon: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn to_f64(polygon: &[(f32, f32)]) -> Vec<Point> {
+    let mut res: Vec<Point> = Vec::with_capacity(polygon.len());
+    for (x, y) in polygon {
+        let p = (f64::from(*x), f64::from(*y));
+        if res.last() != Some(&p) {
+            res.push(p);
+        }
+    }
+    if res.len() > 1 && res.first() == res.last() {
+        res.pop();
+    }
+
+    // always work with counter-clockwise outlines
+    if signed_area(&res) < 0.0 {
+        res.reverse();
+    }
+    res
+}
+
+fn to_f32(polygon: &[Point]) -> Vec<(f32, f32)> {
+    polygon
+        .iter()
+        .map(|(x, y)| (*x as f32, *y as f32))
+        .collect()
+}
+
+enum Crossing {
+    None,
+    At(f64, f64),
+    // touching or overlapping in a way that Greiner-Hormann can't handle
+    Degenerate,
+}
+
+fn crossing(a0: Point, a1: Point, b0: Point, b1: Point, eps: f64) -> Crossing {
+    let da = (a1.0 - a0.0, a1.1 - a0.1);
+    let db = (b1.0 - b0.0, b1.1 - b0.1);
+    let denom = da.0 * db.1 - da.1 * db.0;
+    let offset = (b0.0 - a0.0, b0.1 - a0.1);
+
+    if denom.abs() <= eps * eps {
+        // parallel, only a problem if they're collinear and overlapping
+        let collinear = (offset.0 * da.1 - offset.1 * da.0).abs() <= eps * eps;
+        if !collinear {
+            return Crossing::None;
+        }
+        let len2 = da.0 * da.0 + da.1 * da.1;
+        let t0 = (offset.0 * da.0 + offset.1 * da.1) / len2;
+        let t1 = ((b1.0 - a0.0) * da.0 + (b1.1 - a0.1) * da.1) / len2;
+        if t0.max(t1) < 0.0 || t0.min(t1) > 1.0 {
+            return Crossing::None;
+        }
+        return Crossing::Degenerate;
+    }
+
+    let ta = (offset.0 * db.1 - offset.1 * db.0) / denom;
+    let tb = (offset.0 * da.1 - offset.1 * da.0) / denom;
+
+    let len_a = length_f64(da);
+    let len_b = length_f64(db);
+    let (ea, eb) = (eps / len_a, eps / len_b);
+    if ta < -ea || ta > 1.0 + ea || tb < -eb || tb > 1.0 + eb {
+        Crossing::None
+    } else if ta <= ea || ta >= 1.0 - ea || tb <= eb || tb >= 1.0 - eb {
+        Crossing::Degenerate
+    } else {
+        Crossing::At(ta, tb)
+    }
+}
+
+fn length_f64(v: Point) -> f64 {
+    (v.0 * v.0 + v.1 * v.1).sqrt()
+}
+
+// builds the doubly linked lists of both polygons with the intersections
+// inserted, returns None if there's a degenerate intersection
+fn build_nodes(a: &[Point], b: &[Point], eps: f64) -> Option<(Vec<Node>, Vec<Node>)> {
+    // intersections along each edge as (t, index of the intersection)
+    let mut a_edges: Vec<Vec<(f64, usize)>> = vec![vec![]; a.len()];
+    let mut b_edges: Vec<Vec<(f64, usize)>> = vec![vec![]; b.len()];
+    let mut points: Vec<Point> = vec![];
+
+    for i in 0..a.len() {
+        let (a0, a1) = (a[i], a[(i + 1) % a.len()]);
+        for j in 0..b.len() {
+            let (b0, b1) = (b[j], b[(j + 1) % b.len()]);
+            match crossing(a0, a1, b0, b1, eps) {
+                Crossing::None => {}
+                Crossing::Degenerate => return None,
+                Crossing::At(ta, tb) => {
+                    a_edges[i].push((ta, points.len()));
+                    b_edges[j].push((tb, points.len()));
+                    points.push((a0.0 + ta * (a1.0 - a0.0), a0.1 + ta * (a1.1 - a0.1)));
+                }
+            }
+        }
+    }
+
+    let mut a_of_intersection = vec![0; points.len()];
+    let mut b_of_intersection = vec![0; points.len()];
+    let a_nodes = link(a, &mut a_edges, &points, &mut a_of_intersection);
+    let mut b_nodes = link(b, &mut b_edges, &points, &mut b_of_intersection);
+
+    let mut a_nodes = a_nodes;
+    for k in 0..points.len() {
+        a_nodes[a_of_intersection[k]].neighbour = b_of_intersection[k];
+        b_nodes[b_of_intersection[k]].neighbour = a_of_intersection[k];
+    }
+
+    Some((a_nodes, b_nodes))
+}
+
+fn link(
+    polygon: &[Point],
+    edges: &mut [Vec<(f64, usize)>],
+    points: &[Point],
+    node_of_intersection: &mut [usize],
+) -> Vec<Node> {
+    let mut nodes: Vec<Node> = vec![];
+    let new_node = |p: Point, intersection: bool| Node {
+        p,
+        next: 0,
+        prev: 0,
+        neighbour: 0,
+        intersection,
+        entry: false,
+        visited: false,
+    };
+
+    for (i, p) in polygon.iter().enumerate() {
+        nodes.push(new_node(*p, false));
+        edges[i].sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(std::cmp::Ordering::Equal));
+        for (_, k) in &edges[i] {
+            node_of_intersection[*k] = nodes.len();
+            nodes.push(new_node(points[*k], true));
+        }
+    }
+
+    let n = nodes.len();
+    for (i, node) in nodes.iter_mut().enumerate() {
+        node.next = (i + 1) % n;
+        node.prev = (i + n - 1) % n;
+    }
+    nodes
+}
+
+fn mark_entries(nodes: &mut [Node], other: &[Point], invert: bool) {
+    let mut entry = !contains(other, nodes[0].p);
+    if invert {
+        entry = !entry;
+    }
+    for node in nodes.iter_mut() {
+        if node.intersection {
+            node.entry = entry;
+            entry = !entry;
+        }
+    }
+}
+
+fn trace(a_nodes: &mut [Node], b_nodes: &mut [Node]) -> Vec<Vec<Point>> {
+    let mut res = vec![];
+    let max_len = a_nodes.len() + b_nodes.len();
+
+    while let Some(start) = a_nodes.iter().position(|n| n.intersection && !n.visited) {
+        let mut polygon = vec![];
+        let mut on_a = true;
+        let mut current = start;
+
+        loop {
+            let nodes: &mut [Node] = if on_a { a_nodes } else { b_nodes };
+            nodes[current].visited = true;
+            polygon.push(nodes[current].p);
+
+            // walk along this polygon until the next intersection
+            let forwards = nodes[current].entry;
+            loop {
+                current = if forwards {
+                    nodes[current].next
+                } else {
+                    nodes[current].prev
+                };
+                nodes[current].visited = true;
+                if nodes[current].intersection {
+                    break;
+                }
+                polygon.push(nodes[current].p);
+            }
+
+            // and then switch to the other one
+            let arrived_at_start = on_a && current == start;
+            current = nodes[current].neighbour;
+            on_a = !on_a;
+
+            let arrived_at_start = arrived_at_start || (on_a && current == start);
+            if arrived_at_start || polygon.len() > max_len {
+                break;
+            }
+        }
+
+        res.push(polygon);
+    }
+
+    res
+}
+
+fn boolean_without_crossings(a: &[Point], b: &[Point], op: BooleanOp) -> Vec<Vec<Point>> {
+    let a_in_b = contains(b, a[0]);
+    let b_in_a = contains(a, b[0]);
+
+    match op {
+        BooleanOp::Union => {
+            if a_in_b {
+                vec![b.to_vec()]
+            } else if b_in_a {
+                vec![a.to_vec()]
+            } else {
+                vec![a.to_vec(), b.to_vec()]
+            }
+        }
+        BooleanOp::Intersection => {
+            if a_in_b {
+                vec![a.to_vec()]
+            } else if b_in_a {
+                vec![b.to_vec()]
+            } else {
+                vec![]
+            }
+        }
+        BooleanOp::Difference => {
+            if a_in_b {
+                vec![]
+            } else if b_in_a {
+                let mut hole = b.to_vec();
+                hole.reverse();
+                vec![a.to_vec(), hole]
+            } else {
+                vec![a.to_vec()]
+            }
+        }
+    }
+}
+
+fn segments_cross(a0: Point, a1: Point, b0: Point, b1: Point) -> bool {
+    let side = |o: Point, a: Point, b: Point| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let d1 = side(b0, b1, a0);
+    let d2 = side(b0, b1, a1);
+    let d3 = side(a0, a1, b0);
+    let d4 = side(a0, a1, b1);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+// cuts a bridge from the hole to the outline so that they form one polygon
+fn bridge(outline: &[Point], hole: &[Point]) -> Vec<Point> {
+    let mut best: Option<(f64, usize, usize)> = None;
+
+    for (i, o) in outline.iter().enumerate() {
+        for (j, h) in hole.iter().enumerate() {
+            let d = length_f64((o.0 - h.0, o.1 - h.1));
+            if matches!(best, Some((bd, _, _)) if bd <= d) {
+                continue;
+            }
+            let blocked = |polygon: &[Point]| {
+                (0..polygon.len())
+                    .any(|k| segments_cross(*o, *h, polygon[k], polygon[(k + 1) % polygon.len()]))
+            };
+            if !blocked(outline) && !blocked(hole) {
+                best = Some((d, i, j));
+            }
+        }
+    }
+
+    let (i, j) = match best {
+        Some((_, i, j)) => (i, j),
+        None => return outline.to_vec(),
+    };
+
+    let mut res = Vec::with_capacity(outline.len() + hole.len() + 2);
+    res.extend_from_slice(&outline[..=i]);
+    for k in 0..=hole.len() {
+        res.push(hole[(j + k) % hole.len()]);
+    }
+    res.extend_from_slice(&outline[i..]);
+    res
+}
+
+// a point that's just inside the polygon next to its longest edge, close
+// enough that it can't be inside a hole of the polygon
+fn interior_point(polygon: &[Point]) -> Point {
+    let n = polygon.len();
+    let i = (0..n)
+        .max_by(|i, j| {
+            let len = |k: &usize| {
+                let (a, b) = (polygon[*k], polygon[(*k + 1) % n]);
+                length_f64((b.0 - a.0, b.1 - a.1))
+            };
+            len(i)
+                .partial_cmp(&len(j))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+
+    let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+    // the inside is to the left of a counter-clockwise edge
+    let winding = signed_area(polygon).signum();
+    let nudge = 1e-6 * winding;
+    (
+        (a.0 + b.0) / 2.0 - (b.1 - a.1) * nudge,
+        (a.1 + b.1) / 2.0 + (b.0 - a.0) * nudge,
+    )
+}
+
+// Nudging coincident outlines apart leaves slivers that are about as wide as
+// the nudge and vertices that are about that far apart. Polygons narrower than
+// the tolerance are dropped and vertices closer than it are merged.
+fn remove_slivers(polygons: Vec<Vec<Point>>, tolerance: f64) -> Vec<Vec<Point>> {
+    let distance = |a: Point, b: Point| length_f64((b.0 - a.0, b.1 - a.1));
+
+    polygons
+        .into_iter()
+        .filter(|polygon| {
+            let perimeter: f64 = polygon
+                .iter()
+                .enumerate()
+                .map(|(i, a)| distance(*a, polygon[(i + 1) % polygon.len()]))
+                .sum();
+            signed_area(polygon).abs() >= tolerance * perimeter
+        })
+        .map(|polygon| {
+            let mut res: Vec<Point> = vec![];
+            for p in polygon {
+                match res.last() {
+                    Some(q) if distance(*q, p) < tolerance => {}
+                    _ => res.push(p),
+                }
+            }
+            while res.len() > 1 && distance(res[res.len() - 1], res[0]) < tolerance {
+                res.pop();
+            }
+            res
+        })
+        .filter(|polygon| polygon.len() >= 3)
+        .collect()
+}
+
+// The polygons that are inside an odd number of the others are holes, each
+// hole is joined to the smallest outline that contains it.
+fn merge_holes(polygons: Vec<Vec<Point>>) -> Vec<Vec<Point>> {
+    let mut polygons: Vec<Vec<Point>> = polygons.into_iter().filter(|p| p.len() >= 3).collect();
+
+    // outlines are counter-clockwise and holes are clockwise
+    let is_hole: Vec<bool> = polygons
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let inside = interior_point(p);
+            let depth = polygons
+                .iter()
+                .enumerate()
+                .filter(|(j, q)| *j != i && contains(q, inside))
+                .count();
+            depth % 2 == 1
+        })
+        .collect();
+    for (p, hole) in polygons.iter_mut().zip(is_hole.iter()) {
+        if (signed_area(p) < 0.0) != *hole {
+            p.reverse();
+        }
+    }
+
+    let (holes, mut outlines): (Vec<_>, Vec<_>) = polygons
+        .into_iter()
+        .zip(is_hole)
+        .partition(|(_, hole)| *hole);
+    let holes: Vec<Vec<Point>> = holes.into_iter().map(|(p, _)| p).collect();
+
+    for hole in holes {
+        let inside = interior_point(&hole);
+        let container = outlines
+            .iter_mut()
+            .filter(|(o, _)| contains(o, inside))
+            .min_by(|(a, _), (b, _)| {
+                signed_area(a)
+                    .partial_cmp(&signed_area(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        if let Some((outline, _)) = container {
+            *outline = bridge(outline, &hole);
+        }
+    }
+
+    outlines.into_iter().map(|(p, _)| p).collect()
+}
+
+pub fn boolean(a: &[(f32, f32)], b: &[(f32, f32)], op: BooleanOp) -> Vec<Vec<(f32, f32)>> {
+    let a = to_f64(a);
+    let mut b = to_f64(b);
+
+    if a.len() < 3 || b.len() < 3 {
+        let res = match op {
+            BooleanOp::Union | BooleanOp::Difference if a.len() >= 3 => vec![a],
+            BooleanOp::Union if b.len() >= 3 => vec![b],
+            _ => vec![],
+        };
+        return res.iter().map(|p| to_f32(p)).collect();
+    }
+
+    let scale = a
+        .iter()
+        .chain(b.iter())
+        .fold(1.0f64, |m, p| m.max(p.0.abs()).max(p.1.abs()));
+    let eps = scale * 1e-9;
+
+    // how far the clip polygon has been nudged
+    let mut moved = 0.0;
+
+    for attempt in 0..NUDGE_ATTEMPTS {
+        if let Some((mut a_nodes, mut b_nodes)) = build_nodes(&a, &b, eps) {
+            let polygons = if a_nodes.iter().any(|n| n.intersection) {
+                let (invert_a, invert_b) = match op {
+                    BooleanOp::Intersection => (false, false),
+                    BooleanOp::Union => (true, true),
+                    BooleanOp::Difference => (true, false),
+                };
+                mark_entries(&mut a_nodes, &b, invert_a);
+                mark_entries(&mut b_nodes, &a, invert_b);
+                trace(&mut a_nodes, &mut b_nodes)
+            } else {
+                boolean_without_crossings(&a, &b, op)
+            };
+
+            let polygons = remove_slivers(polygons, moved * 2.0);
+            return merge_holes(polygons).iter().map(|p| to_f32(p)).collect();
+        }
+
+        // move the clip polygon by a tiny amount in a direction that's
+        // unlikely to line up with anything
+        let nudge = eps * 1000.0 * (attempt + 1) as f64;
+        for p in b.iter_mut() {
+            p.0 += nudge * 0.761;
+            p.1 += nudge * 0.649;
+        }
+        moved += nudge;
+    }
+
+    vec![]
+}
+
+// Moves every edge of the polygon outwards by distance (inwards if it's
+// negative). Corners are mitered, but a miter longer than miter_limit times
+// the distance is bevelled. Returns an empty polygon if an inset removes the
+// whole shape.
+pub fn offset(polygon: &[(f32, f32)], distance: f32, miter_limit: f32) -> Vec<(f32, f32)> {
+    let pts = to_f64(polygon);
+    let n = pts.len();
+    if n < 3 {
+        return vec![];
+    }
+
+    let d = f64::from(distance);
+    let limit = f64::from(miter_limit);
+    let mut res: Vec<Point> = vec![];
+    // the offset vertices at the start and end of each edge
+    let mut edge_starts = vec![0; n];
+    let mut edge_ends = vec![0; n];
+
+    for i in 0..n {
+        let prev = pts[(i + n - 1) % n];
+        let p = pts[i];
+        let next = pts[(i + 1) % n];
+
+        // outward normals of the edges either side of the vertex, the outline
+        // is counter-clockwise so the outside is to the right
+        let e0 = (p.0 - prev.0, p.1 - prev.1);
+        let e1 = (next.0 - p.0, next.1 - p.1);
+        let (l0, l1) = (length_f64(e0), length_f64(e1));
+        let n0 = (e0.1 / l0, -e0.0 / l0);
+        let n1 = (e1.1 / l1, -e1.0 / l1);
+
+        let bisector = (n0.0 + n1.0, n0.1 + n1.1);
+        let cos_half = (1.0 + n0.0 * n1.0 + n0.1 * n1.1) / 2.0;
+        let miter_length = if cos_half > 0.0 {
+            1.0 / cos_half.sqrt()
+        } else {
+            f64::MAX
+        };
+
+        if miter_length <= limit {
+            let bl = length_f64(bisector);
+            let scale = d * miter_length / bl;
+            res.push((p.0 + bisector.0 * scale, p.1 + bisector.1 * scale));
+        } else {
+            res.push((p.0 + n0.0 * d, p.1 + n0.1 * d));
+            res.push((p.0 + n1.0 * d, p.1 + n1.1 * d));
+        }
+        edge_ends[(i + n - 1) % n] = res.len() - if miter_length <= limit { 1 } else { 2 };
+        edge_starts[i] = res.len() - 1;
+    }
+
+    // an inset that's larger than the shape turns it inside out, either by
+    // flipping it over or by reversing every edge
+    let all_reversed = (0..n).all(|i| {
+        let e = (pts[(i + 1) % n].0 - pts[i].0, pts[(i + 1) % n].1 - pts[i].1);
+        let (from, to) = (res[edge_starts[i]], res[edge_ends[i]]);
+        (to.0 - from.0) * e.0 + (to.1 - from.1) * e.1 < 0.0
+    });
+    if signed_area(&res) <= 0.0 || all_reversed {
+        return vec![];
+    }
+
+    to_f32(&res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(polygon: &[(f32, f32)]) -> f32 {
+        let pts: Vec<Point> = polygon
+            .iter()
+            .map(|(x, y)| (f64::from(*x), f64::from(*y)))
+            .collect();
+        signed_area(&pts) as f32
+    }
+
+    fn total_area(polygons: &[Vec<(f32, f32)>]) -> f32 {
+        polygons.iter().map(|p| area(p)).sum()
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<(f32, f32)> {
+        vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]
+    }
+
+    #[test]
+    fn test_boolean_overlapping() {
+        let a = square(0.0, 0.0, 10.0);
+        let b = square(5.0, 5.0, 10.0);
+
+        let union = boolean(&a, &b, BooleanOp::Union);
+        assert_eq!(union.len(), 1);
+        assert!((total_area(&union) - 175.0).abs() < 0.001);
+
+        let intersection = boolean(&a, &b, BooleanOp::Intersection);
+        assert_eq!(intersection.len(), 1);
+        assert!((total_area(&intersection) - 25.0).abs() < 0.001);
+
+        let difference = boolean(&a, &b, BooleanOp::Difference);
+        assert_eq!(difference.len(), 1);
+        assert!((total_area(&difference) - 75.0).abs() < 0.001);
+
+        // clockwise input gives the same results
+        let mut reversed = b.clone();
+        reversed.reverse();
+        let difference = boolean(&a, &reversed, BooleanOp::Difference);
+        assert!((total_area(&difference) - 75.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_boolean_degenerate() {
+        // edges that lie on top of each other
+        let a = square(0.0, 0.0, 10.0);
+        let b = square(5.0, 0.0, 10.0);
+        assert!((total_area(&boolean(&a, &b, BooleanOp::Union)) - 150.0).abs() < 0.01);
+        assert!((total_area(&boolean(&a, &b, BooleanOp::Intersection)) - 50.0).abs() < 0.01);
+        assert!((total_area(&boolean(&a, &b, BooleanOp::Difference)) - 50.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_boolean_identical() {
+        // the nudge mustn't leave slivers or notches behind
+        let a = square(0.0, 0.0, 10.0);
+        assert!(boolean(&a, &a, BooleanOp::Difference).is_empty());
+
+        let union = boolean(&a, &a, BooleanOp::Union);
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].len(), 4);
+        assert!((total_area(&union) - 100.0).abs() < 0.01);
+
+        let intersection = boolean(&a, &a, BooleanOp::Intersection);
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(intersection[0].len(), 4);
+        assert!((total_area(&intersection) - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_boolean_contained() {
+        let a = square(0.0, 0.0, 10.0);
+        let b = square(2.0, 2.0, 4.0);
+        let far = square(20.0, 20.0, 4.0);
+
+        assert_eq!(total_area(&boolean(&a, &b, BooleanOp::Union)), 100.0);
+        assert_eq!(total_area(&boolean(&a, &b, BooleanOp::Intersection)), 16.0);
+        assert_eq!(boolean(&a, &far, BooleanOp::Union).len(), 2);
+        assert!(boolean(&a, &far, BooleanOp::Intersection).is_empty());
+        assert!(boolean(&b, &a, BooleanOp::Difference).is_empty());
+
+        // a hole is joined to the outline
+        let difference = boolean(&a, &b, BooleanOp::Difference);
+        assert_eq!(difference.len(), 1);
+        assert_eq!(difference[0].len(), 4 + 4 + 2);
+        assert_eq!(total_area(&difference), 84.0);
+    }
+
+    #[test]
+    fn test_boolean_makes_hole() {
+        // a U shape closed off by a bar leaves a hole in the middle
+        let u = vec![
+            (0.0, 0.0),
+            (30.0, 0.0),
+            (30.0, 30.0),
+            (20.0, 30.0),
+            (20.0, 10.0),
+            (10.0, 10.0),
+            (10.0, 30.0),
+            (0.0, 30.0),
+        ];
+        let bar = vec![(-5.0, 25.0), (35.0, 25.0), (35.0, 35.0), (-5.0, 35.0)];
+
+        let union = boolean(&u, &bar, BooleanOp::Union);
+        assert_eq!(union.len(), 1);
+        // u (700) + bar (400) - overlap (2 * 50)
+        assert!((total_area(&union) - 1000.0).abs() < 0.01);
+        assert_eq!(union[0].len(), 8 + 4 + 2);
+
+        // and the joined up outline can be filled
+        let filled: f32 = crate::geom::triangulate_polygon(&union[0])
+            .iter()
+            .map(|t| area(&[union[0][t[0]], union[0][t[1]], union[0][t[2]]]))
+            .sum();
+        assert!((filled - 1000.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_offset() {
+        let a = square(0.0, 0.0, 10.0);
+
+        let outset = offset(&a, 1.0, 4.0);
+        assert_eq!(outset.len(), 4);
+        assert!((area(&outset) - 144.0).abs() < 0.001);
+
+        let inset = offset(&a, -2.0, 4.0);
+        assert!((area(&inset) - 36.0).abs() < 0.001);
+
+        // too big an inset removes everything
+        assert!(offset(&a, -6.0, 4.0).is_empty());
+
+        // a sharp corner is bevelled
+        let spike = vec![(0.0, 0.0), (10.0, 0.0), (0.0, 1.0)];
+        assert_eq!(offset(&spike, 1.0, 2.0).len(), 4);
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::bitmap_cache::BitmapCache;
+use crate::compile_cache::ProgramTemplate;
+use crate::compiler::compile_preamble;
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::gene::Genotype;
+use crate::palette_cache::PaletteCache;
+use crate::program::Program;
+use crate::render_list::RenderList;
+use crate::run_program_with_compiled_preamble;
+use crate::vm::Vm;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use log::error;
+
+/// Renders every genotype in a population, sharing the work between
+/// num_threads worker threads. The script is only compiled once.
+///
+/// The render list at index i of the result belongs to genotypes[i] and is
+/// identical to the one produced by rendering that genotype on its own.
+pub fn render_population(
+    source: &str,
+    genotypes: &[Genotype],
+    bitmap_cache: &BitmapCache,
+    palette_cache: &PaletteCache,
+    num_threads: usize,
+) -> Result<Vec<RenderList>> {
+    if genotypes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let template = ProgramTemplate::new(source, &genotypes[0])?;
+    let preamble = compile_preamble()?;
+
+    let num_threads = num_threads.max(1).min(genotypes.len());
+
+    // each worker takes the next unrendered genotype until there are none left
+    let next_genotype = AtomicUsize::new(0);
+
+    let worker = || {
+        // cloning the bitmap cache shares the pixel data rather than copying it
+        let mut context = Context {
+            bitmap_cache: bitmap_cache.clone(),
+            palette_cache: palette_cache.clone(),
+            ..Default::default()
+        };
+
+        let mut rendered: Vec<(usize, Result<RenderList>)> = vec![];
+        loop {
+            let i = next_genotype.fetch_add(1, Ordering::Relaxed);
+            if i >= genotypes.len() {
+                break;
+            }
+            let res = render_genotype(&template, &preamble, &genotypes[i], &mut context);
+            rendered.push((i, res));
+        }
+        rendered
+    };
+
+    let mut results: Vec<Option<Result<RenderList>>> = genotypes.iter().map(|_| None).collect();
+
+    thread::scope(|scope| -> Result<()> {
+        let handles: Vec<_> = (0..num_threads).map(|_| scope.spawn(worker)).collect();
+
+        for handle in handles {
+            let rendered = handle.join().map_err(|_| {
+                error!("render_population: worker thread panicked");
+                Error::Context
+            })?;
+            for (i, res) in rendered {
+                results[i] = Some(res);
+            }
+        }
+        Ok(())
+    })?;
+
+    results
+        .into_iter()
+        .map(|res| res.unwrap_or(Err(Error::Context)))
+        .collect()
+}
+
+fn render_genotype(
+    template: &ProgramTemplate,
+    preamble: &Program,
+    genotype: &Genotype,
+    context: &mut Context,
+) -> Result<RenderList> {
+    let program = template.program_for_genotype(genotype)?;
+
+    // a fresh vm for every genotype so that no prng state leaks between them
+    let mut vm: Vm = Default::default();
+    run_program_with_compiled_preamble(&mut vm, context, preamble, &program)?;
+
+    let mut render_list = std::mem::take(&mut context.render_list);
+    render_list.remove_useless_render_packets();
+
+    Ok(render_list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap_cache::BitmapInfo;
+    use crate::gene::Gene;
+    use crate::golden::gallery_scripts;
+    use crate::render_packet::RenderPacket;
+    use crate::{build_traits, program_from_source_and_genotype, run_program_with_preamble};
+
+    fn geometry(render_list: &RenderList) -> Vec<Vec<f32>> {
+        render_list
+            .render_packets
+            .iter()
+            .map(|rp| match rp {
+                RenderPacket::Geometry(rpg) => rpg.geo.clone(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn render_alone(s: &str, genotype: &Genotype) -> Vec<Vec<f32>> {
+        let mut genotype = genotype.clone();
+        let program = program_from_source_and_genotype(s, &mut genotype).unwrap();
+
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+        run_program_with_preamble(&mut vm, &mut context, &program).unwrap();
+        context.render_list.remove_useless_render_packets();
+
+        geometry(&context.render_list)
+    }
+
+    #[test]
+    fn test_vm_and_context_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Vm>();
+        assert_send::<Context>();
+    }
+
+    #[test]
+    fn test_workers_share_bitmap_data() {
+        let mut bitmap_cache: BitmapCache = Default::default();
+        bitmap_cache
+            .insert("a.png", BitmapInfo::new(2, 1, vec![0; 8]))
+            .unwrap();
+
+        let copy = bitmap_cache.clone();
+        assert!(std::ptr::eq(
+            bitmap_cache.get("a.png").unwrap(),
+            copy.get("a.png").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_render_population_is_deterministic() {
+        let s = "(define rng (prng/build seed: 5 min: 0 max: 100))
+                 (loop (i upto: 8)
+                   (rect position: [(prng/value from: rng) (prng/value from: rng)]
+                         width: 20 ~ (gen/scalar min: 5 max: 50)
+                         height: 20
+                         colour: (col/rgb r: 1 g: 0 b: 0 alpha: 1) ~ (gen/col)))";
+
+        let trait_list = build_traits(s).unwrap();
+        let genotypes = Genotype::build_genotypes(&trait_list, 12, 42).unwrap();
+        let bitmap_cache: BitmapCache = Default::default();
+
+        let single =
+            render_population(s, &genotypes, &bitmap_cache, &Default::default(), 1).unwrap();
+        let multi =
+            render_population(s, &genotypes, &bitmap_cache, &Default::default(), 4).unwrap();
+        assert_eq!(single.len(), genotypes.len());
+        assert_eq!(multi.len(), genotypes.len());
+
+        for (i, genotype) in genotypes.iter().enumerate() {
+            let expected = render_alone(s, genotype);
+            assert_eq!(geometry(&single[i]), expected);
+            assert_eq!(geometry(&multi[i]), expected);
+        }
+
+        // the genotypes should produce different geometry
+        assert_ne!(geometry(&multi[0]), geometry(&multi[1]));
+    }
+
+    // older gallery scripts that can't be rendered with a genotype:
+    // 1580-quilt uses syntax that's no longer supported and
+    // 157e-tile-shadow has genes on the elements of vectors
+    const KNOWN_FAILURES: [&str; 2] = ["1580-quilt.seni", "157e-tile-shadow.seni"];
+
+    #[test]
+    fn test_gallery_is_deterministic() {
+        let bitmap_cache: BitmapCache = Default::default();
+        let seeds = [1, 977];
+        let mut num_rendered = 0;
+
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../server/db/seni");
+        for (name, s) in gallery_scripts(&dir).unwrap() {
+            // scripts that use bitmaps can't render without the client loading them
+            if s.contains("bitmap/") {
+                continue;
+            }
+            if KNOWN_FAILURES.contains(&name.as_str()) {
+                let rendered = build_traits(&s)
+                    .and_then(|trait_list| Genotype::build_from_seed(&trait_list, seeds[0]))
+                    .and_then(|genotype| {
+                        render_population(&s, &[genotype], &bitmap_cache, &Default::default(), 1)
+                    });
+                assert!(rendered.is_err(), "{} can now be rendered", name);
+                continue;
+            }
+
+            let trait_list = build_traits(&s).unwrap();
+            let genotypes: Vec<Genotype> = seeds
+                .iter()
+                .map(|seed| Genotype::build_from_seed(&trait_list, *seed).unwrap())
+                .collect();
+
+            let rendered =
+                render_population(&s, &genotypes, &bitmap_cache, &Default::default(), 3).unwrap();
+            let again =
+                render_population(&s, &genotypes, &bitmap_cache, &Default::default(), 1).unwrap();
+
+            for i in 0..seeds.len() {
+                assert_eq!(
+                    rendered[i].geometry_hash(),
+                    again[i].geometry_hash(),
+                    "{} seed {}",
+                    name,
+                    seeds[i]
+                );
+            }
+            num_rendered += 1;
+        }
+
+        assert!(num_rendered > 50);
+    }
+
+    #[test]
+    fn test_gallery_matches_expected_values() {
+        // the genotypes and geometry should be the same on every platform
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../server/db/seni/151c-flower.seni");
+        let s = std::fs::read_to_string(path).unwrap();
+
+        let trait_list = build_traits(&s).unwrap();
+        let genotypes: Vec<Genotype> = [1, 977]
+            .iter()
+            .map(|seed| Genotype::build_from_seed(&trait_list, *seed).unwrap())
+            .collect();
+
+        assert_eq!(
+            genotypes[0].genes[0..3],
+            [
+                Gene::Float(0.14132595),
+                Gene::Float(0.08633549),
+                Gene::Float(0.7288273)
+            ]
+        );
+        assert_eq!(
+            genotypes[1].genes[0..3],
+            [
+                Gene::Float(0.22156769),
+                Gene::Float(0.3511525),
+                Gene::Float(0.23022875)
+            ]
+        );
+
+        let rendered =
+            render_population(&s, &genotypes, &Default::default(), &Default::default(), 2).unwrap();
+        assert_eq!(rendered[0].geometry_hash(), 0x735890b53c9cb7fe);
+        assert_eq!(rendered[1].geometry_hash(), 0x9b3802d86749b4fc);
+    }
+}
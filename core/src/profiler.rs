@@ -0,0 +1,385 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::native::Native;
+use crate::program::Program;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+// the name given to the script's top-level forms in the profiler's output
+const TOP_LEVEL_NAME: &str = "main";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ProfileStats {
+    /// number of times a user defined function was entered
+    pub calls: u64,
+    /// opcodes executed (exclusive of any called functions)
+    pub opcodes: u64,
+    pub native_calls: u64,
+    /// time spent in natives (exclusive of any nested natives)
+    pub native_time: Duration,
+    pub vertices: u64,
+}
+
+/// the measurement used to sort the profiler's tables and to weight the folded stacks
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProfileMetric {
+    Opcodes,
+    NativeTime, // in microseconds
+    Vertices,
+}
+
+#[derive(Debug)]
+pub struct ProfileRow {
+    pub label: String,
+    pub stats: ProfileStats,
+}
+
+// user defined functions are identified by their arg_address as this is the
+// only information that the CALL opcode has about the function being invoked
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+enum Frame {
+    Fn(usize),
+    Native(Native),
+}
+
+#[derive(Debug)]
+struct NativeTimer {
+    native: Native,
+    line: usize,
+    start: Instant,
+    child_time: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    call_stack: Vec<Frame>,
+    native_timers: Vec<NativeTimer>,
+    vertex_watermark: usize,
+
+    // keyed by arg_address, None is the top-level of the script
+    functions: HashMap<Option<usize>, ProfileStats>,
+    lines: HashMap<usize, ProfileStats>,
+    natives: HashMap<Native, ProfileStats>,
+    stacks: HashMap<Vec<Frame>, ProfileStats>,
+}
+
+impl ProfileStats {
+    fn metric(&self, metric: ProfileMetric) -> u64 {
+        match metric {
+            ProfileMetric::Opcodes => self.opcodes,
+            ProfileMetric::NativeTime => self.native_time.as_micros() as u64,
+            ProfileMetric::Vertices => self.vertices,
+        }
+    }
+
+    fn add_native(&mut self, time: Duration, vertices: u64) {
+        self.native_calls += 1;
+        self.native_time += time;
+        self.vertices += vertices;
+    }
+}
+
+impl Profiler {
+    pub fn reset(&mut self) {
+        self.call_stack.clear();
+        self.native_timers.clear();
+        self.vertex_watermark = 0;
+
+        self.functions.clear();
+        self.lines.clear();
+        self.natives.clear();
+        self.stacks.clear();
+    }
+
+    fn current_fn(&self) -> Option<usize> {
+        self.call_stack.iter().rev().find_map(|frame| match frame {
+            Frame::Fn(addr) => Some(*addr),
+            Frame::Native(_) => None,
+        })
+    }
+
+    fn stack_stats(&mut self) -> &mut ProfileStats {
+        // only allocate a new key the first time a particular stack is seen
+        if !self.stacks.contains_key(&self.call_stack) {
+            self.stacks
+                .insert(self.call_stack.clone(), Default::default());
+        }
+        self.stacks.get_mut(&self.call_stack).unwrap()
+    }
+
+    pub fn enter_fn(&mut self, arg_address: usize) {
+        self.call_stack.push(Frame::Fn(arg_address));
+        self.functions.entry(Some(arg_address)).or_default().calls += 1;
+    }
+
+    pub fn exit_fn(&mut self) {
+        self.call_stack.pop();
+    }
+
+    pub fn opcode(&mut self, line: usize) {
+        self.functions.entry(self.current_fn()).or_default().opcodes += 1;
+        self.lines.entry(line).or_default().opcodes += 1;
+        self.stack_stats().opcodes += 1;
+    }
+
+    pub fn native_begin(&mut self, native: Native, line: usize, num_vertices: usize) {
+        if self.native_timers.is_empty() {
+            self.vertex_watermark = num_vertices;
+        }
+
+        self.call_stack.push(Frame::Native(native));
+        self.native_timers.push(NativeTimer {
+            native,
+            line,
+            start: Instant::now(),
+            child_time: Duration::default(),
+        });
+    }
+
+    pub fn native_end(&mut self, num_vertices: usize) {
+        let timer = match self.native_timers.pop() {
+            Some(timer) => timer,
+            None => return,
+        };
+
+        let elapsed = timer.start.elapsed();
+        if let Some(parent) = self.native_timers.last_mut() {
+            parent.child_time += elapsed;
+        }
+        let time = elapsed.checked_sub(timer.child_time).unwrap_or_default();
+
+        // any vertices emitted by nested natives have already been accounted for
+        let vertices = num_vertices.saturating_sub(self.vertex_watermark) as u64;
+        self.vertex_watermark = num_vertices;
+
+        self.stack_stats().add_native(time, vertices);
+        self.call_stack.pop();
+
+        self.natives
+            .entry(timer.native)
+            .or_default()
+            .add_native(time, vertices);
+        self.functions
+            .entry(self.current_fn())
+            .or_default()
+            .add_native(time, vertices);
+        self.lines
+            .entry(timer.line)
+            .or_default()
+            .add_native(time, vertices);
+    }
+
+    pub fn function_rows(&self, program: &Program, metric: ProfileMetric) -> Vec<ProfileRow> {
+        let rows = self
+            .functions
+            .iter()
+            .map(|(addr, stats)| ProfileRow {
+                label: fn_label(program, *addr),
+                stats: *stats,
+            })
+            .collect();
+        sorted_rows(rows, metric)
+    }
+
+    pub fn line_rows(&self, metric: ProfileMetric) -> Vec<ProfileRow> {
+        let rows = self
+            .lines
+            .iter()
+            .map(|(line, stats)| ProfileRow {
+                label: if *line == 0 {
+                    "-".to_string()
+                } else {
+                    format!("line {}", line)
+                },
+                stats: *stats,
+            })
+            .collect();
+        sorted_rows(rows, metric)
+    }
+
+    pub fn native_rows(&self, metric: ProfileMetric) -> Vec<ProfileRow> {
+        let rows = self
+            .natives
+            .iter()
+            .map(|(native, stats)| ProfileRow {
+                label: native.to_string(),
+                stats: *stats,
+            })
+            .collect();
+        sorted_rows(rows, metric)
+    }
+
+    /// one line per unique call stack in the format expected by flamegraph tools
+    /// e.g. "main;draw-leaf;path/linear 1234"
+    pub fn folded_stacks(&self, program: &Program, metric: ProfileMetric) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .filter(|(_, stats)| stats.metric(metric) > 0)
+            .map(|(stack, stats)| {
+                let mut names = vec![TOP_LEVEL_NAME.to_string()];
+                for frame in stack {
+                    names.push(match frame {
+                        Frame::Fn(addr) => fn_label(program, Some(*addr)),
+                        Frame::Native(native) => native.to_string(),
+                    });
+                }
+                format!("{} {}", names.join(";"), stats.metric(metric))
+            })
+            .collect();
+        lines.sort();
+
+        let mut res = String::new();
+        for line in lines {
+            res.push_str(&line);
+            res.push('\n');
+        }
+        res
+    }
+}
+
+fn fn_label(program: &Program, addr: Option<usize>) -> String {
+    match addr {
+        Some(addr) => match program.fn_info.iter().find(|fi| fi.arg_address == addr) {
+            Some(fn_info) => fn_info.fn_name.to_string(),
+            None => format!("fn@{}", addr),
+        },
+        None => TOP_LEVEL_NAME.to_string(),
+    }
+}
+
+fn sorted_rows(mut rows: Vec<ProfileRow>, metric: ProfileMetric) -> Vec<ProfileRow> {
+    rows.sort_by(|a, b| {
+        b.stats
+            .metric(metric)
+            .cmp(&a.stats.metric(metric))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    rows
+}
+
+pub fn format_profile_table(title: &str, rows: &[ProfileRow]) -> String {
+    let mut res = String::new();
+
+    // writing into a String can't fail
+    let _ = writeln!(
+        res,
+        "{:<32} {:>8} {:>12} {:>10} {:>12} {:>10}",
+        title, "calls", "opcodes", "natives", "native ms", "vertices"
+    );
+    for row in rows {
+        let _ = writeln!(
+            res,
+            "{:<32} {:>8} {:>12} {:>10} {:>12.3} {:>10}",
+            row.label,
+            row.stats.calls,
+            row.stats.opcodes,
+            row.stats.native_calls,
+            row.stats.native_time.as_secs_f64() * 1000.0,
+            row.stats.vertices
+        );
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_program;
+    use crate::context::Context;
+    use crate::parser::parse;
+    use crate::vm::{VMProfiling, Vm};
+
+    fn profile(s: &str) -> (Vm, Program) {
+        let (ast, word_lut) = parse(s).unwrap();
+        let program = compile_program(&ast, &word_lut).unwrap();
+
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+
+        context.reset_for_piece();
+        vm.reset();
+        vm.init_for_main_program(&program, VMProfiling::On).unwrap();
+        vm.interpret(&mut context, &program).unwrap();
+
+        (vm, program)
+    }
+
+    fn row<'a>(rows: &'a [ProfileRow], label: &str) -> &'a ProfileStats {
+        &rows.iter().find(|r| r.label == label).unwrap().stats
+    }
+
+    #[test]
+    fn test_profiler_functions() {
+        let (vm, program) = profile(
+            "(fn (square x: 1) (* x x))
+             (fn (sum-squares a: 1 b: 1) (+ (square x: a) (square x: b)))
+             (sum-squares a: 2 b: 3)
+             (square x: 4)",
+        );
+
+        let rows = vm.profiler.function_rows(&program, ProfileMetric::Opcodes);
+        assert_eq!(row(&rows, "square").calls, 3);
+        assert_eq!(row(&rows, "sum-squares").calls, 1);
+        assert_eq!(row(&rows, "main").calls, 0);
+
+        // every executed opcode is attributed to exactly one function and one line
+        let fn_total: u64 = rows.iter().map(|r| r.stats.opcodes).sum();
+        let line_total: u64 = vm
+            .profiler
+            .line_rows(ProfileMetric::Opcodes)
+            .iter()
+            .map(|r| r.stats.opcodes)
+            .sum();
+        assert_eq!(fn_total, vm.opcodes_executed);
+        assert_eq!(line_total, vm.opcodes_executed);
+    }
+
+    #[test]
+    fn test_profiler_lines() {
+        let (vm, _program) = profile(
+            "(define a 1)
+             (rect position: [100 100] width: 10 height: 10 colour: (col/rgb r: 1 g: 0 b: 0 alpha: 1))",
+        );
+
+        let rows = vm.profiler.line_rows(ProfileMetric::Vertices);
+        assert_eq!(rows[0].label, "line 2");
+        assert_eq!(rows[0].stats.vertices, 4);
+        assert_eq!(rows[0].stats.native_calls, 2);
+    }
+
+    #[test]
+    fn test_profiler_folded_stacks() {
+        let (vm, program) = profile(
+            "(fn (box) (rect position: [100 100] width: 10 height: 10))
+             (box)
+             (box)",
+        );
+
+        let folded = vm.profiler.folded_stacks(&program, ProfileMetric::Vertices);
+        // 2 rects of 4 vertices joined by a degenerate triangle
+        assert_eq!(folded, "main;box;rect 10\n");
+
+        let folded = vm.profiler.folded_stacks(&program, ProfileMetric::Opcodes);
+        assert!(folded.contains("main;box "));
+        assert!(!folded.contains("rect"));
+    }
+}
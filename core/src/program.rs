@@ -27,6 +27,7 @@ use crate::opcodes::Opcode;
 use crate::packable::{Mule, Packable};
 
 use log::error;
+use strum::IntoEnumIterator;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Mem {
@@ -37,20 +38,22 @@ pub enum Mem {
     Void = 4,     // nothing
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Program {
     pub data: Data,
     pub code: Vec<Bytecode>,
     pub fn_info: Vec<FnInfo>,
+    /// the source line that generated each bytecode, only used when profiling
+    pub code_lines: Vec<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Data {
     // the sub-section of WordLut::iname_to_word that stores Node::String
     pub strings: BTreeMap<Iname, String>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FnInfo {
     pub fn_name: String,
     pub arg_address: usize,
@@ -59,7 +62,7 @@ pub struct FnInfo {
     pub argument_offsets: Vec<Iname>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bytecode {
     pub op: Opcode,
     pub arg0: BytecodeArg,
@@ -340,14 +343,30 @@ impl Data {
             .collect()
     }
 
+    pub fn palette_strings(&self) -> Vec<String> {
+        self.strings
+            .values()
+            .filter(|s| s.ends_with(".gpl") || s.ends_with(".hex"))
+            .cloned()
+            .collect()
+    }
+
     pub fn string_from_iname(&self, iname: Iname) -> Result<String> {
-        match self.strings.get(&iname) {
-            Some(s) => Ok(s.into()),
-            None => {
-                error!("Data::string_from_iname {}", iname);
-                Err(Error::Program)
-            }
+        if let Some(s) = self.strings.get(&iname) {
+            return Ok(s.into());
+        }
+
+        // strings that are the same as a keyword or native are given the
+        // keyword's or native's iname so they're not stored in strings
+        if let Some(kw) = Keyword::iter().find(|kw| Iname::from(*kw) == iname) {
+            return Ok(kw.to_string());
         }
+        if let Some(n) = Native::iter().find(|n| Iname::from(*n) == iname) {
+            return Ok(n.to_string());
+        }
+
+        error!("Data::string_from_iname {}", iname);
+        Err(Error::Program)
     }
 }
 
@@ -394,10 +413,12 @@ impl Packable for Program {
             code.push(bc);
         }
 
+        // source lines are only used for profiling and aren't packed
         let program = Program {
             data,
             code,
             fn_info,
+            code_lines: Vec::new(),
         };
 
         Ok((program, r))
@@ -409,6 +430,11 @@ impl Program {
         // the final opcode in the program will always be a STOP
         self.code.len() - 1
     }
+
+    pub fn line_at(&self, ip: usize) -> usize {
+        // 0 is used for bytecode that wasn't generated from the script
+        self.code_lines.get(ip).copied().unwrap_or(0)
+    }
 }
 
 #[cfg(test)]
@@ -429,6 +455,31 @@ mod tests {
         assert_eq!(res[1], "bitmap.png");
     }
 
+    #[test]
+    fn test_data_palette_strings() {
+        let mut d: Data = Default::default();
+
+        d.strings.insert(Iname::new(3), "image.png".into());
+        d.strings.insert(Iname::new(4), "sunset.gpl".into());
+        let res = d.palette_strings();
+
+        assert_eq!(res, vec!["sunset.gpl".to_string()]);
+    }
+
+    #[test]
+    fn test_data_string_from_iname() {
+        let mut d: Data = Default::default();
+        d.strings.insert(Iname::new(3), "hello".into());
+
+        assert_eq!(d.string_from_iname(Iname::new(3)).unwrap(), "hello");
+        assert_eq!(d.string_from_iname(Iname::from(Keyword::X)).unwrap(), "x");
+        assert_eq!(
+            d.string_from_iname(Iname::from(Native::Line)).unwrap(),
+            "line"
+        );
+        assert!(d.string_from_iname(Iname::new(4)).is_err());
+    }
+
     #[test]
     fn test_mem_pack() {
         let mut res: String = "".into();
@@ -18,7 +18,8 @@
 use crate::error::{Error, Result};
 use crate::matrix::Matrix;
 use crate::render_packet::{
-    RenderPacket, RenderPacketGeometry, RenderPacketImage, RenderPacketMask, RENDER_PACKET_MAX_SIZE,
+    RenderPacket, RenderPacketBlend, RenderPacketGeometry, RenderPacketImage, RenderPacketLayer,
+    RenderPacketMask, RENDER_PACKET_FLOAT_PER_VERTEX, RENDER_PACKET_MAX_SIZE,
 };
 use log::error;
 
@@ -29,17 +30,25 @@ pub enum RPCommand {
     Geometry = 1,
     Mask = 2,
     Image = 3,
+    Blend = 4,
+    LayerBegin = 5,
+    LayerEnd = 6,
 }
 
 pub struct RenderList {
     pub render_packets: Vec<RenderPacket>,
+    // the number of layers that have begun but not yet ended
+    open_layers: usize,
 }
 
 impl Default for RenderList {
     fn default() -> RenderList {
         let mut render_packets: Vec<RenderPacket> = Vec::new();
         render_packets.push(RenderPacket::Geometry(RenderPacketGeometry::new()));
-        RenderList { render_packets }
+        RenderList {
+            render_packets,
+            open_layers: 0,
+        }
     }
 }
 
@@ -47,7 +56,8 @@ impl RenderList {
     pub fn reset(&mut self) {
         self.render_packets.clear();
         self.render_packets
-            .push(RenderPacket::Geometry(RenderPacketGeometry::new()))
+            .push(RenderPacket::Geometry(RenderPacketGeometry::new()));
+        self.open_layers = 0;
     }
 
     pub fn push_rp_mask(&mut self, render_packet_mask: RenderPacketMask) -> Result<()> {
@@ -64,12 +74,42 @@ impl RenderList {
         Ok(())
     }
 
+    pub fn push_rp_blend(&mut self, render_packet_blend: RenderPacketBlend) -> Result<()> {
+        self.render_packets
+            .push(RenderPacket::Blend(render_packet_blend));
+
+        Ok(())
+    }
+
+    pub fn push_rp_layer_begin(&mut self, render_packet_layer: RenderPacketLayer) -> Result<()> {
+        self.render_packets
+            .push(RenderPacket::LayerBegin(render_packet_layer));
+        self.open_layers += 1;
+
+        Ok(())
+    }
+
+    pub fn push_rp_layer_end(&mut self) -> Result<()> {
+        if self.open_layers == 0 {
+            error!("push_rp_layer_end: there is no layer to end");
+            return Err(Error::RenderList);
+        }
+
+        self.render_packets.push(RenderPacket::LayerEnd);
+        self.open_layers -= 1;
+
+        Ok(())
+    }
+
     pub fn get_rp_command(&self, packet_number: usize) -> Result<RPCommand> {
         let rp = &self.render_packets[packet_number];
         let res = match rp {
             RenderPacket::Geometry(_) => RPCommand::Geometry,
             RenderPacket::Mask(_) => RPCommand::Mask,
             RenderPacket::Image(_) => RPCommand::Image,
+            RenderPacket::Blend(_) => RPCommand::Blend,
+            RenderPacket::LayerBegin(_) => RPCommand::LayerBegin,
+            RenderPacket::LayerEnd => RPCommand::LayerEnd,
         };
 
         Ok(res)
@@ -99,6 +139,22 @@ impl RenderList {
         }
     }
 
+    pub fn get_rp_blend(&self, packet_number: usize) -> Result<&RenderPacketBlend> {
+        let rp = &self.render_packets[packet_number];
+        match rp {
+            RenderPacket::Blend(rpb) => Ok(rpb),
+            _ => Err(Error::RenderList),
+        }
+    }
+
+    pub fn get_rp_layer(&self, packet_number: usize) -> Result<&RenderPacketLayer> {
+        let rp = &self.render_packets[packet_number];
+        match rp {
+            RenderPacket::LayerBegin(rpl) => Ok(rpl),
+            _ => Err(Error::RenderList),
+        }
+    }
+
     // the one place for cleaning up the render packets before they're sent off for rendering
     // do it here rather than spreading the complexity throughout all of the different commands
     //
@@ -107,8 +163,18 @@ impl RenderList {
             RenderPacket::Geometry(rpg) => !rpg.geo.is_empty(),
             RenderPacket::Mask(_) => true,
             RenderPacket::Image(_) => true,
+            RenderPacket::Blend(_) => true,
+            RenderPacket::LayerBegin(_) => true,
+            RenderPacket::LayerEnd => true,
         });
 
+        // scripts don't have to end their layers, but every backend can assume
+        // that each begin has a matching end
+        for _ in 0..self.open_layers {
+            self.render_packets.push(RenderPacket::LayerEnd);
+        }
+        self.open_layers = 0;
+
         // for (index, rp) in self.render_packets.iter().enumerate() {
         //     if rp.command == RPCommand::RenderGeometry {
         //         error!(
@@ -129,6 +195,68 @@ impl RenderList {
         self.render_packets.len()
     }
 
+    pub fn get_num_vertices(&self) -> usize {
+        self.render_packets
+            .iter()
+            .map(|rp| match rp {
+                RenderPacket::Geometry(rpg) => rpg.geo.len() / RENDER_PACKET_FLOAT_PER_VERTEX,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// A 64 bit FNV-1a hash of every render packet. Two render lists only have
+    /// the same hash if their floats are bit-for-bit identical.
+    pub fn geometry_hash(&self) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+
+        for rp in &self.render_packets {
+            match rp {
+                RenderPacket::Geometry(rpg) => {
+                    hash = fnv_u32(hash, RPCommand::Geometry as u32);
+                    for f in &rpg.geo {
+                        hash = fnv_u32(hash, f.to_bits());
+                    }
+                }
+                RenderPacket::Mask(rpm) => {
+                    hash = fnv_u32(hash, RPCommand::Mask as u32);
+                    for b in rpm.filename.bytes() {
+                        hash = fnv_byte(hash, b);
+                    }
+                    hash = fnv_byte(hash, rpm.invert as u8);
+                }
+                RenderPacket::Image(rpi) => {
+                    hash = fnv_u32(hash, RPCommand::Image as u32);
+                    hash = fnv_byte(hash, rpi.linear_colour_space as u8);
+                    hash = fnv_u32(hash, rpi.contrast.to_bits());
+                    hash = fnv_u32(hash, rpi.brightness.to_bits());
+                    hash = fnv_u32(hash, rpi.saturation.to_bits());
+                }
+                RenderPacket::Blend(rpb) => {
+                    hash = fnv_u32(hash, RPCommand::Blend as u32);
+                    hash = fnv_u32(hash, rpb.mode as u32);
+                }
+                RenderPacket::LayerBegin(rpl) => {
+                    hash = fnv_u32(hash, RPCommand::LayerBegin as u32);
+                    for b in rpl.name.bytes() {
+                        hash = fnv_byte(hash, b);
+                    }
+                    hash = fnv_u32(hash, rpl.opacity.to_bits());
+                    hash = fnv_u32(hash, rpl.blend as u32);
+                    hash = fnv_u32(hash, rpl.blur.to_bits());
+                    hash = fnv_u32(hash, rpl.contrast.to_bits());
+                    hash = fnv_u32(hash, rpl.brightness.to_bits());
+                    hash = fnv_u32(hash, rpl.saturation.to_bits());
+                }
+                RenderPacket::LayerEnd => {
+                    hash = fnv_u32(hash, RPCommand::LayerEnd as u32);
+                }
+            }
+        }
+
+        hash
+    }
+
     pub fn prepare_to_add_triangle_strip(
         &mut self,
         matrix: &Matrix,
@@ -167,3 +295,14 @@ impl RenderList {
         }
     }
 }
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv_byte(hash: u64, b: u8) -> u64 {
+    (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+}
+
+fn fnv_u32(hash: u64, x: u32) -> u64 {
+    x.to_le_bytes().iter().fold(hash, |h, b| fnv_byte(h, *b))
+}
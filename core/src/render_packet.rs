@@ -35,6 +35,36 @@ pub struct RenderPacketMask {
     pub invert: bool,
 }
 
+// explicitly number the enum values to make sure they match up with values on the client-side
+//
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    Normal = 0,
+    Multiply = 1,
+    Screen = 2,
+    Overlay = 3,
+    Additive = 4,
+    Darken = 5,
+    Lighten = 6,
+}
+
+// how subsequent geometry is combined with what's already been drawn
+pub struct RenderPacketBlend {
+    pub mode: BlendMode,
+}
+
+// geometry between a layer's begin and end packets is rendered into a separate
+// target which is then adjusted and composited onto its parent. Layers can nest
+pub struct RenderPacketLayer {
+    pub name: String,
+    pub opacity: f32,
+    pub blend: BlendMode,
+    pub blur: f32, // radius in canvas units
+    pub contrast: f32,
+    pub brightness: f32,
+    pub saturation: f32,
+}
+
 // the final image
 pub struct RenderPacketImage {
     pub linear_colour_space: bool,
@@ -47,6 +77,9 @@ pub enum RenderPacket {
     Geometry(RenderPacketGeometry),
     Mask(RenderPacketMask),
     Image(RenderPacketImage),
+    Blend(RenderPacketBlend),
+    LayerBegin(RenderPacketLayer),
+    LayerEnd,
 }
 
 impl RenderPacket {
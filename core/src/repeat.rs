@@ -23,6 +23,9 @@ use crate::mathutil::*;
 use crate::program::Program;
 use crate::vm::*;
 
+// the most cells that repeat/wallpaper, repeat/grid and repeat/hex-grid will tile
+pub const TILING_MAX_CELLS: usize = 100_000;
+
 fn flip(
     vm: &mut Vm,
     context: &mut Context,
@@ -194,8 +197,264 @@ pub fn rotate_mirrored(
     Ok(())
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WallpaperGroup {
+    P1,
+    P2,
+    Pm,
+    Pg,
+    Cm,
+    Pmm,
+    Pmg,
+    Pgg,
+    Cmm,
+    P4,
+    P4m,
+    P4g,
+    P3,
+    P3m1,
+    P31m,
+    P6,
+    P6m,
+}
+
+// a symmetry operation within a cell: scale by (sx, sy) to mirror, rotate by
+// angle and then translate by a fraction of each of the lattice vectors
+#[derive(Copy, Clone, Debug)]
+struct CellOp {
+    sx: f32,
+    sy: f32,
+    angle: f32,
+    tx: f32,
+    ty: f32,
+}
+
+impl CellOp {
+    fn rotation(angle: f32) -> Self {
+        CellOp {
+            sx: 1.0,
+            sy: 1.0,
+            angle,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    fn mirrored(self, sx: f32, sy: f32) -> Self {
+        CellOp {
+            sx: self.sx * sx,
+            sy: self.sy * sy,
+            ..self
+        }
+    }
+
+    fn translated(self, tx: f32, ty: f32) -> Self {
+        CellOp {
+            tx: self.tx + tx,
+            ty: self.ty + ty,
+            ..self
+        }
+    }
+}
+
+fn rotations(n: usize) -> Vec<CellOp> {
+    (0..n)
+        .map(|i| CellOp::rotation(TAU * i as f32 / n as f32))
+        .collect()
+}
+
+impl WallpaperGroup {
+    // the lattice vectors of a cell, the square and hexagonal groups only use width
+    fn lattice(self, width: f32, height: f32) -> ((f32, f32), (f32, f32)) {
+        use WallpaperGroup::*;
+        match self {
+            P1 | P2 | Pm | Pg | Cm | Pmm | Pmg | Pgg | Cmm => ((width, 0.0), (0.0, height)),
+            P4 | P4m | P4g => ((width, 0.0), (0.0, width)),
+            P3 | P3m1 | P31m | P6 | P6m => ((width, 0.0), (width / 2.0, width * 3f32.sqrt() / 2.0)),
+        }
+    }
+
+    fn is_hexagonal(self) -> bool {
+        use WallpaperGroup::*;
+        matches!(self, P3 | P3m1 | P31m | P6 | P6m)
+    }
+
+    // the copies of the motif within each cell, with the origin of the cell on
+    // the highest order rotation centre
+    fn cell_ops(self) -> Vec<CellOp> {
+        use WallpaperGroup::*;
+
+        let identity = CellOp::rotation(0.0);
+        let half_turn = CellOp::rotation(PI);
+
+        // adds a copy of every op, mirrored by (sx, sy) and then translated
+        let with_mirrors = |ops: Vec<CellOp>, sx: f32, sy: f32, tx: f32, ty: f32| {
+            let mirrors: Vec<CellOp> = ops
+                .iter()
+                .map(|op| op.mirrored(sx, sy).translated(tx, ty))
+                .collect();
+            [ops, mirrors].concat()
+        };
+        // the centred lattices also repeat the motif at the centre of each cell
+        let centred = |ops: Vec<CellOp>| with_mirrors(ops, 1.0, 1.0, 0.5, 0.5);
+
+        match self {
+            P1 => vec![identity],
+            P2 => vec![identity, half_turn],
+            Pm => with_mirrors(vec![identity], -1.0, 1.0, 0.0, 0.0),
+            Pg => with_mirrors(vec![identity], -1.0, 1.0, 0.0, 0.5),
+            Cm => centred(with_mirrors(vec![identity], -1.0, 1.0, 0.0, 0.0)),
+            Pmm => with_mirrors(vec![identity, half_turn], -1.0, 1.0, 0.0, 0.0),
+            Pmg => with_mirrors(vec![identity, half_turn], -1.0, 1.0, 0.5, 0.0),
+            Pgg => with_mirrors(vec![identity, half_turn], -1.0, 1.0, 0.5, 0.5),
+            Cmm => centred(with_mirrors(vec![identity, half_turn], -1.0, 1.0, 0.0, 0.0)),
+            P4 => rotations(4),
+            P4m => with_mirrors(rotations(4), -1.0, 1.0, 0.0, 0.0),
+            P4g => with_mirrors(rotations(4), -1.0, 1.0, 0.5, 0.5),
+            P3 => rotations(3),
+            P3m1 => with_mirrors(rotations(3), -1.0, 1.0, 0.0, 0.0),
+            P31m => with_mirrors(rotations(3), 1.0, -1.0, 0.0, 0.0),
+            P6 => rotations(6),
+            P6m => with_mirrors(rotations(6), 1.0, -1.0, 0.0, 0.0),
+        }
+    }
+}
+
+// the origins of columns * rows cells of a lattice, centred on position. The
+// staggered lattices shift every other row back so that they don't shear
+fn lattice_points(
+    position: (f32, f32),
+    a: (f32, f32),
+    b: (f32, f32),
+    columns: usize,
+    rows: usize,
+    staggered: bool,
+) -> Vec<(f32, f32)> {
+    let mut res = Vec::with_capacity(columns * rows);
+    for j in 0..rows {
+        let shift = if staggered { (j / 2) as f32 } else { 0.0 };
+        for i in 0..columns {
+            let u = i as f32 - shift;
+            let v = j as f32;
+            res.push((u * a.0 + v * b.0, u * a.1 + v * b.1));
+        }
+    }
+
+    if let Some(first) = res.first() {
+        let (min_x, min_y, max_x, max_y) = res.iter().fold(
+            (first.0, first.1, first.0, first.1),
+            |(min_x, min_y, max_x, max_y), p| {
+                (
+                    min_x.min(p.0),
+                    min_y.min(p.1),
+                    max_x.max(p.0),
+                    max_y.max(p.1),
+                )
+            },
+        );
+        let dx = position.0 - (min_x + max_x) / 2.0;
+        let dy = position.1 - (min_y + max_y) / 2.0;
+        for p in res.iter_mut() {
+            p.0 += dx;
+            p.1 += dy;
+        }
+    }
+
+    res
+}
+
+// the cells of a lattice and the copies of the motif within each of them
+pub struct Tiling {
+    points: Vec<(f32, f32)>,
+    ops: Vec<CellOp>,
+    lattice: ((f32, f32), (f32, f32)),
+}
+
+impl Tiling {
+    pub fn wallpaper(
+        group: WallpaperGroup,
+        position: (f32, f32),
+        width: f32,
+        height: f32,
+        columns: usize,
+        rows: usize,
+    ) -> Self {
+        let (a, b) = group.lattice(width, height);
+        Tiling {
+            points: lattice_points(position, a, b, columns, rows, group.is_hexagonal()),
+            ops: group.cell_ops(),
+            lattice: (a, b),
+        }
+    }
+
+    pub fn grid(
+        position: (f32, f32),
+        width: f32,
+        height: f32,
+        columns: usize,
+        rows: usize,
+    ) -> Self {
+        let (a, b) = ((width, 0.0), (0.0, height));
+        Tiling {
+            points: lattice_points(position, a, b, columns, rows, false),
+            ops: vec![CellOp::rotation(0.0)],
+            lattice: (a, b),
+        }
+    }
+
+    // pointy topped hexagons, radius is the distance from the centre to a corner
+    pub fn hex_grid(position: (f32, f32), radius: f32, columns: usize, rows: usize) -> Self {
+        let spacing = radius * 3f32.sqrt();
+        let (a, b) = ((spacing, 0.0), (spacing / 2.0, radius * 1.5));
+        Tiling {
+            points: lattice_points(position, a, b, columns, rows, true),
+            ops: vec![CellOp::rotation(0.0)],
+            lattice: (a, b),
+        }
+    }
+}
+
+// invokes the function once for every op in every cell with the index of the
+// cell, t going from 0 to 1 across the cells and the copy within the cell
+pub fn tile(
+    vm: &mut Vm,
+    context: &mut Context,
+    program: &Program,
+    fun: usize,
+    tiling: &Tiling,
+) -> Result<()> {
+    let fn_info = &program.fn_info[fun];
+    let ip = vm.ip;
+
+    let (a, b) = tiling.lattice;
+    let last = tiling.points.len().saturating_sub(1).max(1) as f32;
+
+    for (index, (x, y)) in tiling.points.iter().enumerate() {
+        for (copy, op) in tiling.ops.iter().enumerate() {
+            context.matrix_stack.push();
+            context
+                .matrix_stack
+                .translate(x + op.tx * a.0 + op.ty * b.0, y + op.tx * a.1 + op.ty * b.1);
+            context.matrix_stack.rotate(op.angle);
+            context.matrix_stack.scale(op.sx, op.sy);
+
+            vm.function_call_default_arguments(context, program, fn_info)?;
+            vm.function_set_argument_to_f32(fn_info, Iname::from(Keyword::Index), index as f32);
+            vm.function_set_argument_to_f32(fn_info, Iname::from(Keyword::T), index as f32 / last);
+            vm.function_set_argument_to_f32(fn_info, Iname::from(Keyword::Copy), copy as f32);
+            vm.function_call_body(context, program, fn_info)?;
+
+            context.matrix_stack.pop();
+            vm.ip = ip;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::vm::tests::*;
 
     #[test]
@@ -307,4 +566,107 @@ mod tests {
             .to_vec(),
         );
     }
+
+    #[test]
+    fn test_wallpaper_cell_ops() {
+        use WallpaperGroup::*;
+
+        let counts = [
+            (P1, 1),
+            (P2, 2),
+            (Pm, 2),
+            (Pg, 2),
+            (Cm, 4),
+            (Pmm, 4),
+            (Pmg, 4),
+            (Pgg, 4),
+            (Cmm, 8),
+            (P4, 4),
+            (P4m, 8),
+            (P4g, 8),
+            (P3, 3),
+            (P3m1, 6),
+            (P31m, 6),
+            (P6, 6),
+            (P6m, 12),
+        ];
+        for (group, count) in counts.iter() {
+            assert_eq!(group.cell_ops().len(), *count, "{:?}", group);
+        }
+    }
+
+    #[test]
+    fn test_wallpaper() {
+        probe_has_scalars_v2(
+            "(fn (f) (probe worldspace: [1 2]))
+             (repeat/wallpaper fn: (address-of f) group: wallpaper/pg width: 10 height: 10 columns: 1 rows: 1)",
+            [(1.0, 2.0), (-1.0, 7.0)].to_vec(),
+        );
+
+        probe_has_scalars_v2(
+            "(fn (f) (probe worldspace: [1 2]))
+             (repeat/wallpaper fn: (address-of f) group: wallpaper/cm width: 10 height: 10 columns: 1 rows: 1)",
+            [(1.0, 2.0), (-1.0, 2.0), (6.0, 7.0), (4.0, 7.0)].to_vec(),
+        );
+
+        probe_has_scalars(
+            "(fn (f copy: 0) (probe scalar: copy))
+             (repeat/wallpaper fn: (address-of f) group: wallpaper/p4m columns: 1 rows: 1)",
+            [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0].to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        probe_has_scalars_v2(
+            "(fn (f) (probe worldspace: [0 0]))
+             (repeat/grid fn: (address-of f) position: [100 100] width: 10 height: 20 columns: 2 rows: 2)",
+            [(95.0, 90.0), (105.0, 90.0), (95.0, 110.0), (105.0, 110.0)].to_vec(),
+        );
+
+        probe_has_scalars(
+            "(fn (f index: 0 t: 0) (probe scalar: t))
+             (repeat/grid fn: (address-of f) columns: 5 rows: 1)",
+            [0.0, 0.25, 0.5, 0.75, 1.0].to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_hex_grid() {
+        // every other row is offset by half a cell
+        let points = lattice_points((0.0, 0.0), (2.0, 0.0), (1.0, 3.0), 3, 3, true);
+        assert_eq!(
+            points,
+            [
+                (-2.5, -3.0),
+                (-0.5, -3.0),
+                (1.5, -3.0),
+                (-1.5, 0.0),
+                (0.5, 0.0),
+                (2.5, 0.0),
+                (-2.5, 3.0),
+                (-0.5, 3.0),
+                (1.5, 3.0),
+            ]
+            .to_vec()
+        );
+
+        probe_has_scalars(
+            "(fn (f index: 0) (probe scalar: index))
+             (repeat/hex-grid fn: (address-of f) columns: 2 rows: 2)",
+            [0.0, 1.0, 2.0, 3.0].to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_tiling_max_cells() {
+        let f = "(fn (f) (probe scalar: 1))";
+        for s in &[
+            "(repeat/grid fn: (address-of f) columns: 100000000000 rows: 100000000000)",
+            "(repeat/hex-grid fn: (address-of f) columns: 10000000000 rows: 1)",
+            "(repeat/wallpaper fn: (address-of f) columns: 1000 rows: 1000)",
+        ] {
+            assert!(crate::compile_and_execute(&format!("{} {}", f, s)).is_err());
+        }
+    }
 }
@@ -0,0 +1,342 @@
+// Copyright (C) 2020 Inderjit Gill <email@indy.io>
+
+// This file is part of Seni
+
+// Seni is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Seni is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Ways of scattering points across a region that are more even than just
+// using uniform random numbers. All of them are deterministic for a seed.
+
+use crate::error::{Error, Result};
+use crate::mathutil::{self, length_v2, TAU};
+use crate::prng::PrngStateStruct;
+
+use log::error;
+
+// the number of candidates tried around each point before giving up on it
+const POISSON_DISK_CANDIDATES: usize = 30;
+
+// stops a tiny distance from allocating an enormous grid
+const POISSON_DISK_MAX_CELLS: usize = 4_000_000;
+
+// the most points that a jittered grid or halton sequence can have
+const SAMPLE_MAX_POINTS: usize = 1_000_000;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Region {
+    Rect {
+        position: (f32, f32),
+        width: f32,
+        height: f32,
+    },
+    Circle {
+        position: (f32, f32),
+        radius: f32,
+    },
+}
+
+impl Region {
+    // min x, min y, max x, max y
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        match *self {
+            Region::Rect {
+                position,
+                width,
+                height,
+            } => (
+                position.0 - width / 2.0,
+                position.1 - height / 2.0,
+                position.0 + width / 2.0,
+                position.1 + height / 2.0,
+            ),
+            Region::Circle { position, radius } => (
+                position.0 - radius,
+                position.1 - radius,
+                position.0 + radius,
+                position.1 + radius,
+            ),
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        match *self {
+            Region::Rect { .. } => x >= min_x && x <= max_x && y >= min_y && y <= max_y,
+            Region::Circle { position, radius } => {
+                length_v2(x - position.0, y - position.1) <= radius
+            }
+        }
+    }
+
+    // maps a point in the unit square onto the region without bunching
+    fn map_unit(&self, u: f32, v: f32) -> (f32, f32) {
+        match *self {
+            Region::Rect { .. } => {
+                let (min_x, min_y, max_x, max_y) = self.bounds();
+                (min_x + u * (max_x - min_x), min_y + v * (max_y - min_y))
+            }
+            Region::Circle { position, radius } => {
+                let r = radius * u.sqrt();
+                let angle = v * TAU;
+                (
+                    position.0 + r * mathutil::cos(angle),
+                    position.1 + r * mathutil::sin(angle),
+                )
+            }
+        }
+    }
+}
+
+// Bridson's algorithm: no two points are closer than min_distance and the
+// region is filled so that no more points could be added. A min_distance that's
+// too small for the region is an error rather than an enormous allocation
+pub fn poisson_disk(region: &Region, min_distance: f32, seed: i32) -> Result<Vec<(f32, f32)>> {
+    let mut res: Vec<(f32, f32)> = vec![];
+    if min_distance <= 0.0 {
+        return Ok(res);
+    }
+
+    let (min_x, min_y, max_x, max_y) = region.bounds();
+    let cell_size = min_distance / std::f32::consts::SQRT_2;
+    let columns = ((max_x - min_x) / cell_size).ceil().max(1.0) as usize;
+    let rows = ((max_y - min_y) / cell_size).ceil().max(1.0) as usize;
+    // enormous regions have so many cells that multiplying them would overflow
+    if columns.saturating_mul(rows) > POISSON_DISK_MAX_CELLS {
+        error!(
+            "poisson_disk: a distance of {} is too small for the region",
+            min_distance
+        );
+        return Err(Error::Native);
+    }
+
+    // each cell can contain at most one point
+    let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+    let cell_of = |x: f32, y: f32| {
+        let i = (((x - min_x) / cell_size) as usize).min(columns - 1);
+        let j = (((y - min_y) / cell_size) as usize).min(rows - 1);
+        (i, j)
+    };
+
+    let mut prng = PrngStateStruct::new(seed, 0.0, 1.0);
+
+    // the first point is anywhere in the region
+    let (x, y) = region.map_unit(prng.next_f32(), prng.next_f32());
+    let (i, j) = cell_of(x, y);
+    grid[j * columns + i] = Some(0);
+    res.push((x, y));
+    let mut active: Vec<usize> = vec![0];
+
+    while !active.is_empty() {
+        let a = prng.next_usize_range(0, active.len()).min(active.len() - 1);
+        let (px, py) = res[active[a]];
+
+        let mut found = false;
+        for _ in 0..POISSON_DISK_CANDIDATES {
+            // a candidate in the annulus between min_distance and twice that
+            let angle = prng.next_f32() * TAU;
+            let r = min_distance * (1.0 + prng.next_f32());
+            let x = px + r * mathutil::cos(angle);
+            let y = py + r * mathutil::sin(angle);
+
+            if !region.contains(x, y) {
+                continue;
+            }
+
+            let (ci, cj) = cell_of(x, y);
+            let mut clear = true;
+            'neighbours: for nj in cj.saturating_sub(2)..(cj + 3).min(rows) {
+                for ni in ci.saturating_sub(2)..(ci + 3).min(columns) {
+                    if let Some(n) = grid[nj * columns + ni] {
+                        let (nx, ny) = res[n];
+                        if length_v2(nx - x, ny - y) < min_distance {
+                            clear = false;
+                            break 'neighbours;
+                        }
+                    }
+                }
+            }
+
+            if clear {
+                grid[cj * columns + ci] = Some(res.len());
+                active.push(res.len());
+                res.push((x, y));
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(a);
+        }
+    }
+
+    Ok(res)
+}
+
+// one point in each cell of a grid, randomly displaced within its cell by up
+// to jitter (0 gives a regular grid, 1 anywhere in the cell)
+pub fn jittered_grid(
+    region: &Region,
+    columns: usize,
+    rows: usize,
+    jitter: f32,
+    seed: i32,
+) -> Result<Vec<(f32, f32)>> {
+    if columns.saturating_mul(rows) > SAMPLE_MAX_POINTS {
+        error!(
+            "jittered_grid: {} columns and {} rows is more than the limit of {} points",
+            columns, rows, SAMPLE_MAX_POINTS
+        );
+        return Err(Error::Native);
+    }
+
+    let (min_x, min_y, max_x, max_y) = region.bounds();
+    let cell_width = (max_x - min_x) / columns as f32;
+    let cell_height = (max_y - min_y) / rows as f32;
+
+    let mut prng = PrngStateStruct::new(seed, 0.0, 1.0);
+
+    let mut res = Vec::with_capacity(columns * rows);
+    for j in 0..rows {
+        for i in 0..columns {
+            let dx = (prng.next_f32() - 0.5) * jitter;
+            let dy = (prng.next_f32() - 0.5) * jitter;
+            let x = min_x + (i as f32 + 0.5 + dx) * cell_width;
+            let y = min_y + (j as f32 + 0.5 + dy) * cell_height;
+            if region.contains(x, y) {
+                res.push((x, y));
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+fn radical_inverse(mut index: usize, base: usize) -> f32 {
+    let mut res = 0.0;
+    let mut f = 1.0 / base as f32;
+    while index > 0 {
+        res += f * (index % base) as f32;
+        index /= base;
+        f /= base as f32;
+    }
+    res
+}
+
+// the Halton sequence in bases 2 and 3, the seed rotates the sequence
+// (Cranley-Patterson rotation) so that different seeds give different points
+pub fn halton(region: &Region, num: usize, seed: i32) -> Result<Vec<(f32, f32)>> {
+    if num > SAMPLE_MAX_POINTS {
+        error!(
+            "halton: {} points is more than the limit of {}",
+            num, SAMPLE_MAX_POINTS
+        );
+        return Err(Error::Native);
+    }
+
+    let mut prng = PrngStateStruct::new(seed, 0.0, 1.0);
+    let offset_u = prng.next_f32();
+    let offset_v = prng.next_f32();
+
+    Ok((1..=num)
+        .map(|i| {
+            let u = (radical_inverse(i, 2) + offset_u).fract();
+            let v = (radical_inverse(i, 3) + offset_v).fract();
+            region.map_unit(u, v)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: Region = Region::Rect {
+        position: (500.0, 500.0),
+        width: 1000.0,
+        height: 1000.0,
+    };
+
+    const CIRCLE: Region = Region::Circle {
+        position: (500.0, 500.0),
+        radius: 200.0,
+    };
+
+    fn all_within(points: &[(f32, f32)], region: &Region) -> bool {
+        points.iter().all(|(x, y)| region.contains(*x, *y))
+    }
+
+    #[test]
+    fn test_poisson_disk() {
+        let points = poisson_disk(&SQUARE, 50.0, 4).unwrap();
+        assert!(points.len() > 200);
+        assert!(all_within(&points, &SQUARE));
+        for (i, a) in points.iter().enumerate() {
+            for b in points.iter().skip(i + 1) {
+                assert!(length_v2(a.0 - b.0, a.1 - b.1) >= 50.0);
+            }
+        }
+
+        assert_eq!(points, poisson_disk(&SQUARE, 50.0, 4).unwrap());
+        assert_ne!(points, poisson_disk(&SQUARE, 50.0, 5).unwrap());
+
+        assert!(all_within(
+            &poisson_disk(&CIRCLE, 20.0, 4).unwrap(),
+            &CIRCLE
+        ));
+
+        // too small a distance would need an enormous grid
+        assert!(poisson_disk(&SQUARE, 0.01, 4).is_err());
+        let huge = Region::Rect {
+            position: (0.0, 0.0),
+            width: 1e20,
+            height: 1e20,
+        };
+        assert!(poisson_disk(&huge, 1.0, 4).is_err());
+    }
+
+    #[test]
+    fn test_jittered_grid() {
+        let points = jittered_grid(&SQUARE, 4, 5, 0.0, 1).unwrap();
+        assert_eq!(points.len(), 20);
+        assert_eq!(points[0], (125.0, 100.0));
+
+        let points = jittered_grid(&SQUARE, 4, 5, 1.0, 1).unwrap();
+        assert_eq!(points.len(), 20);
+        assert!(points[0].0 >= 0.0 && points[0].0 <= 250.0);
+
+        assert!(all_within(
+            &jittered_grid(&CIRCLE, 10, 10, 1.0, 1).unwrap(),
+            &CIRCLE
+        ));
+
+        assert!(jittered_grid(&SQUARE, 100_000_000_000, 100_000_000_000, 1.0, 1).is_err());
+    }
+
+    #[test]
+    fn test_halton() {
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert_eq!(radical_inverse(1, 3), 1.0 / 3.0);
+
+        let points = halton(&SQUARE, 100, 7).unwrap();
+        assert_eq!(points.len(), 100);
+        assert!(all_within(&points, &SQUARE));
+        assert_ne!(points, halton(&SQUARE, 100, 8).unwrap());
+
+        assert!(all_within(&halton(&CIRCLE, 100, 7).unwrap(), &CIRCLE));
+
+        assert!(halton(&SQUARE, 100_000_000_000_000, 7).is_err());
+    }
+}
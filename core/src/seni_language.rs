@@ -62,8 +62,12 @@ The public API for the Seni language
  - [rect](#rect) : draws rect centered at position
  - [circle](#circle)
  - [circle-slice](#circle-slice)
+ - [ellipse](#ellipse) : circle with a rotation
+ - [arc](#arc) : stroke around part of a circle
  - [ring](#ring)
+ - [rounded-rect](#rounded-rect) : rect centered at position with rounded corners
  - [poly](#poly)
+ - [polyline](#polyline) : stroke along a series of points
  - [quadratic](#quadratic)
  - [bezier](#bezier)
  - [bezier-bulging](#bezier-bulging)
@@ -74,6 +78,11 @@ The public API for the Seni language
 - [translate](#translate)
 - [rotate](#rotate)
 - [scale](#scale)
+- [skew-x](#skew-x)
+- [skew-y](#skew-y)
+- [reflect](#reflect)
+- [transform/set](#transformset)
+- [transform/point](#transformpoint)
 
 # Colour
 
@@ -83,6 +92,9 @@ The public API for the Seni language
 - [col/hsluv](#colhsluv)
 - [col/hsv](#colhsv)
 - [col/lab](#collab)
+- [col/lch](#collch)
+- [col/oklab](#coloklab)
+- [col/oklch](#coloklch)
 - [col/complementary](#colcomplementary)
 - [col/split-complementary](#colsplit-complementary)
 - [col/analagous](#colanalagous)
@@ -103,7 +115,11 @@ The public API for the Seni language
 - [col/add-alpha](#coladd-alpha)
 - [col/build-procedural](#colbuild-procedural)
 - [col/build-bezier](#colbuild-bezier)
+- [col/build-gradient](#colbuild-gradient)
 - [col/value](#colvalue)
+- [col/palette](#colpalette)
+- [col/define-palette](#coldefine-palette)
+- [col/palette-from-bitmap](#colpalette-from-bitmap)
 
 # Math
 
@@ -113,6 +129,34 @@ The public API for the Seni language
 - [math/radians->degrees](#mathradians->degrees)
 - [math/cos](#mathcos)
 - [math/sin](#mathsin)
+- [math/degrees->radians](#mathdegrees->radians)
+- [math/atan2](#mathatan2)
+- [math/pow](#mathpow)
+- [math/floor](#mathfloor)
+- [math/ceil](#mathceil)
+- [math/round](#mathround)
+- [math/min](#mathmin)
+- [math/max](#mathmax)
+- [math/abs](#mathabs)
+- [math/exp](#mathexp)
+- [math/log](#mathlog)
+- [math/map-range](#mathmap-range)
+
+# V2
+
+The arithmetic functions +, -, * and / also work on 2d vectors, either
+element-wise with another vector or with a number applied to both elements.
+
+- [v2/add](#v2add)
+- [v2/sub](#v2sub)
+- [v2/scale](#v2scale)
+- [v2/dot](#v2dot)
+- [v2/cross](#v2cross)
+- [v2/rotate](#v2rotate)
+- [v2/lerp](#v2lerp)
+- [v2/angle](#v2angle)
+- [v2/length](#v2length)
+- [v2/normalize](#v2normalize)
 
 # Prng
 
@@ -121,6 +165,12 @@ The public API for the Seni language
 - [prng/value](#prngvalue)
 - [prng/perlin](#prngperlin)
 
+# Noise
+
+- [noise/simplex](#noisesimplex)
+- [noise/worley](#noiseworley)
+- [noise/fbm](#noisefbm)
+
 # Interp
 
 - [interp/build](#interpbuild)
@@ -140,6 +190,39 @@ The public API for the Seni language
 - [path/spline](#pathspline)
 - [path/bezier](#pathbezier)
 
+# Flow
+
+- [flow/trace](#flowtrace)
+
+# Sample
+
+- [sample/poisson-disk](#samplepoisson-disk)
+- [sample/jittered-grid](#samplejittered-grid)
+- [sample/halton](#samplehalton)
+
+# Geom
+
+- [geom/delaunay](#geomdelaunay)
+- [geom/voronoi](#geomvoronoi)
+- [geom/union](#geomunion)
+- [geom/intersection](#geomintersection)
+- [geom/difference](#geomdifference)
+- [geom/offset](#geomoffset)
+
+# Fill
+
+- [fill/hatch](#fillhatch)
+- [fill/stipple](#fillstipple)
+
+# Lsystem
+
+- [lsystem/expand](#lsystemexpand)
+- [lsystem/turtle](#lsystemturtle)
+
+# Particle
+
+- [particle/simulate](#particlesimulate)
+
 # Repeat
 
 - [repeat/symmetry-vertical](#repeatsymmetry-vertical)
@@ -148,6 +231,9 @@ The public API for the Seni language
 - [repeat/symmetry-8](#repeatsymmetry-8)
 - [repeat/rotate](#repeatrotate)
 - [repeat/rotate-mirrored](#repeatrotate-mirrored)
+- [repeat/wallpaper](#repeatwallpaper)
+- [repeat/grid](#repeatgrid)
+- [repeat/hex-grid](#repeathex-grid)
 
 # Focal
 
@@ -167,6 +253,15 @@ The public API for the Seni language
 
 - [mask/set](#maskset)
 
+# Blending
+
+- [blend/set](#blendset)
+
+# Layers
+
+- [layer/begin](#layerbegin)
+- [layer/end](#layerend)
+
 # Gen
 
 - [gen/stray-int](#genstray-int)
@@ -282,6 +377,32 @@ Parameter | Default | Description
 | inner-width  |            1 |             |
 | inner-height |            1 |             |
 
+## ellipse
+
+Parameter | Default | Description
+--- | --- | ---
+| width        |            4 |             |
+| height       |           10 |             |
+| radius       |           10 |             |
+| position     |      [10 10] |             |
+| colour       | RGB(0 0 0 1) |             |
+| tessellation |           10 |             |
+| angle        |            0 | rotation in degrees |
+
+## arc
+
+Parameter | Default | Description
+--- | --- | ---
+| position      |      [10 10] |             |
+| radius        |           10 |             |
+| angle-start   |            0 |             |
+| angle-end     |           90 |             |
+| width         |            4 | width of the stroke |
+| colour        | RGB(0 0 0 1) |             |
+| tessellation  |           10 |             |
+| brush         |   brush/flat |             |
+| brush-subtype |            1 |             |
+
 ## ring
 
 Parameter | Default | Description
@@ -293,12 +414,42 @@ Parameter | Default | Description
 | outer-colour       | RGB(0 0 0 1) |             |
 | tessellation |           10 |             |
 
+## rounded-rect
+
+Parameter | Default | Description
+--- | --- | ---
+| width        |            4 |             |
+| height       |           10 |             |
+| position     |      [10 10] |             |
+| colour       | RGB(0 0 0 1) |             |
+| radius       |            1 | radius of every corner |
+| corner-radii |              | e.g. [0 5 10 5] starting at the corner with the smallest x and y |
+| tessellation |            4 | per corner |
+
 ## poly
 
 Parameter | Default | Description
 --- | --- | ---
-| coords    | NULL    |             |
-| colours   | NULL    |             |
+| coords      | NULL         |             |
+| colours     | NULL         | a colour for each coord (overrides colour) |
+| colour      | RGB(0 0 0 1) |             |
+| triangulate | false        | when true the coords are the outline of a polygon rather than a triangle strip |
+
+## polyline
+
+Parameter | Default | Description
+--- | --- | ---
+| coords        |              | vector of points e.g. the output of the path functions |
+| width         |            4 |             |
+| widths        |              | a width for each point (overrides width) |
+| colour        | RGB(0 0 0 1) |             |
+| colours       |              | a colour for each point (overrides colour) |
+| join          |   join/miter | join/miter, join/round or join/bevel |
+| cap           |     cap/butt | cap/butt, cap/round or cap/square |
+| miter-limit   |            4 | miters longer than this multiple of the half width are bevelled |
+| tessellation  |            6 | of round joins and caps |
+| brush         |   brush/flat |             |
+| brush-subtype |            0 |             |
 
 ## quadratic
 
@@ -383,10 +534,55 @@ Parameter | Default | Description
     | vector    | [1 1]   |             |
     | scale     | 1       |             |
 
+## skew-x
+
+Shears along the x axis, points move horizontally in proportion to their y.
+
+Parameter | Default | Description
+--- | --- | ---
+    | angle     |       0 | in degrees  |
+
+## skew-y
+
+Shears along the y axis, points move vertically in proportion to their x.
+
+Parameter | Default | Description
+--- | --- | ---
+    | angle     |       0 | in degrees  |
+
+## reflect
+
+Mirrors everything about the line that passes through position at the given angle.
+
+Parameter | Default | Description
+--- | --- | ---
+    | position  | [0 0]   | a point on the line |
+    | angle     |       0 | the line's direction in degrees |
+
+## transform/set
+
+Replaces the current transform with an explicit 2D affine matrix. Unlike the other transform functions it doesn't compose with what's already on the matrix stack.
+
+Parameter | Default | Description
+--- | --- | ---
+    | x         | [1 0]   | where the unit x axis maps to |
+    | y         | [0 1]   | where the unit y axis maps to |
+    | position  | [0 0]   | where the origin maps to |
+
+## transform/point
+
+Returns the world position of a point after applying the current matrix stack, e.g. to find where something inside an on-matrix-stack block will be drawn.
+
+Parameter | Default | Description
+--- | --- | ---
+    | point     | [0 0]   |             |
+
 # Colour Functions
 
 ## col/convert
 
+The format is one of RGB, HSL, HSLuv, HSV, LAB, LCH, OKLAB or OKLCH.
+
 Parameter | Default | Description
 --- | --- | ---
     | format    | RGB          |             |
@@ -438,6 +634,39 @@ Parameter | Default | Description
     | b         |       0 |       -1..1 |
     | alpha     |       1 |        0..1 |
 
+## col/lch
+
+The cylindrical form of CIELUV, the space that HSLuv is built on.
+
+Parameter | Default | Description
+--- | --- | ---
+    | l         |       0 |      0..100 |
+    | c         |       0 |     0..~180 |
+    | h         |       0 |      0..360 |
+    | alpha     |       1 |        0..1 |
+
+## col/oklab
+
+A perceptually uniform space, interpolating between colours in it avoids the muddy middles of RGB.
+
+Parameter | Default | Description
+--- | --- | ---
+    | l         |       0 |        0..1 |
+    | a         |       0 |   -0.4..0.4 |
+    | b         |       0 |   -0.4..0.4 |
+    | alpha     |       1 |        0..1 |
+
+## col/oklch
+
+The cylindrical form of OKLab.
+
+Parameter | Default | Description
+--- | --- | ---
+    | l         |       0 |        0..1 |
+    | c         |       0 |      0..0.4 |
+    | h         |       0 |      0..360 |
+    | alpha     |       1 |        0..1 |
+
 ## col/complementary
 
 returns the complimentary colour
@@ -588,13 +817,62 @@ Parameter | Default | Description
     | c         | RGB(0 0 0 1) |             |
     | d         | RGB(0 0 0 1) |             |
 
+## col/build-gradient
+
+returns COLOUR_FN_GRADIENT
+
+Each stop is a vector of a position and a colour, e.g. [0.5 (col/rgb r: 1)], optionally followed by the mapping to use until the next stop. The stops can be in any order, before the first stop and after the last one the colour stays the same. The colours are interpolated in the given format, hues take the shortest way round.
+
+Parameter | Default | Description
+--- | --- | ---
+    | stops     | NULL    |             |
+    | format    | OKLAB   |             |
+    | mapping   | linear  | the default mapping between stops |
+
 ## col/value
 
 Parameter | Default | Description
 --- | --- | ---
-    | from      | NULL    | either a FN_PROCEDURAL or FN_BEZIER |
+    | from      | NULL    | either a FN_PROCEDURAL, FN_BEZIER or FN_GRADIENT |
     | t         | 0       |                                     |
 
+## col/palette
+
+Returns a vector of colours. Either one of the built-in 5 colour palettes given
+by index, or a named palette given by from. Named palettes are either defined
+by the script with col/define-palette or are files in the GIMP palette (.gpl)
+or hex list (.hex) formats which are loaded by the host, e.g.
+(col/palette from: "sunset.gpl"). The name can be a gene:
+(col/palette from: "warm" ~ (gen/select from: '("warm" "sunset.gpl")))
+
+Parameter | Default | Description
+--- | --- | ---
+    | index     | 0         |             |
+    | from      |           | the name of the palette |
+
+## col/define-palette
+
+Names a palette so that it can be used by col/palette. Returns the colours.
+
+Parameter | Default | Description
+--- | --- | ---
+    | name      |           |             |
+    | colours   |           | a vector of colours |
+
+## col/palette-from-bitmap
+
+Returns a vector of up to num colours that best represent the bitmap. The same
+seed always gives the same palette. Fully transparent pixels are ignored and
+there will be fewer than num colours if the bitmap doesn't have enough distinct
+ones. At most 256 colours are returned.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      |           | the filename of the bitmap |
+    | num       | 5         |             |
+    | seed      | 0         |             |
+    | sort      | frequency | either frequency (most common first) or lightness (darkest first) |
+
 # Math Functions
 
 ## math/distance
@@ -637,6 +915,176 @@ Parameter | Default | Description
 --- | --- | ---
     | from     | 0       |             |
 
+## math/degrees->radians
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/atan2
+
+Returns the angle in radians of the point [x y] from the x axis.
+
+Parameter | Default | Description
+--- | --- | ---
+    | y        | 0       |             |
+    | x        | 1       |             |
+
+## math/pow
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+    | exponent | 1       |             |
+
+## math/floor
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/ceil
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/round
+
+Rounds halfway values away from zero.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/min
+
+Parameter | Default | Description
+--- | --- | ---
+    | a        | 0       |             |
+    | b        | 0       |             |
+
+## math/max
+
+Parameter | Default | Description
+--- | --- | ---
+    | a        | 0       |             |
+    | b        | 0       |             |
+
+## math/abs
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/exp
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/log
+
+The natural logarithm, from has to be positive.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from     | 0       |             |
+
+## math/map-range
+
+Maps a value from one range onto another, the mapping is one of the
+[Interpolation Constants](#interpolation-constants).
+
+Parameter | Default | Description
+--- | --- | ---
+    | value    | 0       |             |
+    | from     | [0 1]   |             |
+    | to       | [0 100] |             |
+    | clamping | false   |             |
+    | mapping  | linear  |             |
+
+# V2 Functions
+
+## v2/add
+
+Parameter | Default | Description
+--- | --- | ---
+    | vec1      | [0 0]   |             |
+    | vec2      | [0 0]   |             |
+
+## v2/sub
+
+Parameter | Default | Description
+--- | --- | ---
+    | vec1      | [0 0]   |             |
+    | vec2      | [0 0]   |             |
+
+## v2/scale
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      | [0 0]   |             |
+    | scalar    | 1       |             |
+
+## v2/dot
+
+Parameter | Default | Description
+--- | --- | ---
+    | vec1      | [0 0]   |             |
+    | vec2      | [0 0]   |             |
+
+## v2/cross
+
+Returns the z component of the cross product, this is positive if vec2 is
+anticlockwise from vec1.
+
+Parameter | Default | Description
+--- | --- | ---
+    | vec1      | [0 0]   |             |
+    | vec2      | [0 0]   |             |
+
+## v2/rotate
+
+Rotates the vector anticlockwise around the origin.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      | [0 0]   |             |
+    | angle     | 0       | in degrees  |
+
+## v2/lerp
+
+Parameter | Default | Description
+--- | --- | ---
+    | vec1      | [0 0]   |             |
+    | vec2      | [0 0]   |             |
+    | t         | 0.5     |             |
+
+## v2/angle
+
+Returns the angle of the vector from the x axis in degrees, so it can be given
+to v2/rotate.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      | [0 0]   |             |
+
+## v2/length
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      | [0 0]   |             |
+
+## v2/normalize
+
+Returns the vector scaled to have a length of 1, a zero length vector is
+returned unchanged.
+
+Parameter | Default | Description
+--- | --- | ---
+    | from      | [0 0]   |             |
+
 # Prng Functions
 
 ## prng/build
@@ -668,6 +1116,56 @@ Parameter | Default | Description
     | y         |       1 |             |
     | z         |       1 |             |
 
+# Noise Functions
+
+The noise functions are seeded, pass a gene as the seed so that a genotype
+always gives the same noise.
+
+## noise/simplex
+
+Returns a value between -1 and 1. The noise is 2D unless z is given (3D) or w
+is given (4D).
+
+Parameter | Default | Description
+--- | --- | ---
+| x    | 0 |             |
+| y    | 0 |             |
+| z    | 0 |             |
+| w    | 0 |             |
+| seed | 0 |             |
+
+## noise/worley
+
+Cellular noise, returns the distance to the nearest (F1) or second nearest
+(F2) feature point in cell units.
+
+Parameter | Default | Description
+--- | --- | ---
+| x       |         0 |             |
+| y       |         0 |             |
+| seed    |         0 |             |
+| feature | worley/f1 | worley/f1, worley/f2 or worley/f2-f1 |
+
+## noise/fbm
+
+Fractal Brownian motion: octaves of the basis noise summed together, each one
+lacunarity times the frequency and gain times the amplitude of the previous
+octave. The result has the same range as the basis noise. At most 16 octaves
+are used.
+
+Parameter | Default | Description
+--- | --- | ---
+| x          |             0 |             |
+| y          |             0 |             |
+| z          |             0 |             |
+| w          |             0 |             |
+| seed       |             0 |             |
+| basis      | basis/simplex | basis/simplex, basis/worley or basis/perlin |
+| feature    |     worley/f1 | when the basis is basis/worley |
+| octaves    |             4 |             |
+| lacunarity |             2 |             |
+| gain       |           0.5 |             |
+
 # Interp Functions
 
 ## interp/build
@@ -790,6 +1288,289 @@ Parameter | Default | Description
     | fn        |         |             |
     | mapping   |  linear |             |
 
+# Flow Functions
+
+## flow/trace
+
+Follows a vector field from a starting point and returns the points along the
+way. The field is either the fn, which is given the position and step number
+and returns a direction vector or an angle in radians, or fractal noise with
+the given basis. When render is true the points are also drawn as a stroke.
+
+Traces with a min-separation stop when they come within that distance of an
+earlier trace that also had a min-separation. It's an error to ask for more
+than 100,000 steps.
+
+Parameter | Default | Description
+--- | --- | ---
+| from           |        [0 0] |             |
+| fn             |              |             |
+| basis          | basis/simplex | noise used when there's no fn |
+| seed           |            0 |             |
+| frequency      |        0.005 | of the noise |
+| octaves        |            1 | of the noise |
+| step-size      |            2 |             |
+| steps          |          100 |             |
+| min-separation |            0 |             |
+| render         |        false |             |
+| width          |            4 |             |
+| colour         | RGB(0 0 0 1) |             |
+| brush          |   brush/flat |             |
+| brush-subtype  |            0 |             |
+
+# Sample Functions
+
+Each of these returns a vector of points within either a rect (position, width
+and height) or, if a radius is given, a circle. The same seed always gives the
+same points.
+
+## sample/poisson-disk
+
+Points that are no closer than distance to each other, packed until there's
+no room for any more. It's an error if the distance is so small compared to
+the region that there would be millions of points.
+
+Parameter | Default | Description
+--- | --- | ---
+| position |  [500 500] |             |
+| width    |       1000 |             |
+| height   |       1000 |             |
+| radius   |            |             |
+| distance |         50 |             |
+| seed     |          0 |             |
+
+## sample/jittered-grid
+
+One point in each cell of a grid, moved randomly within its cell. A jitter of
+0 gives a regular grid. It's an error to ask for more than 1,000,000 cells.
+
+Parameter | Default | Description
+--- | --- | ---
+| position |  [500 500] |             |
+| width    |       1000 |             |
+| height   |       1000 |             |
+| radius   |            |             |
+| columns  |         10 |             |
+| rows     |         10 |             |
+| jitter   |          1 | 0..1        |
+| seed     |          0 |             |
+
+## sample/halton
+
+Points from the low discrepancy Halton sequence. It's an error to ask for more
+than 1,000,000 points.
+
+Parameter | Default | Description
+--- | --- | ---
+| position |  [500 500] |             |
+| width    |       1000 |             |
+| height   |       1000 |             |
+| radius   |            |             |
+| num      |        100 |             |
+| seed     |          0 |             |
+
+# Geom Functions
+
+## geom/delaunay
+
+Returns the Delaunay triangulation of the points, each triangle is a vector of
+3 points. When neighbours is true it instead returns, for each triangle, the
+indices of the triangles that share an edge with it.
+
+Parameter | Default | Description
+--- | --- | ---
+| points     |       |             |
+| neighbours | false |             |
+
+## geom/voronoi
+
+Returns the Voronoi cell of each point clipped to a rect, each cell is a
+vector of points around its edge. Points outside of the rect have an empty
+cell. When neighbours is true it instead returns, for each cell, the indices
+of the cells that share an edge with it.
+
+Draw the cells with (poly coords: cell triangulate: true).
+
+Parameter | Default | Description
+--- | --- | ---
+| points     |           |             |
+| position   | [500 500] |             |
+| width      |      1000 |             |
+| height     |      1000 |             |
+| neighbours |     false |             |
+
+## geom/union
+
+Combines two polygons, returns a vector of polygons. Where the result has a
+hole it's joined to the outside by a cut so that the polygon can still be
+drawn with (poly coords: p triangulate: true).
+
+Parameter | Default | Description
+--- | --- | ---
+| from |  | a polygon |
+| clip |  | a polygon |
+
+## geom/intersection
+
+The parts of from that are inside clip, returns a vector of polygons.
+
+Parameter | Default | Description
+--- | --- | ---
+| from |  | a polygon |
+| clip |  | a polygon |
+
+## geom/difference
+
+The parts of from that are outside clip, returns a vector of polygons.
+
+Parameter | Default | Description
+--- | --- | ---
+| from |  | a polygon |
+| clip |  | a polygon |
+
+## geom/offset
+
+Moves the edges of a polygon outwards by distance, or inwards if distance is
+negative. Returns an empty vector if the polygon is inset away to nothing.
+
+Parameter | Default | Description
+--- | --- | ---
+| from        |    | a polygon |
+| distance    | 10 |             |
+| miter-limit |  4 | corners longer than this multiple of the distance are bevelled |
+
+# Fill Functions
+
+## fill/hatch
+
+Fills a rect, circle or polygon with parallel lines. The shape is a rect unless
+a radius is given, in which case it's a circle. If coords are given then the
+shape is that polygon instead. It's an error if the spacing is so small compared
+to the shape that there would be more than 100,000 lines.
+
+Parameter | Default | Description
+--- | --- | ---
+| position      | [500 500]  |             |
+| width         |       1000 |             |
+| height        |       1000 |             |
+| radius        |            |             |
+| coords        |            | a polygon |
+| angle         |         45 | in degrees |
+| spacing       |         10 | distance between the lines |
+| cross         |      false | also draw lines at right angles to the first |
+| line-width    |          1 |             |
+| colour        |            |             |
+| brush         | brush/flat |             |
+| brush-subtype |          0 |             |
+
+## fill/stipple
+
+Fills a rect, circle or polygon with short dashes that are at least distance
+apart. The shape is given in the same way as fill/hatch.
+
+The optional fn is given a position and returns the density at that point,
+from 0 to 1. Use focal/value or bitmap/value inside of the fn to drive the
+density from a focal point or an image, e.g. (fn (density position: [0 0])
+(focal/value from: focal position: position)).
+
+Parameter | Default | Description
+--- | --- | ---
+| position      | [500 500]  |             |
+| width         |       1000 |             |
+| height        |       1000 |             |
+| radius        |            |             |
+| coords        |            | a polygon |
+| fn            |            | a density function |
+| distance      |          5 |             |
+| seed          |          0 |             |
+| length        |          2 | length of each dash |
+| angle         |          0 | in degrees |
+| line-width    |          2 |             |
+| colour        |            |             |
+| brush         | brush/flat |             |
+| brush-subtype |          0 |             |
+
+# Lsystem Functions
+
+## lsystem/expand
+
+Rewrites the axiom by replacing every character that has a rule with that
+rule's successor, this is repeated for the given number of iterations. Each
+rule is a vector of a single character string, its successor and an optional
+weight, e.g. ["F" "F[+F]F"]. A character can have several rules, in which case
+one of them is picked according to their weights using rng. Without an rng the
+first rule is always used.
+
+Returns a vector of character codes that can be given to lsystem/turtle or
+expanded again.
+
+It's an error to ask for more than 100 iterations or for the result to have
+more than a million symbols.
+
+Parameter | Default | Description
+--- | --- | ---
+| axiom      |   | a string |
+| rules      |   | a vector of rules |
+| iterations | 3 |             |
+| rng        |   | the result of prng/build |
+
+## lsystem/turtle
+
+Interprets a string or the result of lsystem/expand as instructions for a
+turtle that starts at the origin facing up. F and G invoke fn for a segment
+that goes from [0 0] to [0 length], f moves forward without invoking fn, the
+symbols + and - turn anticlockwise and clockwise by angle, | turns around and
+[ and ] save and restore the turtle's position. Other characters are ignored.
+
+The fn is given n (the segment's index), depth (the number of unclosed [) and
+length.
+
+Parameter | Default | Description
+--- | --- | ---
+| from   |    | a string or the result of lsystem/expand |
+| fn     |    |             |
+| length | 10 |             |
+| angle  | 25 | in degrees |
+
+# Particle Functions
+
+## particle/simulate
+
+Moves a particle from each of the from positions for a number of steps and
+returns their trajectories, each one is a vector of steps + 1 points. At every
+step the forces acting on a particle are added to its velocity, which is then
+reduced by damping and added to its position.
+
+The forces are:
+- fn: given n (the step), index (of the particle), position and velocity,
+  returns a force vector.
+- noise: if strength is given the noise field is used as a direction and
+  pushes the particle with that strength.
+- attractors: each one pulls the particle towards it with the attraction
+  (which can be negative to push it away).
+
+If jitter is given then a random velocity of up to jitter is added to each
+particle at the start using rng, so the same genotype always moves the same
+way. It's an error to ask for more than 100,000 steps and the number of
+particles multiplied by the number of steps is limited to a million.
+
+Parameter | Default | Description
+--- | --- | ---
+| from       |              | a vector of positions |
+| velocity   | [0 0]        | the initial velocity |
+| jitter     |            0 |             |
+| rng        |              | the result of prng/build |
+| fn         |              |             |
+| basis      | basis/simplex |            |
+| seed       |            0 |             |
+| frequency  |        0.005 |             |
+| octaves    |            1 | at most 16  |
+| strength   |            0 |             |
+| attractors |              | a vector of positions |
+| attraction |          0.1 |             |
+| damping    |         0.02 |             |
+| steps      |          100 |             |
+
 # Repeat Functions
 
 Parameter | Default | Description
@@ -832,6 +1613,58 @@ Parameter | Default | Description
     | fn        |         |             |
     | copies    |       3 |             |
 
+## repeat/wallpaper
+
+Tiles the plane with one of the 17 wallpaper groups. The fn is invoked for
+every copy of the motif in every cell of the lattice, with the matrix stack
+placing the origin on the cell's highest order rotation centre. It's given
+index (the cell), t (going from 0 to 1 across the cells) and copy (the copy
+within the cell). It's an error to ask for more than 100,000 cells.
+
+The groups are wallpaper/p1, wallpaper/p2, wallpaper/pm, wallpaper/pg,
+wallpaper/cm, wallpaper/pmm, wallpaper/pmg, wallpaper/pgg, wallpaper/cmm,
+wallpaper/p4, wallpaper/p4m, wallpaper/p4g, wallpaper/p3, wallpaper/p3m1,
+wallpaper/p31m, wallpaper/p6 and wallpaper/p6m. The square and hexagonal
+groups ignore height.
+
+Parameter | Default | Description
+--- | --- | ---
+    | fn        |              |             |
+    | group     | wallpaper/p1 |             |
+    | position  | [0 0]        | the centre of the tiling |
+    | width     | 100          | the width of a cell |
+    | height    | 100          | the height of a cell |
+    | columns   | 3            |             |
+    | rows      | 3            |             |
+
+## repeat/grid
+
+Invokes the fn once for every cell of a grid with the origin at the centre of
+the cell. It's given index and t (going from 0 to 1 across the cells). It's an
+error to ask for more than 100,000 cells.
+
+Parameter | Default | Description
+--- | --- | ---
+    | fn        |         |             |
+    | position  | [0 0]   | the centre of the grid |
+    | width     | 100     | the width of a cell |
+    | height    | 100     | the height of a cell |
+    | columns   | 3       |             |
+    | rows      | 3       |             |
+
+## repeat/hex-grid
+
+As repeat/grid but with pointy topped hexagonal cells, every other row is
+offset by half a cell.
+
+Parameter | Default | Description
+--- | --- | ---
+    | fn        |         |             |
+    | position  | [0 0]   | the centre of the grid |
+    | radius    | 50      | the distance from the centre of a cell to a corner |
+    | columns   | 3       |             |
+    | rows      | 3       |             |
+
 # Focal Functions
 
 ## focal/build-point
@@ -912,6 +1745,48 @@ Parameter | Default | Description
     | TEMP      |       0 |             |
     | TEMP        |       1 |             |
 
+# Blending Functions
+
+## blend/set
+
+Sets how everything drawn afterwards is combined with what's already been
+drawn. The mode is one of blend/normal, blend/multiply, blend/screen,
+blend/overlay, blend/additive, blend/darken or blend/lighten. For example,
+glazes of translucent colour can be layered with blend/multiply.
+
+Parameter | Default | Description
+--- | --- | ---
+    | mode      | blend/normal |             |
+
+# Layer Functions
+
+## layer/begin
+
+Everything drawn up until the matching layer/end is rendered into a separate,
+initially transparent, layer which is then composited onto whatever it was
+drawn over. Layers can be nested and any that haven't been ended by the end of
+the script are ended automatically. The blend mode only affects how the
+finished layer is composited, geometry within the layer starts off with
+blend/normal.
+
+Blur is a radius in canvas units. When an image is rendered in sections (e.g.
+for high resolution output) each section is blurred on its own, so a blurred
+layer can show seams where the sections meet.
+
+Parameter | Default | Description
+--- | --- | ---
+    | name       |            |             |
+    | opacity    |          1 |             |
+    | blend      | blend/normal |           |
+    | blur       |          0 |             |
+    | contrast   |          1 |             |
+    | brightness |          0 |             |
+    | saturation |          1 |             |
+
+## layer/end
+
+Ends the most recently begun layer.
+
 # Gen Functions
 
 ## gen/stray-int
@@ -978,9 +1853,15 @@ Parameter | Default | Description
 
 ## gen/col
 
+Generates a random colour in the given format, each element is picked from the
+range given for the format's constructor. Most of the LAB, LCH, OKLAB and OKLCH
+ranges are outside of what a screen can show, so those colours have their
+chroma reduced until they're within sRGB.
+
 Parameter | Default | Description
 --- | --- | ---
     | alpha     |         |             |
+    | format    | RGB     |             |
 
 
 # Interpolation Constants
@@ -272,6 +272,19 @@ fn format_var_value(node: &Node, genotype: &mut Genotype, word_lut: &WordLut) ->
                 "(col/lab l: {:.*} a: {:.*} b: {:.*} alpha: {:.*})",
                 2, c.e0, 2, c.e1, 2, c.e2, 2, c.e3
             )),
+            ColourFormat::Lch => Ok(format!(
+                "(col/lch l: {:.*} c: {:.*} h: {:.*} alpha: {:.*})",
+                2, c.e0, 2, c.e1, 2, c.e2, 2, c.e3
+            )),
+            // the OK spaces have a much smaller range so need more precision
+            ColourFormat::Oklab => Ok(format!(
+                "(col/oklab l: {:.*} a: {:.*} b: {:.*} alpha: {:.*})",
+                3, c.e0, 3, c.e1, 3, c.e2, 2, c.e3
+            )),
+            ColourFormat::Oklch => Ok(format!(
+                "(col/oklch l: {:.*} c: {:.*} h: {:.*} alpha: {:.*})",
+                3, c.e0, 3, c.e1, 2, c.e2, 2, c.e3
+            )),
         },
         Gene::V2D(x, y) => {
             let mut res: String = "[".into();
@@ -505,6 +518,12 @@ max: 670))",
             "(col/rgb r: 1 g: 0 b: 0.4 alpha: 1) ~ (gen/col alpha: 1)",
             "(col/rgb r: 0.75 g: 0.59 b: 0.33 alpha: 1.00) ~ (gen/col alpha: 1)",
         );
+
+        seeded_unparse_check(
+            653,
+            "(col/rgb r: 1 g: 0 b: 0.4 alpha: 1) ~ (gen/col alpha: 1 format: OKLCH)",
+            "(col/oklch l: 0.747 c: 0.178 h: 120.10 alpha: 1.00) ~ (gen/col alpha: 1 format: OKLCH)",
+        );
     }
 
     #[test]
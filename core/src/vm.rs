@@ -26,11 +26,11 @@ use crate::native::execute_native;
 use crate::opcodes::Opcode;
 use crate::packable::{Mule, Packable};
 use crate::prng::PrngStateStruct;
+use crate::profiler::{format_profile_table, ProfileMetric, Profiler};
 use crate::program::{Bytecode, BytecodeArg, FnInfo, Mem, Program};
 
-use std::cell::RefCell;
 use std::fmt;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 use log::error;
 
@@ -68,7 +68,7 @@ pub enum Var {
     InterpState(InterpStateStruct),
     ProcColourState(ProcColourStateStruct),
     FocalState(FocalStateStruct),
-    PrngState(Rc<RefCell<PrngStateStruct>>),
+    PrngState(Arc<Mutex<PrngStateStruct>>),
 }
 
 impl Packable for Var {
@@ -256,6 +256,7 @@ pub struct Vm {
     pub profiling: VMProfiling,
     pub opcode_count: Vec<u64>,
     pub opcodes_executed: u64,
+    pub profiler: Profiler,
     pub execution_time: f32, // in msec
 
     pub stack: Vec<Var>,
@@ -309,6 +310,7 @@ impl Default for Vm {
             profiling: VMProfiling::Off,
             opcode_count: vec![],
             opcodes_executed: 0,
+            profiler: Default::default(),
             execution_time: 0.0, // in msec
 
             stack,
@@ -421,6 +423,10 @@ impl Vm {
         self.ip = fn_info.arg_address;
         self.fp = fp;
 
+        if self.profiling == VMProfiling::On {
+            self.profiler.enter_fn(fn_info.arg_address);
+        }
+
         // clear the memory that's going to be used for locals
         for _ in 0..MEMORY_LOCAL_SIZE {
             // setting all memory as VAR_INT will prevent any weird ref count
@@ -439,7 +445,7 @@ impl Vm {
         context: &mut Context,
         program: &Program,
         fn_info: &FnInfo,
-    ) -> Result<()> {
+    ) -> Result<Var> {
         // push a frame onto the stack whose return address is the program's STOP
         // instruction
         let stop_address = program.stop_location();
@@ -457,7 +463,31 @@ impl Vm {
         // so we'll need to pop that function's return value off the stack
         self.sp = self.sp_dec()?;
 
-        Ok(())
+        Ok(self.stack[self.sp].clone())
+    }
+
+    /// Calls the script function program.fn_info[fun] from native code and returns
+    /// its result. Any arguments not given in args use the function's defaults.
+    pub fn invoke_function(
+        &mut self,
+        context: &mut Context,
+        program: &Program,
+        fun: usize,
+        args: &[(Keyword, Var)],
+    ) -> Result<Var> {
+        let ip = self.ip;
+
+        let fn_info = &program.fn_info[fun];
+
+        self.function_call_default_arguments(context, program, fn_info)?;
+        for (keyword, var) in args {
+            self.function_set_argument(fn_info, Iname::from(*keyword), var.clone());
+        }
+        let res = self.function_call_body(context, program, fn_info)?;
+
+        self.ip = ip;
+
+        Ok(res)
     }
 
     fn arg_memory_from_iname(
@@ -481,6 +511,12 @@ impl Vm {
         None
     }
 
+    pub fn function_set_argument(&mut self, fn_info: &FnInfo, iname: Iname, var: Var) {
+        if let Some(offset) = self.arg_memory_from_iname(fn_info, iname, self.fp - 1) {
+            self.stack[offset] = var;
+        }
+    }
+
     pub fn function_set_argument_to_col(
         &mut self,
         fn_info: &FnInfo,
@@ -670,7 +706,19 @@ impl Vm {
         })? as usize;
 
         let res = if let BytecodeArg::Native(native) = bc.arg0 {
-            execute_native(self, context, program, native)?
+            if self.profiling == VMProfiling::On {
+                let line = program.line_at(self.ip - 1);
+                let num_vertices = context.render_list.get_num_vertices();
+                self.profiler.native_begin(native, line, num_vertices);
+
+                let res = execute_native(self, context, program, native)?;
+
+                let num_vertices = context.render_list.get_num_vertices();
+                self.profiler.native_end(num_vertices);
+                res
+            } else {
+                execute_native(self, context, program, native)?
+            }
         } else {
             error!("opcode_native");
             return Err(Error::VM);
@@ -736,52 +784,44 @@ impl Vm {
         Ok(())
     }
 
-    fn opcode_add(&mut self) -> Result<()> {
+    // pops two values and pushes the result of op. Either value can be a V2D
+    // in which case op is applied to each element, a Float is used for both
+    fn arithmetic(&mut self, name: &str, op: fn(f32, f32) -> f32) -> Result<()> {
         self.sp = self.sp_dec()?; // stack pop
-        if let Var::Float(f2) = &self.stack[self.sp] {
-            self.sp = self.sp_dec()?; // stack pop
-            if let Var::Float(f1) = &self.stack[self.sp] {
-                self.sp = self.sp_inc()?; // stack push
-                self.stack[self.sp - 1] = Var::Float(f1 + f2);
+        let rhs = self.stack[self.sp].clone();
+        self.sp = self.sp_dec()?; // stack pop
+        let lhs = &self.stack[self.sp];
+
+        let res = match (lhs, rhs) {
+            (Var::Float(f1), Var::Float(f2)) => Var::Float(op(*f1, f2)),
+            (Var::V2D(x1, y1), Var::V2D(x2, y2)) => Var::V2D(op(*x1, x2), op(*y1, y2)),
+            (Var::V2D(x1, y1), Var::Float(f2)) => Var::V2D(op(*x1, f2), op(*y1, f2)),
+            (Var::Float(f1), Var::V2D(x2, y2)) => Var::V2D(op(*f1, x2), op(*f1, y2)),
+            _ => {
+                error!("{} expected floats or 2d vectors", name);
+                return Err(Error::VM);
             }
-        }
+        };
+
+        self.sp = self.sp_inc()?; // stack push
+        self.stack[self.sp - 1] = res;
         Ok(())
     }
 
+    fn opcode_add(&mut self) -> Result<()> {
+        self.arithmetic("opcode_add", |f1, f2| f1 + f2)
+    }
+
     fn opcode_sub(&mut self) -> Result<()> {
-        self.sp = self.sp_dec()?; // stack pop
-        if let Var::Float(f2) = &self.stack[self.sp] {
-            self.sp = self.sp_dec()?; // stack pop
-            if let Var::Float(f1) = &self.stack[self.sp] {
-                self.sp = self.sp_inc()?; // stack push
-                self.stack[self.sp - 1] = Var::Float(f1 - f2);
-            }
-        }
-        Ok(())
+        self.arithmetic("opcode_sub", |f1, f2| f1 - f2)
     }
 
     fn opcode_mul(&mut self) -> Result<()> {
-        self.sp = self.sp_dec()?; // stack pop
-        if let Var::Float(f2) = &self.stack[self.sp] {
-            self.sp = self.sp_dec()?; // stack pop
-            if let Var::Float(f1) = &self.stack[self.sp] {
-                self.sp = self.sp_inc()?; // stack push
-                self.stack[self.sp - 1] = Var::Float(f1 * f2);
-            }
-        }
-        Ok(())
+        self.arithmetic("opcode_mul", |f1, f2| f1 * f2)
     }
 
     fn opcode_div(&mut self) -> Result<()> {
-        self.sp = self.sp_dec()?; // stack pop
-        if let Var::Float(f2) = &self.stack[self.sp] {
-            self.sp = self.sp_dec()?; // stack pop
-            if let Var::Float(f1) = &self.stack[self.sp] {
-                self.sp = self.sp_inc()?; // stack push
-                self.stack[self.sp - 1] = Var::Float(f1 / f2);
-            }
-        }
-        Ok(())
+        self.arithmetic("opcode_div", |f1, f2| f1 / f2)
     }
 
     fn opcode_mod(&mut self) -> Result<()> {
@@ -948,6 +988,10 @@ impl Vm {
         self.ip = addr as usize;
         self.fp = fp;
 
+        if self.profiling == VMProfiling::On {
+            self.profiler.enter_fn(addr as usize);
+        }
+
         // clear the memory that's going to be used for locals
         for _ in 0..MEMORY_LOCAL_SIZE {
             // setting all memory as VAR_INT will prevent any weird ref count
@@ -1019,6 +1063,10 @@ impl Vm {
         self.sp = self.sp_inc()?; // stack push
         self.stack[self.sp - 1] = src.clone();
 
+        if self.profiling == VMProfiling::On {
+            self.profiler.exit_fn();
+        }
+
         Ok(())
     }
 
@@ -1076,6 +1124,10 @@ impl Vm {
         self.ip = addr as usize;
         self.fp = fp;
 
+        if self.profiling == VMProfiling::On {
+            self.profiler.enter_fn(addr);
+        }
+
         // clear the memory that's going to be used for locals
         for _ in 0..MEMORY_LOCAL_SIZE {
             // setting all memory as VAR_INT will prevent any weird ref count
@@ -1345,6 +1397,7 @@ impl Vm {
             for _ in 0..program.code.len() {
                 self.opcode_count.push(0);
             }
+            self.profiler.reset();
         }
 
         Ok(())
@@ -1363,6 +1416,7 @@ impl Vm {
             if self.profiling == VMProfiling::On {
                 self.opcodes_executed += 1;
                 self.opcode_count[self.ip] += 1;
+                self.profiler.opcode(program.line_at(self.ip));
             }
 
             bc = &program.code[self.ip];
@@ -1418,6 +1472,23 @@ impl Vm {
             println!("{:>4}: {:>6}:      {}", i + 1, line, program.code[i]);
         }
 
+        let metric = ProfileMetric::NativeTime;
+        println!();
+        print!(
+            "{}",
+            format_profile_table("fn", &self.profiler.function_rows(program, metric))
+        );
+        println!();
+        print!(
+            "{}",
+            format_profile_table("line", &self.profiler.line_rows(metric))
+        );
+        println!();
+        print!(
+            "{}",
+            format_profile_table("native", &self.profiler.native_rows(metric))
+        );
+
         Ok(())
     }
 
@@ -1568,6 +1639,22 @@ pub mod tests {
         }
     }
 
+    pub fn is_v2d(s: &str, val: (f32, f32)) {
+        let mut vm: Vm = Default::default();
+        let mut context: Context = Default::default();
+
+        if let Var::V2D(x, y) = vm_exec(&mut vm, &mut context, s) {
+            assert!(
+                (x - val.0).abs() < 0.0001 && (y - val.1).abs() < 0.0001,
+                "expected {:?} actual {:?}",
+                val,
+                (x, y)
+            );
+        } else {
+            panic!("expected a V2D");
+        }
+    }
+
     pub fn is_vec_of_f32(s: &str, val: Vec<f32>) {
         let mut vm: Vm = Default::default();
         let mut context: Context = Default::default();
@@ -1630,6 +1717,14 @@ pub mod tests {
         is_float("(% 10 3)", 1.0);
         is_float("(sqrt 144)", 12.0);
 
+        is_v2d("(+ [1 2] [10 20])", (11.0, 22.0));
+        is_v2d("(- [10 20] [1 2])", (9.0, 18.0));
+        is_v2d("(* [1 2] [3 4])", (3.0, 8.0));
+        is_v2d("(* [1 2] 3)", (3.0, 6.0));
+        is_v2d("(* 3 [1 2])", (3.0, 6.0));
+        is_v2d("(/ [10 20] 5)", (2.0, 4.0));
+        is_v2d("(define a [1 1] b 2) (+ a b [3 4])", (6.0, 7.0));
+
         is_bool("(= 4 4)", true);
         is_bool("(= 4 5)", false);
 
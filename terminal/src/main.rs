@@ -24,11 +24,13 @@ use log::{error, info, trace};
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
+use std::thread;
 use std::time::Instant;
 
 use core::{
-    bitmaps_to_transfer, build_traits, compile_preamble, compile_program, parse,
-    BitmapInfo, Context, Packable, Program, ProbeSample, VMProfiling, Var, Vm, RenderPacket,
+    bitmaps_to_transfer, build_traits, compile_preamble, compile_program, compile_str, palettes_to_transfer,
+    parse, render_population, BitmapInfo, Context, Genotype, Packable, ProfileMetric, Program, ProbeSample, VMProfiling, Var, Vm,
+    RenderPacket,
 };
 
 type Result<T> = ::std::result::Result<T, Box<dyn ::std::error::Error>>;
@@ -67,6 +69,20 @@ fn main() {
                 .help("Show opcode count")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("folded")
+                .short("f")
+                .long("folded")
+                .help("Write folded stacks for flamegraph tools to the given file (implies profiling)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("population")
+                .short("n")
+                .long("population")
+                .help("Render a population of the given size across all cores")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("packed_trait_list")
                 .short("t")
@@ -105,12 +121,20 @@ fn run(matches: &ArgMatches) -> Result<()> {
             settings.set("profiling", true)?;
         }
 
+        if let Some(folded) = matches.value_of("folded") {
+            settings.set("profiling", true)?;
+            settings.set("folded", folded)?;
+        }
+
         if matches.is_present("debug") {
             settings.set("debug", true)?;
         }
 
         if matches.is_present("packed_trait_list") {
             print_packed_trait_list(script)?;
+        } else if let Ok(population_size) = value_t!(matches.value_of("population"), usize) {
+            let seed = value_t!(matches.value_of("seed"), i32).unwrap_or(0);
+            run_population(script, population_size, seed, &settings)?;
         } else if let Ok(seed) = value_t!(matches.value_of("seed"), u32) {
             run_script_with_seed(script, seed, &settings)?;
         } else {
@@ -188,6 +212,26 @@ fn load_bitmaps(program: &Program, context: &mut Context, asset_prefix: &String)
     Ok(())
 }
 
+fn load_palettes(program: &Program, context: &mut Context, asset_prefix: &String) -> Result<()> {
+    let palettes_to_transfer = palettes_to_transfer(&program, &context);
+    let len = palettes_to_transfer.len();
+
+    if len == 0 {
+        return Ok(());
+    }
+
+    for f in palettes_to_transfer {
+        let path = Path::new(asset_prefix).join(&f);
+        info!("load_palette: {:?}", path);
+        let source = read_script_file(&path)?;
+        context.palette_cache.insert_from_source(&f, &source)?;
+    }
+
+    info!("loaded {}", quantity(len, "palette"));
+
+    Ok(())
+}
+
 fn run_script(script: &Path, settings: &config::Config) -> Result<()> {
     trace!("run_script");
 
@@ -223,6 +267,7 @@ fn run_script(script: &Path, settings: &config::Config) -> Result<()> {
     } else {
         let asset_prefix = settings.get_str("assets")?;
         load_bitmaps(&program, &mut context, &asset_prefix)?;
+        load_palettes(&program, &mut context, &asset_prefix)?;
 
         let time_run_program = Instant::now();
 
@@ -258,6 +303,16 @@ fn run_script(script: &Path, settings: &config::Config) -> Result<()> {
             vm.println_profiling(&program)?;
         }
 
+        if let Ok(folded) = settings.get_str("folded") {
+            let mut f = File::create(&folded)?;
+            f.write_all(
+                vm.profiler
+                    .folded_stacks(&program, ProfileMetric::NativeTime)
+                    .as_bytes(),
+            )?;
+            info!("wrote folded stacks to {}", folded);
+        }
+
         show_program_results(&res, &context);
 
         if !vm.probe_samples.is_empty() {
@@ -297,6 +352,15 @@ fn show_program_results(result: &Var, context: &Context) {
             RenderPacket::Image(_) => {
                 println!("{}. Image", i);
             }
+            RenderPacket::Blend(rpb) => {
+                println!("{}. Blend: {:?}", i, rpb.mode);
+            }
+            RenderPacket::LayerBegin(rpl) => {
+                println!("{}. LayerBegin: {:?} {:?}", i, rpl.name, rpl.blend);
+            }
+            RenderPacket::LayerEnd => {
+                println!("{}. LayerEnd", i);
+            }
         }
     }
 }
@@ -307,6 +371,48 @@ fn run_script_with_seed(_script: &Path, _seed: u32, _settings: &config::Config)
     Ok(())
 }
 
+fn run_population(
+    script: &Path,
+    population_size: usize,
+    seed: i32,
+    settings: &config::Config,
+) -> Result<()> {
+    trace!("run_population");
+
+    let source = read_script_file(script)?;
+
+    let trait_list = build_traits(&source)?;
+    let genotypes = Genotype::build_genotypes(&trait_list, population_size as i32, seed)?;
+
+    let mut context: Context = Default::default();
+    let asset_prefix = settings.get_str("assets")?;
+    let program = compile_str(&source)?;
+    load_bitmaps(&program, &mut context, &asset_prefix)?;
+    load_palettes(&program, &mut context, &asset_prefix)?;
+
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let time_render = Instant::now();
+    let render_lists = render_population(
+        &source,
+        &genotypes,
+        &context.bitmap_cache,
+        &context.palette_cache,
+        num_threads,
+    )?;
+    info!(
+        "render_population: {:?} using {}",
+        time_render.elapsed(),
+        quantity(num_threads, "thread")
+    );
+
+    for (i, render_list) in render_lists.iter().enumerate() {
+        println!("{}. {} vertices", i, render_list.get_num_vertices());
+    }
+
+    Ok(())
+}
+
 fn print_packed_trait_list(script: &Path) -> Result<()> {
     trace!("print_packed_trait_list");
 
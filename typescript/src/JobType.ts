@@ -9,4 +9,5 @@ enum JobType {
     jobGenerateHelp,
     jobSingleGenotypeFromSeed,
     jobSimplifyScript,
+    jobRender_2_ReceivePaletteData,
 }
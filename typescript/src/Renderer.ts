@@ -24,6 +24,8 @@ enum TextureUnit {
     renderToTexture = 0,
     brushTexture,
     maskTexture,
+    backdropTexture,
+    layerTexture,
 }
 
 
@@ -31,6 +33,20 @@ enum RPCommand {
     geometry = 1,
     mask,
     image,
+    blend,
+    layerBegin,
+    layerEnd,
+}
+
+// matches the BlendMode values on the Rust side
+enum BlendMode {
+    normal = 0,
+    multiply,
+    screen,
+    overlay,
+    additive,
+    darken,
+    lighten,
 }
 
 class SketchShaderTS {
@@ -44,6 +60,9 @@ class SketchShaderTS {
     public maskUniform: WebGLUniformLocation | null;
     public canvasDimUniform: WebGLUniformLocation | null;
     public maskInvert: WebGLUniformLocation | null;
+    public blendModeUniform: WebGLUniformLocation | null;
+    public backdropUniform: WebGLUniformLocation | null;
+    public backdropDimUniform: WebGLUniformLocation | null;
 
     // older versions of seni (pre 4.2.0) did not convert from sRGB space to linear before blending
     // in order to retain the look of these older sketchs we can't carry out the linear -> sRGB conversion
@@ -60,7 +79,10 @@ class SketchShaderTS {
                 maskUniform: WebGLUniformLocation | null,
                 canvasDimUniform: WebGLUniformLocation | null,
                 maskInvert: WebGLUniformLocation | null,
-                outputLinearColourSpaceUniform: WebGLUniformLocation | null) {
+                outputLinearColourSpaceUniform: WebGLUniformLocation | null,
+                blendModeUniform: WebGLUniformLocation | null,
+                backdropUniform: WebGLUniformLocation | null,
+                backdropDimUniform: WebGLUniformLocation | null) {
         this.program = program;
         this.positionAttribute = positionAttribute;
         this.colourAttribute = colourAttribute;
@@ -71,6 +93,9 @@ class SketchShaderTS {
         this.canvasDimUniform = canvasDimUniform;
         this.maskInvert = maskInvert;
         this.outputLinearColourSpaceUniform = outputLinearColourSpaceUniform;
+        this.blendModeUniform = blendModeUniform;
+        this.backdropUniform = backdropUniform;
+        this.backdropDimUniform = backdropDimUniform;
     }
 }
 
@@ -112,6 +137,66 @@ class BlitShaderTS {
     }
 }
 
+class LayerShaderTS {
+    public program: WebGLProgram;
+
+    public positionAttribute: GLint;
+    public textureAttribute: GLint;
+    public pMatrixUniform: WebGLUniformLocation | null;
+    public layerUniform: WebGLUniformLocation | null;
+    public layerDimUniform: WebGLUniformLocation | null;
+    public blurUniform: WebGLUniformLocation | null;
+    public blurDirectionUniform: WebGLUniformLocation | null;
+    public blurOnlyUniform: WebGLUniformLocation | null;
+    public opacityUniform: WebGLUniformLocation | null;
+    public brightnessUniform: WebGLUniformLocation | null;
+    public contrastUniform: WebGLUniformLocation | null;
+    public saturationUniform: WebGLUniformLocation | null;
+    public blendModeUniform: WebGLUniformLocation | null;
+    public backdropUniform: WebGLUniformLocation | null;
+    public backdropDimUniform: WebGLUniformLocation | null;
+
+    constructor(program: WebGLProgram,
+                positionAttribute: GLint,
+                textureAttribute: GLint,
+                pMatrixUniform: WebGLUniformLocation | null,
+                layerUniform: WebGLUniformLocation | null,
+                layerDimUniform: WebGLUniformLocation | null,
+                blurUniform: WebGLUniformLocation | null,
+                blurDirectionUniform: WebGLUniformLocation | null,
+                blurOnlyUniform: WebGLUniformLocation | null,
+                opacityUniform: WebGLUniformLocation | null,
+                brightness: WebGLUniformLocation | null,
+                contrast: WebGLUniformLocation | null,
+                saturation: WebGLUniformLocation | null,
+                blendModeUniform: WebGLUniformLocation | null,
+                backdropUniform: WebGLUniformLocation | null,
+                backdropDimUniform: WebGLUniformLocation | null) {
+        this.program = program;
+        this.positionAttribute = positionAttribute;
+        this.textureAttribute = textureAttribute;
+        this.pMatrixUniform = pMatrixUniform;
+        this.layerUniform = layerUniform;
+        this.layerDimUniform = layerDimUniform;
+        this.blurUniform = blurUniform;
+        this.blurDirectionUniform = blurDirectionUniform;
+        this.blurOnlyUniform = blurOnlyUniform;
+        this.opacityUniform = opacityUniform;
+        this.brightnessUniform = brightness;
+        this.contrastUniform = contrast;
+        this.saturationUniform = saturation;
+        this.blendModeUniform = blendModeUniform;
+        this.backdropUniform = backdropUniform;
+        this.backdropDimUniform = backdropDimUniform;
+    }
+}
+
+// a render target for geometry drawn between layer/begin and layer/end
+interface RenderLayer {
+    texture: WebGLTexture | null;
+    framebuffer: WebGLFramebuffer | null;
+}
+
 class RendererTS {
     static initGL(canvas: HTMLCanvasElement): WebGLRenderingContext | null {
 
@@ -189,7 +274,10 @@ class RendererTS {
                                           gl.getUniformLocation(program, 'mask'),
                                           gl.getUniformLocation(program, 'canvas_dim'),
                                           gl.getUniformLocation(program, 'mask_invert'),
-                                          gl.getUniformLocation(program, 'output_linear_colour_space'));
+                                          gl.getUniformLocation(program, 'output_linear_colour_space'),
+                                          gl.getUniformLocation(program, 'blend_mode'),
+                                          gl.getUniformLocation(program, 'backdrop'),
+                                          gl.getUniformLocation(program, 'backdrop_dim'));
             }
         }
 
@@ -236,6 +324,51 @@ class RendererTS {
         return null;
     }
 
+    static setupLayerShaders(gl: WebGLRenderingContext, vertexSrc: string, fragmentSrc: string): LayerShaderTS | null {
+        const programOrNull: WebGLProgram | null = gl.createProgram();
+
+        if (programOrNull) {
+            const program: WebGLProgram = programOrNull;
+
+            const vertexShader = RendererTS.compileShader(gl, gl.VERTEX_SHADER, vertexSrc);
+            const fragmentShader = RendererTS.compileShader(gl, gl.FRAGMENT_SHADER, fragmentSrc);
+
+            if (vertexShader && fragmentShader) {
+                gl.attachShader(program, vertexShader);
+                gl.attachShader(program, fragmentShader);
+
+                gl.linkProgram(program);
+
+                if (!gl.getProgramParameter(program, gl.LINK_STATUS)) {
+                    let lastError = gl.getProgramInfoLog(program);
+
+                    alert(`Could not initialise shaders: ${lastError}`);;
+                    gl.deleteProgram(program);
+                    return null;
+                }
+
+                return new LayerShaderTS(program,
+                                         gl.getAttribLocation(program, 'pos'),
+                                         gl.getAttribLocation(program, 'uv'),
+                                         gl.getUniformLocation(program, 'proj_matrix'),
+                                         gl.getUniformLocation(program, 'layer'),
+                                         gl.getUniformLocation(program, 'layer_dim'),
+                                         gl.getUniformLocation(program, 'blur'),
+                                         gl.getUniformLocation(program, 'blur_direction'),
+                                         gl.getUniformLocation(program, 'blur_only'),
+                                         gl.getUniformLocation(program, 'opacity'),
+                                         gl.getUniformLocation(program, 'brightness'),
+                                         gl.getUniformLocation(program, 'contrast'),
+                                         gl.getUniformLocation(program, 'saturation'),
+                                         gl.getUniformLocation(program, 'blend_mode'),
+                                         gl.getUniformLocation(program, 'backdrop'),
+                                         gl.getUniformLocation(program, 'backdrop_dim'));
+            }
+        }
+
+        return null;
+    }
+
     static setupGLState(gl: WebGLRenderingContext) {
         // clear colour alpha is 1.0 as we want to treat a blank canvas as opaque white
         gl.clearColor(1.0, 1.0, 1.0, 1.0);
@@ -251,20 +384,63 @@ class RendererTS {
 
     }
 
+    // blendMinMax is the EXT_blend_minmax extension, required by darken and lighten.
+    // returns the mode that was actually set
+    static setBlendMode(gl: WebGLRenderingContext, blendMinMax: any, mode: BlendMode): BlendMode {
+        if ((mode === BlendMode.darken || mode === BlendMode.lighten) && !blendMinMax) {
+            Log.error(`blend mode ${mode} requires EXT_blend_minmax`);
+            mode = BlendMode.normal;
+        }
+
+        // all modes assume that the shader outputs pre-multiplied alpha
+        switch(mode) {
+            case BlendMode.multiply:
+                gl.blendEquation(gl.FUNC_ADD);
+                gl.blendFunc(gl.DST_COLOR, gl.ONE_MINUS_SRC_ALPHA);
+                break;
+            case BlendMode.screen:
+                gl.blendEquation(gl.FUNC_ADD);
+                gl.blendFunc(gl.ONE, gl.ONE_MINUS_SRC_COLOR);
+                break;
+            case BlendMode.overlay:
+                // the shader blends with a copy of the render target
+                gl.blendEquation(gl.FUNC_ADD);
+                gl.blendFunc(gl.ONE, gl.ZERO);
+                break;
+            case BlendMode.additive:
+                gl.blendEquation(gl.FUNC_ADD);
+                gl.blendFunc(gl.ONE, gl.ONE);
+                break;
+            case BlendMode.darken:
+                gl.blendEquation(blendMinMax.MIN_EXT);
+                break;
+            case BlendMode.lighten:
+                gl.blendEquation(blendMinMax.MAX_EXT);
+                break;
+            default:
+                gl.blendEquation(gl.FUNC_ADD);
+                gl.blendFunc(gl.ONE, gl.ONE_MINUS_SRC_ALPHA);
+        }
+
+        return mode;
+    }
+
     static textureUnitToGl(gl: WebGLRenderingContext, unit: TextureUnit): GLint {
         switch(unit) {
             case TextureUnit.renderToTexture: return gl.TEXTURE0;
             case TextureUnit.brushTexture: return gl.TEXTURE1;
             case TextureUnit.maskTexture: return gl.TEXTURE2;
+            case TextureUnit.backdropTexture: return gl.TEXTURE3;
+            case TextureUnit.layerTexture: return gl.TEXTURE4;
             default:
                 Log.error(`invalid unit for texture: ${unit}`);
         };
         return gl.TEXTURE0;
     }
 
-    static createRenderTexture(gl: WebGLRenderingContext, renderTextureWidth: number, renderTextureHeight: number): WebGLTexture | null {
-        let texUnit = RendererTS.textureUnitToGl(gl, TextureUnit.renderToTexture);
-        Log.log(`activeTexture ${TextureUnit.renderToTexture}`);
+    static createRenderTexture(gl: WebGLRenderingContext, unit: TextureUnit, renderTextureWidth: number, renderTextureHeight: number): WebGLTexture | null {
+        let texUnit = RendererTS.textureUnitToGl(gl, unit);
+        Log.log(`activeTexture ${unit}`);
         gl.activeTexture(texUnit);
 
         const targetTexture = gl.createTexture();
@@ -374,10 +550,19 @@ class GLRenderer2 {
     public loadedTextureCache: IHashStrTextureUnit;
     public sketchShader: SketchShaderTS | null;
     public blitShader: BlitShaderTS | null;
+    public layerShader: LayerShaderTS | null;
     public glVertexBuffer: WebGLBuffer | null;
     public pMatrix: Float32Array;
     public renderTexture: WebGLTexture | null;
     public framebuffer: WebGLFramebuffer | null;
+    public backdropTexture: WebGLTexture | null;
+    public blendMinMax: any;
+    // render targets for nested layers, indexed by depth and created when first needed
+    public layers: Array<RenderLayer>;
+    // holds the first pass of a layer's blur, created when first needed
+    public blurTarget: RenderLayer | null;
+    public renderTextureWidth: number;
+    public renderTextureHeight: number;
 
     constructor(prefix: string, canvasElement: HTMLCanvasElement, shaders: IHashStrStr, renderTextureWidth: number, renderTextureHeight: number) {
         this.glDomElement = canvasElement;
@@ -397,6 +582,9 @@ class GLRenderer2 {
             this.blitShader = RendererTS.setupBlitShaders(gl,
                                                           shaders[prefix + '/shader/blit-vert.glsl'],
                                                           shaders[prefix + '/shader/blit-frag.glsl']);
+            this.layerShader = RendererTS.setupLayerShaders(gl,
+                                                            shaders[prefix + '/shader/blit-vert.glsl'],
+                                                            shaders[prefix + '/shader/layer-frag.glsl']);
 
             RendererTS.setupGLState(gl);
 
@@ -405,7 +593,17 @@ class GLRenderer2 {
             // this.mvMatrix = Matrix.create();
             this.pMatrix = Matrix.create();
 
-            this.renderTexture = RendererTS.createRenderTexture(gl, renderTextureWidth, renderTextureHeight);
+            this.blendMinMax = gl.getExtension('EXT_blend_minmax');
+
+            this.layers = [];
+            this.blurTarget = null;
+            this.renderTextureWidth = renderTextureWidth;
+            this.renderTextureHeight = renderTextureHeight;
+
+            // the overlay blend mode reads from a copy of the render texture
+            this.backdropTexture = RendererTS.createRenderTexture(gl, TextureUnit.backdropTexture, renderTextureWidth, renderTextureHeight);
+
+            this.renderTexture = RendererTS.createRenderTexture(gl, TextureUnit.renderToTexture, renderTextureWidth, renderTextureHeight);
             if (this.renderTexture) {
                 this.framebuffer = RendererTS.createFrameBuffer(gl, this.renderTexture);
             }
@@ -486,6 +684,125 @@ class GLRenderer2 {
         }
     }
 
+    // returns the render target for a layer at the given depth (0 is the outermost layer)
+    getLayer(gl: WebGLRenderingContext, depth: number): RenderLayer {
+        if (this.layers[depth] === undefined) {
+            const texture = RendererTS.createRenderTexture(gl, TextureUnit.layerTexture, this.renderTextureWidth, this.renderTextureHeight);
+            const framebuffer = texture ? RendererTS.createFrameBuffer(gl, texture) : null;
+            this.layers[depth] = { texture, framebuffer };
+        }
+
+        return this.layers[depth];
+    }
+
+    getBlurTarget(gl: WebGLRenderingContext): RenderLayer {
+        if (this.blurTarget === null) {
+            const texture = RendererTS.createRenderTexture(gl, TextureUnit.layerTexture, this.renderTextureWidth, this.renderTextureHeight);
+            const framebuffer = texture ? RendererTS.createFrameBuffer(gl, texture) : null;
+            this.blurTarget = { texture, framebuffer };
+        }
+
+        return this.blurTarget;
+    }
+
+    // draws a layer's texture over the given framebuffer. A blurred layer is
+    // first blurred horizontally into the blur target, then that's blurred
+    // vertically as it's composited
+    compositeLayer(gl: WebGLRenderingContext, layer: RenderLayer, target: WebGLFramebuffer | null, buffer: any, blurPixels: number, width: number, height: number) {
+        if (this.layerShader === null) {
+            return;
+        }
+
+        const shader = this.layerShader;
+
+        gl.useProgram(shader.program);
+
+        gl.enableVertexAttribArray(shader.positionAttribute);
+        gl.enableVertexAttribArray(shader.textureAttribute);
+
+        const pMatrix = Matrix.create();
+        Matrix.ortho(pMatrix, 0, width, 0, height, 10, -10);
+        gl.uniformMatrix4fv(shader.pMatrixUniform, false, pMatrix);
+
+        gl.uniform1i(shader.layerUniform, TextureUnit.layerTexture);
+        gl.uniform2f(shader.layerDimUniform, this.renderTextureWidth, this.renderTextureHeight);
+        gl.uniform1f(shader.blurUniform, blurPixels);
+
+        let source = layer.texture;
+        if (blurPixels > 0.0) {
+            const blurTarget = this.getBlurTarget(gl);
+
+            gl.bindFramebuffer(gl.FRAMEBUFFER, blurTarget.framebuffer);
+            gl.clearColor(0.0, 0.0, 0.0, 0.0);
+            gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT);
+            gl.clearColor(1.0, 1.0, 1.0, 1.0);
+
+            RendererTS.setBlendMode(gl, this.blendMinMax, BlendMode.normal);
+            gl.uniform2f(shader.blurDirectionUniform, 1.0, 0.0);
+            gl.uniform1i(shader.blurOnlyUniform, 1);
+            this.drawLayerQuad(gl, shader, layer.texture, width, height);
+
+            source = blurTarget.texture;
+        }
+
+        gl.bindFramebuffer(gl.FRAMEBUFFER, target);
+
+        const blendMode = RendererTS.setBlendMode(gl, this.blendMinMax, buffer.blend_mode);
+        if (blendMode === BlendMode.overlay) {
+            gl.activeTexture(RendererTS.textureUnitToGl(gl, TextureUnit.backdropTexture));
+            gl.bindTexture(gl.TEXTURE_2D, this.backdropTexture);
+            gl.copyTexSubImage2D(gl.TEXTURE_2D, 0, 0, 0, 0, 0, width, height);
+        }
+
+        gl.uniform2f(shader.blurDirectionUniform, 0.0, 1.0);
+        gl.uniform1i(shader.blurOnlyUniform, 0);
+        gl.uniform1f(shader.opacityUniform, buffer.layer_opacity);
+        gl.uniform1f(shader.brightnessUniform, buffer.brightness);
+        gl.uniform1f(shader.contrastUniform, buffer.contrast);
+        gl.uniform1f(shader.saturationUniform, buffer.saturation);
+        gl.uniform1i(shader.blendModeUniform, blendMode);
+        gl.uniform1i(shader.backdropUniform, TextureUnit.backdropTexture);
+        gl.uniform2f(shader.backdropDimUniform, width, height);
+
+        this.drawLayerQuad(gl, shader, source, width, height);
+    }
+
+    // draws the part of the texture that's the size of the viewport over the viewport
+    drawLayerQuad(gl: WebGLRenderingContext, shader: LayerShaderTS, texture: WebGLTexture | null, width: number, height: number) {
+        gl.activeTexture(RendererTS.textureUnitToGl(gl, TextureUnit.layerTexture));
+        gl.bindTexture(gl.TEXTURE_2D, texture);
+
+        // x, y, u, v
+        // the layer only uses the part of its texture that's the size of the viewport
+        const u = width / this.renderTextureWidth;
+        const v = height / this.renderTextureHeight;
+        const jsData = [
+            0.0, 0.0, 0.0, 0.0,
+            width, 0.0, u, 0.0,
+            0.0, height, 0.0, v,
+            width, height, u, v
+        ];
+        const data = Float32Array.from(jsData);
+
+        const vertexItemSize = 2;
+        const textureItemSize = 2;
+        const totalSize = (vertexItemSize + textureItemSize);
+
+        gl.bindBuffer(gl.ARRAY_BUFFER, this.glVertexBuffer);
+        gl.bufferData(gl.ARRAY_BUFFER, data, gl.STATIC_DRAW);
+
+        gl.vertexAttribPointer(shader.positionAttribute,
+                               vertexItemSize,
+                               gl.FLOAT, false, totalSize * 4,
+                               0);
+        gl.vertexAttribPointer(shader.textureAttribute,
+                               textureItemSize,
+                               gl.FLOAT, false, totalSize * 4,
+                               (vertexItemSize) * 4);
+
+        gl.drawArrays(gl.TRIANGLE_STRIP, 0, jsData.length / totalSize);
+    }
+
     public async renderGeometryToTexture(prefix: string, meta: any, destTextureWidth: number, destTextureHeight: number, memoryF32: any, buffers: any, sectionDim: number, section: number) {
         if (this.gl === null) {
             return;
@@ -529,6 +846,11 @@ class GLRenderer2 {
 
         gl.uniform1i(shader.brushUniform, TextureUnit.brushTexture);
         gl.uniform1i(shader.maskUniform, TextureUnit.maskTexture);
+        gl.uniform1i(shader.backdropUniform, TextureUnit.backdropTexture);
+        gl.uniform2f(shader.backdropDimUniform, destTextureWidth, destTextureHeight);
+
+        let blendMode = RendererTS.setBlendMode(gl, this.blendMinMax, BlendMode.normal);
+        gl.uniform1i(shader.blendModeUniform, blendMode);
 
         // setting output_linear_colour_space in meta because the blit shader also requires it
         meta.output_linear_colour_space = false;
@@ -552,6 +874,12 @@ class GLRenderer2 {
 
         await this.ensureTexture(TextureUnit.maskTexture, prefix, 'mask/white.png');
 
+        // the layers that have begun but not yet ended, along with the blend mode that was in use before each one
+        const layerStack: Array<{ buffer: any, parentBlendMode: BlendMode }> = [];
+        const currentFramebuffer = () => {
+            return layerStack.length === 0 ? this.framebuffer : this.getLayer(gl, layerStack.length - 1).framebuffer;
+        };
+
         for(let b = 0; b < buffers.length; b++) {
             let buffer = buffers[b];
             switch(buffer.command) {
@@ -560,6 +888,14 @@ class GLRenderer2 {
                     // a new typed array view is created that views the specified ArrayBuffer
                     const gbuf = new Float32Array(memoryF32, buffer.geo_ptr, buffer.geo_len);
 
+                    if (blendMode === BlendMode.overlay) {
+                        // take a copy of everything that's been rendered so far.
+                        // note: overlapping triangles within this packet won't overlay each other
+                        gl.activeTexture(RendererTS.textureUnitToGl(gl, TextureUnit.backdropTexture));
+                        gl.bindTexture(gl.TEXTURE_2D, this.backdropTexture);
+                        gl.copyTexSubImage2D(gl.TEXTURE_2D, 0, 0, 0, 0, 0, destTextureWidth, destTextureHeight);
+                    }
+
                     gl.bindBuffer(gl.ARRAY_BUFFER, glVertexBuffer);
                     gl.bufferData(gl.ARRAY_BUFFER, gbuf, gl.STATIC_DRAW);
 
@@ -592,10 +928,51 @@ class GLRenderer2 {
                     gl.uniform1i(shader.outputLinearColourSpaceUniform, meta.output_linear_colour_space);
                     // todo(isg): apply the image modifications in the blit shader
                     break;
+                case RPCommand.blend:
+                    blendMode = RendererTS.setBlendMode(gl, this.blendMinMax, buffer.blend_mode);
+                    gl.uniform1i(shader.blendModeUniform, blendMode);
+                    break;
+                case RPCommand.layerBegin:
+                    layerStack.push({ buffer, parentBlendMode: blendMode });
+
+                    gl.bindFramebuffer(gl.FRAMEBUFFER, currentFramebuffer());
+
+                    // layers start off transparent
+                    gl.clearColor(0.0, 0.0, 0.0, 0.0);
+                    gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT);
+                    gl.clearColor(1.0, 1.0, 1.0, 1.0);
+
+                    blendMode = RendererTS.setBlendMode(gl, this.blendMinMax, BlendMode.normal);
+                    gl.uniform1i(shader.blendModeUniform, blendMode);
+                    break;
+                case RPCommand.layerEnd:
+                    const ended = layerStack.pop();
+                    if (ended === undefined) {
+                        Log.error('layerEnd without a matching layerBegin');
+                        break;
+                    }
+                    const layer = this.getLayer(gl, layerStack.length);
+
+                    // the blur is given in canvas units
+                    const blurPixels = ended.buffer.layer_blur * destTextureWidth * sectionDim / canvasDim;
+                    this.compositeLayer(gl, layer, currentFramebuffer(), ended.buffer, blurPixels, destTextureWidth, destTextureHeight);
+
+                    // carry on rendering geometry into the parent
+                    gl.useProgram(shader.program);
+                    gl.enableVertexAttribArray(shader.positionAttribute);
+                    gl.enableVertexAttribArray(shader.colourAttribute);
+                    gl.enableVertexAttribArray(shader.textureAttribute);
+
+                    blendMode = RendererTS.setBlendMode(gl, this.blendMinMax, ended.parentBlendMode);
+                    gl.uniform1i(shader.blendModeUniform, blendMode);
+                    break;
                 default:
                     Log.error(`unknown RenderPacket command ${buffer.command}`);
             }
         }
+
+        // leave the GL state as it was for the blit
+        RendererTS.setBlendMode(gl, this.blendMinMax, BlendMode.normal);
     }
 
     public clearBuffer() {
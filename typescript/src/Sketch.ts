@@ -363,7 +363,8 @@ async function main() {
     const shaders = await loadShaders([`${PREFIX}/shader/main-vert.glsl`,
                                        `${PREFIX}/shader/main-frag.glsl`,
                                        `${PREFIX}/shader/blit-vert.glsl`,
-                                       `${PREFIX}/shader/blit-frag.glsl`]);
+                                       `${PREFIX}/shader/blit-frag.glsl`,
+                                       `${PREFIX}/shader/layer-frag.glsl`]);
     gGLRenderer = new GLRenderer2(PREFIX, canvasElement, <IHashStrStr>shaders, g_render_texture_width, g_render_texture_height);
     //updateGlobalsFromURI();  // todo: 2020-06-25 re-enable
 
@@ -28,21 +28,25 @@ function seniMode() {
   const indentKeys = makeKeywords('define fence loop on-matrix-stack fn');
 
   // functions from the common seni library
-  const seniCommon = makeKeywords(`* + - / < = > append begin bezier
+  const seniCommon = makeKeywords(`* + - / < = > append arc begin bezier blend/set
 bezier-bulging bezier-trailing box canvas/centre canvas/height canvas/width
-circle circle-slice col/analagous col/bezier-fn col/complementary col/convert
-col/darken col/alpha col/hsl col/hsluv col/hsv col/lab col/lighten
-col/procedural-fn col/quadratic-fn col/rgb col/set-alpha col/e0 col/e1 col/e2 col/set-e0 col/set-e1 col/set-e2
-col/split-complementary col/triad define image math/degrees->radians fence fn focal/hline
+circle circle-slice col/analagous col/bezier-fn col/build-gradient col/complementary col/convert
+col/darken col/define-palette col/alpha col/hsl col/hsluv col/hsv col/lab col/lch col/lighten col/oklab col/oklch
+col/palette col/palette-from-bitmap col/procedural-fn col/quadratic-fn col/rgb col/set-alpha col/e0 col/e1 col/e2 col/set-e0 col/set-e1 col/set-e2
+col/split-complementary col/triad define ellipse image math/degrees->radians fence fill/hatch fill/stipple flow/trace fn focal/hline geom/delaunay geom/difference
+geom/intersection geom/offset geom/union geom/voronoi
 focal/point focal/vline if interp/bezier interp/bezier-fn interp/bezier-tangent
-interp/bezier-tangent-fn interp/circle interp/fn line list list/get list/length
-log loop math/PI math/TAU math/atan2 math/clamp math/cos math/distance-2d
-math/sin mod on-matrix-stack path/bezier path/circle path/linear path/spline
-poly pop-matrix print prng/perlin-signed prng/perlin-unsigned prng/range
-push-matrix quote radians->degrees rect repeat/rotate repeat/rotate-mirrored
+interp/bezier-tangent-fn interp/circle interp/fn layer/begin layer/end line list list/get list/length
+log lsystem/expand lsystem/turtle loop math/PI math/TAU math/abs math/atan2 math/ceil math/clamp math/cos math/distance-2d
+math/exp math/floor math/log math/map-range math/max math/min math/pow math/round
+math/sin mod noise/fbm noise/simplex noise/worley on-matrix-stack particle/simulate path/bezier path/circle path/linear path/spline
+poly polyline pop-matrix print prng/perlin-signed prng/perlin-unsigned prng/range
+push-matrix quote radians->degrees rect repeat/grid repeat/hex-grid repeat/rotate repeat/rotate-mirrored
 repeat/symmetry-4 repeat/symmetry-8 repeat/symmetry-horizontal
-repeat/symmetry-vertical rotate scale spline sqrt stroked-bezier
-take translate`);
+reflect repeat/symmetry-vertical repeat/wallpaper rotate rounded-rect sample/halton sample/jittered-grid
+sample/poisson-disk scale skew-x skew-y spline sqrt stroked-bezier
+take transform/point transform/set translate v2/add v2/angle v2/cross v2/dot v2/length v2/lerp v2/normalize
+v2/rotate v2/scale v2/sub`);
 
   function StateStack(indent, type, prev) { // represents a state stack object
     this.indent = indent;
@@ -60,13 +60,13 @@ async function renderJob(parameters) {
   // 2. (retain the id for this worker)
   // 3. after compilation, the worker will return a list of bitmaps that are
   //    required by the program and are not in the web worker's bitmap-cache
-  // 4. sequentially load in the bitmaps and send their data to the worker
+  // 4. sequentially load in the bitmaps and palette files and send their data to the worker
   // 5. can now request a render which will return the render packets
 
   // request a compile job but make sure to retain the worker as it will be performing the rendering
   //
   parameters.__retain = true;
-  const { bitmapsToTransfer, __worker_id } = await Job.request(JobType.jobRender_1_Compile, parameters);
+  const { bitmapsToTransfer, palettesToTransfer, __worker_id } = await Job.request(JobType.jobRender_1_Compile, parameters);
 
   // convert each bitmap path to a function that returns a promise
   //
@@ -82,6 +82,24 @@ async function renderJob(parameters) {
   //
   await sequentialPromises(bitmap_loading_funcs);
 
+  // palette files are plain text so the worker can parse them itself
+  //
+  const palette_loading_funcs = palettesToTransfer.map(filename => async () => {
+    Log.log(`worker ${__worker_id}: palette request: ${filename}`);
+
+    const response = await fetch(filename);
+    if (!response.ok) {
+      // don't pass an error page to the worker as if it were a palette, the
+      // program will fail to find the palette instead
+      Log.error(`worker ${__worker_id}: palette request failed: ${filename} (${response.status})`);
+      return undefined;
+    }
+    const source = await response.text();
+    return Job.request(JobType.jobRender_2_ReceivePaletteData, { filename, source, __retain: true }, __worker_id);
+  });
+
+  await sequentialPromises(palette_loading_funcs);
+
   // now make an explicit job request to the same worker that has recieved the bitmap data
   // note: no __retain as we want the worker to be returned to the available pool
   const renderPacketsResult = await Job.request(JobType.jobRender_3_RenderPackets, {}, __worker_id);
@@ -0,0 +1,150 @@
+// composites a layer's render texture onto its parent render target
+// both textures contain pre-multiplied alpha in linear colour space
+//
+// a blurred layer is drawn in two passes: the first blurs horizontally into an
+// intermediate texture (with blur_only set) and the second blurs that
+// vertically while compositing
+
+precision highp float;
+
+varying vec2 frag_uv;
+
+uniform sampler2D layer;
+// the size of the layer's texture in pixels
+uniform vec2 layer_dim;
+
+// blur radius in pixels
+uniform float blur;
+// either (1, 0) or (0, 1)
+uniform vec2 blur_direction;
+// only output the blurred layer, used by the first of the two blur passes
+uniform bool blur_only;
+uniform float opacity;
+
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+
+// matches the BlendMode values on the Rust side
+uniform int blend_mode;
+// a copy of the parent render target, only used by the overlay blend mode
+uniform sampler2D backdrop;
+uniform vec2 backdrop_dim;
+
+const int BLEND_OVERLAY = 3;
+const int BLEND_DARKEN = 5;
+const int BLEND_LIGHTEN = 6;
+
+// the most samples taken either side of the centre, radii larger than this
+// many pixels spread the samples further apart
+const int BLUR_MAX_TAPS = 32;
+
+mat4 brightness_mat4(float brightness) {
+  return mat4(1, 0, 0, 0,
+              0, 1, 0, 0,
+              0, 0, 1, 0,
+              brightness, brightness, brightness, 1);
+}
+
+mat4 contrast_mat4(float contrast) {
+  float t = (1.0 - contrast) / 2.0;
+
+  return mat4(contrast, 0, 0, 0,
+              0, contrast, 0, 0,
+              0, 0, contrast, 0,
+              t, t, t, 1);
+}
+
+mat4 saturation_mat4(float saturation) {
+  vec3 luminance = vec3(0.3086, 0.6094, 0.0820);
+
+  float one_minus_sat = 1.0 - saturation;
+
+  vec3 red = vec3(luminance.x * one_minus_sat);
+  red += vec3(saturation, 0, 0);
+
+  vec3 green = vec3(luminance.y * one_minus_sat);
+  green += vec3(0, saturation, 0);
+
+  vec3 blue = vec3(luminance.z * one_minus_sat);
+  blue += vec3(0, 0, saturation);
+
+  return mat4(red,     0,
+              green,   0,
+              blue,    0,
+              0, 0, 0, 1);
+}
+
+// a gaussian blur along blur_direction, blurring the pre-multiplied colour so
+// that transparent pixels don't darken the edges. The samples are a pixel apart
+// until the radius is more than BLUR_MAX_TAPS pixels, after that they spread out
+// evenly across the radius and the linear filtering smooths between them
+//
+vec4 sample_layer() {
+  if (blur <= 0.0) {
+    return texture2D(layer, frag_uv);
+  }
+
+  float spacing = max(1.0, blur / float(BLUR_MAX_TAPS));
+  int taps = int(ceil(blur / spacing));
+  vec2 offset = blur_direction * spacing / layer_dim;
+
+  // the weights are close to zero at the blur radius
+  float sigma = blur / 3.0;
+
+  vec4 total = vec4(0.0);
+  float total_weight = 0.0;
+  for (int i = -BLUR_MAX_TAPS; i <= BLUR_MAX_TAPS; i++) {
+    if (i < -taps || i > taps) {
+      continue;
+    }
+    float d = float(i) * spacing;
+    float weight = exp(-(d * d) / (2.0 * sigma * sigma));
+    total += weight * texture2D(layer, frag_uv + float(i) * offset);
+    total_weight += weight;
+  }
+
+  return total / total_weight;
+}
+
+// see the blend function in main-frag.glsl
+//
+vec4 blend(vec4 src) {
+  if (blend_mode == BLEND_DARKEN) {
+    return vec4(src.rgb + vec3(1.0 - src.a), 1.0);
+  } else if (blend_mode == BLEND_LIGHTEN) {
+    return vec4(src.rgb, 0.0);
+  } else if (blend_mode == BLEND_OVERLAY) {
+    vec3 dst = texture2D(backdrop, gl_FragCoord.xy / backdrop_dim).rgb;
+    vec3 col = src.a > 0.0 ? src.rgb / src.a : vec3(0.0);
+    vec3 multiply = 2.0 * dst * col;
+    vec3 screen = vec3(1.0) - 2.0 * (vec3(1.0) - dst) * (vec3(1.0) - col);
+    vec3 overlay = mix(multiply, screen, step(0.5, dst));
+    return vec4(mix(dst, overlay, src.a), 1.0);
+  }
+
+  return src;
+}
+
+void main()
+{
+  vec4 src = sample_layer();
+
+  if (blur_only) {
+    gl_FragColor = src;
+    return;
+  }
+
+  // the adjustments work on the un-premultiplied colour
+  vec3 col = src.a > 0.0 ? src.rgb / src.a : vec3(0.0);
+  vec4 balanced_col =
+    brightness_mat4(brightness) *
+    contrast_mat4(contrast) *
+    saturation_mat4(saturation) *
+    vec4(col, 1.0);
+  col = clamp(balanced_col.rgb, 0.0, 1.0);
+
+  float alpha = src.a * opacity;
+
+  gl_FragColor = blend(vec4(col * alpha, alpha));
+}
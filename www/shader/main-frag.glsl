@@ -15,6 +15,16 @@ uniform float canvas_dim;
 uniform bool mask_invert;
 uniform bool output_linear_colour_space;
 
+// matches the BlendMode values on the Rust side
+uniform int blend_mode;
+// a copy of the render target, only used by the overlay blend mode
+uniform sampler2D backdrop;
+uniform vec2 backdrop_dim;
+
+const int BLEND_OVERLAY = 3;
+const int BLEND_DARKEN = 5;
+const int BLEND_LIGHTEN = 6;
+
 // https://en.wikipedia.org/wiki/SRGB
 vec3 srgb_to_linear(vec3 srgb) {
   float a = 0.055;
@@ -27,6 +37,29 @@ vec3 srgb_to_linear(vec3 srgb) {
               srgb.b > b ? linear_hi.b : linear_lo.b);
 }
 
+// most blend modes are carried out by the fixed function blending, the ones
+// below need the pre-multiplied colour to be adjusted first
+//
+vec4 blend(vec4 src) {
+  if (blend_mode == BLEND_DARKEN) {
+    // blended with MIN, so composite over white to make transparent fragments
+    // leave the destination unchanged
+    return vec4(src.rgb + vec3(1.0 - src.a), 1.0);
+  } else if (blend_mode == BLEND_LIGHTEN) {
+    // blended with MAX, pre-multiplied is the same as compositing over black
+    return vec4(src.rgb, 0.0);
+  } else if (blend_mode == BLEND_OVERLAY) {
+    // the destination is replaced, so blend with the backdrop here
+    vec3 dst = texture2D(backdrop, gl_FragCoord.xy / backdrop_dim).rgb;
+    vec3 col = src.a > 0.0 ? src.rgb / src.a : vec3(0.0);
+    vec3 multiply = 2.0 * dst * col;
+    vec3 screen = vec3(1.0) - 2.0 * (vec3(1.0) - dst) * (vec3(1.0) - col);
+    vec3 overlay = mix(multiply, screen, step(0.5, dst));
+    return vec4(mix(dst, overlay, src.a), 1.0);
+  }
+
+  return src;
+}
 
 void main(void) {
   vec4 brush_col = texture2D(brush, frag_uv);
@@ -55,4 +88,6 @@ void main(void) {
     gl_FragColor.b = brush_col.r * linear_col.b * frag_col.a * mask_val;
     gl_FragColor.a = brush_col.r * linear_col.a * frag_col.a * mask_val;
   }
+
+  gl_FragColor = blend(gl_FragColor);
 }
@@ -32,6 +32,7 @@ const NO_CACHE_URLS = [
   '/shader/main-frag.glsl',
   '/shader/blit-vert.glsl',
   '/shader/blit-frag.glsl',
+  '/shader/layer-frag.glsl',
 ];
 
 // A list of local resources we always want to be cached.
@@ -36,6 +36,7 @@ let JobType;
     JobType[JobType["jobGenerateHelp"] = 7] = "jobGenerateHelp";
     JobType[JobType["jobSingleGenotypeFromSeed"] = 8] = "jobSingleGenotypeFromSeed";
     JobType[JobType["jobSimplifyScript"] = 9] = "jobSimplifyScript";
+    JobType[JobType["jobRender_2_ReceivePaletteData"] = 10] = "jobRender_2_ReceivePaletteData";
 })(JobType || (JobType = {}));
 
 function compile({ script, genotype }) {
@@ -48,8 +49,9 @@ function compile({ script, genotype }) {
   }
 
   const bitmapsToTransfer = JSON.parse(seniBridge.get_bitmap_transfers_as_json());
+  const palettesToTransfer = JSON.parse(seniBridge.get_palette_transfers_as_json());
 
-  return [{}, { bitmapsToTransfer }];
+  return [{}, { bitmapsToTransfer, palettesToTransfer }];
 }
 
 function receiveBitmapData( { filename, imageData } ) {
@@ -65,9 +67,18 @@ function receiveBitmapData( { filename, imageData } ) {
   return [{}, { result: "shabba" }];
 }
 
+function receivePaletteData( { filename, source } ) {
+  const result = seniBridge.add_palette(filename, source);
+
+  return [{}, { result }];
+}
+
 const RPCommand_Geometry = 1;
 const RPCommand_Mask = 2;
 const RPCommand_Image = 3;
+const RPCommand_Blend = 4;
+const RPCommand_LayerBegin = 5;
+const RPCommand_LayerEnd = 6;
 
 function renderPackets({  }) {
   const buffers = [];
@@ -105,6 +116,28 @@ function renderPackets({  }) {
 
       renderPacketImage.free();
       break;
+    case RPCommand_Blend:
+      const renderPacketBlend = seniBridge.rp_blend(i);
+
+      buffer.blend_mode = renderPacketBlend.get_mode();
+
+      renderPacketBlend.free();
+      break;
+    case RPCommand_LayerBegin:
+      const renderPacketLayer = seniBridge.rp_layer(i);
+
+      buffer.layer_name = renderPacketLayer.get_name();
+      buffer.layer_opacity = renderPacketLayer.get_opacity();
+      buffer.blend_mode = renderPacketLayer.get_blend_mode();
+      buffer.layer_blur = renderPacketLayer.get_blur();
+      buffer.contrast = renderPacketLayer.get_contrast();
+      buffer.brightness = renderPacketLayer.get_brightness();
+      buffer.saturation = renderPacketLayer.get_saturation();
+
+      renderPacketLayer.free();
+      break;
+    case RPCommand_LayerEnd:
+      break;
     default:
       console.error(`unknown buffer command: ${buffer.command}`);
       break;
@@ -228,6 +261,8 @@ function messageHandler(type, data) {
     return compile(data);
   case JobType["jobRender_2_ReceiveBitmapData"]:
     return receiveBitmapData(data);
+  case JobType["jobRender_2_ReceivePaletteData"]:
+    return receivePaletteData(data);
   case JobType["jobRender_3_RenderPackets"]:
     return renderPackets(data);
   case JobType["jobUnparse"]: